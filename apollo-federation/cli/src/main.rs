@@ -31,8 +31,11 @@ use serde::Deserialize;
 use tracing_subscriber::prelude::*;
 
 mod bench;
+mod plan_bundle;
 use bench::BenchOutput;
 use bench::run_bench;
+use plan_bundle::Manifest;
+use plan_bundle::build_plan_bundle;
 
 /// Rover supergraph config file format
 #[derive(Debug, Deserialize)]
@@ -151,6 +154,19 @@ enum Command {
         #[command(flatten)]
         planner: QueryPlannerArgs,
     },
+    /// Plan the operations of a persisted query manifest and write them to a plan bundle the
+    /// router can load at startup
+    PlanBundle {
+        /// The path to the supergraph schema file loaded by the router
+        supergraph_schema: PathBuf,
+        /// The path to the persisted query manifest
+        manifest: PathBuf,
+        /// The path of the plan bundle to write, stdout if not specified
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        planner: QueryPlannerArgs,
+    },
 
     /// Expand connector-enabled supergraphs
     Expand {
@@ -228,6 +244,12 @@ fn main() -> ExitCode {
             operations_dir,
             planner,
         } => cmd_bench(&supergraph_schema, &operations_dir, planner),
+        Command::PlanBundle {
+            supergraph_schema,
+            manifest,
+            output,
+            planner,
+        } => cmd_plan_bundle(&supergraph_schema, &manifest, output.as_ref(), planner),
         Command::Expand {
             supergraph_schema,
             destination_dir,
@@ -715,6 +737,66 @@ fn cmd_bench(
     Ok(())
 }
 
+fn cmd_plan_bundle(
+    file_path: &Path,
+    manifest_path: &Path,
+    output: Option<&PathBuf>,
+    planner: QueryPlannerArgs,
+) -> Result<(), AnyError> {
+    let schema_str = read_input(file_path);
+    let manifest = Manifest::parse(&read_input(manifest_path))?;
+    let (bundle, skipped) = build_plan_bundle(&schema_str, manifest, planner.into())?;
+    for operation in &skipped {
+        eprintln!(
+            "skipped operation {id}: {error}",
+            id = operation.id,
+            error = operation.error
+        );
+    }
+
+    let bundle_str = serde_json::to_string(&bundle)?;
+    if let Some(output) = output {
+        fs::write(output, bundle_str)?;
+    } else {
+        println!("{bundle_str}");
+    }
+    eprintln!(
+        "planned {planned} operation(s), skipped {skipped}",
+        planned = bundle.operations.len(),
+        skipped = skipped.len()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_plan_bundle() {
+    let schema_str = read_input(Path::new("./fixtures/starstuff.graphql"));
+    let manifest = Manifest::parse(&read_input(Path::new("./fixtures/manifest.json"))).unwrap();
+    let (bundle, skipped) = build_plan_bundle(&schema_str, manifest, Default::default()).unwrap();
+
+    assert_eq!(
+        bundle
+            .operations
+            .iter()
+            .map(|operation| (operation.id.as_str(), operation.operation_name.as_deref()))
+            .collect::<Vec<_>>(),
+        [("top-products", None), ("me", Some("Me"))]
+    );
+    assert!(
+        bundle
+            .operations
+            .iter()
+            .all(|operation| operation.plan.node.is_some())
+    );
+    assert_eq!(
+        skipped
+            .iter()
+            .map(|operation| operation.id.as_str())
+            .collect::<Vec<_>>(),
+        ["invalid"]
+    );
+}
+
 #[test]
 fn test_bench() {
     insta::assert_json_snapshot!(
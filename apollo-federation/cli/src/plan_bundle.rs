@@ -0,0 +1,104 @@
+use apollo_compiler::ExecutableDocument;
+use apollo_compiler::Name;
+use apollo_federation::Supergraph;
+use apollo_federation::error::FederationError;
+use apollo_federation::query_plan::bundle::PlanBundle;
+use apollo_federation::query_plan::bundle::PlannedOperation;
+use apollo_federation::query_plan::query_planner::QueryPlanner;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use serde::Deserialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// Persisted query manifest, in the format loaded by the router
+#[derive(Debug, Deserialize)]
+pub(crate) struct Manifest {
+    format: String,
+    version: u64,
+    operations: Vec<ManifestOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestOperation {
+    id: String,
+    body: String,
+    name: Option<String>,
+}
+
+impl Manifest {
+    pub(crate) fn parse(manifest: &str) -> Result<Self, anyhow::Error> {
+        let manifest: Self = serde_json::from_str(manifest)?;
+        if manifest.format != "apollo-persisted-query-manifest" || manifest.version != 1 {
+            anyhow::bail!(
+                "unsupported persisted query manifest format `{}` version {}",
+                manifest.format,
+                manifest.version
+            );
+        }
+        Ok(manifest)
+    }
+}
+
+/// An operation of the manifest that could not be planned
+#[derive(Debug)]
+pub(crate) struct SkippedOperation {
+    pub(crate) id: String,
+    pub(crate) error: String,
+}
+
+/// Plan every operation of `manifest` against the supergraph schema `schema_str`.
+///
+/// The schema hash recorded in the bundle is computed from the schema text exactly as the router
+/// does, so `schema_str` must be the file the router loads.
+pub(crate) fn build_plan_bundle(
+    schema_str: &str,
+    manifest: Manifest,
+    config: QueryPlannerConfig,
+) -> Result<(PlanBundle, Vec<SkippedOperation>), FederationError> {
+    let supergraph = Supergraph::new_with_router_specs(schema_str)?;
+    let schema_hash = format!("{:x}", Sha256::digest(schema_str));
+    let mut bundle = PlanBundle::new(schema_hash, &config);
+    let planner = QueryPlanner::new(&supergraph, config)?;
+
+    let mut skipped = Vec::new();
+    for operation in manifest.operations {
+        match plan_operation(&planner, &operation) {
+            Ok(planned) => bundle.operations.push(planned),
+            Err(error) => skipped.push(SkippedOperation {
+                id: operation.id,
+                error: error.to_string(),
+            }),
+        }
+    }
+    Ok((bundle, skipped))
+}
+
+fn plan_operation(
+    planner: &QueryPlanner,
+    operation: &ManifestOperation,
+) -> Result<PlannedOperation, FederationError> {
+    let document = ExecutableDocument::parse_and_validate(
+        planner.api_schema().schema(),
+        &operation.body,
+        "operation.graphql",
+    )
+    .map_err(FederationError::from)?;
+    // The operation name is only needed to select an operation in a document containing several
+    let operation_name = if document.operations.len() > 1 {
+        operation.name.clone()
+    } else {
+        None
+    };
+    let name = operation_name
+        .as_deref()
+        .map(Name::new)
+        .transpose()
+        .map_err(FederationError::from)?;
+    let plan = planner.build_query_plan(&document, name, Default::default())?;
+    Ok(PlannedOperation {
+        id: operation.id.clone(),
+        query: operation.body.clone(),
+        operation_name,
+        plan,
+    })
+}
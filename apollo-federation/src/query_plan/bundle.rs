@@ -0,0 +1,73 @@
+//! Plan bundles: query plans computed ahead of time for a list of operations, usually the
+//! operations of a persisted query manifest, so a router can load them instead of planning the
+//! operations when it starts.
+
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::query_plan::QueryPlan;
+use crate::query_plan::query_planner::QueryPlannerConfig;
+use crate::query_plan::query_planner::QueryPlannerDebugConfig;
+
+/// Version of the plan bundle format. Bundles written with another version are not loaded.
+pub const PLAN_BUNDLE_FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanBundle {
+    pub format_version: u32,
+    /// SHA-256 of the supergraph schema text the plans were computed for, in hexadecimal.
+    pub schema_hash: String,
+    /// Hash of the query planner options the plans were computed with, see
+    /// [`planner_config_hash`].
+    #[serde(default)]
+    pub planner_config_hash: String,
+    pub operations: Vec<PlannedOperation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedOperation {
+    /// Identifier of the operation in the persisted query manifest
+    pub id: String,
+    pub query: String,
+    /// Name of the operation, only set if the query contains several operations.
+    pub operation_name: Option<String>,
+    pub plan: QueryPlan,
+}
+
+impl PlanBundle {
+    pub fn new(schema_hash: impl Into<String>, config: &QueryPlannerConfig) -> Self {
+        Self {
+            format_version: PLAN_BUNDLE_FORMAT_VERSION,
+            schema_hash: schema_hash.into(),
+            planner_config_hash: planner_config_hash(config),
+            operations: Vec::new(),
+        }
+    }
+}
+
+/// SHA-256 of the query planner options that change the bundled plans, in hexadecimal.
+///
+/// `subgraph_graphql_validation` doesn't change the plans, and `@defer` support only changes the
+/// plans of operations using `@defer`, which routers don't take from bundles.
+pub fn planner_config_hash(config: &QueryPlannerConfig) -> String {
+    let QueryPlannerConfig {
+        generate_query_fragments,
+        subgraph_graphql_validation: _,
+        incremental_delivery: _,
+        debug:
+            QueryPlannerDebugConfig {
+                max_evaluated_plans,
+                paths_limit,
+            },
+        type_conditioned_fetching,
+    } = config;
+    let options = serde_json::json!({
+        "generate_query_fragments": generate_query_fragments,
+        "type_conditioned_fetching": type_conditioned_fetching,
+        "max_evaluated_plans": max_evaluated_plans,
+        "paths_limit": paths_limit,
+    });
+    format!("{:x}", Sha256::digest(options.to_string()))
+}
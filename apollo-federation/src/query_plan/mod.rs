@@ -8,6 +8,7 @@ use serde::Serialize;
 
 use crate::query_plan::query_planner::QueryPlanningStatistics;
 
+pub mod bundle;
 pub(crate) mod conditions;
 pub(crate) mod display;
 pub(crate) mod fetch_dependency_graph;
@@ -283,6 +283,26 @@ impl UsageReporting {
         }
     }
 
+    /// The normalized operation signature, if the operation is valid
+    pub(crate) fn get_operation_signature(&self) -> Option<String> {
+        match self {
+            UsageReporting::Operation(operation_details)
+            | UsageReporting::PersistedQuery {
+                operation_details, ..
+            } => operation_details.operation_signature.clone(),
+            UsageReporting::Error { .. } => None,
+        }
+    }
+
+    pub(crate) fn get_persisted_query_id(&self) -> Option<String> {
+        match self {
+            UsageReporting::PersistedQuery {
+                persisted_query_id, ..
+            } => Some(persisted_query_id.clone()),
+            UsageReporting::Operation { .. } | UsageReporting::Error { .. } => None,
+        }
+    }
+
     pub(crate) fn get_referenced_fields(&self) -> HashMap<String, ReferencedFieldsForType> {
         match self {
             UsageReporting::Operation(operation_details)
@@ -8,6 +8,7 @@ use std::time::Instant;
 
 use axum::Router;
 use axum::extract::Extension;
+use axum::extract::Path;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::middleware;
@@ -20,7 +21,11 @@ use futures::prelude::*;
 use http::HeaderValue;
 use http::Request;
 use http::header::ACCEPT_ENCODING;
+use http::header::CACHE_CONTROL;
 use http::header::CONTENT_ENCODING;
+use http::header::CONTENT_TYPE;
+use http::header::LINK;
+use http::header::VARY;
 use http_body::Body;
 use itertools::Itertools;
 use multimap::MultiMap;
@@ -46,6 +51,9 @@ use super::listeners::extra_endpoints;
 use super::utils::PropagatingMakeSpan;
 use crate::Context;
 use crate::axum_factory::compression::Compressor;
+use crate::axum_factory::compression::dictionary::CompressionDictionaries;
+use crate::axum_factory::compression::dictionary::USE_AS_DICTIONARY;
+use crate::axum_factory::compression::dictionary::available_dictionary;
 use crate::axum_factory::listeners::get_extra_listeners;
 use crate::axum_factory::listeners::serve_router_on_listen_addr;
 use crate::configuration::Configuration;
@@ -60,6 +68,7 @@ use crate::plugins::telemetry::config_new::router::instruments::ResponseBodySize
 use crate::router::ApolloRouterError;
 use crate::router_factory::Endpoint;
 use crate::router_factory::RouterFactory;
+use crate::services::layers::persisted_queries::RequestPersistedQueryId;
 use crate::services::router;
 use crate::uplink::license_enforcement::APOLLO_ROUTER_LICENSE_EXPIRED;
 use crate::uplink::license_enforcement::LICENSE_EXPIRED_SHORT_MESSAGE;
@@ -457,6 +466,8 @@ async fn license_handler(
 struct HandlerOptions {
     early_cancel: bool,
     experimental_log_on_broken_pipe: bool,
+    compression_dictionaries: Option<Arc<CompressionDictionaries>>,
+    supergraph_path: String,
 }
 
 pub(super) fn main_router<RF>(configuration: &Configuration) -> axum::Router<()>
@@ -472,9 +483,27 @@ where
         router = router.route("/", get(handle_graphql::<RF>).post(handle_graphql::<RF>));
     }
 
+    let dictionaries_config = &configuration
+        .persisted_queries
+        .experimental_compression_dictionaries;
+    let compression_dictionaries = (configuration.persisted_queries.enabled
+        && dictionaries_config.enabled)
+        .then(|| Arc::new(CompressionDictionaries::new(dictionaries_config.clone())));
+    if compression_dictionaries.is_some() {
+        router = router.route(
+            &format!(
+                "{}/{{name}}",
+                dictionaries_config.path.trim_end_matches('/')
+            ),
+            get(handle_compression_dictionary),
+        );
+    }
+
     router = router.route_layer(Extension(HandlerOptions {
         early_cancel: configuration.supergraph.early_cancel,
         experimental_log_on_broken_pipe: configuration.supergraph.experimental_log_on_broken_pipe,
+        compression_dictionaries,
+        supergraph_path: configuration.supergraph.path.clone(),
     }));
     #[cfg(all(feature = "global-allocator", not(feature = "dhat-heap"), unix))]
     {
@@ -506,6 +535,8 @@ async fn handle_graphql<RF: RouterFactory>(
     let HandlerOptions {
         early_cancel,
         experimental_log_on_broken_pipe,
+        compression_dictionaries,
+        ..
     } = options;
     let service = service_factory.create();
 
@@ -516,6 +547,9 @@ async fn handle_graphql<RF: RouterFactory>(
         .headers()
         .get(ACCEPT_ENCODING)
         .cloned();
+    let available_dictionary = compression_dictionaries
+        .as_ref()
+        .and_then(|_| available_dictionary(request.router_request.headers()));
 
     let res = if early_cancel {
         service.oneshot(request).await
@@ -541,12 +575,61 @@ async fn handle_graphql<RF: RouterFactory>(
     match res {
         Err(err) => internal_server_error(err),
         Ok(response) => {
-            let (mut parts, body) = response.response.into_parts();
-
-            let opt_compressor = accept_encoding
+            let (mut parts, mut body) = response.response.into_parts();
+            let accept_encoding = accept_encoding
                 .as_ref()
-                .and_then(|value| value.to_str().ok())
-                .and_then(|v| Compressor::new(v.split(',').map(|s| s.trim())));
+                .and_then(|value| value.to_str().ok());
+
+            let mut opt_compressor = None;
+            let persisted_query_id = compression_dictionaries.as_ref().and_then(|_| {
+                context.extensions().with_lock(|lock| {
+                    lock.get::<RequestPersistedQueryId>()
+                        .map(|id| id.pq_id.clone())
+                })
+            });
+            if let Some(dictionaries) = &compression_dictionaries
+                && let Some(persisted_query_id) = &persisted_query_id
+            {
+                parts.headers.append(
+                    VARY,
+                    HeaderValue::from_static("accept-encoding, available-dictionary"),
+                );
+
+                // only complete responses are sampled, so buffering them does not delay the client
+                if parts.status == StatusCode::OK
+                    && let Some(size) = body.size_hint().exact()
+                    && dictionaries.wants_sample(persisted_query_id, size as usize)
+                {
+                    match router::body::into_bytes(body).await {
+                        Ok(bytes) => {
+                            dictionaries.add_sample(persisted_query_id, bytes.clone());
+                            body = router::body::from_bytes(bytes);
+                        }
+                        Err(err) => return internal_server_error(err),
+                    }
+                }
+
+                opt_compressor = available_dictionary
+                    .and_then(|hash| dictionaries.get(&hash))
+                    .zip(accept_encoding)
+                    .and_then(|(dictionary, v)| {
+                        Compressor::with_dictionary(v.split(',').map(|s| s.trim()), &dictionary)
+                    });
+                if opt_compressor.is_none()
+                    && let Some(dictionary) = dictionaries.for_operation(persisted_query_id)
+                    && let Ok(link) = HeaderValue::from_str(&format!(
+                        "<{}/{}>; rel=\"compression-dictionary\"",
+                        dictionaries.config().path.trim_end_matches('/'),
+                        dictionary.name()
+                    ))
+                {
+                    parts.headers.append(LINK, link);
+                }
+            }
+
+            let opt_compressor = opt_compressor.or_else(|| {
+                accept_encoding.and_then(|v| Compressor::new(v.split(',').map(|s| s.trim())))
+            });
 
             let response_body_size_recording = context
                 .extensions()
@@ -583,6 +666,56 @@ async fn handle_graphql<RF: RouterFactory>(
     }
 }
 
+/// Serves a compression dictionary, to be used for the responses of the supergraph path
+async fn handle_compression_dictionary(
+    Extension(options): Extension<HandlerOptions>,
+    Path(name): Path<String>,
+) -> Response {
+    let Some(dictionaries) = options.compression_dictionaries else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some(dictionary) = hex::decode(&name)
+        .ok()
+        .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+        .and_then(|hash| dictionaries.get(&hash))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let mut use_as_dictionary = format!("match=\"{}\"", options.supergraph_path);
+    // the operation ID is only used as the dictionary ID if it is a valid structured field string
+    if dictionary
+        .operation_id
+        .chars()
+        .all(|c| c.is_ascii_graphic() && c != '"' && c != '\\')
+    {
+        use_as_dictionary.push_str(&format!(", id=\"{}\"", dictionary.operation_id));
+    }
+    let Ok(use_as_dictionary) = HeaderValue::from_str(&use_as_dictionary) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    (
+        [
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/octet-stream"),
+            ),
+            (USE_AS_DICTIONARY, use_as_dictionary),
+            (
+                CACHE_CONTROL,
+                HeaderValue::from_str(&format!(
+                    "public, max-age={}",
+                    dictionaries.config().rotation_interval.as_secs()
+                ))
+                .expect("cache control header value is valid"),
+            ),
+        ],
+        dictionary.content.clone(),
+    )
+        .into_response()
+}
+
 fn internal_server_error<T>(err: T) -> Response
 where
     T: Display,
@@ -23,6 +23,14 @@ impl BrotliEncoder {
         Self { state }
     }
 
+    pub(crate) fn with_dictionary(params: BrotliEncoderParams, dictionary: &[u8]) -> Self {
+        let mut encoder = Self::new(params);
+        encoder
+            .state
+            .set_custom_dictionary(dictionary.len(), dictionary);
+        encoder
+    }
+
     fn encode(
         &mut self,
         input: &mut PartialBuffer<impl AsRef<[u8]>>,
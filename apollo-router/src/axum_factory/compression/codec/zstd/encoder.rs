@@ -20,6 +20,12 @@ impl ZstdEncoder {
             encoder: Unshared::new(Encoder::new(level).unwrap()),
         }
     }
+
+    pub(crate) fn with_dictionary(level: i32, dictionary: &[u8]) -> Result<Self> {
+        Ok(Self {
+            encoder: Unshared::new(Encoder::with_dictionary(level, dictionary)?),
+        })
+    }
 }
 
 impl Encode for ZstdEncoder {
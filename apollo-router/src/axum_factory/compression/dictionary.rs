@@ -0,0 +1,461 @@
+//! Compression dictionaries trained from the responses of persisted operations
+//!
+//! This follows the Compression Dictionary Transport specification: the dictionary of an
+//! operation is served with a `Use-As-Dictionary` header, and responses of the operation link to
+//! it. Clients then advertise the SHA-256 hash of the dictionary they hold in the
+//! `Available-Dictionary` header, and receive responses compressed against it.
+//!
+//! Dictionaries hold parts of the responses they are trained from and are served without
+//! authentication, so only the operations listed as public are sampled.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bytes::Bytes;
+use bytes::BytesMut;
+use http::HeaderMap;
+use http::HeaderName;
+use lru::LruCache;
+use parking_lot::Mutex;
+use sha2::Digest;
+use sha2::Sha256;
+use tokio::sync::Semaphore;
+use tower::BoxError;
+
+use crate::configuration::PersistedQueriesCompressionDictionaries;
+
+pub(crate) const AVAILABLE_DICTIONARY: HeaderName = HeaderName::from_static("available-dictionary");
+pub(crate) const USE_AS_DICTIONARY: HeaderName = HeaderName::from_static("use-as-dictionary");
+
+/// Prefix of the `dcz` (dictionary-compressed zstd) streams, followed by the dictionary hash
+pub(crate) const DCZ_MAGIC: [u8; 8] = [0x5e, 0x2a, 0x4d, 0x18, 0x20, 0x00, 0x00, 0x00];
+/// Prefix of the `dcb` (dictionary-compressed brotli) streams, followed by the dictionary hash
+pub(crate) const DCB_MAGIC: [u8; 4] = [0xff, 0x44, 0x43, 0x42];
+
+/// A raw dictionary, usable as a prefix by both zstd and brotli
+#[derive(Debug)]
+pub(crate) struct Dictionary {
+    pub(crate) operation_id: String,
+    pub(crate) hash: [u8; 32],
+    pub(crate) content: Bytes,
+}
+
+impl Dictionary {
+    pub(crate) fn new(operation_id: String, content: Bytes) -> Self {
+        Self {
+            operation_id,
+            hash: Sha256::digest(&content).into(),
+            content,
+        }
+    }
+
+    /// Name of the dictionary in the path it is served at
+    pub(crate) fn name(&self) -> String {
+        hex::encode(self.hash)
+    }
+
+    /// Header of the responses compressed against this dictionary
+    pub(crate) fn stream_header(&self, magic: &[u8]) -> Bytes {
+        let mut header = BytesMut::with_capacity(magic.len() + self.hash.len());
+        header.extend_from_slice(magic);
+        header.extend_from_slice(&self.hash);
+        header.freeze()
+    }
+}
+
+/// Hash of the dictionary advertised by the client, from the `Available-Dictionary` header
+///
+/// The header is a structured field byte sequence: the base64 encoded hash between colons.
+pub(crate) fn available_dictionary(headers: &HeaderMap) -> Option<[u8; 32]> {
+    let value = headers.get(AVAILABLE_DICTIONARY)?.to_str().ok()?.trim();
+    let encoded = value.strip_prefix(':')?.strip_suffix(':')?;
+    STANDARD.decode(encoded).ok()?.try_into().ok()
+}
+
+#[derive(Default)]
+struct OperationDictionaries {
+    current: Option<Arc<Dictionary>>,
+    /// Kept after a rotation for the clients still holding it
+    previous: Option<Arc<Dictionary>>,
+    samples: Vec<Bytes>,
+    /// When the last training of this operation ended, successfully or not
+    trained_at: Option<Instant>,
+    training: bool,
+}
+
+impl OperationDictionaries {
+    fn pending_bytes(&self) -> usize {
+        self.samples.iter().map(Bytes::len).sum()
+    }
+}
+
+struct State {
+    /// Operations are evicted when they are the least recently used, once `max_operations` is
+    /// reached
+    operations: LruCache<String, OperationDictionaries>,
+    by_hash: HashMap<[u8; 32], Arc<Dictionary>>,
+    sampled_bytes: usize,
+}
+
+impl State {
+    /// Bytes of the pending samples of the operations other than `operation_id`, which can be
+    /// dropped to sample `operation_id`
+    fn reclaimable_bytes(&self, operation_id: &str) -> usize {
+        self.operations
+            .iter()
+            .filter(|(id, _)| *id != operation_id)
+            .map(|(_, operation)| operation.pending_bytes())
+            .sum()
+    }
+
+    /// Drops the pending samples of the least recently used operations until `size` more bytes
+    /// can be sampled for `operation_id`
+    fn reclaim(&mut self, operation_id: &str, size: usize, max_sampled_bytes: usize) {
+        let State {
+            operations,
+            sampled_bytes,
+            ..
+        } = self;
+        for (id, operation) in operations.iter_mut().rev() {
+            if *sampled_bytes + size <= max_sampled_bytes {
+                return;
+            }
+            if id != operation_id {
+                *sampled_bytes -= operation.pending_bytes();
+                operation.samples.clear();
+            }
+        }
+    }
+
+    /// Releases the dictionaries and samples of an evicted operation
+    fn forget(&mut self, operation: OperationDictionaries) {
+        self.sampled_bytes -= operation.pending_bytes();
+        for dictionary in [operation.current, operation.previous]
+            .into_iter()
+            .flatten()
+        {
+            self.by_hash.remove(&dictionary.hash);
+        }
+    }
+}
+
+/// Samples the responses of public persisted operations and trains their dictionaries
+///
+/// Memory is bounded by the number of operations, the size of dictionaries and the total size of
+/// the samples. Dictionaries are trained one at a time on the blocking thread pool.
+pub(crate) struct CompressionDictionaries {
+    config: PersistedQueriesCompressionDictionaries,
+    public_operations: HashSet<String>,
+    state: Mutex<State>,
+    training: Semaphore,
+}
+
+impl CompressionDictionaries {
+    pub(crate) fn new(config: PersistedQueriesCompressionDictionaries) -> Self {
+        if config.public_operations.is_empty() {
+            tracing::warn!(
+                "compression dictionaries are enabled but no operation is listed in `persisted_queries.experimental_compression_dictionaries.public_operations`, no dictionary will be trained"
+            );
+        }
+        Self {
+            public_operations: config.public_operations.iter().cloned().collect(),
+            state: Mutex::new(State {
+                operations: LruCache::new(config.max_operations),
+                by_hash: Default::default(),
+                sampled_bytes: 0,
+            }),
+            config,
+            training: Semaphore::new(1),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &PersistedQueriesCompressionDictionaries {
+        &self.config
+    }
+
+    /// The dictionary with this hash, if it is still served
+    pub(crate) fn get(&self, hash: &[u8; 32]) -> Option<Arc<Dictionary>> {
+        self.state.lock().by_hash.get(hash).cloned()
+    }
+
+    /// The current dictionary of an operation
+    pub(crate) fn for_operation(&self, operation_id: &str) -> Option<Arc<Dictionary>> {
+        self.state
+            .lock()
+            .operations
+            .get(operation_id)
+            .and_then(|operation| operation.current.clone())
+    }
+
+    /// Whether a response of this size should be sampled for the operation
+    pub(crate) fn wants_sample(&self, operation_id: &str, size: usize) -> bool {
+        self.wants_sample_locked(&self.state.lock(), operation_id, size)
+    }
+
+    fn wants_sample_locked(&self, state: &State, operation_id: &str, size: usize) -> bool {
+        if size == 0
+            || size as u64 > self.config.max_sample_size.as_u64()
+            || !self.public_operations.contains(operation_id)
+            || (state.sampled_bytes - state.reclaimable_bytes(operation_id) + size) as u64
+                > self.config.max_sampled_bytes.as_u64()
+        {
+            return false;
+        }
+        match state.operations.peek(operation_id) {
+            None => true,
+            Some(operation) => {
+                !operation.training
+                    && operation.samples.len() < self.config.samples
+                    && operation
+                        .trained_at
+                        .is_none_or(|at| at.elapsed() >= self.config.rotation_interval)
+            }
+        }
+    }
+
+    /// Adds a response to the samples of the operation, and starts training its dictionary once
+    /// there are enough samples
+    pub(crate) fn add_sample(self: &Arc<Self>, operation_id: &str, sample: Bytes) {
+        let mut state = self.state.lock();
+        if !self.wants_sample_locked(&state, operation_id, sample.len()) {
+            return;
+        }
+        state.reclaim(
+            operation_id,
+            sample.len(),
+            self.config.max_sampled_bytes.as_u64() as usize,
+        );
+        state.sampled_bytes += sample.len();
+        if !state.operations.contains(operation_id)
+            && let Some((_, evicted)) = state
+                .operations
+                .push(operation_id.to_string(), Default::default())
+        {
+            state.forget(evicted);
+        }
+        let Some(operation) = state.operations.get_mut(operation_id) else {
+            return;
+        };
+        operation.samples.push(sample);
+        if operation.samples.len() < self.config.samples {
+            return;
+        }
+
+        operation.training = true;
+        let samples = std::mem::take(&mut operation.samples);
+        drop(state);
+        tokio::task::spawn(self.clone().train(operation_id.to_string(), samples));
+    }
+
+    async fn train(self: Arc<Self>, operation_id: String, samples: Vec<Bytes>) {
+        let sampled_bytes: usize = samples.iter().map(Bytes::len).sum();
+        let max_size = self.config.max_dictionary_size.as_u64() as usize;
+        let result = match self.training.acquire().await {
+            Ok(_permit) => {
+                tokio::task::spawn_blocking(move || train_dictionary(&samples, max_size))
+                    .await
+                    .map_err(BoxError::from)
+                    .and_then(|result| result)
+            }
+            Err(err) => Err(err.into()),
+        };
+
+        let mut state = self.state.lock();
+        state.sampled_bytes -= sampled_bytes;
+        let State {
+            operations,
+            by_hash,
+            ..
+        } = &mut *state;
+        // the operation may have been evicted during the training
+        let Some(operation) = operations.peek_mut(&operation_id) else {
+            return;
+        };
+        operation.training = false;
+        operation.trained_at = Some(Instant::now());
+        match result {
+            Ok(content) => {
+                let dictionary = Arc::new(Dictionary::new(operation_id, content));
+                tracing::debug!(
+                    "trained a {} bytes compression dictionary for the persisted query `{}`",
+                    dictionary.content.len(),
+                    dictionary.operation_id
+                );
+                if let Some(previous) = operation.previous.take() {
+                    by_hash.remove(&previous.hash);
+                }
+                operation.previous = operation.current.replace(dictionary.clone());
+                by_hash.insert(dictionary.hash, dictionary);
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "could not train a compression dictionary for the persisted query `{operation_id}`: {err}"
+                );
+            }
+        }
+    }
+}
+
+/// Trains a zstd dictionary and keeps its content, which zstd and brotli both use as a raw prefix
+fn train_dictionary(samples: &[Bytes], max_size: usize) -> Result<Bytes, BoxError> {
+    use zstd_safe::zstd_sys::ZDICT_getDictHeaderSize;
+    use zstd_safe::zstd_sys::ZDICT_isError;
+
+    let dictionary = zstd::dict::from_samples(samples, max_size)?;
+    // SAFETY: the pointer and length describe the `dictionary` buffer, which is only read
+    let header_size =
+        unsafe { ZDICT_getDictHeaderSize(dictionary.as_ptr().cast(), dictionary.len()) };
+    // SAFETY: this only inspects the returned code
+    if unsafe { ZDICT_isError(header_size) } != 0 || header_size >= dictionary.len() {
+        return Err("the trained dictionary has no content".into());
+    }
+    Ok(Bytes::copy_from_slice(&dictionary[header_size..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    use bytesize::ByteSize;
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn sample(index: usize) -> Bytes {
+        let products: Vec<_> = (0..20)
+            .map(|product| {
+                serde_json::json!({
+                    "__typename": "Product",
+                    "id": format!("product-{index}-{product}"),
+                    "name": format!("Product number {}", index * product),
+                    "price": { "amount": index * 100 + product, "currency": "EUR" },
+                    "reviews": [{ "author": { "name": "someone" }, "rating": product % 5 }]
+                })
+            })
+            .collect();
+        Bytes::from(
+            serde_json::to_vec(&serde_json::json!({ "data": { "products": products } })).unwrap(),
+        )
+    }
+
+    fn dictionaries(samples: usize) -> Arc<CompressionDictionaries> {
+        Arc::new(CompressionDictionaries::new(
+            PersistedQueriesCompressionDictionaries {
+                enabled: true,
+                public_operations: vec!["op".to_string(), "other".to_string()],
+                samples,
+                max_dictionary_size: ByteSize::kib(4),
+                max_operations: NonZeroUsize::MIN,
+                ..Default::default()
+            },
+        ))
+    }
+
+    async fn trained(operation_id: &str) -> (Arc<CompressionDictionaries>, Arc<Dictionary>) {
+        let dictionaries = dictionaries(32);
+        for index in 0..32 {
+            dictionaries.add_sample(operation_id, sample(index));
+        }
+        for _ in 0..100 {
+            if let Some(dictionary) = dictionaries.for_operation(operation_id) {
+                return (dictionaries, dictionary);
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("the dictionary was not trained");
+    }
+
+    #[tokio::test]
+    async fn it_trains_a_dictionary_per_operation() {
+        let (dictionaries, dictionary) = trained("op").await;
+        assert_eq!(dictionary.operation_id, "op");
+        assert!(dictionary.content.len() <= 4096);
+        assert!(dictionaries.get(&dictionary.hash).is_some());
+        // samples are released once the dictionary is trained
+        assert_eq!(dictionaries.state.lock().sampled_bytes, 0);
+        // the operation is not sampled again before the rotation interval
+        assert!(!dictionaries.wants_sample("op", 100));
+    }
+
+    #[test]
+    fn it_only_samples_public_operations() {
+        let dictionaries = dictionaries(1000);
+        assert!(dictionaries.wants_sample("op", 100));
+        assert!(!dictionaries.wants_sample("private", 100));
+
+        dictionaries.add_sample("private", sample(0));
+        assert_eq!(dictionaries.state.lock().sampled_bytes, 0);
+        assert!(dictionaries.state.lock().operations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_evicts_the_least_recently_used_operation() {
+        let (dictionaries, dictionary) = trained("op").await;
+        // only one operation is tracked, the trained one is evicted to sample another one
+        assert!(dictionaries.wants_sample("other", 100));
+        dictionaries.add_sample("other", sample(0));
+        assert!(dictionaries.for_operation("op").is_none());
+        assert!(dictionaries.get(&dictionary.hash).is_none());
+        assert_eq!(dictionaries.state.lock().sampled_bytes, sample(0).len());
+    }
+
+    #[test]
+    fn it_drops_the_pending_samples_of_the_least_recently_used_operation() {
+        let size = sample(0).len();
+        let dictionaries = Arc::new(CompressionDictionaries::new(
+            PersistedQueriesCompressionDictionaries {
+                enabled: true,
+                public_operations: vec!["op".to_string(), "other".to_string()],
+                samples: 1000,
+                max_sampled_bytes: ByteSize::b(size as u64 * 2),
+                ..Default::default()
+            },
+        ));
+        dictionaries.add_sample("op", sample(0));
+        dictionaries.add_sample("op", sample(0));
+        assert!(!dictionaries.wants_sample("op", size));
+
+        // the samples of `op` are dropped to sample `other`, its entry is kept
+        assert!(dictionaries.wants_sample("other", size));
+        dictionaries.add_sample("other", sample(0));
+        let state = dictionaries.state.lock();
+        assert_eq!(state.sampled_bytes, size);
+        assert!(state.operations.peek("op").unwrap().samples.is_empty());
+        assert_eq!(state.operations.peek("other").unwrap().samples.len(), 1);
+    }
+
+    #[test]
+    fn it_bounds_sampled_bytes() {
+        let dictionaries = dictionaries(1000);
+        let too_large = ByteSize::kib(128).as_u64() as usize + 1;
+        assert!(!dictionaries.wants_sample("op", too_large));
+
+        dictionaries.state.lock().sampled_bytes = ByteSize::mib(16).as_u64() as usize - 10;
+        assert!(dictionaries.wants_sample("op", 10));
+        assert!(!dictionaries.wants_sample("op", 11));
+    }
+
+    #[test]
+    fn it_parses_available_dictionary() {
+        let hash = [7u8; 32];
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AVAILABLE_DICTIONARY,
+            HeaderValue::from_str(&format!(":{}:", STANDARD.encode(hash))).unwrap(),
+        );
+        assert_eq!(available_dictionary(&headers), Some(hash));
+
+        headers.insert(AVAILABLE_DICTIONARY, HeaderValue::from_static(":AAAA:"));
+        assert_eq!(available_dictionary(&headers), None);
+        headers.insert(
+            AVAILABLE_DICTIONARY,
+            HeaderValue::from_str(&STANDARD.encode(hash)).unwrap(),
+        );
+        assert_eq!(available_dictionary(&headers), None);
+    }
+}
@@ -14,10 +14,14 @@ use self::codec::DeflateEncoder;
 use self::codec::Encode;
 use self::codec::GzipEncoder;
 use self::codec::ZstdEncoder;
+use self::dictionary::DCB_MAGIC;
+use self::dictionary::DCZ_MAGIC;
+use self::dictionary::Dictionary;
 use self::util::PartialBuffer;
 use crate::services::router::body::RouterBody;
 
 pub(crate) mod codec;
+pub(crate) mod dictionary;
 pub(crate) mod unshared;
 pub(crate) mod util;
 
@@ -28,6 +32,19 @@ pub(crate) enum Compressor {
     Gzip(GzipEncoder),
     Brotli(Box<BrotliEncoder>),
     Zstd(ZstdEncoder),
+    /// zstd against a shared dictionary, the stream starting with the given header
+    DictionaryZstd(Bytes, ZstdEncoder),
+    /// brotli against a shared dictionary, the stream starting with the given header
+    DictionaryBrotli(Bytes, Box<BrotliEncoder>),
+}
+
+fn brotli_params() -> BrotliEncoderParams {
+    BrotliEncoderParams {
+        // '4' is a reasonable setting for 'fast'
+        // https://github.com/dropbox/rust-brotli/issues/93
+        quality: 4,
+        ..BrotliEncoderParams::default()
+    }
 }
 
 impl Compressor {
@@ -46,12 +63,7 @@ impl Compressor {
                 }
                 "br" => {
                     return Some(Compressor::Brotli(Box::new(BrotliEncoder::new(
-                        BrotliEncoderParams {
-                            // '4' is a reasonable setting for 'fast'
-                            // https://github.com/dropbox/rust-brotli/issues/93
-                            quality: 4,
-                            ..BrotliEncoderParams::default()
-                        },
+                        brotli_params(),
                     ))));
                 }
                 "zstd" => {
@@ -63,12 +75,63 @@ impl Compressor {
         None
     }
 
+    /// Compressor using a dictionary advertised by the client, for the first of the `dcz` and
+    /// `dcb` encodings it accepts
+    pub(crate) fn with_dictionary<'a, It>(it: It, dictionary: &Dictionary) -> Option<Self>
+    where
+        It: Iterator<Item = &'a str>,
+        It: 'a,
+    {
+        for s in it {
+            match s {
+                "dcz" => {
+                    match ZstdEncoder::with_dictionary(
+                        zstd_safe::min_c_level(),
+                        &dictionary.content,
+                    ) {
+                        Ok(encoder) => {
+                            return Some(Compressor::DictionaryZstd(
+                                dictionary.stream_header(&DCZ_MAGIC),
+                                encoder,
+                            ));
+                        }
+                        Err(err) => {
+                            tracing::debug!("could not load the zstd compression dictionary: {err}")
+                        }
+                    }
+                }
+                "dcb" => {
+                    return Some(Compressor::DictionaryBrotli(
+                        dictionary.stream_header(&DCB_MAGIC),
+                        Box::new(BrotliEncoder::with_dictionary(
+                            brotli_params(),
+                            &dictionary.content,
+                        )),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
     pub(crate) fn content_encoding(&self) -> &'static str {
         match self {
             Compressor::Deflate(_) => "deflate",
             Compressor::Gzip(_) => "gzip",
             Compressor::Brotli(_) => "br",
             Compressor::Zstd(_) => "zstd",
+            Compressor::DictionaryZstd(..) => "dcz",
+            Compressor::DictionaryBrotli(..) => "dcb",
+        }
+    }
+
+    fn stream_header(&self) -> Option<Bytes> {
+        match self {
+            Compressor::DictionaryZstd(header, _) | Compressor::DictionaryBrotli(header, _) => {
+                Some(header.clone())
+            }
+            _ => None,
         }
     }
 
@@ -82,6 +145,12 @@ where {
         let mut stream = http_body_util::BodyDataStream::new(body);
         tokio::task::spawn(
             async move {
+                if let Some(header) = self.stream_header()
+                    && (tx.send(Ok(header)).await).is_err()
+                {
+                    return;
+                }
+
                 while let Some(data) = stream.next().await {
                     match data {
                         Err(e) => {
@@ -183,6 +252,8 @@ impl Encode for Compressor {
             Compressor::Gzip(e) => e.encode(input, output),
             Compressor::Brotli(e) => e.encode(input, output),
             Compressor::Zstd(e) => e.encode(input, output),
+            Compressor::DictionaryZstd(_, e) => e.encode(input, output),
+            Compressor::DictionaryBrotli(_, e) => e.encode(input, output),
         }
     }
 
@@ -195,6 +266,8 @@ impl Encode for Compressor {
             Compressor::Gzip(e) => e.flush(output),
             Compressor::Brotli(e) => e.flush(output),
             Compressor::Zstd(e) => e.flush(output),
+            Compressor::DictionaryZstd(_, e) => e.flush(output),
+            Compressor::DictionaryBrotli(_, e) => e.flush(output),
         }
     }
 
@@ -207,6 +280,8 @@ impl Encode for Compressor {
             Compressor::Gzip(e) => e.finish(output),
             Compressor::Brotli(e) => e.finish(output),
             Compressor::Zstd(e) => e.finish(output),
+            Compressor::DictionaryZstd(_, e) => e.finish(output),
+            Compressor::DictionaryBrotli(_, e) => e.finish(output),
         }
     }
 }
@@ -279,6 +354,67 @@ mod tests {
         let _ = stream.next().await.unwrap().unwrap();
     }
 
+    async fn compress_with_dictionary(encoding: &str, dictionary: &Dictionary) -> Vec<u8> {
+        let compressor = Compressor::with_dictionary([encoding].into_iter(), dictionary).unwrap();
+        assert_eq!(compressor.content_encoding(), encoding);
+        let body: RouterBody = body::from_bytes(DICTIONARY_RESPONSE);
+
+        let mut compressed = Vec::new();
+        let mut stream = compressor.process(body);
+        while let Some(buf) = stream.next().await {
+            compressed.extend_from_slice(&buf.unwrap());
+        }
+        compressed
+    }
+
+    const DICTIONARY_RESPONSE: &str = r#"{"data":{"allProducts":[{"sku":"federation","id":"apollo-federation"},{"sku":"studio","id":"apollo-studio"}]}}"#;
+
+    fn dictionary() -> Dictionary {
+        Dictionary::new(
+            "op".to_string(),
+            Bytes::from_static(
+                br#"{"data":{"allProducts":[{"sku":"federation","id":"apollo-federation"},{"sku":"client","id":"apollo-client"}]}}"#,
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn zstd_with_dictionary() {
+        let dictionary = dictionary();
+        let compressed = compress_with_dictionary("dcz", &dictionary).await;
+
+        assert_eq!(compressed[..8], DCZ_MAGIC);
+        assert_eq!(compressed[8..40], dictionary.hash);
+        let mut decoder =
+            zstd::stream::read::Decoder::with_dictionary(&compressed[40..], &dictionary.content)
+                .unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut decoder, &mut response).unwrap();
+        assert_eq!(response, DICTIONARY_RESPONSE);
+    }
+
+    #[tokio::test]
+    async fn brotli_with_dictionary() {
+        use brotli::Allocator;
+        use brotli::SliceWrapperMut;
+        use brotli::enc::StandardAlloc;
+
+        let dictionary = dictionary();
+        let compressed = compress_with_dictionary("dcb", &dictionary).await;
+
+        assert_eq!(compressed[..4], DCB_MAGIC);
+        assert_eq!(compressed[4..36], dictionary.hash);
+        let mut dict = <StandardAlloc as Allocator<u8>>::alloc_cell(
+            &mut StandardAlloc::default(),
+            dictionary.content.len(),
+        );
+        dict.slice_mut().copy_from_slice(&dictionary.content);
+        let mut decoder = brotli::Decompressor::new_with_custom_dict(&compressed[36..], 4096, dict);
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut decoder, &mut response).unwrap();
+        assert_eq!(response, DICTIONARY_RESPONSE);
+    }
+
     #[tokio::test]
     async fn flush() {
         let primary_response = r#"
@@ -318,6 +318,12 @@ impl ValueType for crate::graphql::Response {
     }
 }
 
+impl ValueType for bool {
+    fn estimated_size(&self) -> Option<usize> {
+        Some(std::mem::size_of::<bool>())
+    }
+}
+
 impl ValueType for usize {
     fn estimated_size(&self) -> Option<usize> {
         Some(std::mem::size_of::<usize>())
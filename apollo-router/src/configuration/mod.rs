@@ -9,6 +9,7 @@ use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::num::NonZeroU32;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -20,9 +21,14 @@ use itertools::Either;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 pub(crate) use persisted_queries::PersistedQueries;
+pub(crate) use persisted_queries::PersistedQueriesCompressionDictionaries;
+pub(crate) use persisted_queries::PersistedQueriesManifestSignature;
 pub(crate) use persisted_queries::PersistedQueriesPrewarmQueryPlanCache;
-#[cfg(test)]
+pub(crate) use persisted_queries::PersistedQueriesRemoteManifest;
 pub(crate) use persisted_queries::PersistedQueriesSafelist;
+#[cfg(test)]
+pub(crate) use persisted_queries::PersistedQueriesSafelistScope;
+pub(crate) use persisted_queries::PersistedQueriesSafelistScopeSelector;
 use regex::Regex;
 use rustls::ServerConfig;
 use rustls::pki_types::CertificateDer;
@@ -892,6 +898,10 @@ pub(crate) struct Apq {
     pub(crate) router: Router,
 
     pub(crate) subgraph: SubgraphConfiguration<SubgraphApq>,
+
+    /// Records which subgraphs know which query hashes, so the router only sends hash-only
+    /// requests to subgraphs that registered the query
+    pub(crate) subgraph_registry: Option<SubgraphApqRegistry>,
 }
 
 #[cfg(test)]
@@ -914,6 +924,14 @@ pub(crate) struct SubgraphApq {
     pub(crate) enabled: bool,
 }
 
+/// Registry of the query hashes known by subgraphs
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct SubgraphApqRegistry {
+    /// Storage of the registry. Configure Redis to share it between router instances
+    pub(crate) cache: Cache,
+}
+
 fn default_apq() -> bool {
     true
 }
@@ -924,6 +942,7 @@ impl Default for Apq {
             enabled: default_apq(),
             router: Default::default(),
             subgraph: Default::default(),
+            subgraph_registry: None,
         }
     }
 }
@@ -971,6 +990,11 @@ pub(crate) struct QueryPlanning {
     ///
     /// See [`CooperativeCancellation`] for more details.
     pub(crate) experimental_cooperative_cancellation: CooperativeCancellation,
+
+    /// Path to a plan bundle generated by the `apollo-federation plan-bundle` command. Its query
+    /// plans are loaded in the cache when the router starts, instead of planning the operations.
+    /// The bundle is ignored if it was not computed for the schema the router runs.
+    pub(crate) plan_bundle: Option<PathBuf>,
 }
 
 #[buildstructor::buildstructor]
@@ -984,6 +1008,7 @@ impl QueryPlanning {
         experimental_paths_limit: Option<u32>,
         experimental_reuse_query_plans: Option<bool>,
         experimental_cooperative_cancellation: Option<CooperativeCancellation>,
+        plan_bundle: Option<PathBuf>,
     ) -> Self {
         Self {
             cache: cache.unwrap_or_default(),
@@ -993,6 +1018,7 @@ impl QueryPlanning {
             experimental_reuse_query_plans: experimental_reuse_query_plans.unwrap_or_default(),
             experimental_cooperative_cancellation: experimental_cooperative_cancellation
                 .unwrap_or_default(),
+            plan_bundle,
         }
     }
 }
@@ -1005,6 +1031,28 @@ pub(crate) struct QueryPlanCache {
     pub(crate) in_memory: InMemoryCache,
     /// Configures and activates the Redis cache
     pub(crate) redis: Option<QueryPlanRedisCache>,
+    /// Configures and activates the disk cache, which keeps query plans across restarts
+    pub(crate) disk: Option<QueryPlanDiskCache>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// Disk cache configuration
+pub(crate) struct QueryPlanDiskCache {
+    /// Directory where query plans are written
+    pub(crate) path: PathBuf,
+
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_query_plan_disk_cache_interval"
+    )]
+    #[schemars(with = "Option<String>", default)]
+    /// Interval between two writes of the in memory cache to disk. The cache is also written when the router shuts down (default: 5m)
+    pub(crate) interval: Duration,
+}
+
+fn default_query_plan_disk_cache_interval() -> Duration {
+    Duration::from_secs(5 * 60)
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
@@ -1,3 +1,8 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use bytesize::ByteSize;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
@@ -24,6 +29,15 @@ pub struct PersistedQueries {
 
     /// Enables hot reloading of the local persisted query manifests
     pub hot_reload: bool,
+
+    /// Verifies the detached signature of each local persisted query manifest before applying it
+    pub manifest_signature: Option<PersistedQueriesManifestSignature>,
+
+    /// Polls the persisted query manifest from an HTTP(S) URL or an OCI registry instead of GraphOS
+    pub remote_manifest: Option<PersistedQueriesRemoteManifest>,
+
+    /// Experimental compression of persisted query responses with dictionaries trained per operation
+    pub experimental_compression_dictionaries: PersistedQueriesCompressionDictionaries,
 }
 
 #[cfg(test)]
@@ -37,6 +51,9 @@ impl PersistedQueries {
         local_manifests: Option<Vec<String>>,
         hot_reload: Option<bool>,
         experimental_prewarm_query_plan_cache: Option<PersistedQueriesPrewarmQueryPlanCache>,
+        manifest_signature: Option<PersistedQueriesManifestSignature>,
+        remote_manifest: Option<PersistedQueriesRemoteManifest>,
+        experimental_compression_dictionaries: Option<PersistedQueriesCompressionDictionaries>,
     ) -> Self {
         Self {
             enabled: enabled.unwrap_or_else(default_pq),
@@ -46,6 +63,10 @@ impl PersistedQueries {
             experimental_prewarm_query_plan_cache: experimental_prewarm_query_plan_cache
                 .unwrap_or_default(),
             hot_reload: hot_reload.unwrap_or_default(),
+            manifest_signature,
+            remote_manifest,
+            experimental_compression_dictionaries: experimental_compression_dictionaries
+                .unwrap_or_default(),
         }
     }
 }
@@ -59,16 +80,169 @@ pub struct PersistedQueriesSafelist {
 
     /// Enabling this field configures the router to reject any request that does not include the persisted query ID
     pub require_id: bool,
+
+    /// How the client of a request is identified to select its safelist scope (default: client_name)
+    pub scope_selector: PersistedQueriesSafelistScopeSelector,
+
+    /// Restricts clients to the operations of their own manifests. Clients that are not bound to
+    /// a scope use the persisted query list of the graph.
+    pub scopes: Vec<PersistedQueriesSafelistScope>,
+}
+
+/// Identity of the client of a request, used to select its safelist scope
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum PersistedQueriesSafelistScopeSelector {
+    /// The client name used to look up persisted queries, from the
+    /// `apollo_persisted_queries::client_name` context entry or the client name header
+    #[default]
+    ClientName,
+    /// The value of a request header
+    Header(String),
+}
+
+/// Persisted query manifests bound to a set of clients
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PersistedQueriesSafelistScope {
+    /// Name of the scope, reported in telemetry
+    pub name: String,
+
+    /// Clients bound to this scope, matched against the identity given by `scope_selector`
+    pub clients: Vec<String>,
+
+    /// Paths to the persisted query manifests containing the operations allowed for these clients
+    pub manifests: Vec<String>,
 }
 
 #[cfg(test)]
 #[buildstructor::buildstructor]
 impl PersistedQueriesSafelist {
     #[builder]
-    pub(crate) fn new(enabled: Option<bool>, require_id: Option<bool>) -> Self {
+    pub(crate) fn new(
+        enabled: Option<bool>,
+        require_id: Option<bool>,
+        scope_selector: Option<PersistedQueriesSafelistScopeSelector>,
+        scopes: Option<Vec<PersistedQueriesSafelistScope>>,
+    ) -> Self {
         Self {
             enabled: enabled.unwrap_or_else(default_safelist),
             require_id: require_id.unwrap_or_else(default_require_id),
+            scope_selector: scope_selector.unwrap_or_default(),
+            scopes: scopes.unwrap_or_default(),
+        }
+    }
+}
+
+/// Persisted Queries (PQ) manifest signature configuration
+///
+/// The signature of a manifest is read from the file at the manifest path with a `.sig` suffix. It
+/// is either the base64 encoded Ed25519 signature of the manifest, or a compact JWS with a detached
+/// payload.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PersistedQueriesManifestSignature {
+    /// Paths to the PEM encoded public keys (Ed25519, EC or RSA) trusted to sign manifests
+    pub public_keys: Vec<String>,
+}
+
+/// Persisted Queries (PQ) remote manifest configuration
+///
+/// Exactly one of `url` and `oci_reference` must be set.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PersistedQueriesRemoteManifest {
+    /// HTTP(S) URL of the manifest
+    #[serde(default)]
+    pub url: Option<url::Url>,
+
+    /// Headers sent when fetching the manifest from `url`, for example to authenticate
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// OCI reference of an artifact containing the manifest, in a layer of media type
+    /// `application/apollo.persisted-query-manifest`
+    #[serde(default)]
+    pub oci_reference: Option<String>,
+
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_remote_manifest_poll_interval"
+    )]
+    #[schemars(with = "Option<String>", default)]
+    /// Interval between two polls of the manifest (default: 30s)
+    pub poll_interval: Duration,
+
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_remote_manifest_timeout"
+    )]
+    #[schemars(with = "Option<String>", default)]
+    /// Timeout of each request fetching the manifest from `url` (default: 30s)
+    pub timeout: Duration,
+}
+
+/// Persisted Queries (PQ) compression dictionaries configuration
+///
+/// Responses of persisted operations are sampled to train a compression dictionary per operation,
+/// served following the Compression Dictionary Transport specification. Clients advertising a
+/// dictionary with the `Available-Dictionary` header receive responses compressed against it with
+/// the `dcz` (zstd) or `dcb` (brotli) content encodings.
+///
+/// Dictionaries are trained from the responses of all clients and served without authentication,
+/// so only operations whose responses hold no private data may be listed in `public_operations`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub struct PersistedQueriesCompressionDictionaries {
+    /// Enables compression dictionaries (disabled by default)
+    pub enabled: bool,
+
+    /// HTTP path prefix on which dictionaries are served (default: /compression-dictionaries)
+    pub path: String,
+
+    /// IDs of the persisted queries whose responses are sampled to train dictionaries (default: none)
+    ///
+    /// Dictionaries contain parts of the sampled responses and are served to any client, only list
+    /// operations whose responses are the same for all users and hold no private data.
+    pub public_operations: Vec<String>,
+
+    /// Number of responses sampled to train the dictionary of an operation (default: 64)
+    pub samples: usize,
+
+    /// Responses larger than this are not sampled (default: 128KiB)
+    #[schemars(with = "String")]
+    pub max_sample_size: ByteSize,
+
+    /// Maximum size of the samples held in memory across all operations (default: 16MiB)
+    #[schemars(with = "String")]
+    pub max_sampled_bytes: ByteSize,
+
+    /// Maximum size of a dictionary (default: 64KiB)
+    #[schemars(with = "String")]
+    pub max_dictionary_size: ByteSize,
+
+    /// Maximum number of operations with a dictionary, the least recently used operations are
+    /// evicted (default: 128)
+    pub max_operations: NonZeroUsize,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// Interval after which the dictionary of an operation is retrained from new samples (default: 24h)
+    pub rotation_interval: Duration,
+}
+
+impl Default for PersistedQueriesCompressionDictionaries {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "/compression-dictionaries".to_string(),
+            public_operations: Vec::new(),
+            samples: 64,
+            max_sample_size: ByteSize::kib(128),
+            max_sampled_bytes: ByteSize::mib(16),
+            max_dictionary_size: ByteSize::kib(64),
+            max_operations: NonZeroUsize::new(128).expect("not zero"),
+            rotation_interval: Duration::from_secs(24 * 60 * 60),
         }
     }
 }
@@ -92,6 +266,10 @@ impl Default for PersistedQueries {
             log_unknown: default_log_unknown(),
             local_manifests: None,
             hot_reload: false,
+            manifest_signature: None,
+            remote_manifest: None,
+            experimental_compression_dictionaries: PersistedQueriesCompressionDictionaries::default(
+            ),
             experimental_prewarm_query_plan_cache: PersistedQueriesPrewarmQueryPlanCache::default(),
         }
     }
@@ -102,6 +280,8 @@ impl Default for PersistedQueriesSafelist {
         Self {
             enabled: default_safelist(),
             require_id: default_require_id(),
+            scope_selector: PersistedQueriesSafelistScopeSelector::default(),
+            scopes: Vec::new(),
         }
     }
 }
@@ -130,3 +310,11 @@ const fn default_require_id() -> bool {
 const fn default_log_unknown() -> bool {
     false
 }
+
+const fn default_remote_manifest_poll_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+const fn default_remote_manifest_timeout() -> Duration {
+    Duration::from_secs(30)
+}
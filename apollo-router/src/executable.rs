@@ -31,6 +31,7 @@ use crate::configuration::schema::Mode;
 use crate::configuration::validate_yaml_configuration;
 use crate::metrics::meter_provider_internal;
 use crate::plugin::plugins;
+use crate::plugins::rhai;
 use crate::plugins::telemetry::reload::otel::init_telemetry;
 use crate::registry::OciConfig;
 use crate::registry::should_use_ssl;
@@ -66,6 +67,9 @@ const FORBIDDEN_OTEL_VARS: [&str; 3] = [
 enum Commands {
     /// Configuration subcommands.
     Config(ConfigSubcommandArgs),
+
+    /// Rhai script subcommands.
+    Rhai(RhaiSubcommandArgs),
 }
 
 #[derive(Args, Debug)]
@@ -102,6 +106,47 @@ enum ConfigSubcommand {
     Preview,
 }
 
+#[derive(Args, Debug)]
+struct RhaiSubcommandArgs {
+    /// Subcommands
+    #[clap(subcommand)]
+    command: RhaiSubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum RhaiSubcommand {
+    /// Compile Rhai scripts and register their service callbacks without starting the router.
+    Check(RhaiScriptArgs),
+    /// Run the `test_` functions defined in Rhai scripts.
+    Test(RhaiScriptArgs),
+}
+
+#[derive(Args, Debug)]
+struct RhaiScriptArgs {
+    /// The directory where Rhai scripts can be found. Defaults to `rhai`.
+    #[clap(long, value_parser)]
+    scripts: Option<PathBuf>,
+
+    /// The main entry point for Rhai script evaluation. Defaults to `main.rhai`.
+    #[clap(long)]
+    main: Option<String>,
+
+    /// Supergraph schema exposed to scripts as `Router.APOLLO_SDL`.
+    #[clap(long = "supergraph", value_parser)]
+    supergraph_path: Option<PathBuf>,
+}
+
+impl RhaiScriptArgs {
+    fn supergraph_sdl(&self) -> Result<String> {
+        Ok(self
+            .supergraph_path
+            .as_ref()
+            .map(std::fs::read_to_string)
+            .transpose()?
+            .unwrap_or_default())
+    }
+}
+
 /// Options for the router
 #[derive(Parser, Debug)]
 #[clap(name = "router", about = "Apollo federation router")]
@@ -460,6 +505,45 @@ impl Executable {
                 Discussed::new().print_preview();
                 Ok(())
             }
+            Some(Commands::Rhai(RhaiSubcommandArgs {
+                command: RhaiSubcommand::Check(args),
+            })) => {
+                let main = rhai::runner::check(
+                    args.scripts.clone(),
+                    args.main.clone(),
+                    &args.supergraph_sdl()?,
+                )
+                .map_err(|err| anyhow!(err))?;
+
+                println!("Rhai script at path {main:?} is valid!");
+
+                Ok(())
+            }
+            Some(Commands::Rhai(RhaiSubcommandArgs {
+                command: RhaiSubcommand::Test(args),
+            })) => {
+                let outcomes = rhai::runner::run_tests(
+                    args.scripts.clone(),
+                    args.main.clone(),
+                    &args.supergraph_sdl()?,
+                )
+                .map_err(|err| anyhow!(err))?;
+
+                for outcome in &outcomes {
+                    match &outcome.error {
+                        None => println!("test {} ... ok", outcome.name),
+                        Some(error) => println!("test {} ... FAILED: {error}", outcome.name),
+                    }
+                }
+                let failed = outcomes.iter().filter(|outcome| !outcome.passed()).count();
+                println!("\n{} passed; {failed} failed", outcomes.len() - failed);
+
+                if failed > 0 {
+                    Err(anyhow!("{failed} Rhai test(s) failed"))
+                } else {
+                    Ok(())
+                }
+            }
             None => Self::inner_start(shutdown, schema, config, license, opt).await,
         };
 
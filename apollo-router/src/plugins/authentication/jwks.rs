@@ -42,6 +42,8 @@ use super::Header;
 use super::Source;
 use crate::Context;
 use crate::plugins::authentication::error::AuthenticationError;
+use crate::plugins::telemetry::tracing::propagation::OutboundClient;
+use crate::plugins::telemetry::tracing::propagation::inject_trace_context;
 
 #[derive(Clone)]
 pub(super) struct JwksManager {
@@ -189,6 +191,14 @@ pub(super) async fn get_jwks(url: Url, headers: Vec<Header>) -> Option<JwkSet> {
             .get(url)
             .header(ACCEPT, APPLICATION_JSON.essence_str());
 
+        let mut trace_headers = HeaderMap::new();
+        inject_trace_context(
+            OutboundClient::Jwks,
+            &tracing::Span::current(),
+            &mut trace_headers,
+        );
+        builder = builder.headers(trace_headers);
+
         for header in headers.into_iter() {
             builder = builder.header(header.name, header.value);
         }
@@ -1,14 +1,17 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::hash_map::Entry;
 use std::sync::Arc;
 use std::task::Context;
 use std::task::Poll;
 
 use apollo_federation::connectors::runtime::http_json_transport::TransportRequest;
+use apollo_federation::connectors::runtime::http_json_transport::TransportResponse;
 use http::HeaderMap;
 use http::HeaderValue;
 use http::header::ACCEPT;
 use http::header::ACCEPT_ENCODING;
+use http::header::CACHE_CONTROL;
 use http::header::CONNECTION;
 use http::header::CONTENT_ENCODING;
 use http::header::CONTENT_LENGTH;
@@ -17,6 +20,7 @@ use http::header::HOST;
 use http::header::HeaderName;
 use http::header::PROXY_AUTHENTICATE;
 use http::header::PROXY_AUTHORIZATION;
+use http::header::SET_COOKIE;
 use http::header::TE;
 use http::header::TRAILER;
 use http::header::TRANSFER_ENCODING;
@@ -41,30 +45,42 @@ use crate::plugin::serde::deserialize_jsonpath;
 use crate::plugin::serde::deserialize_option_header_name;
 use crate::plugin::serde::deserialize_option_header_value;
 use crate::plugin::serde::deserialize_regex;
+use crate::plugins::response_cache::cache_control::CacheControl;
+use crate::plugins::telemetry::config_new::Selector;
+use crate::plugins::telemetry::config_new::Stage;
+use crate::plugins::telemetry::config_new::conditions::Condition;
+use crate::plugins::telemetry::config_new::connector::selectors::ConnectorSelector;
+use crate::plugins::telemetry::config_new::subgraph::selectors::SubgraphSelector;
 use crate::services::SubgraphRequest;
 use crate::services::connector;
 use crate::services::subgraph;
+use crate::services::supergraph;
 
 register_private_plugin!("apollo", "headers", Headers);
 
-#[derive(Clone, JsonSchema, Deserialize, Default)]
+#[derive(Clone, JsonSchema, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
-struct HeadersLocation {
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "HeadersLocation{T}")]
+struct HeadersLocation<T> {
     /// Propagate/Insert/Remove headers from request
-    request: Vec<Operation>,
-    // Propagate/Insert/Remove headers from response
-    // response: Option<Operation>
+    #[serde(default)]
+    request: Vec<Operation<T>>,
+    /// Propagate headers from responses back to the client
+    #[serde(default)]
+    response: Vec<ResponseOperation>,
 }
 
 #[derive(Clone, JsonSchema, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
-enum Operation {
-    Insert(Insert),
-    Remove(Remove),
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "Operation{T}")]
+enum Operation<T> {
+    Insert(Insert<T>),
+    Remove(Remove<T>),
     Propagate(Propagate),
 }
 
-schemar_fn!(remove_named, String, "Remove a header given a header name");
 schemar_fn!(
     remove_matching,
     String,
@@ -72,37 +88,57 @@ schemar_fn!(
 );
 
 #[derive(Clone, JsonSchema, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[serde(untagged, bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "Remove{T}")]
 /// Remove header
-enum Remove {
-    #[schemars(schema_with = "remove_named")]
-    #[serde(deserialize_with = "deserialize_header_name")]
+enum Remove<T> {
     /// Remove a header given a header name
-    Named(HeaderName),
+    Named {
+        /// The header name
+        #[schemars(with = "String")]
+        #[serde(deserialize_with = "deserialize_header_name")]
+        named: HeaderName,
 
-    #[schemars(schema_with = "remove_matching")]
-    #[serde(deserialize_with = "deserialize_regex")]
+        /// Only remove the header when this condition holds
+        #[serde(default = "Condition::empty::<T>")]
+        condition: Condition<T>,
+    },
     /// Remove a header given a regex matching header name
-    Matching(Regex),
+    Matching {
+        /// The regex on header name
+        #[schemars(schema_with = "remove_matching")]
+        #[serde(deserialize_with = "deserialize_regex")]
+        matching: Regex,
+
+        /// Only remove the headers when this condition holds
+        #[serde(default = "Condition::empty::<T>")]
+        condition: Condition<T>,
+    },
 }
 
 #[derive(Clone, JsonSchema, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
-#[serde(untagged)]
+#[serde(untagged, bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "Insert{T}")]
 /// Insert header
-enum Insert {
+enum Insert<T> {
     /// Insert static header
-    Static(InsertStatic),
+    Static(InsertStatic<T>),
     /// Insert header with a value coming from context key (works only for a string in the context)
-    FromContext(InsertFromContext),
+    FromContext(InsertFromContext<T>),
     /// Insert header with a value coming from body
-    FromBody(InsertFromBody),
+    FromBody(InsertFromBody<T>),
+    /// Insert header with a value coming from a selector
+    FromSelector(InsertFromSelector<T>),
 }
 
 #[derive(Clone, JsonSchema, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "InsertStatic{T}")]
 /// Insert static header
-struct InsertStatic {
+struct InsertStatic<T> {
     /// The name of the header
     #[schemars(with = "String")]
     #[serde(deserialize_with = "deserialize_header_name")]
@@ -112,24 +148,35 @@ struct InsertStatic {
     #[schemars(with = "String")]
     #[serde(deserialize_with = "deserialize_header_value")]
     value: HeaderValue,
+
+    /// Only insert the header when this condition holds
+    #[serde(default = "Condition::empty::<T>")]
+    condition: Condition<T>,
 }
 
 #[derive(Clone, JsonSchema, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "InsertFromContext{T}")]
 /// Insert header with a value coming from context key
-struct InsertFromContext {
+struct InsertFromContext<T> {
     #[schemars(with = "String")]
     #[serde(deserialize_with = "deserialize_header_name")]
     /// Specify header name
     name: HeaderName,
     /// Specify context key to fetch value
     from_context: String,
+    /// Only insert the header when this condition holds
+    #[serde(default = "Condition::empty::<T>")]
+    condition: Condition<T>,
 }
 
 #[derive(Clone, JsonSchema, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "InsertFromBody{T}")]
 /// Insert header with a value coming from body
-struct InsertFromBody {
+struct InsertFromBody<T> {
     /// The target header name
     #[schemars(with = "String")]
     #[serde(deserialize_with = "deserialize_header_name")]
@@ -144,6 +191,30 @@ struct InsertFromBody {
     #[schemars(with = "Option<String>", default)]
     #[serde(deserialize_with = "deserialize_option_header_value", default)]
     default: Option<HeaderValue>,
+
+    /// Only insert the header when this condition holds
+    #[serde(default = "Condition::empty::<T>")]
+    condition: Condition<T>,
+}
+
+#[derive(Clone, JsonSchema, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+#[schemars(rename = "InsertFromSelector{T}")]
+/// Insert header with a value coming from a selector
+struct InsertFromSelector<T> {
+    /// The target header name
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "deserialize_header_name")]
+    name: HeaderName,
+
+    /// The selector computing the header value. The header is not inserted if the selector
+    /// does not return a value.
+    from_selector: T,
+
+    /// Only insert the header when this condition holds
+    #[serde(default = "Condition::empty::<T>")]
+    condition: Condition<T>,
 }
 
 schemar_fn!(
@@ -183,16 +254,93 @@ enum Propagate {
     },
 }
 
+#[derive(Clone, JsonSchema, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ResponseOperation {
+    Propagate(PropagateResponse),
+}
+
+schemar_fn!(
+    propagate_response_matching,
+    String,
+    "Propagate response headers given a regex matching header name"
+);
+
+#[derive(Clone, JsonSchema, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+#[serde(untagged)]
+/// Propagate response header
+enum PropagateResponse {
+    /// Propagate header given a header name
+    Named {
+        /// The source header name
+        #[schemars(with = "String")]
+        #[serde(deserialize_with = "deserialize_header_name")]
+        named: HeaderName,
+
+        /// An optional target header name
+        #[schemars(with = "Option<String>", default)]
+        #[serde(deserialize_with = "deserialize_option_header_name", default)]
+        rename: Option<HeaderName>,
+
+        /// How to merge values when several responses contain the header.
+        /// Defaults to `append` for `set-cookie`, `most_restrictive` for `cache-control` and
+        /// `last` for any other header.
+        #[serde(default)]
+        merge: Option<MergeStrategy>,
+    },
+    /// Propagate header given a regex to match header name
+    Matching {
+        /// The regex on header name
+        #[schemars(schema_with = "propagate_response_matching")]
+        #[serde(deserialize_with = "deserialize_regex")]
+        matching: Regex,
+
+        /// How to merge values when several responses contain the header.
+        /// Defaults to `append` for `set-cookie`, `most_restrictive` for `cache-control` and
+        /// `last` for any other header.
+        #[serde(default)]
+        merge: Option<MergeStrategy>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, JsonSchema, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How to merge a header returned by several subgraph or connector responses
+enum MergeStrategy {
+    /// Keep the values from the first response containing the header
+    First,
+    /// Keep the values from the last response containing the header
+    Last,
+    /// Keep the values from every response, as separate header lines
+    Append,
+    /// Combine `cache-control` values, keeping the most restrictive directives and the lowest
+    /// max-age
+    MostRestrictive,
+}
+
+impl MergeStrategy {
+    fn default_for(name: &HeaderName) -> Self {
+        if name == SET_COOKIE {
+            MergeStrategy::Append
+        } else if name == CACHE_CONTROL {
+            MergeStrategy::MostRestrictive
+        } else {
+            MergeStrategy::Last
+        }
+    }
+}
+
 #[derive(Clone, JsonSchema, Default, Deserialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields, default)]
 struct ConnectorHeadersConfiguration {
     /// Map of subgraph_name.connector_source_name to configuration
     #[serde(default)]
-    sources: HashMap<String, HeadersLocation>,
+    sources: HashMap<String, HeadersLocation<ConnectorSelector>>,
 
     /// Options applying to all sources across all subgraphs
     #[serde(default)]
-    all: Option<HeadersLocation>,
+    all: Option<HeadersLocation<ConnectorSelector>>,
 }
 
 /// Configuration for header propagation
@@ -201,18 +349,25 @@ struct ConnectorHeadersConfiguration {
 #[schemars(rename = "HeadersConfig")]
 struct Config {
     /// Rules to apply to all subgraphs
-    all: Option<HeadersLocation>,
+    all: Option<HeadersLocation<SubgraphSelector>>,
     /// Rules to specific subgraphs
-    subgraphs: HashMap<String, HeadersLocation>,
+    subgraphs: HashMap<String, HeadersLocation<SubgraphSelector>>,
     /// Rules for connectors
     connector: ConnectorHeadersConfiguration,
 }
 
+type ConnectorOperations = Arc<Vec<Operation<ConnectorSelector>>>;
+
 struct Headers {
-    all_operations: Arc<Vec<Operation>>,
-    subgraph_operations: HashMap<String, Arc<Vec<Operation>>>,
-    all_connector_operations: Arc<Vec<Operation>>,
-    connector_source_operations: HashMap<String, Arc<Vec<Operation>>>,
+    all_operations: Arc<Vec<Operation<SubgraphSelector>>>,
+    subgraph_operations: HashMap<String, Arc<Vec<Operation<SubgraphSelector>>>>,
+    all_connector_operations: Arc<Vec<Operation<ConnectorSelector>>>,
+    connector_source_operations: HashMap<String, ConnectorOperations>,
+    all_response_operations: Arc<Vec<ResponseOperation>>,
+    subgraph_response_operations: HashMap<String, Arc<Vec<ResponseOperation>>>,
+    all_connector_response_operations: Arc<Vec<ResponseOperation>>,
+    connector_source_response_operations: HashMap<String, Arc<Vec<ResponseOperation>>>,
+    merge_strategies: Arc<MergeStrategies>,
 }
 
 #[async_trait::async_trait]
@@ -220,7 +375,7 @@ impl PluginPrivate for Headers {
     type Config = Config;
 
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
-        let operations: Vec<Operation> = init
+        let operations: Vec<Operation<SubgraphSelector>> = init
             .config
             .all
             .as_ref()
@@ -236,34 +391,102 @@ impl PluginPrivate for Headers {
                 (subgraph_name.clone(), Arc::new(operations))
             })
             .collect();
-        let all_connector_operations: Vec<Operation> = init
+        let all_connector_operations: Vec<Operation<ConnectorSelector>> = init
             .config
             .connector
             .all
             .as_ref()
             .map(|a| a.request.clone())
             .unwrap_or_default();
-        let connector_source_operations = init
+        let connector_source_operations = connector_source_operations(&init.config);
+
+        for location in init.config.all.iter().chain(init.config.subgraphs.values()) {
+            location.validate()?;
+        }
+        for location in init
             .config
             .connector
-            .sources
+            .all
+            .iter()
+            .chain(init.config.connector.sources.values())
+        {
+            location.validate()?;
+        }
+
+        let response_operations: Vec<ResponseOperation> = init
+            .config
+            .all
+            .as_ref()
+            .map(|a| a.response.clone())
+            .unwrap_or_default();
+        let subgraph_response_operations = init
+            .config
+            .subgraphs
             .iter()
             .map(|(subgraph_name, op)| {
-                let mut operations = operations.clone();
-                operations.append(&mut op.request.clone());
+                let mut operations = response_operations.clone();
+                operations.append(&mut op.response.clone());
                 (subgraph_name.clone(), Arc::new(operations))
             })
             .collect();
+        let all_connector_response_operations: Vec<ResponseOperation> = init
+            .config
+            .connector
+            .all
+            .as_ref()
+            .map(|a| a.response.clone())
+            .unwrap_or_default();
+        let connector_source_response_operations = init
+            .config
+            .connector
+            .sources
+            .iter()
+            .map(|(source_name, op)| {
+                let mut operations = all_connector_response_operations.clone();
+                operations.append(&mut op.response.clone());
+                (source_name.clone(), Arc::new(operations))
+            })
+            .collect();
+        let merge_strategies = Arc::new(MergeStrategies::new(&init.config)?);
 
         Ok(Headers {
             all_operations: Arc::new(operations),
             all_connector_operations: Arc::new(all_connector_operations),
             subgraph_operations,
             connector_source_operations,
+            all_response_operations: Arc::new(response_operations),
+            all_connector_response_operations: Arc::new(all_connector_response_operations),
+            subgraph_response_operations,
+            connector_source_response_operations,
+            merge_strategies,
         })
     }
 
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if !self.has_response_operations() {
+            return service;
+        }
+        service
+            .map_response(|mut response: supergraph::Response| {
+                let propagated = response
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.remove::<PropagatedResponseHeaders>());
+                if let Some(propagated) = propagated {
+                    propagated.apply(response.response.headers_mut());
+                }
+                response
+            })
+            .boxed()
+    }
+
     fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        let response_operations = self
+            .subgraph_response_operations
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| self.all_response_operations.clone());
+        let merge_strategies = self.merge_strategies.clone();
         ServiceBuilder::new()
             .layer(HeadersLayer::new(
                 self.subgraph_operations
@@ -271,6 +494,15 @@ impl PluginPrivate for Headers {
                     .cloned()
                     .unwrap_or_else(|| self.all_operations.clone()),
             ))
+            .map_response(move |response: subgraph::Response| {
+                collect_response_headers(
+                    &response_operations,
+                    &merge_strategies,
+                    &response.context,
+                    response.response.headers(),
+                );
+                response
+            })
             .service(service)
             .boxed()
     }
@@ -280,6 +512,12 @@ impl PluginPrivate for Headers {
         service: crate::services::connector::request_service::BoxService,
         source_name: String,
     ) -> crate::services::connector::request_service::BoxService {
+        let response_operations = self
+            .connector_source_response_operations
+            .get(&source_name)
+            .cloned()
+            .unwrap_or_else(|| self.all_connector_response_operations.clone());
+        let merge_strategies = self.merge_strategies.clone();
         ServiceBuilder::new()
             .layer(HeadersLayer::new(
                 self.connector_source_operations
@@ -287,23 +525,69 @@ impl PluginPrivate for Headers {
                     .cloned()
                     .unwrap_or_else(|| self.all_connector_operations.clone()),
             ))
+            .map_response(move |response: connector::request_service::Response| {
+                if let Ok(TransportResponse::Http(http_response)) = &response.transport_result {
+                    collect_response_headers(
+                        &response_operations,
+                        &merge_strategies,
+                        &response.context,
+                        &http_response.inner.headers,
+                    );
+                }
+                response
+            })
             .service(service)
             .boxed()
     }
 }
 
-struct HeadersLayer {
-    operations: Arc<Vec<Operation>>,
+impl Headers {
+    fn has_response_operations(&self) -> bool {
+        !self.all_response_operations.is_empty()
+            || !self.all_connector_response_operations.is_empty()
+            || self
+                .subgraph_response_operations
+                .values()
+                .chain(self.connector_source_response_operations.values())
+                .any(|operations| !operations.is_empty())
+    }
 }
 
-impl HeadersLayer {
-    fn new(operations: Arc<Vec<Operation>>) -> Self {
+fn collect_response_headers(
+    operations: &[ResponseOperation],
+    merge_strategies: &MergeStrategies,
+    context: &crate::Context,
+    response_headers: &HeaderMap,
+) {
+    if operations.is_empty() {
+        return;
+    }
+    context.extensions().with_lock(|lock| {
+        let propagated = lock.get_or_default_mut::<PropagatedResponseHeaders>();
+        let mut already_propagated: HashSet<HeaderName> = HashSet::new();
+        for operation in operations {
+            operation.process_header_rules(
+                &mut already_propagated,
+                response_headers,
+                merge_strategies,
+                propagated,
+            );
+        }
+    });
+}
+
+struct HeadersLayer<T> {
+    operations: Arc<Vec<Operation<T>>>,
+}
+
+impl<T> HeadersLayer<T> {
+    fn new(operations: Arc<Vec<Operation<T>>>) -> Self {
         Self { operations }
     }
 }
 
-impl<S> Layer<S> for HeadersLayer {
-    type Service = HeadersService<S>;
+impl<S, T> Layer<S> for HeadersLayer<T> {
+    type Service = HeadersService<S, T>;
 
     fn layer(&self, inner: S) -> Self::Service {
         HeadersService {
@@ -312,9 +596,9 @@ impl<S> Layer<S> for HeadersLayer {
         }
     }
 }
-struct HeadersService<S> {
+struct HeadersService<S, T> {
     inner: S,
-    operations: Arc<Vec<Operation>>,
+    operations: Arc<Vec<Operation<T>>>,
 }
 
 // Headers from https://datatracker.ietf.org/doc/html/rfc2616#section-13.5.1
@@ -339,7 +623,7 @@ static RESERVED_HEADERS: [HeaderName; 14] = [
     HeaderName::from_static("keep-alive"),
 ];
 
-impl<S> Service<SubgraphRequest> for HeadersService<S>
+impl<S> Service<SubgraphRequest> for HeadersService<S, SubgraphSelector>
 where
     S: Service<SubgraphRequest>,
 {
@@ -357,7 +641,7 @@ where
     }
 }
 
-impl<S> Service<connector::request_service::Request> for HeadersService<S>
+impl<S> Service<connector::request_service::Request> for HeadersService<S, ConnectorSelector>
 where
     S: Service<connector::request_service::Request>,
 {
@@ -375,30 +659,45 @@ where
     }
 }
 
-impl<S> HeadersService<S> {
+impl<S> HeadersService<S, SubgraphSelector> {
     fn modify_subgraph_request(&self, req: &mut SubgraphRequest) {
         let mut already_propagated: HashSet<String> = HashSet::new();
 
+        let active_operations: Vec<_> = self
+            .operations
+            .iter()
+            .filter_map(|operation| operation.evaluate(req))
+            .collect();
+
         let body_to_value = serde_json_bytes::value::to_value(req.supergraph_request.body()).ok();
         let supergraph_headers = req.supergraph_request.headers();
         let context = &req.context;
         let headers_mut = req.subgraph_request.headers_mut();
 
-        for operation in &*self.operations {
-            operation.process_header_rules(
+        for active in active_operations {
+            active.operation.process_header_rules(
                 &mut already_propagated,
                 supergraph_headers,
                 &body_to_value,
                 context,
                 headers_mut,
                 None,
+                active.selected,
             );
         }
     }
+}
 
+impl<S> HeadersService<S, ConnectorSelector> {
     fn modify_connector_request(&self, req: &mut connector::request_service::Request) {
         let mut already_propagated: HashSet<String> = HashSet::new();
 
+        let active_operations: Vec<_> = self
+            .operations
+            .iter()
+            .filter_map(|operation| operation.evaluate(req))
+            .collect();
+
         let TransportRequest::Http(ref mut http_request) = req.transport_request;
         let body_to_value = serde_json::from_str(http_request.inner.body()).ok();
         let supergraph_headers = req.supergraph_request.headers();
@@ -407,20 +706,190 @@ impl<S> HeadersService<S> {
         let existing_headers = http_request.inner.headers().clone();
         let headers_mut = http_request.inner.headers_mut();
 
-        for operation in &*self.operations {
-            operation.process_header_rules(
+        for active in active_operations {
+            active.operation.process_header_rules(
                 &mut already_propagated,
                 supergraph_headers,
                 &body_to_value,
                 context,
                 headers_mut,
                 Some(&existing_headers),
+                active.selected,
             );
         }
     }
 }
 
-impl Operation {
+/// A request rule whose condition holds for the current request.
+///
+/// Conditions and selectors are evaluated before any rule is applied, so they always see the
+/// request as it was before header rules modified it.
+struct ActiveOperation<'a, T> {
+    operation: &'a Operation<T>,
+    /// The value returned by the rule's selector, if it has one
+    selected: Option<opentelemetry::Value>,
+}
+
+impl<T> HeadersLocation<T>
+where
+    T: Selector + Clone,
+{
+    fn validate(&self) -> Result<(), BoxError> {
+        for operation in &self.request {
+            operation.validate()?;
+        }
+        for operation in &self.response {
+            operation.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// The request rules of each configured connector source: the top-level `all` rules, then the
+/// rules of the source, like for subgraphs.
+///
+/// Top-level rules with a condition or a selector depend on subgraph selectors, so they only apply
+/// to subgraphs.
+fn connector_source_operations(config: &Config) -> HashMap<String, ConnectorOperations> {
+    if config.connector.sources.is_empty() {
+        return HashMap::new();
+    }
+    let mut skipped = 0;
+    let inherited: Vec<_> = config
+        .all
+        .iter()
+        .flat_map(|all| all.request.iter())
+        .filter_map(|operation| {
+            let operation = operation.for_connector();
+            if operation.is_none() {
+                skipped += 1;
+            }
+            operation
+        })
+        .collect();
+    if skipped > 0 {
+        tracing::warn!(
+            "{skipped} top-level header rule(s) with a condition or a selector are not applied to connector sources, add them to `connector.all` to apply them to connectors"
+        );
+    }
+    config
+        .connector
+        .sources
+        .iter()
+        .map(|(source_name, op)| {
+            let mut operations = inherited.clone();
+            operations.append(&mut op.request.clone());
+            (source_name.clone(), Arc::new(operations))
+        })
+        .collect()
+}
+
+impl Operation<SubgraphSelector> {
+    /// The same rule for connectors, if it doesn't depend on subgraph selectors
+    fn for_connector(&self) -> Option<Operation<ConnectorSelector>> {
+        if !matches!(self.condition(), None | Some(Condition::True)) {
+            return None;
+        }
+        Some(match self {
+            Operation::Insert(Insert::Static(insert)) => {
+                Operation::Insert(Insert::Static(InsertStatic {
+                    name: insert.name.clone(),
+                    value: insert.value.clone(),
+                    condition: Condition::True,
+                }))
+            }
+            Operation::Insert(Insert::FromContext(insert)) => {
+                Operation::Insert(Insert::FromContext(InsertFromContext {
+                    name: insert.name.clone(),
+                    from_context: insert.from_context.clone(),
+                    condition: Condition::True,
+                }))
+            }
+            Operation::Insert(Insert::FromBody(insert)) => {
+                Operation::Insert(Insert::FromBody(InsertFromBody {
+                    name: insert.name.clone(),
+                    path: insert.path.clone(),
+                    default: insert.default.clone(),
+                    condition: Condition::True,
+                }))
+            }
+            Operation::Insert(Insert::FromSelector(_)) => return None,
+            Operation::Remove(Remove::Named { named, .. }) => Operation::Remove(Remove::Named {
+                named: named.clone(),
+                condition: Condition::True,
+            }),
+            Operation::Remove(Remove::Matching { matching, .. }) => {
+                Operation::Remove(Remove::Matching {
+                    matching: matching.clone(),
+                    condition: Condition::True,
+                })
+            }
+            Operation::Propagate(propagate) => Operation::Propagate(propagate.clone()),
+        })
+    }
+}
+
+impl<T> Operation<T>
+where
+    T: Selector + Clone,
+{
+    fn condition(&self) -> Option<&Condition<T>> {
+        match self {
+            Operation::Insert(Insert::Static(InsertStatic { condition, .. }))
+            | Operation::Insert(Insert::FromContext(InsertFromContext { condition, .. }))
+            | Operation::Insert(Insert::FromBody(InsertFromBody { condition, .. }))
+            | Operation::Insert(Insert::FromSelector(InsertFromSelector { condition, .. }))
+            | Operation::Remove(Remove::Named { condition, .. })
+            | Operation::Remove(Remove::Matching { condition, .. }) => Some(condition),
+            Operation::Propagate(_) => None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), BoxError> {
+        if let Some(condition) = self.condition() {
+            condition
+                .validate(Some(Stage::Request))
+                .map_err(|err| format!("invalid header rule condition: {err}"))?;
+        }
+        if let Operation::Insert(Insert::FromSelector(insert)) = self
+            && !insert.from_selector.is_active(Stage::Request)
+        {
+            return Err(format!(
+                "selector {:?} for header '{}' cannot be computed from a request",
+                insert.from_selector, insert.name
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self, request: &T::Request) -> Option<ActiveOperation<'_, T>> {
+        let holds = match self.condition() {
+            None | Some(Condition::True) => true,
+            // Evaluating a condition resolves it in place, so each request works on its own copy
+            Some(condition) => {
+                let mut condition = condition.clone();
+                condition.evaluate_request(request) == Some(true)
+            }
+        };
+        if !holds {
+            return None;
+        }
+        let selected = match self {
+            Operation::Insert(Insert::FromSelector(insert)) => {
+                insert.from_selector.on_request(request)
+            }
+            _ => None,
+        };
+        Some(ActiveOperation {
+            operation: self,
+            selected,
+        })
+    }
+}
+
+impl<T> Operation<T> {
+    #[allow(clippy::too_many_arguments)]
     fn process_header_rules(
         &self,
         already_propagated: &mut HashSet<String>,
@@ -429,10 +898,11 @@ impl Operation {
         context: &crate::Context,
         headers_mut: &mut HeaderMap,
         existing_headers: Option<&HeaderMap>,
+        selected: Option<opentelemetry::Value>,
     ) {
         match self {
             Operation::Insert(insert) => {
-                insert.process_header_rules(body_to_value, context, headers_mut)
+                insert.process_header_rules(body_to_value, context, headers_mut, selected)
             }
             Operation::Remove(remove) => remove.process_header_rules(headers_mut),
             Operation::Propagate(propagate) => propagate.process_header_rules(
@@ -445,12 +915,13 @@ impl Operation {
     }
 }
 
-impl Insert {
+impl<T> Insert<T> {
     fn process_header_rules(
         &self,
         body_to_value: &Option<Value>,
         context: &crate::Context,
         headers_mut: &mut HeaderMap,
+        selected: Option<opentelemetry::Value>,
     ) {
         match self {
             Insert::Static(insert_static) => {
@@ -504,17 +975,30 @@ impl Insert {
                     headers_mut.insert(&from_body.name, default_val.clone());
                 }
             }
+            Insert::FromSelector(from_selector) => {
+                if let Some(selected) = selected {
+                    match HeaderValue::from_str(&selected.as_str()) {
+                        Ok(header_value) => {
+                            headers_mut.insert(&from_selector.name, header_value);
+                        }
+                        Err(err) => {
+                            let header_name = &from_selector.name;
+                            tracing::error!(%header_name, ?err, "cannot convert the selected value into a header value for header name");
+                        }
+                    }
+                }
+            }
         }
     }
 }
 
-impl Remove {
+impl<T> Remove<T> {
     fn process_header_rules(&self, headers_mut: &mut HeaderMap) {
         match self {
-            Remove::Named(name) => {
-                headers_mut.remove(name);
+            Remove::Named { named, .. } => {
+                headers_mut.remove(named);
             }
-            Remove::Matching(matching) => {
+            Remove::Matching { matching, .. } => {
                 let new_headers = headers_mut
                     .drain()
                     .filter_map(|(name, value)| {
@@ -606,6 +1090,233 @@ impl Propagate {
     }
 }
 
+impl ResponseOperation {
+    fn validate(&self) -> Result<(), BoxError> {
+        match self {
+            ResponseOperation::Propagate(PropagateResponse::Named {
+                named,
+                merge: Some(MergeStrategy::MostRestrictive),
+                ..
+            }) if named != CACHE_CONTROL => Err(format!(
+                "the most_restrictive merge strategy is only supported for the cache-control header, not '{named}'"
+            )
+            .into()),
+            ResponseOperation::Propagate(PropagateResponse::Matching {
+                merge: Some(MergeStrategy::MostRestrictive),
+                ..
+            }) => Err(
+                "the most_restrictive merge strategy is only supported for the cache-control header, use a named rule"
+                    .into(),
+            ),
+            ResponseOperation::Propagate(_) => Ok(()),
+        }
+    }
+
+    fn process_header_rules(
+        &self,
+        already_propagated: &mut HashSet<HeaderName>,
+        response_headers: &HeaderMap,
+        merge_strategies: &MergeStrategies,
+        propagated: &mut PropagatedResponseHeaders,
+    ) {
+        match self {
+            ResponseOperation::Propagate(PropagateResponse::Named { named, rename, .. }) => {
+                let target_header = rename.as_ref().unwrap_or(named);
+                if RESERVED_HEADERS.contains(named)
+                    || RESERVED_HEADERS.contains(target_header)
+                    || already_propagated.contains(target_header)
+                {
+                    return;
+                }
+                let values: Vec<HeaderValue> =
+                    response_headers.get_all(named).iter().cloned().collect();
+                if !values.is_empty() {
+                    propagated.merge(
+                        target_header.clone(),
+                        merge_strategies.get(target_header),
+                        values,
+                    );
+                    already_propagated.insert(target_header.clone());
+                }
+            }
+            ResponseOperation::Propagate(PropagateResponse::Matching { matching, .. }) => {
+                for name in response_headers.keys() {
+                    if RESERVED_HEADERS.contains(name)
+                        || !matching.is_match(name.as_str())
+                        || already_propagated.contains(name)
+                    {
+                        continue;
+                    }
+                    propagated.merge(
+                        name.clone(),
+                        merge_strategies.get(name),
+                        response_headers.get_all(name).iter().cloned().collect(),
+                    );
+                    already_propagated.insert(name.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Merge strategy of each response header.
+///
+/// The strategy only depends on the header name, so the result doesn't depend on the order in which
+/// the subgraph and connector responses containing the header are received.
+#[derive(Default)]
+struct MergeStrategies {
+    named: HashMap<HeaderName, MergeStrategy>,
+    matching: Vec<(Regex, MergeStrategy)>,
+}
+
+impl MergeStrategies {
+    fn new(config: &Config) -> Result<Self, BoxError> {
+        let mut strategies = MergeStrategies::default();
+        let locations = config
+            .all
+            .iter()
+            .map(|location| &location.response)
+            .chain(
+                config
+                    .subgraphs
+                    .iter()
+                    .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                    .map(|(_, location)| &location.response),
+            )
+            .chain(
+                config
+                    .connector
+                    .all
+                    .iter()
+                    .map(|location| &location.response),
+            )
+            .chain(
+                config
+                    .connector
+                    .sources
+                    .iter()
+                    .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                    .map(|(_, location)| &location.response),
+            );
+        for operation in locations.flatten() {
+            match operation {
+                ResponseOperation::Propagate(PropagateResponse::Named {
+                    named,
+                    rename,
+                    merge: Some(merge),
+                }) => {
+                    let target_header = rename.as_ref().unwrap_or(named);
+                    match strategies.named.entry(target_header.clone()) {
+                        Entry::Vacant(entry) => {
+                            entry.insert(*merge);
+                        }
+                        Entry::Occupied(entry) if entry.get() != merge => {
+                            return Err(format!(
+                                "the response header '{target_header}' is propagated with different merge strategies"
+                            )
+                            .into());
+                        }
+                        Entry::Occupied(_) => {}
+                    }
+                }
+                ResponseOperation::Propagate(PropagateResponse::Matching {
+                    matching,
+                    merge: Some(merge),
+                }) => strategies.matching.push((matching.clone(), *merge)),
+                ResponseOperation::Propagate(_) => {}
+            }
+        }
+        Ok(strategies)
+    }
+
+    /// The strategy of the first named rule of the header, else of the first matching rule, else
+    /// the default strategy of the header
+    fn get(&self, name: &HeaderName) -> MergeStrategy {
+        self.named
+            .get(name)
+            .copied()
+            .or_else(|| {
+                self.matching
+                    .iter()
+                    .find(|(matching, _)| matching.is_match(name.as_str()))
+                    .map(|(_, strategy)| *strategy)
+            })
+            .unwrap_or_else(|| MergeStrategy::default_for(name))
+    }
+}
+
+/// Headers collected from subgraph and connector responses during a request, written to the
+/// client response by the supergraph service.
+#[derive(Default)]
+struct PropagatedResponseHeaders {
+    headers: HashMap<HeaderName, (MergeStrategy, Vec<HeaderValue>)>,
+}
+
+impl PropagatedResponseHeaders {
+    fn merge(&mut self, name: HeaderName, strategy: MergeStrategy, values: Vec<HeaderValue>) {
+        match self.headers.entry(name) {
+            Entry::Vacant(entry) => {
+                entry.insert((strategy, values));
+            }
+            Entry::Occupied(mut entry) => {
+                let (strategy, existing) = entry.get_mut();
+                match strategy {
+                    MergeStrategy::First => {}
+                    MergeStrategy::Last => *existing = values,
+                    // Set-Cookie values cannot be folded into a single line, so every value is
+                    // kept as its own header line.
+                    MergeStrategy::Append | MergeStrategy::MostRestrictive => {
+                        existing.extend(values)
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply(self, headers: &mut HeaderMap) {
+        for (name, (strategy, values)) in self.headers {
+            match strategy {
+                MergeStrategy::Append => {
+                    for value in values {
+                        headers.append(&name, value);
+                    }
+                }
+                MergeStrategy::First | MergeStrategy::Last => {
+                    headers.remove(&name);
+                    for value in values {
+                        headers.append(&name, value);
+                    }
+                }
+                MergeStrategy::MostRestrictive => {
+                    headers.insert(&name, most_restrictive_cache_control(&values));
+                }
+            }
+        }
+    }
+}
+
+/// Merge Cache-Control values, keeping the lowest max-age and the most restrictive directives.
+///
+/// Values that cannot be parsed make the whole response `no-store`.
+fn most_restrictive_cache_control(values: &[HeaderValue]) -> HeaderValue {
+    values
+        .iter()
+        .try_fold(None, |merged: Option<CacheControl>, value| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CACHE_CONTROL, value.clone());
+            let cache_control = CacheControl::new(&headers, None)?;
+            Ok::<_, BoxError>(Some(match merged {
+                Some(merged) => merged.merge(&cache_control),
+                None => cache_control,
+            }))
+        })
+        .ok()
+        .flatten()
+        .and_then(|merged| merged.to_cache_control_header().ok())
+        .and_then(|value| HeaderValue::from_str(&value).ok())
+        .unwrap_or_else(|| HeaderValue::from_static("no-store"))
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashSet;
@@ -745,6 +1456,505 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_conditional_config() {
+        serde_yaml::from_str::<Config>(
+            r#"
+        all:
+            request:
+                - insert:
+                    name: "x-operation-kind"
+                    from_selector:
+                        supergraph_operation_kind: string
+                - insert:
+                    name: "x-mutation"
+                    value: "true"
+                    condition:
+                        eq:
+                            - supergraph_operation_kind: string
+                            - mutation
+                - remove:
+                    named: "x-legacy"
+                    condition:
+                        exists:
+                            supergraph_request_header: "x-modern"
+        connector:
+            all:
+                request:
+                    - insert:
+                        name: "x-from-context"
+                        from_selector:
+                            request_context: "my_key"
+        "#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_conditional_validation() {
+        let operation = |yaml| serde_yaml::from_str::<Operation<SubgraphSelector>>(yaml).unwrap();
+
+        assert!(
+            operation(
+                "insert: { name: x-header, from_selector: { supergraph_request_header: x-other } }"
+            )
+            .validate()
+            .is_ok()
+        );
+        assert!(
+            operation(
+                "insert: { name: x-status, from_selector: { subgraph_response_status: code } }"
+            )
+            .validate()
+            .is_err()
+        );
+        assert!(
+            operation(
+                "remove: { named: x-header, condition: { exists: { subgraph_response_status: code } } }"
+            )
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_connector_sources_inherit_top_level_rules() {
+        let config = serde_yaml::from_str::<Config>(
+            r#"
+        all:
+            request:
+                - propagate:
+                    named: "authorization"
+                - remove:
+                    named: "x-internal"
+        connector:
+            all:
+                request:
+                    - propagate:
+                        named: "x-connector"
+            sources:
+                subgraph_name.source:
+                    request:
+                        - insert:
+                            name: "x-source"
+                            value: "source"
+        "#,
+        )
+        .unwrap();
+        let operations = connector_source_operations(&config);
+        assert_eq!(operations["subgraph_name.source"].len(), 3);
+
+        let config = serde_yaml::from_str::<Config>(
+            r#"
+        all:
+            request:
+                - insert:
+                    name: "x-static"
+                    value: "static"
+                - insert:
+                    name: "x-mutation"
+                    value: "true"
+                    condition:
+                        eq:
+                            - subgraph_operation_kind: string
+                            - mutation
+        connector:
+            sources:
+                subgraph_name.source:
+                    request:
+                        - propagate:
+                            named: "authorization"
+        "#,
+        )
+        .unwrap();
+        // the conditional rule only applies to subgraphs
+        let operations = connector_source_operations(&config);
+        assert_eq!(operations["subgraph_name.source"].len(), 2);
+    }
+
+    #[test]
+    fn test_response_config() {
+        serde_yaml::from_str::<Config>(
+            r#"
+        all:
+            response:
+                - propagate:
+                    named: "set-cookie"
+                - propagate:
+                    named: "x-version"
+                    rename: "x-subgraph-version"
+                    merge: first
+                - propagate:
+                    matching: "x-trace-.*"
+                    merge: append
+        connector:
+            sources:
+                subgraph_name.source:
+                    response:
+                        - propagate:
+                            named: "cache-control"
+                            merge: most_restrictive
+        "#,
+        )
+        .unwrap();
+
+        assert!(
+            serde_yaml::from_str::<Config>(
+                r#"
+        all:
+            response:
+                - propagate:
+                    named: "set-cookie"
+                    merge: biggest
+        "#,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_response_most_restrictive_validation() {
+        let operation = |yaml| serde_yaml::from_str::<ResponseOperation>(yaml).unwrap();
+
+        assert!(
+            operation("propagate: { named: cache-control, merge: most_restrictive }")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            operation("propagate: { named: x-custom, merge: most_restrictive }")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            operation("propagate: { matching: .*, merge: most_restrictive }")
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_response_merge_strategy_per_header() {
+        let config = |yaml| serde_yaml::from_str::<Config>(yaml).unwrap();
+        let strategies = MergeStrategies::new(&config(
+            r#"
+        all:
+            response:
+                - propagate:
+                    matching: "x-trace-.*"
+                    merge: first
+        subgraphs:
+            reviews:
+                response:
+                    - propagate:
+                        matching: ".*"
+                        merge: append
+                    - propagate:
+                        named: "x-trace-version"
+                        merge: last
+            products:
+                response:
+                    - propagate:
+                        named: "x-trace-version"
+                        merge: last
+        "#,
+        ))
+        .unwrap();
+        let strategy = |name| strategies.get(&HeaderName::from_static(name));
+        assert_eq!(strategy("x-trace-version"), MergeStrategy::Last);
+        assert_eq!(strategy("x-trace-id"), MergeStrategy::First);
+        assert_eq!(strategy("x-custom"), MergeStrategy::Append);
+
+        assert_eq!(
+            MergeStrategies::default().get(&SET_COOKIE),
+            MergeStrategy::Append
+        );
+        assert!(
+            MergeStrategies::new(&config(
+                r#"
+        subgraphs:
+            reviews:
+                response:
+                    - propagate:
+                        named: "x-version"
+                        merge: first
+            products:
+                response:
+                    - propagate:
+                        named: "x-version"
+                        merge: last
+        "#,
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_response_reserved_headers_are_not_propagated() {
+        let operations: Vec<ResponseOperation> = serde_yaml::from_str(
+            r#"
+            - propagate:
+                named: "content-length"
+            - propagate:
+                named: "x-length"
+                rename: "transfer-encoding"
+            - propagate:
+                named: "x-version"
+            "#,
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("42"));
+        headers.insert("x-length", HeaderValue::from_static("42"));
+        headers.insert("x-version", HeaderValue::from_static("1"));
+
+        let mut propagated = PropagatedResponseHeaders::default();
+        let mut already_propagated = HashSet::new();
+        for operation in &operations {
+            operation.process_header_rules(
+                &mut already_propagated,
+                &headers,
+                &MergeStrategies::default(),
+                &mut propagated,
+            );
+        }
+        let mut response_headers = HeaderMap::new();
+        propagated.apply(&mut response_headers);
+        assert_eq!(response_headers.len(), 1);
+        assert_eq!(response_headers["x-version"], "1");
+    }
+
+    #[test]
+    fn test_response_merge_strategies() {
+        let value = |v: &'static str| HeaderValue::from_static(v);
+        let mut propagated = PropagatedResponseHeaders::default();
+        for values in [vec![value("a")], vec![value("b"), value("c")]] {
+            propagated.merge(
+                HeaderName::from_static("first"),
+                MergeStrategy::First,
+                values.clone(),
+            );
+            propagated.merge(
+                HeaderName::from_static("last"),
+                MergeStrategy::Last,
+                values.clone(),
+            );
+            propagated.merge(
+                HeaderName::from_static("append"),
+                MergeStrategy::Append,
+                values,
+            );
+        }
+        propagated.merge(
+            CACHE_CONTROL,
+            MergeStrategy::MostRestrictive,
+            vec![value("max-age=60, public")],
+        );
+        propagated.merge(
+            CACHE_CONTROL,
+            MergeStrategy::MostRestrictive,
+            vec![value("max-age=30, private")],
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("last", value("client"));
+        headers.insert("append", value("client"));
+        propagated.apply(&mut headers);
+
+        let values = |name: &str| {
+            headers
+                .get_all(name)
+                .iter()
+                .map(|v| v.to_str().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values("first"), vec!["a"]);
+        assert_eq!(values("last"), vec!["b", "c"]);
+        assert_eq!(values("append"), vec!["client", "a", "b", "c"]);
+        assert_eq!(values("cache-control"), vec!["max-age=30,private"]);
+    }
+
+    #[test]
+    fn test_most_restrictive_cache_control() {
+        assert_eq!(
+            most_restrictive_cache_control(&[
+                HeaderValue::from_static("max-age=60"),
+                HeaderValue::from_static("no-store"),
+            ]),
+            "no-store"
+        );
+        assert_eq!(
+            most_restrictive_cache_control(&[
+                HeaderValue::from_static("max-age=60"),
+                HeaderValue::from_static("not a directive"),
+            ]),
+            "no-store"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_propagate_response_headers() {
+        let test_harness = PluginTestHarness::<Headers>::builder()
+            .config(include_str!("fixtures/propagate_response.router.yaml"))
+            .build()
+            .await
+            .expect("test harness");
+        let context = Context::new();
+
+        for (subgraph, headers) in [
+            (
+                "products",
+                vec![
+                    ("set-cookie", "a=1; Path=/"),
+                    ("cache-control", "max-age=60, public"),
+                    ("x-trace-id", "products"),
+                    ("x-reviews-version", "1"),
+                ],
+            ),
+            (
+                "reviews",
+                vec![
+                    ("set-cookie", "b=2"),
+                    ("set-cookie", "c=3"),
+                    ("cache-control", "max-age=30, private"),
+                    ("x-trace-id", "reviews"),
+                    ("x-reviews-version", "2"),
+                ],
+            ),
+        ] {
+            let service = test_harness.subgraph_service(subgraph, move |request| {
+                let headers = headers.clone();
+                async move {
+                    let mut header_map = HeaderMap::new();
+                    for (name, value) in headers {
+                        header_map.append(name, HeaderValue::from_static(value));
+                    }
+                    Ok(subgraph::Response::fake_builder()
+                        .headers(header_map)
+                        .context(request.context)
+                        .build())
+                }
+            });
+            service
+                .call(
+                    subgraph::Request::fake_builder()
+                        .context(context.clone())
+                        .build(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let service = test_harness.supergraph_service(|request| async move {
+            supergraph::Response::fake_builder()
+                .context(request.context)
+                .build()
+        });
+        let response = service
+            .call(
+                supergraph::Request::fake_builder()
+                    .context(context)
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let headers = response.response.headers();
+        let values = |name: &str| {
+            headers
+                .get_all(name)
+                .iter()
+                .map(|v| v.to_str().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values("set-cookie"), vec!["a=1; Path=/", "b=2", "c=3"]);
+        assert_eq!(values("cache-control"), vec!["max-age=30,private"]);
+        assert_eq!(values("x-trace-id"), vec!["reviews"]);
+        // Only the reviews subgraph propagates its version
+        assert_eq!(values("x-version"), vec!["2"]);
+        assert!(values("x-reviews-version").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conditional_rules() -> Result<(), BoxError> {
+        let mut mock = MockSubgraphService::new();
+        mock.expect_call()
+            .times(1)
+            .withf(|request| {
+                request.assert_headers(vec![("ab", "vab"), ("ac", "vac"), ("x-da", "vda")])
+            })
+            .returning(example_response);
+
+        let operations: Vec<Operation<SubgraphSelector>> = serde_yaml::from_str(
+            r#"
+            - insert:
+                name: "x-da"
+                from_selector:
+                    supergraph_request_header: "da"
+            - insert:
+                name: "x-missing"
+                from_selector:
+                    supergraph_request_header: "missing"
+            - insert:
+                name: "c"
+                value: "d"
+                condition:
+                    exists:
+                        supergraph_request_header: "missing"
+            - remove:
+                named: "aa"
+                condition:
+                    eq:
+                        - supergraph_request_header: "da"
+                        - "vda"
+            - remove:
+                named: "ab"
+                condition:
+                    eq:
+                        - supergraph_request_header: "da"
+                        - "other"
+            "#,
+        )?;
+        let mut service = HeadersLayer::new(Arc::new(operations)).layer(mock);
+
+        service.ready().await?.call(example_request()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connector_insert_from_selector() -> Result<(), BoxError> {
+        let mut mock = MockConnectorService::new();
+        mock.expect_call()
+            .times(1)
+            .withf(|request| {
+                request.assert_headers(vec![
+                    ("aa", "vaa"),
+                    ("ab", "vab"),
+                    ("ac", "vac"),
+                    ("x-from-context", "my_value_from_context"),
+                ])
+            })
+            .returning(example_connector_response);
+
+        let operations: Vec<Operation<ConnectorSelector>> = serde_yaml::from_str(
+            r#"
+            - insert:
+                name: "x-from-context"
+                from_selector:
+                    request_context: "my_key"
+            "#,
+        )?;
+        let mut service = HeadersLayer::new(Arc::new(operations)).layer(mock);
+
+        service
+            .ready()
+            .await?
+            .call(example_connector_request())
+            .await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_insert_static() -> Result<(), BoxError> {
         let mut mock = MockSubgraphService::new();
@@ -764,6 +1974,7 @@ mod test {
             InsertStatic {
                 name: "c".try_into()?,
                 value: "d".try_into()?,
+                condition: Condition::True,
             },
         ))]))
         .layer(mock);
@@ -791,6 +2002,7 @@ mod test {
             InsertStatic {
                 name: "c".try_into()?,
                 value: "d".try_into()?,
+                condition: Condition::True,
             },
         ))]))
         .layer(mock);
@@ -822,6 +2034,7 @@ mod test {
             Insert::FromContext(InsertFromContext {
                 name: "header_from_context".try_into()?,
                 from_context: "my_key".to_string(),
+                condition: Condition::True,
             }),
         )]))
         .layer(mock);
@@ -849,6 +2062,7 @@ mod test {
             Insert::FromContext(InsertFromContext {
                 name: "header_from_context".try_into()?,
                 from_context: "my_key".to_string(),
+                condition: Condition::True,
             }),
         )]))
         .layer(mock);
@@ -881,6 +2095,7 @@ mod test {
                 name: "header_from_request".try_into()?,
                 path: JsonPathInst::from_str("$.operationName").unwrap(),
                 default: None,
+                condition: Condition::True,
             },
         ))]))
         .layer(mock);
@@ -909,6 +2124,7 @@ mod test {
                 name: "header_from_request".try_into()?,
                 path: JsonPathInst::from_str("$.myCoolField").unwrap(),
                 default: None,
+                condition: Condition::True,
             },
         ))]))
         .layer(mock);
@@ -941,6 +2157,7 @@ mod test {
                 name: "header_from_request".try_into()?,
                 path: JsonPathInst::from_str(".operationName").unwrap(),
                 default: None,
+                condition: Condition::True,
             },
         ))]))
         .layer(mock);
@@ -970,6 +2187,7 @@ mod test {
                 name: "header_from_request".try_into()?,
                 path: JsonPathInst::from_str(".myCoolField").unwrap(),
                 default: None,
+                condition: Condition::True,
             },
         ))]))
         .layer(mock);
@@ -990,9 +2208,10 @@ mod test {
             .withf(|request| request.assert_headers(vec![("ac", "vac"), ("ab", "vab")]))
             .returning(example_response);
 
-        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Named(
-            "aa".try_into()?,
-        ))]))
+        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Named {
+            named: "aa".try_into()?,
+            condition: Condition::True,
+        })]))
         .layer(mock);
 
         service.ready().await?.call(example_request()).await?;
@@ -1007,9 +2226,10 @@ mod test {
             .withf(|request| request.assert_headers(vec![("ac", "vac"), ("ab", "vab")]))
             .returning(example_response);
 
-        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Named(
-            "aa".try_into()?,
-        ))]))
+        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Named {
+            named: "aa".try_into()?,
+            condition: Condition::True,
+        })]))
         .layer(mock);
 
         let ctx = Context::new();
@@ -1067,9 +2287,10 @@ mod test {
             .withf(|request| request.assert_headers(vec![("ac", "vac"), ("ab", "vab")]))
             .returning(example_connector_response);
 
-        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Named(
-            "aa".try_into()?,
-        ))]))
+        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Named {
+            named: "aa".try_into()?,
+            condition: Condition::True,
+        })]))
         .layer(mock);
 
         service
@@ -1088,9 +2309,10 @@ mod test {
             .withf(|request| request.assert_headers(vec![("ac", "vac")]))
             .returning(example_response);
 
-        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Matching(
-            Regex::from_str("a[ab]")?,
-        ))]))
+        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Matching {
+            matching: Regex::from_str("a[ab]")?,
+            condition: Condition::True,
+        })]))
         .layer(mock);
 
         service.ready().await?.call(example_request()).await?;
@@ -1105,9 +2327,10 @@ mod test {
             .withf(|request| request.assert_headers(vec![("ac", "vac")]))
             .returning(example_connector_response);
 
-        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Matching(
-            Regex::from_str("a[ab]")?,
-        ))]))
+        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Remove(Remove::Matching {
+            matching: Regex::from_str("a[ab]")?,
+            condition: Condition::True,
+        })]))
         .layer(mock);
 
         service
@@ -31,6 +31,28 @@ use crate::plugin::PluginPrivate;
 use crate::register_private_plugin;
 use crate::services::router;
 
+/// Number of [`ReadinessHold`]s currently alive in this process
+static READINESS_HOLDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps the router reporting unready as long as it is alive.
+///
+/// Used by components which must finish some work before the router receives traffic, such as
+/// cache warming.
+pub(crate) struct ReadinessHold(());
+
+impl ReadinessHold {
+    pub(crate) fn new() -> Self {
+        READINESS_HOLDS.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for ReadinessHold {
+    fn drop(&mut self) {
+        READINESS_HOLDS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
 #[allow(dead_code)]
@@ -258,7 +280,9 @@ impl PluginPrivate for HealthCheck {
                         let query_upper = query.to_ascii_uppercase();
                         // Could be more precise, but sloppy match is fine for this use case
                         if query_upper.starts_with("READY") {
-                            let status = if my_ready.load(Ordering::SeqCst) {
+                            let status = if my_ready.load(Ordering::SeqCst)
+                                && READINESS_HOLDS.load(Ordering::SeqCst) == 0
+                            {
                                 HealthStatus::Up
                             } else {
                                 // It's hard to get k8s to parse payloads. Especially since we
@@ -43,6 +43,8 @@ pub(crate) struct CacheControl {
     immutable: bool,
     #[serde(skip_serializing_if = "is_false", default)]
     stale_if_error: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    stale_if_error_window: Option<u64>,
 }
 
 fn is_false(b: &bool) -> bool {
@@ -74,6 +76,7 @@ impl Default for CacheControl {
             no_transform: false,
             immutable: false,
             stale_if_error: false,
+            stale_if_error_window: None,
         }
     }
 }
@@ -138,6 +141,10 @@ impl CacheControl {
                     ("stale-if-error", None) => {
                         result.stale_if_error = true;
                     }
+                    ("stale-if-error", Some(v)) => {
+                        result.stale_if_error = true;
+                        result.stale_if_error_window = Some(v.parse()?);
+                    }
                     _ => {
                         return Err("invalid Cache-Control header value".into());
                     }
@@ -246,6 +253,9 @@ impl CacheControl {
         }
         if self.stale_if_error {
             write!(&mut s, "{}stale-if-error", if prev { "," } else { "" },)?;
+            if let Some(window) = self.stale_if_error_window {
+                write!(&mut s, "={window}")?;
+            }
         }
 
         Ok(s)
@@ -337,6 +347,11 @@ impl CacheControl {
             no_transform: self.no_transform || other.no_transform,
             immutable: self.immutable || other.immutable,
             stale_if_error: self.stale_if_error || other.stale_if_error,
+            stale_if_error_window: match (self.stale_if_error_window, other.stale_if_error_window) {
+                (None, None) => None,
+                (None, Some(window)) | (Some(window), None) => Some(window),
+                (Some(window1), Some(window2)) => Some(std::cmp::min(window1, window2)),
+            },
         }
     }
 
@@ -381,11 +396,47 @@ impl CacheControl {
             self.ttl().map(|ttl| ttl < elapsed as u64).unwrap_or(false)
         };
 
-        // FIXME: we don't honor stale-while-revalidate yet
-        // !expired || self.stale_while_revalidate
         !expired && !self.no_cache
     }
 
+    /// An expired entry can be served while it is refreshed in the background
+    pub(crate) fn can_use_stale_while_revalidate(&self) -> bool {
+        self.can_use_stale(self.stale_while_revalidate, now_epoch_seconds())
+    }
+
+    /// An expired entry can be served if the subgraph request fails
+    pub(crate) fn can_use_stale_if_error(&self) -> bool {
+        self.can_use_stale(self.stale_if_error_window, now_epoch_seconds())
+    }
+
+    fn can_use_stale(&self, window: Option<u64>, now: u64) -> bool {
+        if self.no_cache || self.must_revalidate {
+            return false;
+        }
+        match (window, self.stale_for(now)) {
+            (Some(window), Some(stale_for)) => stale_for <= window,
+            _ => false,
+        }
+    }
+
+    /// Number of seconds since the entry expired, `None` if it has not expired
+    fn stale_for(&self, now: u64) -> Option<u64> {
+        let elapsed = u64::try_from(self.elapsed_inner(now)).ok()?;
+        let ttl = self.ttl()?;
+        (elapsed > ttl).then(|| elapsed - ttl)
+    }
+
+    /// How long an entry has to be kept in storage after it expires, to be served stale
+    pub(crate) fn stale_ttl(&self) -> u64 {
+        if self.must_revalidate {
+            return 0;
+        }
+        std::cmp::max(
+            self.stale_while_revalidate.unwrap_or_default(),
+            self.stale_if_error_window.unwrap_or_default(),
+        )
+    }
+
     pub(crate) fn is_no_cache(&self) -> bool {
         self.no_cache
     }
@@ -564,4 +615,60 @@ mod tests {
         assert!(!cc.can_use()); // Because created is bigger than now
         assert!(!cc.should_store()); // Because age is bigger than max_age
     }
+
+    #[test]
+    fn parse_stale_directives() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("max-age=60,stale-while-revalidate=30,stale-if-error=300"),
+        );
+        let cc = CacheControl::new(&headers, None).unwrap();
+        assert_eq!(cc.stale_while_revalidate, Some(30));
+        assert!(cc.stale_if_error);
+        assert_eq!(cc.stale_if_error_window, Some(300));
+        assert_eq!(cc.stale_ttl(), 300);
+        assert_eq!(
+            cc.to_cache_control_header().unwrap(),
+            "max-age=60,stale-while-revalidate=30,stale-if-error=300"
+        );
+
+        headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("max-age=60,stale-if-error"),
+        );
+        let cc = CacheControl::new(&headers, None).unwrap();
+        assert!(cc.stale_if_error);
+        assert_eq!(cc.stale_if_error_window, None);
+        assert_eq!(cc.stale_ttl(), 0);
+    }
+
+    #[test]
+    fn use_stale_entries() {
+        let now = now_epoch_seconds();
+        let cc = CacheControl {
+            created: now - 100,
+            max_age: Some(60),
+            stale_while_revalidate: Some(30),
+            stale_if_error_window: Some(60),
+            stale_if_error: true,
+            ..Default::default()
+        };
+        assert!(!cc.can_use());
+        // expired for 40 seconds
+        assert_eq!(cc.stale_for(now), Some(40));
+        assert!(!cc.can_use_stale(cc.stale_while_revalidate, now));
+        assert!(cc.can_use_stale(cc.stale_if_error_window, now));
+
+        // still fresh
+        assert_eq!(cc.stale_for(now - 50), None);
+        assert!(!cc.can_use_stale(cc.stale_if_error_window, now - 50));
+
+        let cc = CacheControl {
+            must_revalidate: true,
+            ..cc
+        };
+        assert!(!cc.can_use_stale(cc.stale_if_error_window, now));
+        assert_eq!(cc.stale_ttl(), 0);
+    }
 }
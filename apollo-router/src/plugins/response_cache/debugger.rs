@@ -65,6 +65,11 @@ pub(crate) enum CacheKeySource {
     Subgraph,
     /// Data fetched from cache
     Cache,
+    /// Expired data fetched from cache, served while it is refreshed or because the subgraph
+    /// request failed
+    Stale,
+    /// Entity returned by a mutation, whose cache tags were invalidated
+    Invalidation,
 }
 
 impl CacheKeyContext {
@@ -291,6 +291,7 @@ mod tests {
                     enabled: true,
                     shared_key: String::from("test"),
                 }),
+                invalidate_on_mutation: None,
             },
             subgraphs: HashMap::new(),
         });
@@ -342,6 +343,7 @@ mod tests {
                     enabled: true,
                     shared_key: String::from("test"),
                 }),
+                invalidate_on_mutation: None,
             },
             subgraphs: [(
                 String::from("test"),
@@ -354,6 +356,7 @@ mod tests {
                         enabled: true,
                         shared_key: String::from("test_test"),
                     }),
+                    invalidate_on_mutation: None,
                 },
             )]
             .into_iter()
@@ -402,6 +405,7 @@ mod tests {
                     enabled: true,
                     shared_key: String::from("test"),
                 }),
+                invalidate_on_mutation: None,
             },
             subgraphs: [
                 (
@@ -415,6 +419,7 @@ mod tests {
                             enabled: true,
                             shared_key: String::from("test_test"),
                         }),
+                        invalidate_on_mutation: None,
                     },
                 ),
                 (
@@ -428,6 +433,7 @@ mod tests {
                             enabled: true,
                             shared_key: String::from("test_test_bis"),
                         }),
+                        invalidate_on_mutation: None,
                     },
                 ),
             ]
@@ -482,6 +488,7 @@ mod tests {
                     enabled: true,
                     shared_key: String::from("test"),
                 }),
+                invalidate_on_mutation: None,
             },
             subgraphs: [
                 (
@@ -495,6 +502,7 @@ mod tests {
                             enabled: true,
                             shared_key: String::from("test_test"),
                         }),
+                        invalidate_on_mutation: None,
                     },
                 ),
                 (
@@ -508,6 +516,7 @@ mod tests {
                             enabled: true,
                             shared_key: String::from("test_test_bis"),
                         }),
+                        invalidate_on_mutation: None,
                     },
                 ),
             ]
@@ -562,6 +571,7 @@ mod tests {
                     enabled: true,
                     shared_key: String::from("test"),
                 }),
+                invalidate_on_mutation: None,
             },
             subgraphs: HashMap::new(),
         });
@@ -12,6 +12,7 @@ mod storage;
     any(not(feature = "ci"), all(target_arch = "x86_64", target_os = "linux"))
 ))]
 pub(crate) mod tests;
+mod warming;
 
 pub(super) trait ErrorCode {
     fn code(&self) -> &'static str;
@@ -23,6 +23,7 @@ use multimap::MultiMap;
 use opentelemetry::Array;
 use opentelemetry::Key;
 use opentelemetry::StringValue;
+use parking_lot::Mutex;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
@@ -42,11 +43,14 @@ use tracing::Span;
 
 use super::cache_control::CacheControl;
 use super::invalidation::Invalidation;
+use super::invalidation::InvalidationRequest;
 use super::invalidation_endpoint::InvalidationEndpointConfig;
 use super::invalidation_endpoint::InvalidationService;
 use super::invalidation_endpoint::SubgraphInvalidationConfig;
 use super::metrics::CacheMetricContextKey;
 use super::metrics::record_fetch_error;
+use super::warming;
+use super::warming::Warming;
 use crate::Context;
 use crate::Endpoint;
 use crate::ListenAddr;
@@ -121,6 +125,9 @@ pub(crate) struct ResponseCache {
     /// map containing the enum GRAPH
     subgraph_enums: Arc<HashMap<String, String>>,
     lru_size_instrument: LruSizeInstrument,
+    /// Cache keys of the stale entries currently refreshed in the background
+    revalidating: Arc<Mutex<HashSet<String>>>,
+    warming: Option<Arc<Warming>>,
     /// Sender to tell spawned tasks to abort when this struct is dropped
     drop_tx: broadcast::Sender<()>,
 }
@@ -143,6 +150,15 @@ impl StorageInterface {
         storage.get()
     }
 
+    /// Whether all storages are connected.
+    pub(crate) fn is_connected(&self) -> bool {
+        self.all.iter().all(|storage| storage.get().is_some())
+            && self
+                .subgraphs
+                .values()
+                .all(|storage| storage.get().is_some())
+    }
+
     /// Activate all storages so they can start emitting metrics.
     pub(crate) fn activate(&self) {
         if let Some(all) = &self.all
@@ -206,6 +222,10 @@ pub(crate) struct Config {
     /// Buffer size for known private queries (default: 2048)
     #[serde(default = "default_lru_private_queries_size")]
     private_queries_buffer_size: NonZeroUsize,
+
+    /// Replay operations in the background to populate the cache
+    #[serde(default)]
+    warming: warming::Config,
 }
 
 const fn default_lru_private_queries_size() -> NonZeroUsize {
@@ -230,6 +250,10 @@ pub(crate) struct Subgraph {
 
     /// Invalidation configuration
     pub(crate) invalidation: Option<SubgraphInvalidationConfig>,
+
+    /// Invalidate the cache tags of the entities returned by mutation fields, derived from their
+    /// `@cacheTag` directives, overrides the global configuration
+    pub(crate) invalidate_on_mutation: Option<bool>,
 }
 
 impl Default for Subgraph {
@@ -240,6 +264,7 @@ impl Default for Subgraph {
             ttl: Default::default(),
             private_id: Default::default(),
             invalidation: Default::default(),
+            invalidate_on_mutation: Default::default(),
         }
     }
 }
@@ -262,6 +287,8 @@ pub(crate) struct CacheSubgraph(pub(crate) HashMap<String, CacheHitMiss>);
 pub(crate) struct CacheHitMiss {
     pub(crate) hit: usize,
     pub(crate) miss: usize,
+    /// Expired entries served from cache because of `stale-while-revalidate` or `stale-if-error`
+    pub(crate) stale: usize,
 }
 
 #[async_trait::async_trait]
@@ -356,6 +383,7 @@ impl PluginPrivate for ResponseCache {
 
         let storage_interface = Arc::new(storage_interface);
         let invalidation = Invalidation::new(storage_interface.clone()).await?;
+        let warming = Warming::new(init.config.warming, init.previous_config.is_some());
 
         Ok(Self {
             storage: storage_interface,
@@ -371,15 +399,24 @@ impl PluginPrivate for ResponseCache {
             subgraph_enums: Arc::new(get_subgraph_enums(&init.supergraph_schema)),
             supergraph_schema: init.supergraph_schema,
             lru_size_instrument: LruSizeInstrument::new(LRU_PRIVATE_QUERIES_INSTRUMENT_NAME),
+            revalidating: Default::default(),
+            warming,
             drop_tx,
         })
     }
 
     fn activate(&self) {
         self.storage.activate();
+        if let Some(warming) = &self.warming {
+            warming.start(self.storage.clone(), self.drop_tx.subscribe());
+        }
     }
 
     fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        let service = match &self.warming {
+            Some(warming) => warming.supergraph_service(service),
+            None => service,
+        };
         let debug = self.debug;
         ServiceBuilder::new()
             .map_response(move |mut response: supergraph::Response| {
@@ -430,6 +467,7 @@ impl PluginPrivate for ResponseCache {
             .unwrap_or_else(|| Duration::from_secs(60 * 60 * 24)); // The unwrap should not happen because it's checked when creating the plugin (except for tests)
         let subgraph_enabled = self.subgraph_enabled(name);
         let private_id = self.subgraphs.get(name).private_id.clone();
+        let invalidate_on_mutation = self.subgraph_invalidate_on_mutation(name);
 
         let name = name.to_string();
 
@@ -461,6 +499,9 @@ impl PluginPrivate for ResponseCache {
                     supergraph_schema: self.supergraph_schema.clone(),
                     subgraph_enums: self.subgraph_enums.clone(),
                     lru_size_instrument: self.lru_size_instrument.clone(),
+                    revalidating: self.revalidating.clone(),
+                    invalidation: self.invalidation.clone(),
+                    invalidate_on_mutation,
                 });
             tower::util::BoxService::new(inner)
         } else {
@@ -589,6 +630,8 @@ impl ResponseCache {
             subgraph_enums: Arc::new(get_subgraph_enums(&supergraph_schema)),
             supergraph_schema,
             lru_size_instrument: LruSizeInstrument::new(LRU_PRIVATE_QUERIES_INSTRUMENT_NAME),
+            revalidating: Default::default(),
+            warming: None,
             drop_tx,
         })
     }
@@ -642,6 +685,8 @@ impl ResponseCache {
             subgraph_enums: Arc::new(get_subgraph_enums(&supergraph_schema)),
             supergraph_schema,
             lru_size_instrument: LruSizeInstrument::new(LRU_PRIVATE_QUERIES_INSTRUMENT_NAME),
+            revalidating: Default::default(),
+            warming: None,
             drop_tx,
         })
     }
@@ -671,6 +716,14 @@ impl ResponseCache {
     }
 
     // Returns the configured ttl for this subgraph
+    fn subgraph_invalidate_on_mutation(&self, subgraph_name: &str) -> bool {
+        self.subgraphs
+            .get(subgraph_name)
+            .invalidate_on_mutation
+            .or(self.subgraphs.all.invalidate_on_mutation)
+            .unwrap_or_default()
+    }
+
     fn subgraph_ttl(&self, subgraph_name: &str) -> Option<Duration> {
         self.subgraphs
             .get(subgraph_name)
@@ -721,6 +774,9 @@ struct CacheService {
     supergraph_schema: Arc<Valid<Schema>>,
     subgraph_enums: Arc<HashMap<String, String>>,
     lru_size_instrument: LruSizeInstrument,
+    revalidating: Arc<Mutex<HashSet<String>>>,
+    invalidation: Invalidation,
+    invalidate_on_mutation: bool,
 }
 
 impl Service<subgraph::Request> for CacheService {
@@ -788,6 +844,10 @@ impl CacheService {
             return self.service.call(request).await;
         }
 
+        if self.invalidate_on_mutation && request.operation_kind == OperationKind::Mutation {
+            return self.call_service_for_mutation(request).await;
+        }
+
         // [RFC 9111](https://datatracker.ietf.org/doc/html/rfc9111):
         //  * no-store: allows serving response from cache, but prohibits storing response in cache
         //  * no-cache: prohibits serving response from cache, but allows storing response in cache
@@ -877,6 +937,89 @@ impl CacheService {
         }
     }
 
+    /// Call the subgraph for a mutation, then invalidate the cache tags of the entities returned by
+    /// its root fields.
+    async fn call_service_for_mutation(
+        mut self,
+        request: subgraph::Request,
+    ) -> Result<subgraph::Response, BoxError> {
+        let root_fields = mutation_root_fields(&request);
+        let debug_subgraph_request = self.debug.then(|| request.subgraph_request.body().clone());
+        let response = self.service.call(request).await?;
+
+        let entities = mutation_entities(
+            response.response.body().data.as_ref(),
+            &root_fields,
+            &self.name,
+            &self.supergraph_schema,
+            &self.subgraph_enums,
+        );
+        let cache_tags: HashSet<String> = entities
+            .iter()
+            .flat_map(|entity| entity.cache_tags.iter().cloned())
+            .collect();
+        if cache_tags.is_empty() {
+            return Ok(response);
+        }
+
+        let span = tracing::info_span!(
+            "response_cache.invalidation.mutation",
+            subgraph.name = self.name.clone(),
+            "cache.tags" = cache_tags.len(),
+            "cache.invalidated" = ::tracing::field::Empty,
+        );
+        let requests = cache_tags
+            .into_iter()
+            .map(|cache_tag| InvalidationRequest::CacheTag {
+                subgraphs: HashSet::from([self.name.clone()]),
+                cache_tag,
+            })
+            .collect();
+        match self
+            .invalidation
+            .invalidate(requests)
+            .instrument(span.clone())
+            .await
+        {
+            Ok(count) => {
+                span.record("cache.invalidated", count);
+            }
+            Err(err) => {
+                span.mark_as_error(format!("cannot invalidate cache entries: {err}"));
+                tracing::error!(
+                    cache = "response",
+                    subgraph.name = self.name,
+                    error = %err,
+                    "could not invalidate response cache entries after a mutation",
+                );
+            }
+        }
+
+        if let Some(subgraph_request) = debug_subgraph_request {
+            add_cache_keys_to_context(
+                &response.context,
+                entities.into_iter().map(|entity| CacheKeyContext {
+                    key: "-".to_string(),
+                    invalidation_keys: entity.cache_tags,
+                    kind: CacheEntryKind::Entity {
+                        typename: entity.typename,
+                        entity_key: entity.entity_key,
+                    },
+                    hashed_private_id: None,
+                    subgraph_name: self.name.clone(),
+                    subgraph_request: subgraph_request.clone(),
+                    source: CacheKeySource::Invalidation,
+                    cache_control: CacheControl::no_store(),
+                    data: entity.data,
+                    warnings: Vec::new(),
+                    should_store: false,
+                }),
+            )?;
+        }
+
+        Ok(response)
+    }
+
     async fn call_service_for_private_query_without_id(
         mut self,
         request: subgraph::Request,
@@ -962,114 +1105,218 @@ impl CacheService {
         ))
         .await?
         {
-            ControlFlow::Break(response) => {
-                cache_hit.insert("Query".to_string(), CacheHitMiss { hit: 1, miss: 0 });
+            ControlFlow::Break((response, revalidation)) => {
+                let cache_hit_miss = if revalidation.is_some() {
+                    CacheHitMiss {
+                        stale: 1,
+                        ..Default::default()
+                    }
+                } else {
+                    CacheHitMiss {
+                        hit: 1,
+                        ..Default::default()
+                    }
+                };
+                cache_hit.insert("Query".to_string(), cache_hit_miss);
                 let _ = response.context.insert(
                     CacheMetricContextKey::new(response.subgraph_name.clone()),
                     CacheSubgraph(cache_hit),
                 );
 
+                if let Some(revalidation) = revalidation {
+                    self.revalidate(
+                        revalidation,
+                        storage,
+                        is_known_private,
+                        private_id,
+                        private_query_key,
+                        request_cache_control,
+                    );
+                }
+
                 Ok(response)
             }
-            ControlFlow::Continue((request, mut root_cache_key, mut invalidation_keys)) => {
-                cache_hit.insert("Query".to_string(), CacheHitMiss { hit: 0, miss: 1 });
+            ControlFlow::Continue((request, root_cache_key, invalidation_keys, stale_entry)) => {
+                cache_hit.insert(
+                    "Query".to_string(),
+                    CacheHitMiss {
+                        miss: 1,
+                        ..Default::default()
+                    },
+                );
                 let _ = request.context.insert(
                     CacheMetricContextKey::new(request.subgraph_name.clone()),
                     CacheSubgraph(cache_hit),
                 );
 
                 // stash a few pieces of the request to use for debugging later
-                let mut root_operation_fields: Vec<String> = Vec::new();
-                let mut debug_subgraph_request = None;
+                let mut debug_request = None;
                 if self.debug {
-                    root_operation_fields = request.root_operation_fields();
-                    debug_subgraph_request = Some(request.subgraph_request.body().clone());
+                    debug_request = Some((
+                        request.root_operation_fields(),
+                        request.subgraph_request.body().clone(),
+                    ));
                 }
 
-                let response = self.service.call(request).await?;
-
-                let mut cache_control =
-                    response.subgraph_cache_control(self.subgraph_ttl.into())?;
-
-                // Support cache tags coming from subgraph response extensions
-                if let Some(Value::Array(cache_tags)) =
-                    response.get_from_extensions(GRAPHQL_RESPONSE_EXTENSION_ROOT_FIELDS_CACHE_TAGS)
-                {
-                    invalidation_keys.extend(
-                        cache_tags
-                            .iter()
-                            .filter_map(|v| v.as_str())
-                            .map(|s| s.to_owned()),
-                    );
-                }
-                save_original_cache_control(
-                    response.id.clone(),
-                    &response.context,
-                    cache_control.clone(),
-                );
-
-                if cache_control.private() {
-                    // we did not know in advance that this was a query with a private scope, so we update the cache key
-                    if !is_known_private {
-                        let size = {
-                            let mut private_queries = self.private_queries.write().await;
-                            private_queries.put(private_query_key.clone(), ());
-                            private_queries.len()
-                        };
-                        self.lru_size_instrument.update(size as u64);
+                let Some(stale_entry) = stale_entry else {
+                    let response = self.service.call(request).await?;
+                    return self
+                        .store_root_fields_response(
+                            response,
+                            storage,
+                            is_known_private,
+                            private_id,
+                            private_query_key,
+                            request_cache_control,
+                            root_cache_key,
+                            invalidation_keys,
+                            debug_request,
+                        )
+                        .await;
+                };
 
-                        if let Some(s) = private_id.as_ref() {
-                            root_cache_key = format!("{root_cache_key}:{s}");
-                        }
+                // keep what is needed to answer with the stale entry if the subgraph request fails
+                let id = request.id.clone();
+                let context = request.context.clone();
+                let subgraph_name = request.subgraph_name.clone();
+                match self.service.call(request).await {
+                    Ok(response) if !is_subgraph_failure(&response) => {
+                        self.store_root_fields_response(
+                            response,
+                            storage,
+                            is_known_private,
+                            private_id,
+                            private_query_key,
+                            request_cache_control,
+                            root_cache_key,
+                            invalidation_keys,
+                            debug_request,
+                        )
+                        .await
+                    }
+                    _ => {
+                        tracing::debug!(
+                            subgraph.name = subgraph_name,
+                            "subgraph request failed, serving stale data from response cache"
+                        );
+                        let _ = context.insert(
+                            CacheMetricContextKey::new(subgraph_name.clone()),
+                            CacheSubgraph(HashMap::from([(
+                                "Query".to_string(),
+                                CacheHitMiss {
+                                    stale: 1,
+                                    ..Default::default()
+                                },
+                            )])),
+                        );
+                        cached_root_response(
+                            stale_entry,
+                            id,
+                            context,
+                            subgraph_name,
+                            private_id.as_deref(),
+                            debug_request,
+                        )
                     }
                 }
+            }
+        }
+    }
 
-                // if the request had no_store on it, propagate that to this cache control
-                if let Some(request_cache_control) = request_cache_control {
-                    cache_control.no_store |= request_cache_control.no_store;
-                }
+    /// Store the subgraph response for a root fields query in the cache
+    #[allow(clippy::too_many_arguments)]
+    async fn store_root_fields_response(
+        self,
+        response: subgraph::Response,
+        storage: Storage,
+        is_known_private: bool,
+        private_id: Option<String>,
+        private_query_key: PrivateQueryKey,
+        request_cache_control: Option<CacheControl>,
+        mut root_cache_key: String,
+        mut invalidation_keys: Vec<String>,
+        // Only Some if debug is enabled
+        debug_request: Option<(Vec<String>, graphql::Request)>,
+    ) -> Result<subgraph::Response, BoxError> {
+        let mut cache_control = response.subgraph_cache_control(self.subgraph_ttl.into())?;
 
-                if self.debug {
-                    let cache_key_context = CacheKeyContext {
-                        key: root_cache_key.clone(),
-                        hashed_private_id: private_id.clone(),
-                        invalidation_keys: external_invalidation_keys(invalidation_keys.clone()),
-                        kind: CacheEntryKind::RootFields {
-                            root_fields: root_operation_fields,
-                        },
-                        subgraph_name: self.name.clone(),
-                        subgraph_request: debug_subgraph_request.unwrap_or_default(),
-                        source: CacheKeySource::Subgraph,
-                        cache_control: cache_control.clone(),
-                        data: serde_json_bytes::to_value(response.response.body().clone())
-                            .unwrap_or_default(),
-                        warnings: Vec::new(),
-                        should_store: true,
-                    }
-                    .update_metadata();
-                    add_cache_key_to_context(&response.context, cache_key_context)?;
-                }
+        // Support cache tags coming from subgraph response extensions
+        if let Some(Value::Array(cache_tags)) =
+            response.get_from_extensions(GRAPHQL_RESPONSE_EXTENSION_ROOT_FIELDS_CACHE_TAGS)
+        {
+            invalidation_keys.extend(
+                cache_tags
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.to_owned()),
+            );
+        }
+        save_original_cache_control(
+            response.id.clone(),
+            &response.context,
+            cache_control.clone(),
+        );
 
-                // the response has a private scope but we don't have a way to differentiate
-                // users, so we do not store the response in cache
-                let unstorable_private_response = cache_control.private() && private_id.is_none();
+        if cache_control.private() {
+            // we did not know in advance that this was a query with a private scope, so we update the cache key
+            if !is_known_private {
+                let size = {
+                    let mut private_queries = self.private_queries.write().await;
+                    private_queries.put(private_query_key.clone(), ());
+                    private_queries.len()
+                };
+                self.lru_size_instrument.update(size as u64);
 
-                if !unstorable_private_response && cache_control.should_store() {
-                    cache_store_root_from_response(
-                        storage,
-                        self.subgraph_ttl,
-                        &response,
-                        cache_control,
-                        root_cache_key,
-                        invalidation_keys,
-                        self.debug,
-                    )
-                    .await?;
+                if let Some(s) = private_id.as_ref() {
+                    root_cache_key = format!("{root_cache_key}:{s}");
                 }
+            }
+        }
 
-                Ok(response)
+        // if the request had no_store on it, propagate that to this cache control
+        if let Some(request_cache_control) = request_cache_control {
+            cache_control.no_store |= request_cache_control.no_store;
+        }
+
+        if let Some((root_operation_fields, debug_subgraph_request)) = debug_request {
+            let cache_key_context = CacheKeyContext {
+                key: root_cache_key.clone(),
+                hashed_private_id: private_id.clone(),
+                invalidation_keys: external_invalidation_keys(invalidation_keys.clone()),
+                kind: CacheEntryKind::RootFields {
+                    root_fields: root_operation_fields,
+                },
+                subgraph_name: self.name.clone(),
+                subgraph_request: debug_subgraph_request,
+                source: CacheKeySource::Subgraph,
+                cache_control: cache_control.clone(),
+                data: serde_json_bytes::to_value(response.response.body().clone())
+                    .unwrap_or_default(),
+                warnings: Vec::new(),
+                should_store: true,
             }
+            .update_metadata();
+            add_cache_key_to_context(&response.context, cache_key_context)?;
         }
+
+        // the response has a private scope but we don't have a way to differentiate
+        // users, so we do not store the response in cache
+        let unstorable_private_response = cache_control.private() && private_id.is_none();
+
+        if !unstorable_private_response && cache_control.should_store() {
+            cache_store_root_from_response(
+                storage,
+                self.subgraph_ttl,
+                &response,
+                cache_control,
+                root_cache_key,
+                invalidation_keys,
+                self.debug,
+            )
+            .await?;
+        }
+
+        Ok(response)
     }
 
     async fn call_service_for_entities_query(
@@ -1102,7 +1349,19 @@ impl CacheService {
         ))
         .await?
         {
-            ControlFlow::Break(response) => Ok(response),
+            ControlFlow::Break((response, revalidation)) => {
+                if let Some(revalidation) = revalidation {
+                    self.revalidate(
+                        revalidation,
+                        storage,
+                        is_known_private,
+                        private_id,
+                        private_query_key,
+                        request_cache_control,
+                    );
+                }
+                Ok(response)
+            }
             ControlFlow::Continue((request, mut cache_result)) => {
                 let context = request.context.clone();
                 let mut debug_subgraph_request = None;
@@ -1131,7 +1390,7 @@ impl CacheService {
                     add_cache_keys_to_context(&request.context, debug_cache_keys_ctx)?;
                 }
                 let req_id = request.id.clone();
-                let mut response = match self.service.call(request).await {
+                let response = match self.service.call(request).await {
                     Ok(response) => response,
                     Err(e) => {
                         let e = match e.downcast::<FetchError>() {
@@ -1152,8 +1411,15 @@ impl CacheService {
 
                         let graphql_error = e.to_graphql_error(None);
 
-                        let (new_entities, new_errors) =
+                        let (new_entities, new_errors, stale_entities) =
                             assemble_response_from_errors(&[graphql_error], &mut cache_result.0);
+                        record_stale_if_error(
+                            stale_entities,
+                            &self.name,
+                            &context,
+                            private_id.as_deref(),
+                            debug_subgraph_request.as_ref(),
+                        )?;
 
                         let mut data = Object::default();
                         data.insert(ENTITIES, new_entities.into());
@@ -1172,48 +1438,154 @@ impl CacheService {
                     }
                 };
 
-                let mut cache_control =
-                    response.subgraph_cache_control(self.subgraph_ttl.into())?;
-
-                save_original_cache_control(
-                    response.id.clone(),
-                    &response.context,
-                    cache_control.clone(),
-                );
-
-                if let Some(control_from_cached) = cache_result.1 {
-                    cache_control = cache_control.merge(&control_from_cached);
-                }
-
-                // if the request had no_store on it, propagate that to this cache control
-                if let Some(request_cache_control) = request_cache_control {
-                    cache_control.no_store |= request_cache_control.no_store;
-                }
-
-                if !is_known_private && cache_control.private() {
-                    self.private_queries
-                        .write()
-                        .await
-                        .put(private_query_key, ());
-                }
-
-                cache_store_entities_from_response(
+                self.store_entities_response(
+                    response,
                     storage,
-                    self.subgraph_ttl,
-                    &mut response,
-                    cache_control.clone(),
-                    cache_result.0,
+                    cache_result,
                     is_known_private,
                     private_id,
+                    private_query_key,
+                    request_cache_control,
                     debug_subgraph_request,
                 )
-                .await?;
+                .await
+            }
+        }
+    }
 
-                cache_control.to_headers(response.response.headers_mut())?;
+    /// Store the entities from the subgraph response in the cache, and merge them with the
+    /// entities that were already found in the cache
+    #[allow(clippy::too_many_arguments)]
+    async fn store_entities_response(
+        self,
+        mut response: subgraph::Response,
+        storage: Storage,
+        cache_result: ResponseCacheResults,
+        is_known_private: bool,
+        private_id: Option<String>,
+        private_query_key: PrivateQueryKey,
+        request_cache_control: Option<CacheControl>,
+        // Only Some if debug is enabled
+        debug_subgraph_request: Option<graphql::Request>,
+    ) -> Result<subgraph::Response, BoxError> {
+        let mut cache_control = response.subgraph_cache_control(self.subgraph_ttl.into())?;
 
-                Ok(response)
-            }
+        save_original_cache_control(
+            response.id.clone(),
+            &response.context,
+            cache_control.clone(),
+        );
+
+        if let Some(control_from_cached) = cache_result.1 {
+            cache_control = cache_control.merge(&control_from_cached);
+        }
+
+        // if the request had no_store on it, propagate that to this cache control
+        if let Some(request_cache_control) = request_cache_control {
+            cache_control.no_store |= request_cache_control.no_store;
         }
+
+        if !is_known_private && cache_control.private() {
+            self.private_queries
+                .write()
+                .await
+                .put(private_query_key, ());
+        }
+
+        cache_store_entities_from_response(
+            storage,
+            self.subgraph_ttl,
+            &mut response,
+            cache_control.clone(),
+            cache_result.0,
+            is_known_private,
+            private_id,
+            debug_subgraph_request,
+        )
+        .await?;
+
+        cache_control.to_headers(response.response.headers_mut())?;
+
+        Ok(response)
+    }
+
+    /// Refresh stale entries from the subgraph in the background, while they are served to the
+    /// client (`stale-while-revalidate`)
+    fn revalidate(
+        &self,
+        revalidation: Revalidation,
+        storage: Storage,
+        is_known_private: bool,
+        private_id: Option<String>,
+        private_query_key: PrivateQueryKey,
+        request_cache_control: Option<CacheControl>,
+    ) {
+        let Some(revalidating) =
+            RevalidatingKeys::acquire(self.revalidating.clone(), revalidation.cache_keys())
+        else {
+            // another request is already refreshing these entries
+            return;
+        };
+        let mut cache_service = self.clone();
+        // the debugger entries would be added once the client response was already sent
+        cache_service.debug = false;
+        let span = tracing::info_span!(
+            "response_cache.revalidate",
+            kind = revalidation.kind(),
+            "subgraph.name" = self.name.clone()
+        );
+
+        tokio::spawn(
+            async move {
+                // the keys are released when this task ends, even if it panics
+                let _revalidating = revalidating;
+                let result = match revalidation {
+                    Revalidation::Root {
+                        request,
+                        cache_key,
+                        invalidation_keys,
+                    } => match cache_service.service.clone().oneshot(request).await {
+                        Ok(response) => cache_service
+                            .store_root_fields_response(
+                                response,
+                                storage,
+                                is_known_private,
+                                private_id,
+                                private_query_key,
+                                request_cache_control,
+                                cache_key,
+                                invalidation_keys,
+                                None,
+                            )
+                            .await
+                            .map(|_| ()),
+                        Err(err) => Err(err),
+                    },
+                    Revalidation::Entities { request, results } => {
+                        match cache_service.service.clone().oneshot(request).await {
+                            Ok(response) => cache_service
+                                .store_entities_response(
+                                    response,
+                                    storage,
+                                    ResponseCacheResults(results, None),
+                                    is_known_private,
+                                    private_id,
+                                    private_query_key,
+                                    request_cache_control,
+                                    None,
+                                )
+                                .await
+                                .map(|_| ()),
+                            Err(err) => Err(err),
+                        }
+                    }
+                };
+                if let Err(err) = result {
+                    tracing::debug!(error = %err, "cannot revalidate stale response cache entries");
+                }
+            }
+            .instrument(span),
+        );
     }
 
     fn get_private_id(&self, context: &Context) -> Option<String> {
@@ -1226,7 +1598,7 @@ impl CacheService {
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 async fn cache_lookup_root(
     name: String,
     entity_type_opt: Option<&str>,
@@ -1238,7 +1610,13 @@ async fn cache_lookup_root(
     supergraph_schema: Arc<Valid<Schema>>,
     subgraph_enums: &HashMap<String, String>,
     cache_control: Option<&CacheControl>,
-) -> Result<ControlFlow<subgraph::Response, (subgraph::Request, String, Vec<String>)>, BoxError> {
+) -> Result<
+    ControlFlow<
+        (subgraph::Response, Option<Revalidation>),
+        (subgraph::Request, String, Vec<String>, Option<CacheEntry>),
+    >,
+    BoxError,
+> {
     let invalidation_cache_keys =
         get_invalidation_root_keys_from_schema(&request, subgraph_enums, supergraph_schema)?;
     let body = request.subgraph_request.body_mut();
@@ -1261,77 +1639,72 @@ async fn cache_lookup_root(
     if cache_control.is_some_and(|c| c.is_no_cache()) {
         // skip cache lookup if no-cache is set - we have no means of revalidating entries without
         // just performing the query, so there's no benefit to hitting the cache
-        return Ok(ControlFlow::Continue((request, key, invalidation_keys)));
+        return Ok(ControlFlow::Continue((
+            request,
+            key,
+            invalidation_keys,
+            None,
+        )));
     }
 
     match cache.fetch(&key, &request.subgraph_name).await {
-        Ok(value) => {
-            if value.control.can_use() {
-                let control = value.control.clone();
-                // Keep original cache control for every subgraph request (useful for telemetry)
-                save_original_cache_control(request.id.clone(), &request.context, control.clone());
-                update_cache_control(&request.context, &control);
-                if debug {
-                    // TODO: this uses iter() rather than get(request.subgraph_operation_name()) - why?
-                    let root_operation_fields: Vec<String> = request
-                        .executable_document
-                        .as_ref()
-                        .and_then(|executable_document| {
-                            Some(
-                                executable_document
-                                    .operations
-                                    .iter()
-                                    .next()?
-                                    .root_fields(executable_document)
-                                    .map(|f| f.name.to_string())
-                                    .collect(),
-                            )
-                        })
-                        .unwrap_or_default();
-
-                    let cache_key_context = CacheKeyContext {
-                        key: value.key.clone(),
-                        hashed_private_id: private_id.map(ToString::to_string),
-                        invalidation_keys: value
-                            .cache_tags
-                            .map(external_invalidation_keys)
-                            .unwrap_or_default(),
-                        kind: CacheEntryKind::RootFields {
-                            root_fields: root_operation_fields,
-                        },
-                        subgraph_name: request.subgraph_name.clone(),
-                        subgraph_request: request.subgraph_request.body().clone(),
-                        source: CacheKeySource::Cache,
-                        cache_control: value.control.clone(),
-                        data: serde_json_bytes::json!({"data": value.data.clone()}),
-                        warnings: Vec::new(),
-                        should_store: false,
-                    }
-                    .update_metadata();
-                    add_cache_key_to_context(&request.context, cache_key_context)?;
-                }
+        Ok(value) if value.control.can_use() || value.control.can_use_stale_while_revalidate() => {
+            let stale = !value.control.can_use();
+            let debug_request = debug.then(|| {
+                // TODO: this uses iter() rather than get(request.subgraph_operation_name()) - why?
+                let root_operation_fields: Vec<String> = request
+                    .executable_document
+                    .as_ref()
+                    .and_then(|executable_document| {
+                        Some(
+                            executable_document
+                                .operations
+                                .iter()
+                                .next()?
+                                .root_fields(executable_document)
+                                .map(|f| f.name.to_string())
+                                .collect(),
+                        )
+                    })
+                    .unwrap_or_default();
+                (
+                    root_operation_fields,
+                    request.subgraph_request.body().clone(),
+                )
+            });
 
-                Span::current().set_span_dyn_attribute(
-                    opentelemetry::Key::new("cache.status"),
-                    opentelemetry::Value::String("hit".into()),
-                );
-                let mut response = subgraph::Response::builder()
-                    .data(value.data)
-                    .extensions(Object::new())
-                    .id(request.id)
-                    .context(request.context)
-                    .subgraph_name(request.subgraph_name.clone())
-                    .build();
-
-                value.control.to_headers(response.response.headers_mut())?;
-                Ok(ControlFlow::Break(response))
-            } else {
-                Span::current().set_span_dyn_attribute(
-                    opentelemetry::Key::new("cache.status"),
-                    opentelemetry::Value::String("miss".into()),
-                );
-                Ok(ControlFlow::Continue((request, key, invalidation_keys)))
-            }
+            Span::current().set_span_dyn_attribute(
+                opentelemetry::Key::new("cache.status"),
+                opentelemetry::Value::String(if stale { "stale" } else { "hit" }.into()),
+            );
+            let revalidation = stale.then(|| Revalidation::Root {
+                request: revalidation_request(&request),
+                cache_key: key,
+                invalidation_keys,
+            });
+            let response = cached_root_response(
+                value,
+                request.id,
+                request.context,
+                request.subgraph_name,
+                private_id,
+                debug_request,
+            )?;
+            Ok(ControlFlow::Break((response, revalidation)))
+        }
+        Ok(value) => {
+            Span::current().set_span_dyn_attribute(
+                opentelemetry::Key::new("cache.status"),
+                opentelemetry::Value::String("miss".into()),
+            );
+            // an expired entry can still be used if the subgraph request fails
+            let stale_entry = value.control.can_use_stale_if_error().then_some(value);
+            Ok(ControlFlow::Continue((
+                request,
+                key,
+                invalidation_keys,
+                stale_entry,
+            )))
         }
         Err(err) => {
             let span = Span::current();
@@ -1343,11 +1716,66 @@ async fn cache_lookup_root(
                 opentelemetry::Key::new("cache.status"),
                 opentelemetry::Value::String("miss".into()),
             );
-            Ok(ControlFlow::Continue((request, key, invalidation_keys)))
+            Ok(ControlFlow::Continue((
+                request,
+                key,
+                invalidation_keys,
+                None,
+            )))
         }
     }
 }
 
+/// Build the subgraph response for a root fields query from a cache entry
+fn cached_root_response(
+    value: CacheEntry,
+    id: SubgraphRequestId,
+    context: Context,
+    subgraph_name: String,
+    private_id: Option<&str>,
+    // Only Some if debug is enabled
+    debug_request: Option<(Vec<String>, graphql::Request)>,
+) -> Result<subgraph::Response, BoxError> {
+    let control = value.control.clone();
+    // Keep original cache control for every subgraph request (useful for telemetry)
+    save_original_cache_control(id.clone(), &context, control.clone());
+    update_cache_control(&context, &control);
+    if let Some((root_operation_fields, subgraph_request)) = debug_request {
+        let cache_key_context = CacheKeyContext {
+            key: value.key.clone(),
+            hashed_private_id: private_id.map(ToString::to_string),
+            invalidation_keys: value
+                .cache_tags
+                .clone()
+                .map(external_invalidation_keys)
+                .unwrap_or_default(),
+            kind: CacheEntryKind::RootFields {
+                root_fields: root_operation_fields,
+            },
+            subgraph_name: subgraph_name.clone(),
+            subgraph_request,
+            source: cache_key_source(&control),
+            cache_control: control.clone(),
+            data: serde_json_bytes::json!({"data": value.data.clone()}),
+            warnings: Vec::new(),
+            should_store: false,
+        }
+        .update_metadata();
+        add_cache_key_to_context(&context, cache_key_context)?;
+    }
+
+    let mut response = subgraph::Response::builder()
+        .data(value.data)
+        .extensions(Object::new())
+        .id(id)
+        .context(context)
+        .subgraph_name(subgraph_name)
+        .build();
+
+    control.to_headers(response.response.headers_mut())?;
+    Ok(response)
+}
+
 fn get_invalidation_root_keys_from_schema(
     request: &subgraph::Request,
     subgraph_enums: &HashMap<String, String>,
@@ -1477,7 +1905,7 @@ fn get_invalidation_root_keys_from_schema(
 #[derive(Default)]
 struct ResponseCacheResults(Vec<IntermediateResult>, Option<CacheControl>);
 
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 async fn cache_lookup_entities(
     name: String,
     supergraph_schema: Arc<Valid<Schema>>,
@@ -1488,7 +1916,13 @@ async fn cache_lookup_entities(
     mut request: subgraph::Request,
     debug: bool,
     cache_control: Option<&CacheControl>,
-) -> Result<ControlFlow<subgraph::Response, (subgraph::Request, ResponseCacheResults)>, BoxError> {
+) -> Result<
+    ControlFlow<
+        (subgraph::Response, Option<Revalidation>),
+        (subgraph::Request, ResponseCacheResults),
+    >,
+    BoxError,
+> {
     let cache_metadata = extract_cache_keys(
         &name,
         supergraph_schema,
@@ -1528,7 +1962,13 @@ async fn cache_lookup_entities(
         Ok(res) => res
             .into_iter()
             .map(|v| match v {
-                Some(v) if v.control.can_use() => Some(v),
+                Some(v)
+                    if v.control.can_use()
+                        || v.control.can_use_stale_while_revalidate()
+                        || v.control.can_use_stale_if_error() =>
+                {
+                    Some(v)
+                }
                 _ => None,
             })
             .collect(),
@@ -1549,14 +1989,15 @@ async fn cache_lookup_entities(
         .and_then(|value| value.as_array_mut())
         .expect("we already checked that representations exist");
     // remove from representations the entities we already obtained from the cache
-    let (new_representations, cache_result, cache_control) = filter_representations(
-        &name,
-        &request.id,
-        representations,
-        cache_metadata,
-        cache_result,
-        &request.context,
-    )?;
+    let (new_representations, cache_result, cache_control, stale_entities) =
+        filter_representations(
+            &name,
+            &request.id,
+            representations,
+            cache_metadata,
+            cache_result,
+            &request.context,
+        )?;
 
     if !new_representations.is_empty() {
         body.variables
@@ -1574,6 +2015,16 @@ async fn cache_lookup_entities(
             ResponseCacheResults(cache_result, cache_control),
         )))
     } else {
+        let revalidation = (!stale_entities.0.is_empty()).then(|| {
+            let (representations, results) = stale_entities;
+            let mut request = revalidation_request(&request);
+            request
+                .subgraph_request
+                .body_mut()
+                .variables
+                .insert(REPRESENTATIONS, representations.into());
+            Revalidation::Entities { request, results }
+        });
         if debug {
             let debug_cache_keys_ctx = cache_result.iter().filter_map(|ir| {
                 ir.cache_entry.as_ref().map(|cache_entry| {
@@ -1591,7 +2042,7 @@ async fn cache_lookup_entities(
                         },
                         subgraph_name: name.clone(),
                         subgraph_request: request.subgraph_request.body().clone(),
-                        source: CacheKeySource::Cache,
+                        source: cache_key_source(&cache_entry.control),
                         cache_control: cache_entry.control.clone(),
                         data: serde_json_bytes::json!({"data": cache_entry.data.clone()}),
                         warnings: Vec::new(),
@@ -1602,10 +2053,13 @@ async fn cache_lookup_entities(
             });
             add_cache_keys_to_context(&request.context, debug_cache_keys_ctx)?;
         }
-        Span::current().set_span_dyn_attribute(
-            opentelemetry::Key::new("cache.status"),
-            opentelemetry::Value::String("hit".into()),
-        );
+        let cache_status = if revalidation.is_some() {
+            opentelemetry::Value::String("stale".into())
+        } else {
+            opentelemetry::Value::String("hit".into())
+        };
+        Span::current()
+            .set_span_dyn_attribute(opentelemetry::Key::new("cache.status"), cache_status);
 
         let entities = cache_result
             .into_iter()
@@ -1627,7 +2081,7 @@ async fn cache_lookup_entities(
             .unwrap_or_default()
             .to_headers(response.response.headers_mut())?;
 
-        Ok(ControlFlow::Break(response))
+        Ok(ControlFlow::Break((response, revalidation)))
     }
 }
 
@@ -1673,12 +2127,14 @@ async fn cache_store_root_from_response(
             .unwrap_or(default_subgraph_ttl);
 
         if response.response.body().errors.is_empty() && cache_control.should_store() {
+            // keep the entry long enough to be served stale
+            let expire = ttl + Duration::from_secs(cache_control.stale_ttl());
             let document = Document {
                 key: cache_key,
                 data: data.clone(),
                 control: cache_control,
                 invalidation_keys,
-                expire: ttl,
+                expire,
                 debug,
             };
 
@@ -1778,8 +2234,15 @@ async fn cache_store_entities_from_response(
         response.response.body_mut().data = data;
         response.response.body_mut().errors = new_errors;
     } else {
-        let (new_entities, new_errors) =
+        let (new_entities, new_errors, stale_entities) =
             assemble_response_from_errors(&response.response.body().errors, &mut result_from_cache);
+        record_stale_if_error(
+            stale_entities,
+            &response.subgraph_name,
+            &response.context,
+            private_id.as_deref(),
+            subgraph_request.as_ref(),
+        )?;
 
         let mut data = Object::default();
         data.insert(ENTITIES, new_entities.into());
@@ -1958,6 +2421,117 @@ fn extract_cache_keys(
     Ok(res)
 }
 
+/// Entity returned by a mutation field
+struct MutationEntity {
+    typename: String,
+    entity_key: Object,
+    /// Cache tags derived from the `@cacheTag` directives of the entity type
+    cache_tags: Vec<String>,
+    data: Value,
+}
+
+/// Response keys and return types of the root fields of a mutation
+fn mutation_root_fields(request: &subgraph::Request) -> Vec<(String, String)> {
+    request
+        .executable_document
+        .as_ref()
+        .and_then(|executable_document| {
+            Some(
+                executable_document
+                    .operations
+                    .get(request.subgraph_operation_name())
+                    .ok()?
+                    .root_fields(executable_document)
+                    .map(|field| {
+                        (
+                            field.response_key().to_string(),
+                            field.ty().inner_named_type().to_string(),
+                        )
+                    })
+                    .collect(),
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Find the entities returned by the root fields of a mutation, and the cache tags they are
+/// associated with in `subgraph_name`.
+///
+/// Objects which are not entities in this subgraph, or which don't contain the fields of one of
+/// their `@key` directives, are ignored.
+fn mutation_entities(
+    data: Option<&Value>,
+    root_fields: &[(String, String)],
+    subgraph_name: &str,
+    supergraph_schema: &Arc<Valid<Schema>>,
+    subgraph_enums: &HashMap<String, String>,
+) -> Vec<MutationEntity> {
+    fn collect_objects<'a>(value: &'a Value, objects: &mut Vec<&'a Object>) {
+        match value {
+            Value::Object(object) => objects.push(object),
+            Value::Array(values) => {
+                for value in values {
+                    collect_objects(value, objects);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(data) = data.and_then(|data| data.as_object()) else {
+        return Vec::new();
+    };
+    let mut entities = Vec::new();
+    for (response_key, return_type) in root_fields {
+        let Some(value) = data.get(response_key.as_str()) else {
+            continue;
+        };
+        let mut objects = Vec::new();
+        collect_objects(value, &mut objects);
+        for object in objects {
+            let typename = object
+                .get(TYPENAME)
+                .and_then(|typename| typename.as_str())
+                .unwrap_or(return_type);
+            let Ok(key_selection_set) = find_matching_key_field_set(
+                object,
+                typename,
+                subgraph_name,
+                supergraph_schema,
+                subgraph_enums,
+            ) else {
+                continue;
+            };
+            let entity_key = get_entity_key_from_selection_set(object, &key_selection_set);
+            let cache_tags = match get_invalidation_entity_keys_from_schema(
+                supergraph_schema,
+                subgraph_name,
+                subgraph_enums,
+                typename,
+                &entity_key,
+            ) {
+                Ok(cache_tags) => cache_tags,
+                Err(err) => {
+                    tracing::debug!(
+                        "cannot compute cache tags for {typename} returned by a mutation: {err}"
+                    );
+                    continue;
+                }
+            };
+            if cache_tags.is_empty() {
+                continue;
+            }
+            entities.push(MutationEntity {
+                typename: typename.to_string(),
+                entity_key,
+                cache_tags: cache_tags.into_iter().sorted().collect(),
+                data: Value::Object(object.clone()),
+            });
+        }
+    }
+    entities
+}
+
 /// Get invalidation keys from @cacheTag directives in supergraph schema for entities
 fn get_invalidation_entity_keys_from_schema(
     supergraph_schema: &Arc<Valid<Schema>>,
@@ -2319,6 +2893,80 @@ struct IntermediateResult {
     // Only set when debug mode is enabled
     entity_key: Option<serde_json_bytes::Map<ByteString, Value>>,
     cache_entry: Option<CacheEntry>,
+    /// Expired entry served if the subgraph fails to return this entity (`stale-if-error`)
+    stale_if_error: Option<CacheEntry>,
+}
+
+/// Cache keys being refreshed in the background, released when dropped
+struct RevalidatingKeys {
+    revalidating: Arc<Mutex<HashSet<String>>>,
+    cache_keys: Vec<String>,
+}
+
+impl RevalidatingKeys {
+    /// `None` if one of the keys is already being refreshed
+    fn acquire(revalidating: Arc<Mutex<HashSet<String>>>, cache_keys: Vec<String>) -> Option<Self> {
+        {
+            let mut keys = revalidating.lock();
+            if cache_keys.iter().any(|key| keys.contains(key)) {
+                return None;
+            }
+            keys.extend(cache_keys.iter().cloned());
+        }
+        Some(Self {
+            revalidating,
+            cache_keys,
+        })
+    }
+}
+
+impl Drop for RevalidatingKeys {
+    fn drop(&mut self) {
+        let mut keys = self.revalidating.lock();
+        for key in &self.cache_keys {
+            keys.remove(key);
+        }
+    }
+}
+
+/// Stale entries served to the client, to refresh from the subgraph in the background
+enum Revalidation {
+    Root {
+        request: subgraph::Request,
+        cache_key: String,
+        invalidation_keys: Vec<String>,
+    },
+    Entities {
+        request: subgraph::Request,
+        /// One result without cache entry per representation in the request
+        results: Vec<IntermediateResult>,
+    },
+}
+
+impl Revalidation {
+    fn cache_keys(&self) -> Vec<String> {
+        match self {
+            Revalidation::Root { cache_key, .. } => vec![cache_key.clone()],
+            Revalidation::Entities { results, .. } => {
+                results.iter().map(|result| result.key.clone()).collect()
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Revalidation::Root { .. } => "root",
+            Revalidation::Entities { .. } => "entity",
+        }
+    }
+}
+
+/// Copy of the subgraph request used to refresh stale entries, with its own id so that it is
+/// reported separately from the client request
+fn revalidation_request(request: &subgraph::Request) -> subgraph::Request {
+    let mut request = request.clone();
+    request.id = SubgraphRequestId::new();
+    request
 }
 
 // build a new list of representations without the ones we got from the cache
@@ -2331,9 +2979,27 @@ fn filter_representations(
     keys: Vec<CacheMetadata>,
     mut cache_result: Vec<Option<CacheEntry>>,
     context: &Context,
-) -> Result<(Vec<Value>, Vec<IntermediateResult>, Option<CacheControl>), BoxError> {
+) -> Result<
+    (
+        Vec<Value>,
+        Vec<IntermediateResult>,
+        Option<CacheControl>,
+        (Vec<Value>, Vec<IntermediateResult>),
+    ),
+    BoxError,
+> {
+    // Expired entries are only served while they are refreshed in the background if all the
+    // other entities were found in the cache. If the subgraph has to be called anyway, they are
+    // fetched again in the same request.
+    let serve_stale = cache_result.iter().all(|entry| {
+        entry.as_ref().is_some_and(|entry| {
+            entry.control.can_use() || entry.control.can_use_stale_while_revalidate()
+        })
+    });
     let mut new_representations: Vec<Value> = Vec::new();
     let mut result = Vec::new();
+    let mut stale_representations: Vec<Value> = Vec::new();
+    let mut stale_results = Vec::new();
     let mut cache_hit: HashMap<String, CacheHitMiss> = HashMap::new();
     let mut cache_control = None;
     // Useful for telemetry
@@ -2364,9 +3030,10 @@ fn filter_representations(
 
         let typename = opt_type.as_str().unwrap_or("-").to_string();
 
-        // do not use that cache entry if it is stale
-        if let Some(false) = cache_entry.as_ref().map(|c| c.control.can_use()) {
-            cache_entry = None;
+        // do not use that cache entry if it is stale, unless it can be served while it is refreshed
+        let mut stale_if_error = None;
+        if let Some(entry) = cache_entry.take_if(|c| !c.control.can_use() && !serve_stale) {
+            stale_if_error = entry.control.can_use_stale_if_error().then_some(entry);
         }
         match cache_entry.as_ref() {
             None => {
@@ -2378,7 +3045,24 @@ fn filter_representations(
                 new_representations.push(representation);
             }
             Some(entry) => {
-                cache_hit.entry(typename.clone()).or_default().hit += 1;
+                if entry.control.can_use() {
+                    cache_hit.entry(typename.clone()).or_default().hit += 1;
+                } else {
+                    cache_hit.entry(typename.clone()).or_default().stale += 1;
+
+                    representation
+                        .as_object_mut()
+                        .map(|o| o.insert(TYPENAME, opt_type));
+                    stale_representations.push(representation);
+                    stale_results.push(IntermediateResult {
+                        key: key.clone(),
+                        invalidation_keys: invalidation_keys.clone(),
+                        typename: typename.clone(),
+                        entity_key: entity_key.clone(),
+                        cache_entry: None,
+                        stale_if_error: None,
+                    });
+                }
                 match cache_control.as_mut() {
                     None => cache_control = Some(entry.control.clone()),
                     Some(c) => *c = c.merge(&entry.control),
@@ -2396,6 +3080,7 @@ fn filter_representations(
             typename,
             cache_entry,
             entity_key,
+            stale_if_error,
         });
     }
 
@@ -2408,7 +3093,12 @@ fn filter_representations(
         CacheSubgraph(cache_hit),
     );
 
-    Ok((new_representations, result, cache_control))
+    Ok((
+        new_representations,
+        result,
+        cache_control,
+        (stale_representations, stale_results),
+    ))
 }
 
 // fill in the entities for the response
@@ -2435,9 +3125,12 @@ async fn insert_entities_in_result(
         .ttl()
         .map(Duration::from_secs)
         .unwrap_or(default_subgraph_ttl);
+    // keep the entries long enough to be served stale
+    let expire = ttl + Duration::from_secs(cache_control.stale_ttl());
 
     let mut new_entities = Vec::new();
     let mut new_errors = Vec::new();
+    let mut stale_entities = Vec::new();
 
     let mut inserted_types: HashMap<String, usize> = HashMap::new();
     let mut to_insert: Vec<_> = Vec::new();
@@ -2457,6 +3150,7 @@ async fn insert_entities_in_result(
             typename,
             cache_entry,
             entity_key,
+            stale_if_error,
         },
     ) in result.drain(..).enumerate()
     {
@@ -2479,7 +3173,7 @@ async fn insert_entities_in_result(
                     key = format!("{key}:{id}");
                 }
 
-                let mut has_errors = false;
+                let mut entity_errors = Vec::new();
                 for error in errors.iter().filter(|e| {
                     e.path
                         .as_ref()
@@ -2497,9 +3191,24 @@ async fn insert_entities_in_result(
                         path.0[1] = PathElement::Index(new_entity_idx);
                     }
 
-                    new_errors.push(e);
-                    has_errors = true;
+                    entity_errors.push(e);
+                }
+                let has_errors = !entity_errors.is_empty();
+
+                // the subgraph failed to return this entity, serve the expired entry instead
+                if has_errors && let Some(stale_entry) = stale_if_error {
+                    new_entities.push(stale_entry.data.clone());
+                    stale_entities.push(IntermediateResult {
+                        key,
+                        invalidation_keys,
+                        typename,
+                        entity_key,
+                        cache_entry: Some(stale_entry),
+                        stale_if_error: None,
+                    });
+                    continue;
                 }
+                new_errors.extend(entity_errors);
 
                 // apply per-entity cache tags from the subgraph's apolloEntityCacheTags extension; these tags
                 // enable targeted cache invalidation for this specific entity
@@ -2538,7 +3247,7 @@ async fn insert_entities_in_result(
                         data: value.clone(),
                         key,
                         invalidation_keys,
-                        expire: ttl,
+                        expire,
                         debug,
                     });
                 }
@@ -2552,6 +3261,13 @@ async fn insert_entities_in_result(
     if !debug_ctx_entries.is_empty() {
         add_cache_keys_to_context(&context, debug_ctx_entries.into_iter())?;
     }
+    record_stale_if_error(
+        stale_entities,
+        subgraph_name,
+        &context,
+        private_id_for_dbg.as_deref(),
+        subgraph_request.as_ref(),
+    )?;
 
     if !to_insert.is_empty() {
         let batch_size = to_insert.len();
@@ -2581,19 +3297,30 @@ fn external_invalidation_keys<I: IntoIterator<Item = String>>(invalidation_keys:
         .collect()
 }
 
+/// Fill in the entities for a failed subgraph request, from the cache when possible. Entities
+/// served from expired entries (`stale-if-error`) are returned in the last element.
 fn assemble_response_from_errors(
     graphql_errors: &[Error],
     result: &mut Vec<IntermediateResult>,
-) -> (Vec<Value>, Vec<Error>) {
+) -> (Vec<Value>, Vec<Error>, Vec<IntermediateResult>) {
     let mut new_entities = Vec::new();
     let mut new_errors = Vec::new();
+    let mut stale_entities = Vec::new();
 
-    for (new_entity_idx, IntermediateResult { cache_entry, .. }) in result.drain(..).enumerate() {
-        match cache_entry {
-            Some(v) => {
+    for (new_entity_idx, mut intermediate_result) in result.drain(..).enumerate() {
+        match (
+            intermediate_result.cache_entry.take(),
+            intermediate_result.stale_if_error.take(),
+        ) {
+            (Some(v), _) => {
                 new_entities.push(v.data);
             }
-            None => {
+            (None, Some(stale_entry)) => {
+                new_entities.push(stale_entry.data.clone());
+                intermediate_result.cache_entry = Some(stale_entry);
+                stale_entities.push(intermediate_result);
+            }
+            (None, None) => {
                 new_entities.push(Value::Null);
 
                 for mut error in graphql_errors.iter().cloned() {
@@ -2606,7 +3333,86 @@ fn assemble_response_from_errors(
             }
         }
     }
-    (new_entities, new_errors)
+    (new_entities, new_errors, stale_entities)
+}
+
+/// Report the entities served from expired entries because the subgraph failed to return them,
+/// in the cache metrics and in the cache debugger
+fn record_stale_if_error(
+    stale_entities: Vec<IntermediateResult>,
+    subgraph_name: &str,
+    context: &Context,
+    private_id: Option<&str>,
+    // Only Some if debug is enabled
+    subgraph_request: Option<&graphql::Request>,
+) -> Result<(), BoxError> {
+    if stale_entities.is_empty() {
+        return Ok(());
+    }
+    tracing::debug!(
+        subgraph.name = subgraph_name,
+        "subgraph request failed, serving stale entities from response cache"
+    );
+
+    context.upsert::<_, CacheSubgraph>(
+        CacheMetricContextKey::new(subgraph_name.to_string()),
+        |mut cache_subgraph| {
+            for stale_entity in &stale_entities {
+                let cache_hit_miss = cache_subgraph
+                    .0
+                    .entry(stale_entity.typename.clone())
+                    .or_default();
+                cache_hit_miss.miss = cache_hit_miss.miss.saturating_sub(1);
+                cache_hit_miss.stale += 1;
+            }
+            cache_subgraph
+        },
+    )?;
+
+    if let Some(subgraph_request) = subgraph_request {
+        let debug_cache_keys_ctx = stale_entities.into_iter().filter_map(|stale_entity| {
+            let cache_entry = stale_entity.cache_entry?;
+            Some(
+                CacheKeyContext {
+                    key: stale_entity.key,
+                    hashed_private_id: private_id.map(ToString::to_string),
+                    invalidation_keys: external_invalidation_keys(stale_entity.invalidation_keys),
+                    kind: CacheEntryKind::Entity {
+                        typename: stale_entity.typename,
+                        entity_key: stale_entity.entity_key.unwrap_or_default(),
+                    },
+                    subgraph_name: subgraph_name.to_string(),
+                    subgraph_request: subgraph_request.clone(),
+                    source: CacheKeySource::Stale,
+                    cache_control: cache_entry.control,
+                    data: serde_json_bytes::json!({"data": cache_entry.data}),
+                    warnings: Vec::new(),
+                    should_store: false,
+                }
+                .update_metadata(),
+            )
+        });
+        add_cache_keys_to_context(context, debug_cache_keys_ctx)?;
+    }
+
+    Ok(())
+}
+
+/// Source reported in the cache debugger for an entry read from the cache
+fn cache_key_source(control: &CacheControl) -> CacheKeySource {
+    if control.can_use() {
+        CacheKeySource::Cache
+    } else {
+        CacheKeySource::Stale
+    }
+}
+
+/// Whether a subgraph response is a failure that allows serving stale data (`stale-if-error`):
+/// a server error, or errors without any data
+fn is_subgraph_failure(response: &subgraph::Response) -> bool {
+    let body = response.response.body();
+    response.response.status().is_server_error()
+        || (!body.errors.is_empty() && body.data.as_ref().is_none_or(Value::is_null))
 }
 
 async fn connect_or_spawn_reconnection_task(
@@ -2672,30 +3478,47 @@ pub(crate) type CacheControls = HashMap<SubgraphRequestId, CacheControl>;
 ))]
 mod tests {
     use std::collections::HashMap;
+    use std::collections::HashSet;
     use std::sync::Arc;
     use std::time::Duration;
 
     use apollo_compiler::Schema;
     use apollo_compiler::parser::Parser;
+    use http::HeaderName;
+    use http::HeaderValue;
     use serde_json_bytes::json;
     use tokio::sync::broadcast;
+    use tower::ServiceExt;
     use uuid::Uuid;
 
+    use super::CACHE_DEBUG_EXTENSIONS_KEY;
+    use super::CACHE_DEBUG_HEADER_NAME;
+    use super::RevalidatingKeys;
     use super::Subgraph;
     use super::Ttl;
+    use crate::TestHarness;
     use crate::configuration::subgraph::SubgraphConfiguration;
+    use crate::graphql;
     use crate::plugin::PluginInit;
     use crate::plugin::PluginPrivate;
+    use crate::plugin::test::MockSubgraphService;
+    use crate::plugins::response_cache::debugger::CacheKeySource;
+    use crate::plugins::response_cache::debugger::CacheKeysContext;
     use crate::plugins::response_cache::plugin::ResponseCache;
     use crate::plugins::response_cache::plugin::get_entity_key_from_selection_set;
     use crate::plugins::response_cache::plugin::get_invalidation_entity_keys_from_schema;
     use crate::plugins::response_cache::plugin::get_invalidation_root_keys_from_schema;
     use crate::plugins::response_cache::plugin::matches_selection_set;
+    use crate::plugins::response_cache::plugin::mutation_entities;
     use crate::plugins::response_cache::storage::redis::Config;
     use crate::plugins::response_cache::storage::redis::Storage;
     use crate::plugins::response_cache::tests::create_subgraph_conf;
+    use crate::plugins::response_cache::tests::expected_cached_keys;
+    use crate::plugins::response_cache::tests::get_cache_keys_context;
+    use crate::plugins::response_cache::tests::wait_for_cache;
     use crate::services::OperationKind;
     use crate::services::subgraph;
+    use crate::services::supergraph;
 
     const SCHEMA: &str = include_str!("../../testdata/orga_supergraph_cache_key.graphql");
 
@@ -3244,6 +4067,198 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_revalidating_keys_are_released_when_the_task_panics() {
+        let revalidating = Arc::new(parking_lot::Mutex::new(HashSet::new()));
+        let keys = vec!["a".to_string(), "b".to_string()];
+        let guard = RevalidatingKeys::acquire(revalidating.clone(), keys.clone()).unwrap();
+        assert!(RevalidatingKeys::acquire(revalidating.clone(), vec!["b".to_string()]).is_none());
+
+        let task = tokio::spawn(async move {
+            let _guard = guard;
+            panic!("revalidation failed");
+        });
+        assert!(task.await.unwrap_err().is_panic());
+        assert!(revalidating.lock().is_empty());
+        assert!(RevalidatingKeys::acquire(revalidating, keys).is_some());
+    }
+
+    const ACTIVE_ORGANIZATION_QUERY: &str = "query { currentUser { activeOrganization { id } } }";
+
+    async fn stale_test_response_cache(
+        namespace: &str,
+    ) -> (Storage, ResponseCache, broadcast::Sender<()>) {
+        let valid_schema = Arc::new(Schema::parse_and_validate(SCHEMA, "test.graphql").unwrap());
+        let (drop_tx, drop_rx) = broadcast::channel(2);
+        let storage = Storage::new(&Config::test(false, namespace), drop_rx)
+            .await
+            .unwrap();
+        let subgraphs = [(
+            "user".to_string(),
+            Subgraph {
+                enabled: true.into(),
+                ..Default::default()
+            },
+        )]
+        .into_iter()
+        .collect();
+        let response_cache = ResponseCache::for_test(
+            storage.clone(),
+            create_subgraph_conf(subgraphs),
+            valid_schema,
+            true,
+            drop_tx.clone(),
+        )
+        .await
+        .unwrap();
+        (storage, response_cache, drop_tx)
+    }
+
+    /// Supergraph where the `user` subgraph returns the active organization `organization_id`
+    async fn user_subgraph_service(
+        response_cache: &ResponseCache,
+        organization_id: &str,
+        cache_control: &str,
+    ) -> supergraph::BoxCloneService {
+        TestHarness::builder()
+            .configuration_json(serde_json::json!({
+                "include_subgraph_errors": { "all": true },
+                "experimental_mock_subgraphs": {
+                    "user": {
+                        "query": {
+                            "currentUser": {
+                                "activeOrganization": {
+                                    "__typename": "Organization",
+                                    "id": organization_id,
+                                }
+                            }
+                        },
+                        "headers": {"cache-control": cache_control},
+                    },
+                },
+            }))
+            .unwrap()
+            .schema(SCHEMA)
+            .extra_private_plugin(response_cache.clone())
+            .build_supergraph()
+            .await
+            .unwrap()
+    }
+
+    /// Queries the active organization, and returns the response along with its cache keys
+    async fn query_active_organization(
+        service: &supergraph::BoxCloneService,
+    ) -> (graphql::Response, CacheKeysContext) {
+        let request = supergraph::Request::fake_builder()
+            .query(ACTIVE_ORGANIZATION_QUERY)
+            .header(
+                HeaderName::from_static(CACHE_DEBUG_HEADER_NAME),
+                HeaderValue::from_static("true"),
+            )
+            .build()
+            .unwrap();
+        let mut response = service.clone().oneshot(request).await.unwrap();
+        let cache_keys = get_cache_keys_context(&response).expect("missing cache keys");
+        let mut response = response.next_response().await.unwrap();
+        response.extensions.remove(CACHE_DEBUG_EXTENSIONS_KEY);
+        (response, cache_keys)
+    }
+
+    fn active_organization_id(response: &graphql::Response) -> Option<&str> {
+        response
+            .data
+            .as_ref()?
+            .get("currentUser")?
+            .get("activeOrganization")?
+            .get("id")?
+            .as_str()
+    }
+
+    /// Waits for the entry of the first request to be stored, then for it to expire
+    async fn store_expired_entry(
+        storage: &Storage,
+        response_cache: &ResponseCache,
+        cache_control: &str,
+    ) {
+        let service = user_subgraph_service(response_cache, "1", cache_control).await;
+        let (response, cache_keys) = query_active_organization(&service).await;
+        assert_eq!(active_organization_id(&response), Some("1"));
+        wait_for_cache(storage, expected_cached_keys(&cache_keys)).await;
+
+        // max-age is counted in whole seconds
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+    }
+
+    #[tokio::test]
+    async fn test_serve_stale_entry_while_revalidating() {
+        let (storage, response_cache, _drop_tx) =
+            stale_test_response_cache("test_serve_stale_entry_while_revalidating").await;
+        let cache_control = "public, max-age=1, stale-while-revalidate=60";
+        store_expired_entry(&storage, &response_cache, cache_control).await;
+
+        // the subgraph now returns another organization, but the stale entry is served
+        let service = user_subgraph_service(&response_cache, "2", cache_control).await;
+        let (response, cache_keys) = query_active_organization(&service).await;
+        assert_eq!(active_organization_id(&response), Some("1"));
+        assert!(response.errors.is_empty());
+        assert!(
+            cache_keys
+                .iter()
+                .all(|context| matches!(context.source, CacheKeySource::Stale))
+        );
+
+        // the entry is refreshed in the background
+        for _ in 0..50 {
+            let (response, _) = query_active_organization(&service).await;
+            if active_organization_id(&response) == Some("2") {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("the stale entry was not revalidated");
+    }
+
+    #[tokio::test]
+    async fn test_serve_stale_entry_on_subgraph_error() {
+        let (storage, response_cache, _drop_tx) =
+            stale_test_response_cache("test_serve_stale_entry_on_subgraph_error").await;
+        store_expired_entry(
+            &storage,
+            &response_cache,
+            "public, max-age=1, stale-if-error=60",
+        )
+        .await;
+
+        let service = TestHarness::builder()
+            .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+            .unwrap()
+            .schema(SCHEMA)
+            .extra_private_plugin(response_cache.clone())
+            .subgraph_hook(|name, service| {
+                if name == "user" {
+                    let mut subgraph = MockSubgraphService::new();
+                    subgraph
+                        .expect_call()
+                        .times(1)
+                        .returning(|_req: subgraph::Request| Err("user not available".into()));
+                    subgraph.boxed()
+                } else {
+                    service
+                }
+            })
+            .build_supergraph()
+            .await
+            .unwrap();
+        let (response, cache_keys) = query_active_organization(&service).await;
+        assert_eq!(active_organization_id(&response), Some("1"));
+        assert!(response.errors.is_empty());
+        assert!(
+            cache_keys
+                .iter()
+                .all(|context| matches!(context.source, CacheKeySource::Stale))
+        );
+    }
+
     #[test]
     fn test_matches_selection_set_handles_arrays() {
         // Simulate the real-world Availability type scenario
@@ -4122,4 +5137,90 @@ mod tests {
             "should handle concrete type (isInterfaceObject: false)"
         );
     }
+
+    #[test]
+    fn test_mutation_entities() {
+        let schema_text = r#"
+                 directive @join__type(graph: join__Graph!, key: join__FieldSet, isInterfaceObject: Boolean! = false) repeatable on
+     OBJECT | INTERFACE
+                 directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+                 directive @join__directive(graphs: [join__Graph!], name: String!, args: join__DirectiveArguments) repeatable on SCHEMA | OBJECT | INTERFACE | FIELD_DEFINITION
+                 scalar join__FieldSet
+                 scalar join__DirectiveArguments
+
+                 enum join__Graph {
+                  PRODUCTS @join__graph(name: "products", url: "http://products")
+                  REVIEWS @join__graph(name: "reviews", url: "http://reviews")
+                }
+
+                type Query { dummy: String }
+
+                type Mutation {
+                    updateProduct(upc: String!): Product
+                    updateProducts: [Product]
+                    updateStatus: Status
+                }
+
+                type Product
+                    @join__type(graph: PRODUCTS, key: "upc")
+                    @join__type(graph: REVIEWS, key: "upc")
+                    @join__directive(graphs: [PRODUCTS], name: "federation__cacheTag", args: {format: "product-{$key.upc}"})
+                {
+                    upc: String!
+                    name: String
+                }
+
+                type Status @join__type(graph: PRODUCTS) {
+                    ok: Boolean
+                }
+              "#;
+
+        let schema = Arc::new(Schema::parse_and_validate(schema_text, "schema.graphql").unwrap());
+        let subgraph_enums = HashMap::from([
+            ("PRODUCTS".into(), "products".into()),
+            ("REVIEWS".into(), "reviews".into()),
+        ]);
+        let root_fields = [
+            ("updateProduct".to_string(), "Product".to_string()),
+            ("renamed".to_string(), "Product".to_string()),
+            ("updateStatus".to_string(), "Status".to_string()),
+        ];
+        let data = json!({
+            "updateProduct": {"upc": "1", "name": "Table"},
+            "renamed": [{"upc": "2"}, null, {"name": "Chair"}],
+            "updateStatus": {"ok": true}
+        });
+
+        let entities = mutation_entities(
+            Some(&data),
+            &root_fields,
+            "products",
+            &schema,
+            &subgraph_enums,
+        );
+        assert_eq!(
+            entities
+                .iter()
+                .map(|entity| (entity.typename.as_str(), entity.cache_tags.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("Product", vec!["product-1".to_string()]),
+                ("Product", vec!["product-2".to_string()]),
+            ]
+        );
+        assert_eq!(
+            entities[0].entity_key,
+            json!({"upc": "1"}).as_object().unwrap().clone()
+        );
+
+        // no @cacheTag for this type in the reviews subgraph
+        let entities = mutation_entities(
+            Some(&data),
+            &root_fields,
+            "reviews",
+            &schema,
+            &subgraph_enums,
+        );
+        assert!(entities.is_empty());
+    }
 }
@@ -79,6 +79,10 @@ pub(crate) struct Config {
     #[schemars(with = "Option<String>", default)]
     /// Interval for collecting Redis metrics (default: 1s)
     pub(crate) metrics_interval: Duration,
+
+    #[serde(default)]
+    /// Keep recently used entries in memory in front of Redis (disabled by default)
+    pub(crate) in_memory: Option<super::memory::Config>,
 }
 
 fn default_fetch_timeout() -> Duration {
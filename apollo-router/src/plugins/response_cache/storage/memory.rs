@@ -0,0 +1,227 @@
+//! Bounded in-process tier kept in front of Redis.
+//!
+//! Entries are evicted in least-recently-used order once their estimated size exceeds the
+//! configured budget, and expire with the TTL they were stored with in Redis.
+
+use std::collections::HashSet;
+use std::time::Duration;
+use std::time::Instant;
+
+use bytesize::ByteSize;
+use lru::LruCache;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::CacheEntry;
+use crate::cache::estimate_size;
+use crate::plugins::response_cache::cache_control::CacheControl;
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// In-memory cache tier configuration
+pub(crate) struct Config {
+    /// Maximum estimated size of the entries kept in memory, for example `64MB`
+    #[schemars(with = "String")]
+    pub(crate) max_size: ByteSize,
+}
+
+struct MemoryEntry {
+    entry: CacheEntry,
+    /// Cache tags of the entry, in the same form as the tags stored in Redis without the
+    /// namespace and version prefix, ie `subgraph-{s}` and `subgraph-{s}:key-{i}`
+    cache_tags: HashSet<String>,
+    expires_at: Instant,
+    size: usize,
+}
+
+struct Entries {
+    lru: LruCache<String, MemoryEntry>,
+    size: usize,
+}
+
+pub(super) struct MemoryTier {
+    entries: Mutex<Entries>,
+    max_size: usize,
+}
+
+impl MemoryTier {
+    pub(super) fn new(config: &Config) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                lru: LruCache::unbounded(),
+                size: 0,
+            }),
+            max_size: config.max_size.as_u64() as usize,
+        }
+    }
+
+    pub(super) fn get(&self, key: &str) -> Option<CacheEntry> {
+        let mut entries = self.entries.lock();
+        let memory_entry = entries.lru.get(key)?;
+        if memory_entry.expires_at > Instant::now() {
+            return Some(memory_entry.entry.clone());
+        }
+        entries.remove(key);
+        None
+    }
+
+    /// Keep `entry` in memory for `ttl`. Entries larger than the whole budget are not kept.
+    pub(super) fn insert(&self, entry: CacheEntry, cache_tags: HashSet<String>, ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+        let size = entry.key.len()
+            + estimate_size(&entry.data)
+            + cache_tags.iter().map(String::len).sum::<usize>();
+        if size > self.max_size {
+            return;
+        }
+
+        let mut entries = self.entries.lock();
+        let key = entry.key.clone();
+        entries.remove(&key);
+        entries.size += size;
+        entries.lru.put(
+            key,
+            MemoryEntry {
+                entry,
+                cache_tags,
+                expires_at: Instant::now() + ttl,
+                size,
+            },
+        );
+        while entries.size > self.max_size {
+            let Some((_, evicted)) = entries.lru.pop_lru() else {
+                break;
+            };
+            entries.size -= evicted.size;
+        }
+    }
+
+    /// Remove every entry associated with at least one of `cache_tags`.
+    pub(super) fn invalidate(&self, cache_tags: &[String]) -> u64 {
+        let mut entries = self.entries.lock();
+        let keys: Vec<String> = entries
+            .lru
+            .iter()
+            .filter(|(_, memory_entry)| {
+                cache_tags
+                    .iter()
+                    .any(|cache_tag| memory_entry.cache_tags.contains(cache_tag))
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            entries.remove(key);
+        }
+        keys.len() as u64
+    }
+
+    /// Remove every entry, used when invalidation messages may have been missed.
+    pub(super) fn clear(&self) {
+        let mut entries = self.entries.lock();
+        entries.lru.clear();
+        entries.size = 0;
+    }
+}
+
+impl Entries {
+    fn remove(&mut self, key: &str) {
+        if let Some(removed) = self.lru.pop(key) {
+            self.size -= removed.size;
+        }
+    }
+}
+
+/// How long an entry read from Redis can be kept in memory, based on its `Cache-Control`. This
+/// includes the time during which the entry can be served stale.
+pub(super) fn remaining_ttl(control: &CacheControl) -> Option<Duration> {
+    let ttl = control.ttl()? + control.stale_ttl();
+    let elapsed = u64::try_from(control.elapsed()).ok()?;
+    Some(Duration::from_secs(ttl.saturating_sub(elapsed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    fn entry(key: &str) -> CacheEntry {
+        CacheEntry {
+            key: key.to_string(),
+            data: json!({"name": "a product"}),
+            control: CacheControl::default(),
+            cache_tags: None,
+        }
+    }
+
+    fn tags(tags: &[&str]) -> HashSet<String> {
+        tags.iter().map(ToString::to_string).collect()
+    }
+
+    fn tier(max_size: u64) -> MemoryTier {
+        MemoryTier::new(&Config {
+            max_size: ByteSize::b(max_size),
+        })
+    }
+
+    #[test]
+    fn it_returns_inserted_entries_until_they_expire() {
+        let tier = tier(1024);
+        tier.insert(entry("a"), tags(&[]), Duration::from_secs(60));
+        tier.insert(entry("b"), tags(&[]), Duration::from_nanos(1));
+        std::thread::sleep(Duration::from_millis(1));
+
+        assert_eq!(tier.get("a").map(|entry| entry.key), Some("a".to_string()));
+        assert!(tier.get("b").is_none());
+        assert!(tier.get("c").is_none());
+    }
+
+    #[test]
+    fn it_evicts_least_recently_used_entries_over_the_budget() {
+        let size = {
+            let tier = tier(1024);
+            tier.insert(entry("a"), tags(&[]), Duration::from_secs(60));
+            tier.entries.lock().size as u64
+        };
+        let tier = tier(2 * size);
+        tier.insert(entry("a"), tags(&[]), Duration::from_secs(60));
+        tier.insert(entry("b"), tags(&[]), Duration::from_secs(60));
+        assert!(tier.get("a").is_some());
+        tier.insert(entry("c"), tags(&[]), Duration::from_secs(60));
+
+        assert!(tier.get("a").is_some());
+        assert!(tier.get("b").is_none());
+        assert!(tier.get("c").is_some());
+        assert_eq!(tier.entries.lock().size as u64, 2 * size);
+    }
+
+    #[test]
+    fn it_invalidates_entries_by_cache_tag() {
+        let tier = tier(1024);
+        tier.insert(
+            entry("a"),
+            tags(&["subgraph-products", "subgraph-products:key-product-1"]),
+            Duration::from_secs(60),
+        );
+        tier.insert(
+            entry("b"),
+            tags(&["subgraph-products", "subgraph-products:key-product-2"]),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(
+            tier.invalidate(&["subgraph-products:key-product-1".to_string()]),
+            1
+        );
+        assert!(tier.get("a").is_none());
+        assert!(tier.get("b").is_some());
+
+        assert_eq!(tier.invalidate(&["subgraph-products".to_string()]), 1);
+        assert!(tier.get("b").is_none());
+        assert_eq!(tier.entries.lock().size, 0);
+    }
+}
@@ -1,5 +1,6 @@
 mod config;
 mod error;
+pub(super) mod memory;
 pub(super) mod redis;
 
 use std::collections::HashMap;
@@ -100,13 +101,17 @@ pub(super) trait CacheStorage {
     }
 
     #[doc(hidden)]
-    async fn internal_fetch(&self, cache_key: &str) -> StorageResult<CacheEntry>;
+    async fn internal_fetch(
+        &self,
+        cache_key: &str,
+        subgraph_name: &str,
+    ) -> StorageResult<CacheEntry>;
 
     /// Fetch the value belonging to `cache_key`. Command will be timed out after `self.fetch_timeout()`.
     async fn fetch(&self, cache_key: &str, subgraph_name: &str) -> StorageResult<CacheEntry> {
         let now = Instant::now();
         let result = flatten_storage_error(
-            self.internal_fetch(cache_key)
+            self.internal_fetch(cache_key, subgraph_name)
                 .timeout(self.fetch_timeout())
                 .await,
         );
@@ -119,6 +124,7 @@ pub(super) trait CacheStorage {
     async fn internal_fetch_multiple(
         &self,
         cache_keys: &[&str],
+        subgraph_name: &str,
     ) -> StorageResult<Vec<StorageResult<CacheEntry>>>;
 
     /// Fetch the values belonging to `cache_keys`. Command will be timed out after `self.fetch_timeout()`.
@@ -131,7 +137,7 @@ pub(super) trait CacheStorage {
 
         let now = Instant::now();
         let result = flatten_storage_error(
-            self.internal_fetch_multiple(cache_keys)
+            self.internal_fetch_multiple(cache_keys, subgraph_name)
                 .timeout(self.fetch_timeout())
                 .await,
         );
@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
 use fred::interfaces::ClientLike;
+use fred::interfaces::EventInterface;
 use fred::interfaces::KeysInterface;
+use fred::interfaces::PubsubInterface;
 use fred::interfaces::SortedSetsInterface;
 use fred::prelude::Options;
 use fred::types::Expiration;
@@ -16,6 +19,7 @@ use fred::types::sorted_sets::Ordering;
 use serde::Deserialize;
 use serde::Serialize;
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc;
 use tokio::task::JoinSet;
 use tokio_util::time::FutureExt;
@@ -25,6 +29,8 @@ use super::CacheEntry;
 use super::CacheStorage;
 use super::Document;
 use super::StorageResult;
+use super::memory::MemoryTier;
+use super::memory::remaining_ttl;
 use crate::cache::redis::RedisCacheStorage;
 use crate::cache::redis::RedisKey;
 use crate::cache::redis::RedisValue;
@@ -68,6 +74,7 @@ pub(crate) struct Storage {
     insert_timeout: Duration,
     invalidate_timeout: Duration,
     maintenance_timeout: Duration,
+    memory: Option<Arc<MemoryTier>>,
 }
 
 impl Storage {
@@ -95,7 +102,12 @@ impl Storage {
             insert_timeout: config.insert_timeout,
             invalidate_timeout: config.invalidate_timeout,
             maintenance_timeout: config.maintenance_timeout,
+            memory: config
+                .in_memory
+                .as_ref()
+                .map(|config| Arc::new(MemoryTier::new(config))),
         };
+        s.subscribe_to_invalidations(drop_rx.resubscribe()).await?;
         s.perform_periodic_maintenance(cache_tag_rx, drop_rx).await;
         Ok(s)
     }
@@ -110,12 +122,20 @@ impl Storage {
     }
 
     async fn invalidate_keys(&self, invalidation_keys: Vec<String>) -> StorageResult<u64> {
+        let result = self.delete_tagged_keys(&invalidation_keys).await;
+        // In-memory entries are evicted even if Redis held none of the keys or couldn't be
+        // reached, as they may outlive the Redis entries
+        self.publish_invalidation(&invalidation_keys).await;
+        result
+    }
+
+    async fn delete_tagged_keys(&self, invalidation_keys: &[String]) -> StorageResult<u64> {
         let options = Options {
             timeout: Some(self.invalidate_timeout()),
             ..Options::default()
         };
         let pipeline = self.storage.pipeline().with_options(&options);
-        for invalidation_key in &invalidation_keys {
+        for invalidation_key in invalidation_keys {
             let invalidation_key =
                 format!("version:{RESPONSE_CACHE_VERSION}:cache-tag:{invalidation_key}");
             self.send_to_maintenance_queue(invalidation_key.clone());
@@ -146,9 +166,114 @@ impl Storage {
         // encounter a race condition - if another router inserted a value associated with this cache
         // tag between when we run the `zrange` and the `delete`.
         // it's safer to just rely on the TTL-based cleanup.
+
         Ok(deleted as u64)
     }
 
+    /// Name of the pub/sub channel used to evict invalidated entries from the in-memory tier of
+    /// every router instance sharing this namespace.
+    fn invalidation_channel(&self) -> String {
+        self.make_key(format!(
+            "version:{RESPONSE_CACHE_VERSION}:in-memory-invalidation"
+        ))
+    }
+
+    async fn publish_invalidation(&self, invalidation_keys: &[String]) {
+        let Some(memory) = &self.memory else {
+            return;
+        };
+        // evict locally right away rather than waiting for our own message
+        memory.invalidate(invalidation_keys);
+
+        let result = match serde_json::to_string(invalidation_keys) {
+            Ok(message) => self
+                .storage
+                .client()
+                .publish::<i64, _, _>(self.invalidation_channel(), message)
+                .await
+                .map_err(BoxError::from),
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = result {
+            tracing::error!(
+                error = %err,
+                "cannot publish response cache invalidation to other router instances, their in-memory entries will expire with their TTL"
+            );
+        }
+    }
+
+    /// Listen for invalidations published by any router instance and evict the matching entries
+    /// from the in-memory tier.
+    ///
+    /// Invalidations published while the subscriber is disconnected are lost, so the in-memory
+    /// tier is cleared whenever it reconnects.
+    async fn subscribe_to_invalidations(
+        &self,
+        mut drop_rx: broadcast::Receiver<()>,
+    ) -> Result<(), BoxError> {
+        let Some(memory) = self.memory.clone() else {
+            return Ok(());
+        };
+
+        let subscriber = self.storage.client().clone_new();
+        let _connection = subscriber.init().await?;
+        let channel = self.invalidation_channel();
+        subscriber.subscribe(channel.clone()).await?;
+
+        let mut message_rx = subscriber.message_rx();
+        let mut reconnect_rx = subscriber.reconnect_rx();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = drop_rx.recv() => break,
+                    Ok(_) = reconnect_rx.recv() => {
+                        memory.clear();
+                        if let Err(err) = subscriber.subscribe(channel.clone()).await {
+                            tracing::error!(error = %err, "cannot subscribe to response cache invalidations");
+                        }
+                    }
+                    message = message_rx.recv() => match message {
+                        Ok(message) => {
+                            match message
+                                .value
+                                .as_string()
+                                .and_then(|message| serde_json::from_str::<Vec<String>>(&message).ok())
+                            {
+                                Some(invalidation_keys) => {
+                                    memory.invalidate(&invalidation_keys);
+                                }
+                                None => memory.clear(),
+                            }
+                        }
+                        Err(RecvError::Lagged(_)) => memory.clear(),
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            }
+            let _ = subscriber.quit().await;
+        });
+
+        Ok(())
+    }
+
+    /// Keep an entry read from Redis in memory, if it carries the cache tags needed to invalidate
+    /// it later.
+    fn keep_in_memory(&self, entry: &CacheEntry, subgraph_name: &str) {
+        let Some(memory) = &self.memory else {
+            return;
+        };
+        let (Some(cache_tags), Some(ttl)) = (&entry.cache_tags, remaining_ttl(&entry.control))
+        else {
+            return;
+        };
+        let cache_tags = subgraph_cache_tags(
+            &cache_tags.iter().cloned().collect::<Vec<_>>(),
+            subgraph_name,
+        );
+        memory.insert(entry.clone(), cache_tags.into_iter().collect(), ttl);
+    }
+
     fn send_to_maintenance_queue(&self, cache_tag_key: String) {
         if let Err(err) = self.cache_tag_tx.try_send(cache_tag_key) {
             record_maintenance_queue_error(&err);
@@ -227,12 +352,7 @@ impl Storage {
         document_invalidation_keys: &[String],
         subgraph_name: &str,
     ) -> Vec<String> {
-        let mut cache_tags = Vec::with_capacity(1 + document_invalidation_keys.len());
-        cache_tags.push(format!("subgraph-{subgraph_name}"));
-        for invalidation_key in document_invalidation_keys {
-            cache_tags.push(format!("subgraph-{subgraph_name}:key-{invalidation_key}"));
-        }
-
+        let mut cache_tags = subgraph_cache_tags(document_invalidation_keys, subgraph_name);
         for cache_tag in cache_tags.iter_mut() {
             *cache_tag = format!("version:{RESPONSE_CACHE_VERSION}:cache-tag:{cache_tag}");
         }
@@ -280,7 +400,8 @@ impl CacheStorage for Storage {
         let mut original_cache_tags = Vec::with_capacity(batch_docs.len());
         // phase 1
         for document in &mut batch_docs {
-            if document.debug {
+            // the in-memory tier of other instances needs the cache tags to evict the entry
+            if document.debug || self.memory.is_some() {
                 original_cache_tags.push(document.invalidation_keys.clone());
             } else {
                 original_cache_tags.push(Vec::new());
@@ -362,12 +483,20 @@ impl CacheStorage for Storage {
 
         // phase 3
         let pipeline = self.storage.client().pipeline().with_options(&options);
+        let mut in_memory = Vec::new();
         for (document, cache_tags) in batch_docs.into_iter().zip(original_cache_tags.into_iter()) {
             let value = CacheValue {
                 data: document.data,
                 cache_control: document.control,
-                cache_tags: document.debug.then(|| cache_tags.into_iter().collect()),
+                cache_tags: (document.debug || self.memory.is_some())
+                    .then(|| cache_tags.into_iter().collect()),
             };
+            if self.memory.is_some() {
+                in_memory.push((
+                    CacheEntry::from((document.key.as_str(), value.clone())),
+                    document.expire,
+                ));
+            }
             let _: () = pipeline
                 .set::<(), _, _>(
                     self.make_key(document.key),
@@ -387,10 +516,35 @@ impl CacheStorage for Storage {
             }
         }
 
+        if let Some(memory) = &self.memory {
+            for (entry, expire) in in_memory {
+                let cache_tags = entry
+                    .cache_tags
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let cache_tags = subgraph_cache_tags(&cache_tags, subgraph_name);
+                memory.insert(entry, cache_tags.into_iter().collect(), expire);
+            }
+        }
+
         Ok(())
     }
 
-    async fn internal_fetch(&self, cache_key: &str) -> StorageResult<CacheEntry> {
+    async fn internal_fetch(
+        &self,
+        cache_key: &str,
+        subgraph_name: &str,
+    ) -> StorageResult<CacheEntry> {
+        if let Some(entry) = self
+            .memory
+            .as_ref()
+            .and_then(|memory| memory.get(cache_key))
+        {
+            return Ok(entry);
+        }
+
         // NB: don't need `make_key` for `get` - the storage layer already runs it
         let options = Options {
             timeout: Some(self.fetch_timeout()),
@@ -400,16 +554,38 @@ impl CacheStorage for Storage {
             .storage
             .get_with_options(RedisKey(cache_key), options)
             .await?;
-        Ok(CacheEntry::from((cache_key, value.0)))
+        let entry = CacheEntry::from((cache_key, value.0));
+        self.keep_in_memory(&entry, subgraph_name);
+        Ok(entry)
     }
 
     async fn internal_fetch_multiple(
         &self,
         cache_keys: &[&str],
+        subgraph_name: &str,
     ) -> StorageResult<Vec<StorageResult<CacheEntry>>> {
-        let keys: Vec<RedisKey<String>> = cache_keys
+        let mut entries: Vec<Option<StorageResult<CacheEntry>>> = cache_keys
+            .iter()
+            .map(|cache_key| {
+                self.memory
+                    .as_ref()
+                    .and_then(|memory| memory.get(cache_key))
+                    .map(Ok)
+            })
+            .collect();
+        let missing: Vec<(usize, &str)> = cache_keys
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| entries[*index].is_none())
+            .map(|(index, cache_key)| (index, *cache_key))
+            .collect();
+        if missing.is_empty() {
+            return Ok(entries.into_iter().flatten().collect());
+        }
+
+        let keys: Vec<RedisKey<String>> = missing
             .iter()
-            .map(|key| RedisKey(key.to_string()))
+            .map(|(_, key)| RedisKey(key.to_string()))
             .collect();
         let options = Options {
             timeout: Some(self.fetch_timeout()),
@@ -420,17 +596,17 @@ impl CacheStorage for Storage {
             .get_multiple_with_options(keys, options)
             .await?;
 
-        let entries = values
-            .into_iter()
-            .zip(cache_keys)
-            .map(|(opt_value, cache_key)| {
-                opt_value
-                    .map(|value| CacheEntry::from((*cache_key, value.0)))
-                    .map_err(Into::into)
-            })
-            .collect();
+        for (opt_value, (index, cache_key)) in values.into_iter().zip(missing) {
+            let entry = opt_value
+                .map(|value| CacheEntry::from((cache_key, value.0)))
+                .map_err(Into::into);
+            if let Ok(entry) = &entry {
+                self.keep_in_memory(entry, subgraph_name);
+            }
+            entries[index] = Some(entry);
+        }
 
-        Ok(entries)
+        Ok(entries.into_iter().flatten().collect())
     }
 
     async fn internal_invalidate_by_subgraph(&self, subgraph_name: &str) -> StorageResult<u64> {
@@ -474,6 +650,16 @@ impl CacheStorage for Storage {
     }
 }
 
+/// The subgraph-invalidation-key permutations of a document, without namespace and version.
+fn subgraph_cache_tags(document_invalidation_keys: &[String], subgraph_name: &str) -> Vec<String> {
+    let mut cache_tags = Vec::with_capacity(1 + document_invalidation_keys.len());
+    cache_tags.push(format!("subgraph-{subgraph_name}"));
+    for invalidation_key in document_invalidation_keys {
+        cache_tags.push(format!("subgraph-{subgraph_name}:key-{invalidation_key}"));
+    }
+    cache_tags
+}
+
 fn now() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -507,6 +693,7 @@ impl Storage {
             insert_timeout: config.insert_timeout,
             invalidate_timeout: config.invalidate_timeout,
             maintenance_timeout: config.maintenance_timeout,
+            memory: None,
         };
         s.perform_periodic_maintenance(cache_tag_rx, drop_rx).await;
         Ok(s)
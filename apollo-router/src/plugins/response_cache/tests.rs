@@ -46,7 +46,7 @@ const SCHEMA_NESTED_KEYS: &str =
 /// `TestHarness` service return value.
 ///
 /// Instead, we wait for up to 5 seconds for the keys we expected to be present in the cache storage.
-async fn wait_for_cache(storage: &Storage, keys: Vec<String>) {
+pub(super) async fn wait_for_cache(storage: &Storage, keys: Vec<String>) {
     if keys.is_empty() {
         return;
     }
@@ -86,7 +86,7 @@ pub(super) fn create_subgraph_conf(
 ///
 /// NB: this is not always accurate! For example, a key might not be stored if it's private but
 /// wasn't passed the private ID. But it's a good approximation for most test cases.
-fn expected_cached_keys(cache_keys_context: &CacheKeysContext) -> Vec<String> {
+pub(super) fn expected_cached_keys(cache_keys_context: &CacheKeysContext) -> Vec<String> {
     cache_keys_context
         .iter()
         .filter(|context| context.cache_control.should_store())
@@ -96,7 +96,7 @@ fn expected_cached_keys(cache_keys_context: &CacheKeysContext) -> Vec<String> {
 
 /// Extract `CacheKeysContext` from `supergraph::Response` and prepare it for a snapshot, sorting
 /// the invalidation keys and setting `created` to zero.
-fn get_cache_keys_context(response: &supergraph::Response) -> Option<CacheKeysContext> {
+pub(super) fn get_cache_keys_context(response: &supergraph::Response) -> Option<CacheKeysContext> {
     let mut cache_keys: CacheKeysContext = response
         .context
         .get(super::plugin::CONTEXT_DEBUG_CACHE_KEYS)
@@ -0,0 +1,418 @@
+//! Replay of operations in the background to populate the response cache, for example after a
+//! deploy or once Redis was flushed.
+//!
+//! Operations are either listed in the configuration or taken from the queries most frequently
+//! executed since the plugin was created, which are replayed periodically. When the router starts,
+//! it reports unready until warming is done or its time budget is spent.
+
+use std::num::NonZeroU32;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use http::Method;
+use http::Uri;
+use lru::LruCache;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tokio::sync::oneshot;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+use tracing::Instrument;
+
+use super::plugin::StorageInterface;
+use crate::Context;
+use crate::context::CONTAINS_GRAPHQL_ERROR;
+use crate::context::OPERATION_KIND;
+use crate::json_ext::Object;
+use crate::layers::ServiceBuilderExt;
+use crate::plugins::healthcheck::ReadinessHold;
+use crate::query_planner::OperationKind;
+use crate::services::supergraph;
+
+/// Number of distinct queries recorded to find the most frequently executed ones
+const RECORDED_OPERATIONS_CAPACITY: NonZeroUsize = NonZeroUsize::new(1024).unwrap();
+const STORAGE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Queries executed without variables since the plugin was created, with their number of executions
+type RecordedOperations = Arc<Mutex<LruCache<Operation, u64>>>;
+
+/// Cache warming configuration
+#[derive(Clone, Debug, JsonSchema, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+pub(crate) struct Config {
+    /// Replay operations in the background to populate the cache when the router starts or reloads
+    pub(crate) enabled: bool,
+
+    /// Operations to replay
+    pub(crate) operations: Vec<Operation>,
+
+    /// Also replay the N queries without variables executed most frequently since the configuration was loaded, every `top_operations_interval` (default: 0)
+    pub(crate) top_operations: usize,
+
+    /// Interval between replays of the most frequently executed queries (default: 5m)
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    pub(crate) top_operations_interval: Duration,
+
+    /// Maximum number of operations replayed per second (default: 10)
+    pub(crate) rate: NonZeroU32,
+
+    /// Time budget for warming. When the router starts, it reports ready once warming is done or this budget is spent (default: 30s)
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    pub(crate) timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            operations: Vec::new(),
+            top_operations: 0,
+            top_operations_interval: Duration::from_secs(5 * 60),
+            rate: NonZeroU32::new(10).expect("10 is not zero"),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Operation replayed to warm the cache
+#[derive(Clone, Debug, PartialEq, Eq, Hash, JsonSchema, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub(crate) struct Operation {
+    /// GraphQL query
+    pub(crate) query: String,
+
+    /// Name of the operation to execute, required if the query contains several operations
+    #[serde(default)]
+    pub(crate) operation_name: Option<String>,
+
+    /// Variables of the operation
+    #[serde(default)]
+    #[schemars(with = "serde_json::Map<String, serde_json::Value>")]
+    pub(crate) variables: Object,
+}
+
+impl Operation {
+    /// Variables are specific to a client, so only operations without variables are recorded
+    fn from_request(request: &supergraph::Request) -> Option<Self> {
+        let body = request.supergraph_request.body();
+        if !body.variables.is_empty() {
+            return None;
+        }
+        Some(Self {
+            query: body.query.clone()?,
+            operation_name: body.operation_name.clone(),
+            variables: Object::new(),
+        })
+    }
+
+    fn to_request(&self) -> Result<supergraph::Request, BoxError> {
+        supergraph::Request::builder()
+            .query(self.query.clone())
+            .and_operation_name(self.operation_name.clone())
+            .variables(self.variables.clone())
+            .context(Context::new())
+            .uri(Uri::from_static("http://localhost/"))
+            .method(Method::POST)
+            .build()
+    }
+}
+
+/// Warming state of a response cache instance
+pub(super) struct Warming {
+    config: Config,
+    /// Keeps the router unready until warming is done, only set when the router starts
+    readiness_hold: Mutex<Option<ReadinessHold>>,
+    service_tx: Mutex<Option<oneshot::Sender<supergraph::BoxCloneService>>>,
+    service_rx: Mutex<Option<oneshot::Receiver<supergraph::BoxCloneService>>>,
+    recorded: RecordedOperations,
+}
+
+impl Warming {
+    /// `is_reload` is false when the router starts, in which case warming delays readiness.
+    pub(super) fn new(config: Config, is_reload: bool) -> Option<Arc<Self>> {
+        if !config.enabled {
+            return None;
+        }
+        let (service_tx, service_rx) = oneshot::channel();
+        Some(Arc::new(Self {
+            config,
+            readiness_hold: Mutex::new((!is_reload).then(ReadinessHold::new)),
+            service_tx: Mutex::new(Some(service_tx)),
+            service_rx: Mutex::new(Some(service_rx)),
+            recorded: Arc::new(Mutex::new(LruCache::new(RECORDED_OPERATIONS_CAPACITY))),
+        }))
+    }
+
+    /// Keep a handle on the supergraph service to replay operations, and record the queries it
+    /// executes if the most frequent ones should be replayed.
+    pub(super) fn supergraph_service(
+        &self,
+        service: supergraph::BoxService,
+    ) -> supergraph::BoxService {
+        let service = ServiceBuilder::new()
+            .buffered()
+            .service(service)
+            .boxed_clone();
+        if let Some(service_tx) = self.service_tx.lock().take() {
+            let _ = service_tx.send(service.clone());
+        }
+        if self.config.top_operations == 0 {
+            return service.boxed();
+        }
+
+        let recorded = self.recorded.clone();
+        ServiceBuilder::new()
+            .map_future_with_request_data(Operation::from_request, move |operation, future| {
+                let recorded = recorded.clone();
+                async move {
+                    let response: supergraph::Response = future.await?;
+                    let is_query = response
+                        .context
+                        .get::<_, OperationKind>(OPERATION_KIND)
+                        .ok()
+                        .flatten()
+                        == Some(OperationKind::Query);
+                    let has_errors = response
+                        .context
+                        .get_json_value(CONTAINS_GRAPHQL_ERROR)
+                        .and_then(|value| value.as_bool())
+                        .unwrap_or_default();
+                    if let Some(operation) = operation
+                        && is_query
+                        && !has_errors
+                    {
+                        record(&recorded, operation);
+                    }
+                    Ok(response)
+                }
+            })
+            .service(service)
+            .boxed()
+    }
+
+    /// Start replaying operations once the supergraph service is available
+    pub(super) fn start(
+        &self,
+        storage: Arc<StorageInterface>,
+        mut drop_rx: broadcast::Receiver<()>,
+    ) {
+        let Some(service_rx) = self.service_rx.lock().take() else {
+            return;
+        };
+        let readiness_hold = self.readiness_hold.lock().take();
+        let config = self.config.clone();
+        let recorded = self.recorded.clone();
+
+        tokio::spawn(async move {
+            let Ok(service) = service_rx.await else {
+                return;
+            };
+            let operations = config.operations.clone();
+            let total = operations.len();
+            let start = Instant::now();
+            let warm = replay(service.clone(), storage.clone(), operations, config.rate)
+                .instrument(tracing::info_span!("response_cache.warming"));
+
+            tokio::select! {
+                result = tokio::time::timeout(config.timeout, warm) => match result {
+                    Ok(replayed) => tracing::info!(
+                        "response cache warming replayed {replayed}/{total} operations in {:?}",
+                        start.elapsed()
+                    ),
+                    Err(_) => tracing::warn!(
+                        "response cache warming did not finish within its time budget of {:?}",
+                        config.timeout
+                    ),
+                },
+                _ = drop_rx.recv() => return,
+            }
+            drop(readiness_hold);
+
+            if config.top_operations == 0 {
+                return;
+            }
+            // repopulate the entries of the most frequent queries, for example after a Redis flush
+            let mut interval = tokio::time::interval(config.top_operations_interval);
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let operations = top_operations(&recorded, &config);
+                        let warm = replay(service.clone(), storage.clone(), operations, config.rate)
+                            .instrument(tracing::info_span!("response_cache.warming"));
+                        tokio::select! {
+                            _ = tokio::time::timeout(config.timeout, warm) => {}
+                            _ = drop_rx.recv() => return,
+                        }
+                    }
+                    _ = drop_rx.recv() => return,
+                }
+            }
+        });
+    }
+}
+
+fn record(recorded: &RecordedOperations, operation: Operation) {
+    let mut recorded = recorded.lock();
+    match recorded.get_mut(&operation) {
+        Some(count) => *count += 1,
+        None => {
+            recorded.put(operation, 1);
+        }
+    }
+}
+
+/// The most frequently executed queries which are not already configured
+fn top_operations(recorded: &RecordedOperations, config: &Config) -> Vec<Operation> {
+    let recorded = recorded.lock();
+    let mut top: Vec<_> = recorded.iter().collect();
+    top.sort_by(|(_, a), (_, b)| b.cmp(a));
+    top.into_iter()
+        .map(|(operation, _)| operation)
+        .filter(|operation| !config.operations.contains(operation))
+        .take(config.top_operations)
+        .cloned()
+        .collect()
+}
+
+/// Replay `operations` at most `rate` times per second, once the cache storage is connected.
+/// Returns the number of operations replayed successfully.
+async fn replay(
+    service: supergraph::BoxCloneService,
+    storage: Arc<StorageInterface>,
+    operations: Vec<Operation>,
+    rate: NonZeroU32,
+) -> usize {
+    while !storage.is_connected() {
+        tokio::time::sleep(STORAGE_POLL_INTERVAL).await;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / rate.get());
+    let mut replayed = 0;
+    for operation in operations {
+        interval.tick().await;
+        let service = service.clone();
+        let result = async move {
+            let mut response = service.oneshot(operation.to_request()?).await?;
+            while response.next_response().await.is_some() {}
+            Ok::<_, BoxError>(())
+        }
+        .await;
+        let status = match result {
+            Ok(()) => {
+                replayed += 1;
+                "success"
+            }
+            Err(err) => {
+                tracing::debug!("cannot replay operation to warm the response cache: {err}");
+                "error"
+            }
+        };
+        u64_counter_with_unit!(
+            "apollo.router.operations.response_cache.warming.operation",
+            "Operations replayed to warm the response cache",
+            "{operation}",
+            1,
+            "status" = status
+        );
+    }
+    replayed
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    fn operation(id: u64) -> Operation {
+        Operation {
+            query: format!("{{ product(id: {id}) {{ name }} }}"),
+            operation_name: None,
+            variables: Object::new(),
+        }
+    }
+
+    fn request(query: &str, variables: serde_json_bytes::Value) -> supergraph::Request {
+        supergraph::Request::fake_builder()
+            .query(query)
+            .variables(variables.as_object().unwrap().clone())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn it_replays_the_most_frequent_operations_which_are_not_configured() {
+        let recorded = Arc::new(Mutex::new(LruCache::new(RECORDED_OPERATIONS_CAPACITY)));
+        record(&recorded, operation(1));
+        for _ in 0..3 {
+            record(&recorded, operation(2));
+        }
+        for _ in 0..2 {
+            record(&recorded, operation(3));
+        }
+        for _ in 0..4 {
+            record(&recorded, operation(4));
+        }
+
+        let config = Config {
+            enabled: true,
+            operations: vec![operation(4)],
+            top_operations: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            top_operations(&recorded, &config),
+            [operation(2), operation(3)]
+        );
+    }
+
+    #[test]
+    fn it_only_records_operations_without_variables() {
+        let query = "query Product($id: ID!) { product(id: $id) { name } }";
+        assert!(Operation::from_request(&request(query, json!({ "id": "1" }))).is_none());
+
+        let query = "{ product(id: 1) { name } }";
+        assert_eq!(
+            Operation::from_request(&request(query, json!({}))),
+            Some(operation(1))
+        );
+    }
+
+    #[test]
+    fn it_parses_the_configuration() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            enabled: true
+            operations:
+              - query: "query Product($id: ID!) { product(id: $id) { name } }"
+                variables:
+                  id: "1"
+            top_operations: 100
+            top_operations_interval: 10m
+            rate: 5
+            timeout: 1m
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.operations,
+            [Operation {
+                query: "query Product($id: ID!) { product(id: $id) { name } }".to_string(),
+                operation_name: None,
+                variables: json!({ "id": "1" }).as_object().unwrap().clone(),
+            }]
+        );
+        assert_eq!(config.top_operations, 100);
+        assert_eq!(config.top_operations_interval, Duration::from_secs(600));
+        assert_eq!(config.rate.get(), 5);
+        assert_eq!(config.timeout, Duration::from_secs(60));
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::fmt;
 use std::ops::ControlFlow;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -41,6 +42,7 @@ pub(crate) const RHAI_SPAN_NAME: &str = "rhai_plugin";
 
 mod execution;
 mod router;
+pub(crate) mod runner;
 mod subgraph;
 mod supergraph;
 
@@ -62,31 +64,23 @@ pub(crate) struct Conf {
     main: Option<String>,
 }
 
-#[async_trait::async_trait]
-impl Plugin for Rhai {
-    type Config = Conf;
+impl Conf {
+    fn scripts_path(&self) -> PathBuf {
+        self.scripts.clone().unwrap_or_else(|| "rhai".into())
+    }
 
-    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
-        let sdl = init.supergraph_sdl.clone();
-        let scripts_path = match init.config.scripts {
-            Some(path) => path,
-            None => "rhai".into(),
-        };
-
-        let main_file = match init.config.main {
-            Some(main) => main,
-            None => "main.rhai".to_string(),
-        };
-
-        let main = scripts_path.join(main_file);
-
-        let engine = Arc::new(Rhai::new_rhai_engine(
-            Some(scripts_path),
-            sdl.to_string(),
-            main.clone(),
-        ));
+    fn main_path(&self) -> PathBuf {
+        self.scripts_path()
+            .join(self.main.as_deref().unwrap_or("main.rhai"))
+    }
+}
+
+impl Rhai {
+    /// Compile the main script and evaluate its global statements with the router constants in
+    /// scope.
+    fn load(engine: Engine, main: &Path, sdl: &str) -> Result<Self, BoxError> {
         let ast = engine
-            .compile_file(main.clone())
+            .compile_file(main.to_path_buf())
             .map_err(|err| format!("in Rhai script {}: {}", main.display(), err))?;
         let mut scope = Scope::new();
         // Keep these two lower cases ones as mistakes until 2.0
@@ -102,10 +96,25 @@ impl Plugin for Rhai {
 
         Ok(Self {
             ast,
-            engine,
+            engine: Arc::new(engine),
             scope: Arc::new(Mutex::new(scope)),
         })
     }
+}
+
+#[async_trait::async_trait]
+impl Plugin for Rhai {
+    type Config = Conf;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let main = init.config.main_path();
+        let engine = Rhai::new_rhai_engine(
+            Some(init.config.scripts_path()),
+            init.supergraph_sdl.to_string(),
+            main.clone(),
+        );
+        Rhai::load(engine, &main, &init.supergraph_sdl)
+    }
 
     fn router_service(&self, service: router::BoxService) -> router::BoxService {
         const FUNCTION_NAME_SERVICE: &str = "router_service";
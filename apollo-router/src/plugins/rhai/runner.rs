@@ -0,0 +1,194 @@
+//! Offline checking and unit testing of Rhai scripts.
+//!
+//! This backs the `router rhai check` and `router rhai test` subcommands, so that broken scripts
+//! can be caught before they are deployed rather than when traffic first reaches them.
+
+use std::path::PathBuf;
+
+use parking_lot::Mutex;
+use rhai::Dynamic;
+use rhai::EvalAltResult;
+use rhai::Module;
+use rhai::Shared;
+use tower::BoxError;
+use tower::util::BoxService;
+
+use super::Conf;
+use super::Rhai;
+use super::ServiceStep;
+use super::engine::SharedMut;
+use super::execution;
+use super::router;
+use super::subgraph;
+use super::supergraph;
+
+/// Functions whose name starts with this prefix and which take no parameters are run as tests.
+pub(crate) const TEST_FUNCTION_PREFIX: &str = "test_";
+
+/// Name of the subgraph passed to `subgraph_service` when checking a script.
+const CHECK_SUBGRAPH_NAME: &str = "rhai_check";
+
+/// The result of running a single script-defined test.
+#[derive(Debug)]
+pub(crate) struct TestOutcome {
+    pub(crate) name: String,
+    pub(crate) error: Option<String>,
+}
+
+impl TestOutcome {
+    pub(crate) fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Compile the main script, evaluate its global statements and invoke every service callback
+/// it defines, exactly as the plugin would on startup.
+///
+/// Returns the path of the checked script.
+pub(crate) fn check(
+    scripts: Option<PathBuf>,
+    main: Option<String>,
+    sdl: &str,
+) -> Result<PathBuf, BoxError> {
+    let conf = Conf { scripts, main };
+    let main = conf.main_path();
+    let engine = Rhai::new_rhai_engine(Some(conf.scripts_path()), sdl.to_string(), main.clone());
+    let rhai = Rhai::load(engine, &main, sdl)?;
+    rhai.check_service_callbacks()
+        .map_err(|err| format!("in Rhai script {}: {}", main.display(), err))?;
+    Ok(main)
+}
+
+/// Run every test function defined by the main script.
+///
+/// Each test runs against its own copy of the global scope, with a `test` module in which
+/// synthetic router, supergraph, execution and subgraph requests and responses can be created.
+pub(crate) fn run_tests(
+    scripts: Option<PathBuf>,
+    main: Option<String>,
+    sdl: &str,
+) -> Result<Vec<TestOutcome>, BoxError> {
+    let conf = Conf { scripts, main };
+    let main = conf.main_path();
+    let mut engine =
+        Rhai::new_rhai_engine(Some(conf.scripts_path()), sdl.to_string(), main.clone());
+    engine.register_static_module("test", test_module().into());
+    let rhai = Rhai::load(engine, &main, sdl)?;
+
+    let tests: Vec<String> = rhai
+        .ast
+        .iter_functions()
+        .filter(|function| {
+            function.name.starts_with(TEST_FUNCTION_PREFIX) && function.params.is_empty()
+        })
+        .map(|function| function.name.to_string())
+        .collect();
+
+    Ok(tests
+        .into_iter()
+        .map(|name| {
+            let mut scope = rhai.scope.lock().clone();
+            let error = rhai
+                .engine
+                .call_fn::<Dynamic>(&mut scope, &rhai.ast, &name, ())
+                .err()
+                .map(|error| error.to_string());
+            TestOutcome { name, error }
+        })
+        .collect())
+}
+
+impl Rhai {
+    fn check_service_callbacks(&self) -> Result<(), String> {
+        if self.ast_has_function("router_service") {
+            self.run_rhai_service(
+                "router_service",
+                None,
+                ServiceStep::Router(shared(unreachable_service())),
+                self.scope.clone(),
+            )?;
+        }
+        if self.ast_has_function("supergraph_service") {
+            self.run_rhai_service(
+                "supergraph_service",
+                None,
+                ServiceStep::Supergraph(shared(unreachable_service())),
+                self.scope.clone(),
+            )?;
+        }
+        if self.ast_has_function("execution_service") {
+            self.run_rhai_service(
+                "execution_service",
+                None,
+                ServiceStep::Execution(shared(unreachable_service())),
+                self.scope.clone(),
+            )?;
+        }
+        if self.ast_has_function("subgraph_service") {
+            self.run_rhai_service(
+                "subgraph_service",
+                Some(CHECK_SUBGRAPH_NAME),
+                ServiceStep::Subgraph(shared(unreachable_service())),
+                self.scope.clone(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn shared<T>(value: T) -> SharedMut<T> {
+    Shared::new(Mutex::new(Some(value)))
+}
+
+// Service callbacks only wrap the service they are given, so checking a script never needs to
+// call it.
+fn unreachable_service<Req, Res>() -> BoxService<Req, Res, BoxError>
+where
+    Req: Send + 'static,
+    Res: Send + 'static,
+{
+    BoxService::new(tower::service_fn(|_request: Req| async {
+        Err::<Res, BoxError>("requests are not executed when checking Rhai scripts".into())
+    }))
+}
+
+fn test_module() -> Module {
+    let mut module = Module::new();
+    module.set_native_fn("router_request", || {
+        Ok(shared(router::FirstRequest::default()))
+    });
+    module.set_native_fn("router_response", || {
+        Ok(shared(router::FirstResponse::default()))
+    });
+    module.set_native_fn("supergraph_request", || {
+        supergraph::Request::fake_builder()
+            .build()
+            .map(shared)
+            .map_err(|err| Box::<EvalAltResult>::from(err.to_string()))
+    });
+    module.set_native_fn("supergraph_response", || {
+        Ok(shared(supergraph::FirstResponse::default()))
+    });
+    module.set_native_fn("execution_request", || {
+        Ok(shared(execution::Request::fake_builder().build()))
+    });
+    module.set_native_fn("execution_response", || {
+        Ok(shared(execution::FirstResponse::default()))
+    });
+    module.set_native_fn("subgraph_request", || {
+        Ok(shared(subgraph::Request::fake_builder().build()))
+    });
+    module.set_native_fn("subgraph_response", || {
+        Ok(shared(subgraph::Response::fake_builder().build()))
+    });
+    module.set_native_fn("assert", |condition: bool, message: &str| {
+        if condition {
+            Ok(())
+        } else {
+            Err(Box::<EvalAltResult>::from(format!(
+                "assertion failed: {message}"
+            )))
+        }
+    });
+    module
+}
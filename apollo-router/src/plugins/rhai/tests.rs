@@ -704,6 +704,66 @@ async fn it_mentions_source_when_syntax_error_occurs() {
     assert!(err.to_string().contains("syntax_errors.rhai"));
 }
 
+#[test]
+fn it_checks_scripts_without_starting_the_router() {
+    let main = super::runner::check(
+        Some(PathBuf::from("tests/fixtures")),
+        Some("test_runner.rhai".to_string()),
+        "",
+    )
+    .expect("script is valid");
+    assert_eq!(main, PathBuf::from("tests/fixtures/test_runner.rhai"));
+
+    let err = super::runner::check(
+        Some(PathBuf::from("tests/fixtures")),
+        Some("syntax_errors.rhai".to_string()),
+        "",
+    )
+    .expect_err("script has syntax errors");
+    assert!(err.to_string().contains("syntax_errors.rhai"));
+}
+
+#[test]
+fn it_rejects_service_callbacks_with_the_wrong_signature() {
+    let err = super::runner::check(
+        Some(PathBuf::from("tests/fixtures")),
+        Some("test_runner_bad_callback.rhai".to_string()),
+        "",
+    )
+    .expect_err("subgraph_service must take two parameters");
+    assert!(err.to_string().contains("subgraph_service"));
+}
+
+#[test]
+fn it_runs_script_defined_tests() {
+    let outcomes = super::runner::run_tests(
+        Some(PathBuf::from("tests/fixtures")),
+        Some("test_runner.rhai".to_string()),
+        "",
+    )
+    .expect("tests can run");
+
+    let mut results: Vec<(&str, bool)> = outcomes
+        .iter()
+        .map(|outcome| (outcome.name.as_str(), outcome.passed()))
+        .collect();
+    results.sort();
+    assert_eq!(
+        results,
+        vec![
+            ("test_fails", false),
+            ("test_process_request_sets_header", true),
+            ("test_process_subgraph_response_removes_header", true),
+        ]
+    );
+    let failure = outcomes
+        .iter()
+        .find(|outcome| outcome.name == "test_fails")
+        .and_then(|outcome| outcome.error.as_deref())
+        .unwrap();
+    assert!(failure.contains("x-missing should be present"));
+}
+
 #[test]
 #[should_panic(
     expected = "can use env: ErrorRuntime(\"could not expand variable: THIS_SHOULD_NOT_EXIST, environment variable not found\", none)"
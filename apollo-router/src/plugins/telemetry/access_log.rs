@@ -0,0 +1,923 @@
+//! Access log
+//!
+//! Emits exactly one line per router request, once the response body has been sent or the request
+//! was abandoned, in the Common or Combined Log Format or as an Elastic Common Schema (ECS) JSON
+//! document, to stdout, to a file or as OpenTelemetry log records over OTLP.
+use std::fs;
+use std::io::BufWriter;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::mpsc;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use axum::Error as AxumError;
+use bytes::Bytes;
+use http::Method;
+use http::StatusCode;
+use http::Version;
+use http::header::REFERER;
+use http::header::USER_AGENT;
+use http_body::Body as HttpBody;
+use http_body::Frame;
+use http_body::SizeHint;
+use opentelemetry::KeyValue;
+use opentelemetry::logs::LogRecord;
+use opentelemetry::logs::Logger;
+use opentelemetry::logs::Severity;
+use opentelemetry::trace::SpanContext;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry_sdk::logs::SdkLogger;
+use opentelemetry_sdk::logs::SdkLoggerProvider;
+use parking_lot::Mutex;
+use pin_project_lite::pin_project;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use time::OffsetDateTime;
+use tower::BoxError;
+use tracing::Span;
+
+use crate::Context;
+use crate::axum_factory::utils::ConnectionInfo;
+use crate::context::OPERATION_KIND;
+use crate::context::OPERATION_NAME;
+use crate::context::ROUTER_RESPONSE_ERRORS;
+use crate::plugins::telemetry::APOLLO_CLIENT_NAME_ATTRIBUTE;
+use crate::plugins::telemetry::APOLLO_CLIENT_VERSION_ATTRIBUTE;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::plugins::telemetry::CLIENT_VERSION;
+use crate::plugins::telemetry::GRAPHQL_OPERATION_NAME_ATTRIBUTE;
+use crate::plugins::telemetry::GRAPHQL_OPERATION_TYPE_ATTRIBUTE;
+use crate::plugins::telemetry::config_new::Selectors;
+use crate::plugins::telemetry::config_new::extendable::Extendable;
+use crate::plugins::telemetry::config_new::router::attributes::RouterAttributes;
+use crate::plugins::telemetry::config_new::router::selectors::RouterSelector;
+use crate::plugins::telemetry::config_new::router_overhead::RouterOverheadTracker;
+use crate::plugins::telemetry::logging::otlp::any_value;
+use crate::plugins::telemetry::logging::otlp::router_logger;
+use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
+use crate::plugins::telemetry::otlp;
+use crate::plugins::telemetry::resource::ConfigResource;
+use crate::services::router;
+use crate::services::router::body::RouterBody;
+
+const ACCESS_LOG_EVENT_NAME: &str = "apollo.router.access";
+/// Lines are dropped when a writer falls behind by this many lines
+const ACCESS_LOG_QUEUE_SIZE: usize = 10_000;
+/// Dropped lines are reported in a warning at most this often
+const DROPPED_LINES_WARNING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The queue of the access log file writer thread, along with the path of the file it writes.
+///
+/// A single thread writes the access log file, and is handed over to the access logger of each
+/// telemetry reload, so that the file isn't reopened unless its path changes and the lines of the
+/// requests still running on the previous configuration don't interleave with the new ones.
+static FILE_WRITER: LazyLock<Mutex<Option<FileWriter>>> = LazyLock::new(Default::default);
+
+/// The queue of the access log stdout writer thread, shared by the access loggers of every
+/// telemetry reload
+static STDOUT_WRITER: LazyLock<Mutex<Option<mpsc::SyncSender<WriterMessage>>>> =
+    LazyLock::new(Default::default);
+
+/// When a warning was last logged for dropped lines
+static DROPPED_LINES_WARNING: LazyLock<Mutex<Option<Instant>>> = LazyLock::new(Default::default);
+
+struct FileWriter {
+    sender: mpsc::SyncSender<WriterMessage>,
+    /// The path of the file written by the thread, None if the access log file is disabled
+    path: Option<PathBuf>,
+}
+
+enum WriterMessage {
+    /// Writes the next lines to this output, or drops them if there is none
+    Configure(Option<Box<dyn Write + Send>>),
+    Line(String),
+}
+
+/// Access log configuration
+#[derive(Clone, Default, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Config {
+    /// The format of the access log lines.
+    pub(crate) format: Format,
+    /// Settings for writing the access log to stdout.
+    pub(crate) stdout: StdOut,
+    /// Settings for writing the access log to a file.
+    pub(crate) file: FileConfig,
+    /// Settings for exporting the access log as OpenTelemetry log records over OTLP.
+    pub(crate) otlp: otlp::Config,
+    /// Additional fields of the access log lines, from router selectors.
+    pub(crate) attributes: Extendable<RouterAttributes, RouterSelector>,
+}
+
+/// The format of the access log lines
+#[derive(Clone, Copy, Default, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Format {
+    /// The Common Log Format of web servers. Additional fields are appended as `key="value"` pairs.
+    Common,
+    /// The Combined Log Format of web servers, which adds the referrer and user agent to the Common
+    /// Log Format. Additional fields are appended as `key="value"` pairs.
+    Combined,
+    /// Elastic Common Schema JSON documents, with the GraphQL operation, client, subgraph request
+    /// count, error count and cost of the request.
+    #[default]
+    Ecs,
+}
+
+/// Access log to stdout
+#[derive(Clone, Default, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct StdOut {
+    /// Set to true to write the access log to stdout.
+    pub(crate) enabled: bool,
+}
+
+/// Access log to a file
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct FileConfig {
+    /// Set to true to write the access log to a file.
+    pub(crate) enabled: bool,
+    /// The path of the access log file. Lines are appended to the file if it exists.
+    pub(crate) path: PathBuf,
+}
+
+impl Default for FileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("access.log"),
+        }
+    }
+}
+
+/// Counts the GraphQL errors of the supergraph responses of a request
+#[derive(Clone, Default)]
+pub(crate) struct SupergraphErrorCount(Arc<Mutex<Option<usize>>>);
+
+impl SupergraphErrorCount {
+    pub(crate) fn add(&self, errors: usize) {
+        *self.0.lock().get_or_insert(0) += errors;
+    }
+
+    fn get(&self) -> Option<usize> {
+        *self.0.lock()
+    }
+}
+
+/// Writes the access log lines of the router requests
+pub(crate) struct AccessLogger {
+    format: Format,
+    attributes: Extendable<RouterAttributes, RouterSelector>,
+    stdout: Option<mpsc::SyncSender<WriterMessage>>,
+    file: Option<mpsc::SyncSender<WriterMessage>>,
+    logger: Option<SdkLogger>,
+    logger_provider: Option<SdkLoggerProvider>,
+}
+
+impl AccessLogger {
+    /// Returns the access logger if any access log output is enabled
+    pub(crate) fn new(
+        config: &Config,
+        resource: &impl ConfigResource,
+    ) -> Result<Option<Self>, BoxError> {
+        if !config.stdout.enabled && !config.file.enabled && !config.otlp.enabled {
+            return Ok(None);
+        }
+
+        let file = configure_file_writer(&config.file)?;
+        let stdout = if config.stdout.enabled {
+            Some(stdout_writer()?)
+        } else {
+            None
+        };
+
+        let (logger, logger_provider) = if config.otlp.enabled {
+            let logger_provider = config.otlp.build_logger_provider(resource, "access-log")?;
+            (Some(router_logger(&logger_provider)), Some(logger_provider))
+        } else {
+            (None, None)
+        };
+
+        Ok(Some(Self {
+            format: config.format,
+            attributes: config.attributes.clone(),
+            stdout,
+            file,
+            logger,
+            logger_provider,
+        }))
+    }
+
+    /// Starts the access log entry of a request, which is written when dropped
+    pub(crate) fn on_request(self: &Arc<Self>, request: &router::Request) -> AccessLogEntry {
+        request
+            .context
+            .extensions()
+            .with_lock(|lock| lock.insert(SupergraphErrorCount::default()));
+        let http_request = &request.router_request;
+        let header = |name| {
+            http_request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        AccessLogEntry {
+            logger: self.clone(),
+            context: request.context.clone(),
+            start: Instant::now(),
+            time: OffsetDateTime::now_utc(),
+            span_context: Span::current().context().span().span_context().clone(),
+            client_address: http_request
+                .extensions()
+                .get::<ConnectionInfo>()
+                .and_then(|connection_info| connection_info.peer_address)
+                .map(|address| address.ip()),
+            method: http_request.method().clone(),
+            path: http_request.uri().path().to_string(),
+            version: http_request.version(),
+            referrer: header(REFERER),
+            user_agent: header(USER_AGENT),
+            status: None,
+            body_bytes: 0,
+            attributes: self.attributes.on_request(request),
+        }
+    }
+
+    fn log(&self, entry: &AccessLogEntry) {
+        let duration = entry.start.elapsed();
+        let line = match self.format {
+            Format::Common => entry.common_log_line(false),
+            Format::Combined => entry.common_log_line(true),
+            Format::Ecs => entry.ecs_document(duration),
+        };
+        if let Some(logger) = &self.logger {
+            let mut log_record = logger.create_log_record();
+            log_record.set_timestamp(SystemTime::from(entry.time));
+            log_record.set_observed_timestamp(SystemTime::now());
+            log_record.set_event_name(ACCESS_LOG_EVENT_NAME);
+            log_record.set_severity_number(Severity::Info);
+            log_record.set_severity_text("INFO");
+            if entry.span_context.is_valid() {
+                log_record.set_trace_context(
+                    entry.span_context.trace_id(),
+                    entry.span_context.span_id(),
+                    Some(entry.span_context.trace_flags()),
+                );
+            }
+            log_record.add_attributes(
+                entry
+                    .fields(duration)
+                    .into_iter()
+                    .map(|kv| (kv.key, any_value(kv.value))),
+            );
+            log_record.set_body(line.clone().into());
+            logger.emit(log_record);
+        }
+        for (output, writer) in [("stdout", &self.stdout), ("file", &self.file)] {
+            if let Some(writer) = writer
+                && let Err(err) = writer.try_send(WriterMessage::Line(line.clone()))
+            {
+                dropped_line(output, err);
+            }
+        }
+    }
+}
+
+/// Counts a line dropped by an output, and warns about it unless a warning was logged recently
+fn dropped_line(output: &'static str, err: mpsc::TrySendError<WriterMessage>) {
+    u64_counter!(
+        "apollo.router.telemetry.access_log.dropped_lines",
+        "Access log lines dropped because an output fell behind",
+        1,
+        "output" = output
+    );
+    let mut last_warning = DROPPED_LINES_WARNING.lock();
+    if last_warning
+        .is_none_or(|last_warning| last_warning.elapsed() >= DROPPED_LINES_WARNING_INTERVAL)
+    {
+        *last_warning = Some(Instant::now());
+        let err = match err {
+            mpsc::TrySendError::Full(_) => "queue is full",
+            mpsc::TrySendError::Disconnected(_) => "writer stopped",
+        };
+        ::tracing::warn!(
+            "could not write access log line to {output}, lines will be dropped: {err}"
+        );
+    }
+}
+
+/// Configures the access log file writer thread, starting it if needed, and returns its queue if
+/// the access log file is enabled
+fn configure_file_writer(
+    config: &FileConfig,
+) -> Result<Option<mpsc::SyncSender<WriterMessage>>, BoxError> {
+    let path = config.enabled.then(|| config.path.clone());
+    let mut file_writer = FILE_WRITER.lock();
+    if let Some(FileWriter {
+        sender,
+        path: current_path,
+    }) = &mut *file_writer
+    {
+        if *current_path == path {
+            return Ok(path.map(|_| sender.clone()));
+        }
+        let file = path.as_ref().map(open_file).transpose()?;
+        // Waits for the space in the queue, the lines queued before are written to the previous
+        // file
+        if sender.send(WriterMessage::Configure(file)).is_ok() {
+            *current_path = path.clone();
+            return Ok(path.map(|_| sender.clone()));
+        }
+    }
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let sender = spawn_writer("access-log", open_file(&path)?)?;
+    *file_writer = Some(FileWriter {
+        sender: sender.clone(),
+        path: Some(path),
+    });
+    Ok(Some(sender))
+}
+
+fn open_file(path: &PathBuf) -> Result<Box<dyn Write + Send>, BoxError> {
+    if let Some(directory) = path.parent()
+        && !directory.as_os_str().is_empty()
+    {
+        fs::create_dir_all(directory)?;
+    }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Returns the queue of the stdout writer thread, starting it if needed
+fn stdout_writer() -> Result<mpsc::SyncSender<WriterMessage>, BoxError> {
+    let mut stdout_writer = STDOUT_WRITER.lock();
+    if let Some(sender) = &*stdout_writer {
+        return Ok(sender.clone());
+    }
+    // Writing to stdout blocks when the output is not consumed fast enough
+    let sender = spawn_writer("access-log-stdout", Box::new(std::io::stdout()))?;
+    *stdout_writer = Some(sender.clone());
+    Ok(sender)
+}
+
+/// Spawns a thread writing the queued lines to `writer`, so that the request path never blocks on
+/// I/O. The thread keeps running for the next reloads.
+fn spawn_writer(
+    name: &str,
+    writer: Box<dyn Write + Send>,
+) -> Result<mpsc::SyncSender<WriterMessage>, BoxError> {
+    let (sender, receiver) = mpsc::sync_channel(ACCESS_LOG_QUEUE_SIZE);
+    let mut writer = Some(writer);
+    std::thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            while let Ok(message) = receiver.recv() {
+                // Write all the queued lines before flushing
+                let mut next = Some(message);
+                while let Some(message) = next {
+                    match message {
+                        WriterMessage::Configure(output) => {
+                            if let Some(writer) = &mut writer
+                                && let Err(err) = writer.flush()
+                            {
+                                ::tracing::error!("could not write access log lines: {err}");
+                            }
+                            writer = output;
+                        }
+                        WriterMessage::Line(line) => {
+                            if let Some(writer) = &mut writer
+                                && let Err(err) = writeln!(writer, "{line}")
+                            {
+                                ::tracing::error!("could not write access log line: {err}");
+                            }
+                        }
+                    }
+                    next = receiver.try_recv().ok();
+                }
+                if let Some(writer) = &mut writer
+                    && let Err(err) = writer.flush()
+                {
+                    ::tracing::error!("could not write access log lines: {err}");
+                }
+            }
+        })?;
+    Ok(sender)
+}
+
+impl Drop for AccessLogger {
+    fn drop(&mut self) {
+        // Shutting down the logger provider flushes the pending records, which blocks
+        if let Some(logger_provider) = self.logger_provider.take() {
+            tokio::task::spawn_blocking(move || drop(logger_provider));
+        }
+    }
+}
+
+/// The access log entry of a request, written when dropped
+pub(crate) struct AccessLogEntry {
+    logger: Arc<AccessLogger>,
+    context: Context,
+    start: Instant,
+    time: OffsetDateTime,
+    span_context: SpanContext,
+    client_address: Option<IpAddr>,
+    method: Method,
+    path: String,
+    version: Version,
+    referrer: Option<String>,
+    user_agent: Option<String>,
+    status: Option<StatusCode>,
+    body_bytes: u64,
+    attributes: Vec<KeyValue>,
+}
+
+impl AccessLogEntry {
+    pub(crate) fn on_response(&mut self, response: &router::Response) {
+        self.status = Some(response.response.status());
+        self.attributes
+            .extend(self.logger.attributes.on_response(response));
+    }
+
+    pub(crate) fn on_error(&mut self, error: &BoxError) {
+        self.status = Some(StatusCode::INTERNAL_SERVER_ERROR);
+        self.attributes
+            .extend(self.logger.attributes.on_error(error, &self.context));
+    }
+
+    /// Defers the entry until the body of the response has been sent
+    pub(crate) fn wrap_response(self, response: router::Response) -> router::Response {
+        let router::Response { response, context } = response;
+        let response = response.map(|body| {
+            RouterBody::new(AccessLogBody {
+                inner: body,
+                entry: self,
+            })
+        });
+        router::Response { response, context }
+    }
+
+    fn error_count(&self) -> usize {
+        let supergraph_errors = self
+            .context
+            .extensions()
+            .with_lock(|lock| lock.get::<SupergraphErrorCount>().and_then(|c| c.get()));
+        // Errors of the router service are only in the context when the supergraph was not reached
+        supergraph_errors.unwrap_or_else(|| {
+            self.context
+                .get_json_value(ROUTER_RESPONSE_ERRORS)
+                .and_then(|errors| errors.as_object().map(|errors| errors.len()))
+                .unwrap_or_default()
+        })
+    }
+
+    /// The fields of the entry, named after the Elastic Common Schema and the router attributes
+    fn fields(&self, duration: Duration) -> Vec<KeyValue> {
+        let mut fields = Vec::with_capacity(20 + self.attributes.len());
+        fields.push(KeyValue::new(
+            "event.duration",
+            duration.as_nanos().min(i64::MAX as u128) as i64,
+        ));
+        if let Some(client_address) = self.client_address {
+            fields.push(KeyValue::new("client.address", client_address.to_string()));
+        }
+        fields.push(KeyValue::new(
+            "http.request.method",
+            self.method.as_str().to_string(),
+        ));
+        fields.push(KeyValue::new("url.path", self.path.clone()));
+        fields.push(KeyValue::new(
+            "http.version",
+            http_version(self.version)
+                .trim_start_matches("HTTP/")
+                .to_string(),
+        ));
+        if let Some(referrer) = &self.referrer {
+            fields.push(KeyValue::new("http.request.referrer", referrer.clone()));
+        }
+        if let Some(user_agent) = &self.user_agent {
+            fields.push(KeyValue::new("user_agent.original", user_agent.clone()));
+        }
+        if let Some(status) = self.status {
+            fields.push(KeyValue::new(
+                "http.response.status_code",
+                status.as_u16() as i64,
+            ));
+        }
+        fields.push(KeyValue::new(
+            "http.response.body.bytes",
+            self.body_bytes as i64,
+        ));
+
+        let get = |key| self.context.get::<_, String>(key).ok().flatten();
+        if let Some(operation_name) = get(OPERATION_NAME) {
+            fields.push(KeyValue::new(
+                GRAPHQL_OPERATION_NAME_ATTRIBUTE,
+                operation_name,
+            ));
+        }
+        if let Some(operation_kind) = get(OPERATION_KIND) {
+            fields.push(KeyValue::new(
+                GRAPHQL_OPERATION_TYPE_ATTRIBUTE,
+                operation_kind,
+            ));
+        }
+        if let Some(client_name) = get(CLIENT_NAME) {
+            fields.push(KeyValue::new(APOLLO_CLIENT_NAME_ATTRIBUTE, client_name));
+        }
+        if let Some(client_version) = get(CLIENT_VERSION) {
+            fields.push(KeyValue::new(
+                APOLLO_CLIENT_VERSION_ATTRIBUTE,
+                client_version,
+            ));
+        }
+        if let Some(tracker) = self
+            .context
+            .extensions()
+            .with_lock(|lock| lock.get::<RouterOverheadTracker>().cloned())
+        {
+            fields.push(KeyValue::new(
+                "subgraph.request.count",
+                tracker.calculate_overhead().subgraph_requests as i64,
+            ));
+        }
+        fields.push(KeyValue::new(
+            "graphql.error.count",
+            self.error_count() as i64,
+        ));
+        if let Ok(Some(cost)) = self.context.get_estimated_cost() {
+            fields.push(KeyValue::new("cost.estimated", cost));
+        }
+        if let Ok(Some(cost)) = self.context.get_actual_cost() {
+            fields.push(KeyValue::new("cost.actual", cost));
+        }
+        if self.span_context.is_valid() {
+            fields.push(KeyValue::new(
+                "trace.id",
+                self.span_context.trace_id().to_string(),
+            ));
+        }
+        fields.extend(self.attributes.iter().cloned());
+        fields
+    }
+
+    /// `host ident authuser [date] "request" status bytes`, followed by `"referrer" "user agent"`
+    /// in the Combined Log Format
+    fn common_log_line(&self, combined: bool) -> String {
+        let time = self.time;
+        let mut line = format!(
+            "{} - - [{:02}/{}/{:04}:{:02}:{:02}:{:02} +0000] \"{} {} {}\" {} {}",
+            self.client_address
+                .map(|address| address.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            time.day(),
+            &time.month().to_string()[..3],
+            time.year(),
+            time.hour(),
+            time.minute(),
+            time.second(),
+            self.method,
+            escape(&self.path),
+            http_version(self.version),
+            self.status
+                .map(|status| status.as_u16().to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            if self.body_bytes == 0 {
+                "-".to_string()
+            } else {
+                self.body_bytes.to_string()
+            },
+        );
+        if combined {
+            for header in [&self.referrer, &self.user_agent] {
+                line.push_str(&format!(
+                    " \"{}\"",
+                    header.as_deref().map(escape).unwrap_or_else(|| "-".into())
+                ));
+            }
+        }
+        for KeyValue { key, value, .. } in &self.attributes {
+            line.push_str(&format!(" {key}=\"{}\"", escape(&value.to_string())));
+        }
+        line
+    }
+
+    fn ecs_document(&self, duration: Duration) -> String {
+        let mut document = serde_json::Map::new();
+        document.insert(
+            "@timestamp".to_string(),
+            self.time
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap_or_default()
+                .into(),
+        );
+        document.insert(
+            "message".to_string(),
+            format!(
+                "{} {} {}",
+                self.method,
+                self.path,
+                self.status
+                    .map(|status| status.as_u16().to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            )
+            .into(),
+        );
+        document.insert("event.dataset".to_string(), ACCESS_LOG_EVENT_NAME.into());
+        for KeyValue { key, value, .. } in self.fields(duration) {
+            document.insert(key.to_string(), json_value(value));
+        }
+        serde_json::Value::Object(document).to_string()
+    }
+}
+
+impl Drop for AccessLogEntry {
+    fn drop(&mut self) {
+        self.logger.log(self);
+    }
+}
+
+fn http_version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_2 => "HTTP/2.0",
+        Version::HTTP_3 => "HTTP/3.0",
+        _ => "HTTP/1.1",
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_value(value: opentelemetry::Value) -> serde_json::Value {
+    match value {
+        opentelemetry::Value::Bool(value) => value.into(),
+        opentelemetry::Value::I64(value) => value.into(),
+        opentelemetry::Value::F64(value) => value.into(),
+        value => value.to_string().into(),
+    }
+}
+
+pin_project! {
+    /// Response body holding the access log entry of its request, written once the body has
+    /// been sent or dropped
+    struct AccessLogBody {
+        #[pin]
+        inner: RouterBody,
+        entry: AccessLogEntry,
+    }
+}
+
+impl HttpBody for AccessLogBody {
+    type Data = Bytes;
+    type Error = AxumError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = this.inner.poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &frame
+            && let Some(data) = frame.data_ref()
+        {
+            this.entry.body_bytes += data.len() as u64;
+        }
+        frame
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+    use crate::metrics::FutureMetricsExt;
+    use crate::plugins::demand_control::COST_ESTIMATED_KEY;
+    use crate::plugins::telemetry::config_new::logging::LoggingCommon;
+
+    fn access_logger(config: Config) -> Arc<AccessLogger> {
+        Arc::new(AccessLogger {
+            format: config.format,
+            attributes: config.attributes,
+            stdout: None,
+            file: None,
+            logger: None,
+            logger_provider: None,
+        })
+    }
+
+    fn entry(logger: Arc<AccessLogger>, context: Context) -> AccessLogEntry {
+        AccessLogEntry {
+            logger,
+            context,
+            start: Instant::now(),
+            // 2024-03-07 09:15:00 UTC
+            time: OffsetDateTime::from_unix_timestamp(1709802900).unwrap(),
+            span_context: SpanContext::empty_context(),
+            client_address: Some(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))),
+            method: Method::POST,
+            path: "/graphql".to_string(),
+            version: Version::HTTP_11,
+            referrer: None,
+            user_agent: Some("curl/8.5.0 \"beta\"".to_string()),
+            status: Some(StatusCode::OK),
+            body_bytes: 1024,
+            attributes: vec![KeyValue::new("region", "eu")],
+        }
+    }
+
+    #[test]
+    fn it_formats_common_log_lines() {
+        let logger = access_logger(Config::default());
+        let entry = entry(logger, Context::new());
+        assert_eq!(
+            entry.common_log_line(false),
+            r#"192.168.0.1 - - [07/Mar/2024:09:15:00 +0000] "POST /graphql HTTP/1.1" 200 1024 region="eu""#
+        );
+        assert_eq!(
+            entry.common_log_line(true),
+            r#"192.168.0.1 - - [07/Mar/2024:09:15:00 +0000] "POST /graphql HTTP/1.1" 200 1024 "-" "curl/8.5.0 \"beta\"" region="eu""#
+        );
+    }
+
+    #[test]
+    fn it_formats_ecs_documents() {
+        let context = Context::new();
+        let _ = context.insert(OPERATION_NAME, "TopProducts".to_string());
+        let _ = context.insert(OPERATION_KIND, "query".to_string());
+        let _ = context.insert(CLIENT_NAME, "web".to_string());
+        let _ = context.insert(COST_ESTIMATED_KEY, 12.0);
+        let error_count = SupergraphErrorCount::default();
+        error_count.add(1);
+        error_count.add(2);
+        context.extensions().with_lock(|lock| {
+            lock.insert(error_count);
+            lock.insert(RouterOverheadTracker::new());
+        });
+
+        let logger = access_logger(Config::default());
+        let entry = entry(logger, context);
+        let document: serde_json::Value =
+            serde_json::from_str(&entry.ecs_document(Duration::from_millis(25))).unwrap();
+        insta::assert_json_snapshot!(document);
+    }
+
+    #[test]
+    fn it_counts_router_errors_when_the_supergraph_was_not_reached() {
+        let context = Context::new();
+        let _ = context.insert(
+            ROUTER_RESPONSE_ERRORS,
+            serde_json::json!({"a3c5b7c8-0000-4000-8000-000000000000": {"message": "invalid"}}),
+        );
+        let logger = access_logger(Config::default());
+        assert_eq!(entry(logger, context).error_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_writes_one_line_once_the_response_body_is_sent() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("logs").join("access.log");
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "format": "common",
+            "file": {"enabled": true, "path": path},
+        }))
+        .unwrap();
+        let logger = Arc::new(
+            AccessLogger::new(&config, &LoggingCommon::default())
+                .unwrap()
+                .unwrap(),
+        );
+
+        let request = router::Request::fake_builder().build().unwrap();
+        let mut entry = logger.on_request(&request);
+        drop(logger);
+        let response = router::Response::fake_builder()
+            .data(serde_json::json!({"topProducts": []}))
+            .context(request.context)
+            .build()
+            .unwrap();
+        entry.on_response(&response);
+        let response = entry.wrap_response(response);
+        let body = router::body::into_bytes(response.response.into_body())
+            .await
+            .unwrap();
+
+        // The writer thread flushes the line and stops once the logger is dropped
+        let mut lines = String::new();
+        for _ in 0..100 {
+            lines = fs::read_to_string(&path).unwrap();
+            if !lines.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(lines.lines().count(), 1);
+        assert!(
+            lines.starts_with("- - - [")
+                && lines.ends_with(&format!("] \"GET / HTTP/1.1\" 200 {}\n", body.len())),
+            "{lines}"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_counts_the_dropped_lines() {
+        async {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            drop(receiver);
+            let logger = Arc::new(AccessLogger {
+                format: Format::Common,
+                attributes: Default::default(),
+                stdout: None,
+                file: Some(sender),
+                logger: None,
+                logger_provider: None,
+            });
+            drop(entry(logger.clone(), Context::new()));
+            drop(entry(logger, Context::new()));
+
+            assert_counter!(
+                "apollo.router.telemetry.access_log.dropped_lines",
+                2,
+                "output" = "file"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[test]
+    fn it_writes_the_queued_lines_to_the_configured_output() {
+        #[derive(Clone, Default)]
+        struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let first = SharedWriter::default();
+        let second = SharedWriter::default();
+        let sender = spawn_writer("access-log-test", Box::new(first.clone())).unwrap();
+        sender
+            .try_send(WriterMessage::Line("first".to_string()))
+            .unwrap();
+        sender
+            .try_send(WriterMessage::Configure(Some(Box::new(second.clone()))))
+            .unwrap();
+        sender
+            .try_send(WriterMessage::Line("second".to_string()))
+            .unwrap();
+        sender.try_send(WriterMessage::Configure(None)).unwrap();
+        sender
+            .try_send(WriterMessage::Line("dropped".to_string()))
+            .unwrap();
+        drop(sender);
+
+        for _ in 0..100 {
+            if second.0.lock().len() == "second\n".len() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(
+            String::from_utf8(first.0.lock().clone()).unwrap(),
+            "first\n"
+        );
+        assert_eq!(
+            String::from_utf8(second.0.lock().clone()).unwrap(),
+            "second\n"
+        );
+    }
+}
@@ -33,6 +33,7 @@ use crate::plugins::telemetry::apollo_exporter::proto::reports::ReportHeader;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::StatsContext;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::Trace;
 use crate::plugins::telemetry::config::SamplerOption;
+use crate::plugins::telemetry::local_usage;
 use crate::plugins::telemetry::tracing::BatchProcessorConfig;
 use crate::plugins::telemetry::tracing::max_export_timeout_default;
 use crate::plugins::telemetry::tracing::max_queue_size_default;
@@ -144,6 +145,9 @@ pub(crate) struct Config {
     /// Enable field metrics that are generated without FTV1 to be sent to Apollo Studio.
     pub(crate) experimental_local_field_metrics: bool,
 
+    /// Write usage data to local files or to an OTLP logs endpoint, without sending it to Apollo Studio.
+    pub(crate) experimental_local_usage_reporting: local_usage::Config,
+
     /// Enable sending additional subgraph metrics to Apollo Studio via OTLP
     pub(crate) subgraph_metrics: bool,
 }
@@ -414,6 +418,7 @@ impl Default for Config {
             errors: ErrorsConfiguration::default(),
             signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm::default(),
             experimental_local_field_metrics: false,
+            experimental_local_usage_reporting: Default::default(),
             metrics_reference_mode: ApolloMetricsReferenceMode::default(),
             subgraph_metrics: false,
         }
@@ -10,6 +10,7 @@ use opentelemetry::Value;
 use opentelemetry_sdk::metrics::Aggregation;
 use opentelemetry_sdk::metrics::Instrument;
 use opentelemetry_sdk::metrics::Stream;
+use opentelemetry_sdk::trace::ShouldSample;
 use opentelemetry_sdk::trace::SpanLimits;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -18,10 +19,12 @@ use serde::Serialize;
 use super::*;
 use crate::Configuration;
 use crate::plugin::serde::deserialize_option_header_name;
+use crate::plugins::telemetry::access_log;
 use crate::plugins::telemetry::apollo::Config as ApolloTelemetryConfig;
 use crate::plugins::telemetry::metrics;
 use crate::plugins::telemetry::resource::ConfigResource;
 use crate::plugins::telemetry::tracing::datadog::DatadogAgentSampling;
+use crate::plugins::telemetry::tracing::sampling_rules::RuleBasedSampler;
 
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum Error {
@@ -82,6 +85,8 @@ pub(crate) struct Instrumentation {
     pub(crate) spans: config_new::spans::Spans,
     /// Instrument configuration
     pub(crate) instruments: config_new::instruments::InstrumentsConfig,
+    /// Access log configuration, writing one line per router request
+    pub(crate) experimental_access_log: access_log::Config,
 }
 
 impl Instrumentation {
@@ -283,6 +288,10 @@ impl Tracing {
         self.propagation.zipkin || self.zipkin.enabled
     }
 
+    pub(crate) fn is_b3_single_header_propagation_enabled(&self) -> bool {
+        self.propagation.b3_single_header
+    }
+
     pub(crate) fn is_aws_xray_propagation_enabled(&self) -> bool {
         self.propagation.aws_xray
     }
@@ -378,10 +387,42 @@ pub(crate) struct Propagation {
     pub(crate) jaeger: bool,
     /// Propagate Datadog
     pub(crate) datadog: Option<bool>,
-    /// Propagate Zipkin
+    /// Propagate Zipkin, with the multiple B3 headers (`X-B3-TraceId`, `X-B3-SpanId`, ...)
     pub(crate) zipkin: bool,
+    /// Propagate Zipkin with the single B3 header (`b3`) https://github.com/openzipkin/b3-propagation#single-header
+    pub(crate) b3_single_header: bool,
     /// Propagate AWS X-Ray
     pub(crate) aws_xray: bool,
+    /// The outbound requests the trace context is propagated to
+    pub(crate) clients: PropagationClients,
+}
+
+/// The outbound requests of the router the trace context is propagated to
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct PropagationClients {
+    /// Propagate the trace context to subgraph requests, including subscription websockets
+    pub(crate) subgraph: bool,
+    /// Propagate the trace context to the HTTP requests of connectors
+    pub(crate) connector: bool,
+    /// Propagate the trace context to coprocessor requests
+    pub(crate) coprocessor: bool,
+    /// Propagate the trace context to the JWKS downloads of the JWT authentication
+    pub(crate) jwks: bool,
+    /// Propagate the trace context to Apollo Uplink fetches
+    pub(crate) uplink: bool,
+}
+
+impl Default for PropagationClients {
+    fn default() -> Self {
+        Self {
+            subgraph: true,
+            connector: true,
+            coprocessor: true,
+            jwks: true,
+            uplink: true,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema, Default, PartialEq)]
@@ -424,6 +465,14 @@ pub(crate) struct TracingCommon {
     pub(crate) max_attributes_per_link: u32,
     /// The Open Telemetry resource
     pub(crate) resource: BTreeMap<String, AttributeValue>,
+    /// Samplers for the requests matching a condition, evaluated in order against the router
+    /// request. The `sampler` applies to the requests matching no rule.
+    pub(crate) experimental_sampling_rules: Vec<tracing::sampling_rules::SamplingRule>,
+    /// Tail-based sampling of the traces sent to the OTLP, Datadog and Zipkin exporters
+    pub(crate) experimental_tail_sampling: tracing::tail_sampling::Config,
+    /// Resolver spans created from the field execution traces of subgraphs, for the OTLP, Datadog
+    /// and Zipkin exporters
+    pub(crate) experimental_resolver_spans: tracing::resolver_spans::Config,
 }
 
 impl ConfigResource for TracingCommon {
@@ -472,6 +521,9 @@ impl Default for TracingCommon {
             max_attributes_per_event: default_max_attributes_per_event(),
             max_attributes_per_link: default_max_attributes_per_link(),
             resource: Default::default(),
+            experimental_sampling_rules: Default::default(),
+            experimental_tail_sampling: Default::default(),
+            experimental_resolver_spans: Default::default(),
         }
     }
 }
@@ -716,6 +768,17 @@ impl From<Sampler> for opentelemetry_sdk::trace::Sampler {
     }
 }
 
+impl SamplerOption {
+    /// The fraction of the traces that are sampled
+    pub(crate) fn ratio(&self) -> f64 {
+        match self {
+            SamplerOption::TraceIdRatioBased(ratio) => *ratio,
+            SamplerOption::Always(Sampler::AlwaysOn) => 1.0,
+            SamplerOption::Always(Sampler::AlwaysOff) => 0.0,
+        }
+    }
+}
+
 impl From<SamplerOption> for opentelemetry_sdk::trace::Sampler {
     fn from(s: SamplerOption) -> Self {
         match s {
@@ -748,6 +811,21 @@ impl TracingCommon {
             })
             .with_resource(self.to_resource());
 
+        if self.experimental_sampling_rules.is_empty() {
+            self.with_sampler(builder, sampler)
+        } else {
+            self.with_sampler(
+                builder,
+                RuleBasedSampler::new(sampler, self.parent_based_sampler),
+            )
+        }
+    }
+
+    fn with_sampler(
+        &self,
+        builder: opentelemetry_sdk::trace::TracerProviderBuilder,
+        sampler: impl ShouldSample + 'static,
+    ) -> opentelemetry_sdk::trace::TracerProviderBuilder {
         if self.preview_datadog_agent_sampling.unwrap_or_default() {
             builder.with_sampler(DatadogAgentSampling::new(
                 sampler,
@@ -30,6 +30,7 @@ pub(crate) const CACHE_METRIC: &str = "apollo.router.operations.entity.cache";
 pub(crate) const RESPONSE_CACHE_METRIC: &str = "apollo.router.response.cache";
 const ENTITY_TYPE: Key = Key::from_static_str("graphql.type.name");
 const CACHE_HIT: Key = Key::from_static_str("cache.hit");
+const CACHE_STALE: Key = Key::from_static_str("cache.stale");
 
 #[derive(Deserialize, JsonSchema, Clone, Default, Debug)]
 #[serde(deny_unknown_fields, default)]
@@ -163,7 +164,7 @@ impl Instrumented for CacheInstruments {
         };
 
         if let Some(cache_hit) = &self.cache_hit_response_cache {
-            for (entity_type, ResponseCacheHitMiss { hit, miss }) in &cache_info.0 {
+            for (entity_type, ResponseCacheHitMiss { hit, miss, stale }) in &cache_info.0 {
                 // Cache hit
                 {
                     let cloned_cache_hit = cache_hit.clone();
@@ -214,6 +215,35 @@ impl Instrumented for CacheInstruments {
                     }
                     cloned_cache_miss.on_response(response);
                 }
+                // Stale entries, served from cache but counted apart from fresh hits
+                if *stale > 0 {
+                    let cloned_cache_stale = cache_hit.clone();
+                    {
+                        let mut inner_cache_stale = cloned_cache_stale.inner.lock();
+                        inner_cache_stale.selector =
+                            Some(Arc::new(SubgraphSelector::StaticField {
+                                r#static: AttributeValue::I64(*stale as i64),
+                            }));
+                        if let Some(key) = inner_cache_stale
+                            .selectors
+                            .as_ref()
+                            .and_then(|s| s.attributes.entity_type.as_ref())
+                            .and_then(|a| a.key(ENTITY_TYPE))
+                        {
+                            inner_cache_stale.attributes.push(KeyValue::new(
+                                key,
+                                opentelemetry::Value::String(entity_type.to_string().into()),
+                            ));
+                        }
+                        inner_cache_stale
+                            .attributes
+                            .push(KeyValue::new(CACHE_HIT, opentelemetry::Value::Bool(true)));
+                        inner_cache_stale
+                            .attributes
+                            .push(KeyValue::new(CACHE_STALE, opentelemetry::Value::Bool(true)));
+                    }
+                    cloned_cache_stale.on_response(response);
+                }
             }
             // Make sure it won't be incremented when dropped
             let _ = cache_hit.inner.lock().counter.take();
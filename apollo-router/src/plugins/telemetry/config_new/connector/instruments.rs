@@ -52,6 +52,14 @@ pub(crate) struct ConnectorInstrumentsConfig {
         DefaultedStandardInstrument<Extendable<ConnectorAttributes, ConnectorSelector>>,
 }
 
+impl ConnectorInstrumentsConfig {
+    pub(crate) fn has_exemplars(&self) -> bool {
+        self.http_client_request_duration.has_exemplars()
+            || self.http_client_request_body_size.has_exemplars()
+            || self.http_client_response_body_size.has_exemplars()
+    }
+}
+
 impl DefaultForLevel for ConnectorInstrumentsConfig {
     fn defaults_for_level(
         &mut self,
@@ -116,7 +124,7 @@ impl ConnectorInstruments {
                     let selectors = match &config.attributes.http_client_request_duration {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -139,6 +147,7 @@ impl ConnectorInstruments {
                             attributes: Vec::with_capacity(nb_attributes),
                             selector: None,
                             selectors,
+                            exemplars: config.attributes.http_client_request_duration.exemplars(HTTP_CLIENT_REQUEST_DURATION_METRIC),
                             updated: false,
                             _phantom: Default::default()
                         }),
@@ -154,7 +163,7 @@ impl ConnectorInstruments {
                     let selectors = match &config.attributes.http_client_request_body_size {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -181,6 +190,7 @@ impl ConnectorInstruments {
                                 default: None,
                             })),
                             selectors,
+                            exemplars: config.attributes.http_client_request_body_size.exemplars(HTTP_CLIENT_REQUEST_BODY_SIZE_METRIC),
                             updated: false,
                             _phantom: Default::default()
                         }),
@@ -196,7 +206,7 @@ impl ConnectorInstruments {
                     let selectors = match &config.attributes.http_client_response_body_size {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -221,6 +231,7 @@ impl ConnectorInstruments {
                                 connector_http_response_body_size: true,
                             })),
                             selectors,
+                            exemplars: config.attributes.http_client_response_body_size.exemplars(HTTP_CLIENT_RESPONSE_BODY_SIZE_METRIC),
                             updated: false,
                             _phantom: Default::default()
                         }),
@@ -155,6 +155,12 @@ pub(crate) struct CostInstrumentsConfig {
 }
 
 impl CostInstrumentsConfig {
+    pub(crate) fn has_exemplars(&self) -> bool {
+        self.cost_estimated.has_exemplars()
+            || self.cost_actual.has_exemplars()
+            || self.cost_delta.has_exemplars()
+    }
+
     pub(crate) fn new_static_instruments(&self) -> HashMap<String, StaticInstrument> {
         let meter = metrics::meter_provider()
             .meter(crate::plugins::telemetry::config_new::instruments::METER_NAME);
@@ -231,7 +237,7 @@ impl CostInstrumentsConfig {
         let mut nb_attributes = 0;
         let selectors = match config {
             DefaultedStandardInstrument::Bool(_) | DefaultedStandardInstrument::Unset => None,
-            DefaultedStandardInstrument::Extendable { attributes } => {
+            DefaultedStandardInstrument::Extendable { attributes, .. } => {
                 nb_attributes = attributes.custom.len();
                 Some(attributes.clone())
             }
@@ -251,6 +257,7 @@ impl CostInstrumentsConfig {
                 attributes: Vec::with_capacity(nb_attributes),
                 selector: Some(Arc::new(selector)),
                 selectors,
+                exemplars: config.exemplars(name),
                 updated: false,
                 _phantom: PhantomData,
             }),
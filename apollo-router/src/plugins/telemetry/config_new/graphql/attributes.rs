@@ -13,6 +13,7 @@ use crate::plugins::telemetry::config_new::DefaultForLevel;
 use crate::plugins::telemetry::config_new::Selector;
 use crate::plugins::telemetry::config_new::Selectors;
 use crate::plugins::telemetry::config_new::attributes::StandardAttribute;
+use crate::plugins::telemetry::config_new::graphql::selectors::ClientName;
 use crate::plugins::telemetry::config_new::graphql::selectors::FieldName;
 use crate::plugins::telemetry::config_new::graphql::selectors::FieldType;
 use crate::plugins::telemetry::config_new::graphql::selectors::GraphQLSelector;
@@ -40,6 +41,9 @@ pub(crate) struct GraphQLAttributes {
     /// The GraphQL type name
     #[serde(rename = "graphql.type.name")]
     pub(crate) type_name: Option<StandardAttribute>,
+    /// Optional client name populated from the request headers.
+    #[serde(rename = "client.name")]
+    pub(crate) client_name: Option<StandardAttribute>,
 }
 
 impl DefaultForLevel for GraphQLAttributes {
@@ -137,6 +141,18 @@ impl Selectors<supergraph::Request, supergraph::Response, crate::graphql::Respon
         {
             attrs.push(KeyValue::new(key, length));
         }
+        if let Some(key) = self
+            .client_name
+            .as_ref()
+            .and_then(|a| a.key(Key::from_static_str("client.name")))
+            && let Some(client_name) = (GraphQLSelector::ClientName {
+                client_name: ClientName::String,
+                default: None,
+            })
+            .on_response_field(ty, field, value, ctx)
+        {
+            attrs.push(KeyValue::new(key, client_name));
+        }
     }
 }
 
@@ -174,6 +190,7 @@ mod test {
             list_length: Some(StandardAttribute::Bool(true)),
             operation_name: Some(StandardAttribute::Bool(true)),
             type_name: Some(StandardAttribute::Bool(true)),
+            client_name: None,
         };
         let ctx = Context::default();
         let _ = ctx.insert(OPERATION_NAME, "operation_name".to_string());
@@ -198,6 +215,7 @@ mod test {
             list_length: Some(StandardAttribute::Bool(true)),
             operation_name: Some(StandardAttribute::Bool(true)),
             type_name: Some(StandardAttribute::Bool(true)),
+            client_name: None,
         };
         let ctx = Context::default();
         let _ = ctx.insert(OPERATION_NAME, "operation_name".to_string());
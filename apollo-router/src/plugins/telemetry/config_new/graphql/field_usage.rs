@@ -0,0 +1,340 @@
+//! Field usage instruments
+//!
+//! Field executions, durations and errors are measured by the subgraphs and sent back in the
+//! field-level instrumentation traces (FTV1), which are only requested for a sample of the
+//! operations. Counters are extrapolated from that sample, like the field stats reported to Apollo.
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use apollo_compiler::Name;
+use apollo_compiler::Node;
+use apollo_compiler::ast::FieldDefinition;
+use apollo_compiler::ast::Type;
+use apollo_compiler::executable::Field;
+use opentelemetry::metrics::Counter;
+use opentelemetry::metrics::Histogram;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json_bytes::Value;
+
+use crate::Context;
+use crate::plugins::telemetry::Telemetry;
+use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::Node as TraceNode;
+use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::node::Id;
+use crate::plugins::telemetry::config_new::DefaultForLevel;
+use crate::plugins::telemetry::config_new::Selectors;
+use crate::plugins::telemetry::config_new::attributes::DefaultAttributeRequirementLevel;
+use crate::plugins::telemetry::config_new::attributes::StandardAttribute;
+use crate::plugins::telemetry::config_new::extendable::Extendable;
+use crate::plugins::telemetry::config_new::graphql::attributes::GraphQLAttributes;
+use crate::plugins::telemetry::config_new::graphql::selectors::GraphQLSelector;
+use crate::plugins::telemetry::otlp::TelemetryDataKind;
+
+pub(crate) const FIELD_USAGE: &str = "graphql.field.usage";
+pub(crate) const FIELD_USAGE_DURATION: &str = "graphql.field.usage.duration";
+pub(crate) const FIELD_USAGE_ERRORS: &str = "graphql.field.usage.errors";
+
+#[derive(Deserialize, JsonSchema, Clone, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct FieldUsageConfig {
+    /// Enable the field usage instruments, measured from the field-level instrumentation traces of subgraphs.
+    pub(crate) enabled: bool,
+    /// The types and fields to measure, as `Type` for all the fields of a type or as `Type.field`.
+    /// Other fields are not measured.
+    pub(crate) include: Vec<String>,
+    /// Attributes of the field usage instruments.
+    pub(crate) attributes: Extendable<GraphQLAttributes, GraphQLSelector>,
+}
+
+impl FieldUsageConfig {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        for entry in &self.include {
+            let valid = match entry.split_once('.') {
+                Some((type_name, field_name)) => {
+                    Name::new(type_name).is_ok() && Name::new(field_name).is_ok()
+                }
+                None => Name::new(entry).is_ok(),
+            };
+            if !valid {
+                return Err(format!(
+                    "invalid entry {entry:?} in field usage include list, expected `Type` or `Type.field`"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_included(&self, type_name: &str, field_name: &str) -> bool {
+        self.include
+            .iter()
+            .any(|entry| match entry.split_once('.') {
+                Some((included_type, included_field)) => {
+                    included_type == type_name && included_field == field_name
+                }
+                None => entry == type_name,
+            })
+    }
+}
+
+impl DefaultForLevel for FieldUsageConfig {
+    fn defaults_for_level(
+        &mut self,
+        requirement_level: DefaultAttributeRequirementLevel,
+        kind: TelemetryDataKind,
+    ) {
+        self.attributes.defaults_for_level(requirement_level, kind);
+        // Field usage is keyed by operation and client on top of the field
+        if let DefaultAttributeRequirementLevel::Required = requirement_level {
+            let attributes = &mut self.attributes.attributes;
+            attributes
+                .operation_name
+                .get_or_insert(StandardAttribute::Bool(true));
+            attributes
+                .client_name
+                .get_or_insert(StandardAttribute::Bool(true));
+        }
+    }
+}
+
+pub(crate) struct FieldUsageInstruments {
+    pub(crate) config: FieldUsageConfig,
+    /// Each sampled trace stands for `1 / field_level_instrumentation_ratio` operations
+    pub(crate) field_execution_weight: f64,
+    pub(crate) usage: Counter<f64>,
+    pub(crate) duration: Histogram<f64>,
+    pub(crate) errors: Counter<f64>,
+    /// Traces accumulate in the context across deferred responses and subscription events, only the new ones are recorded
+    pub(crate) recorded_traces: AtomicUsize,
+}
+
+impl FieldUsageInstruments {
+    pub(crate) fn on_response_event(&self, ctx: &Context) {
+        let traces = Telemetry::subgraph_ftv1_traces(ctx);
+        let recorded_traces = self.recorded_traces.swap(traces.len(), Ordering::Relaxed);
+        for (_, trace) in traces.iter().skip(recorded_traces) {
+            if let Some(root) = &trace.root {
+                self.record(root, ctx);
+            }
+        }
+    }
+
+    fn record(&self, node: &TraceNode, ctx: &Context) {
+        for child in &node.child {
+            self.record(child, ctx);
+        }
+        let Some(Id::ResponseName(response_name)) = &node.id else {
+            return;
+        };
+        let field_name = if node.original_field_name.is_empty() {
+            response_name
+        } else {
+            &node.original_field_name
+        };
+        if !self.config.is_included(&node.parent_type, field_name) {
+            return;
+        }
+        let (Ok(type_name), Ok(field_name), Ok(ty)) = (
+            Name::new(&node.parent_type),
+            Name::new(field_name),
+            Type::parse(&node.r#type, "ftv1"),
+        ) else {
+            return;
+        };
+        let field = Field::new(
+            field_name.clone(),
+            Node::new(FieldDefinition {
+                description: None,
+                name: field_name,
+                arguments: vec![],
+                ty,
+                directives: Default::default(),
+            }),
+        );
+        let value = field_value(&field, node);
+
+        let mut attributes = Vec::with_capacity(self.config.attributes.custom.len());
+        self.config
+            .attributes
+            .on_response_field(&mut attributes, &type_name, &field, &value, ctx);
+
+        self.usage.add(self.field_execution_weight, &attributes);
+        if node.start_time != 0 && node.end_time != 0 {
+            let duration = node.end_time.saturating_sub(node.start_time);
+            self.duration
+                .record(duration as f64 / 1_000_000_000.0, &attributes);
+        }
+        if !node.error.is_empty() {
+            self.errors.add(
+                node.error.len() as f64 * self.field_execution_weight,
+                &attributes,
+            );
+        }
+    }
+}
+
+/// The value of a field as seen by the selectors. Traces don't carry values, only the shape of
+/// the response: lists have one child per item, and objects have one child per selected field.
+fn field_value(field: &Field, node: &TraceNode) -> Value {
+    if field.ty().is_list() {
+        Value::Array(
+            node.child
+                .iter()
+                .filter(|child| matches!(child.id, Some(Id::Index(_))))
+                .map(|_| Value::Null)
+                .collect(),
+        )
+    } else if node.child.is_empty() {
+        Value::Bool(true)
+    } else {
+        Value::Object(Default::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::metrics::MeterProvider;
+
+    use super::*;
+    use crate::context::OPERATION_NAME;
+    use crate::metrics::FutureMetricsExt;
+    use crate::metrics::meter_provider;
+    use crate::plugins::telemetry::CLIENT_NAME;
+    use crate::plugins::telemetry::SUBGRAPH_FTV1;
+    use crate::plugins::telemetry::apollo_exporter::proto::reports::Trace;
+    use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::Error;
+    use crate::plugins::telemetry::tracing::apollo_telemetry::encode_ftv1_trace;
+
+    fn field_node(
+        parent_type: &str,
+        name: &str,
+        ty: &str,
+        duration: u64,
+        child: Vec<TraceNode>,
+    ) -> TraceNode {
+        TraceNode {
+            id: Some(Id::ResponseName(name.to_string())),
+            parent_type: parent_type.to_string(),
+            r#type: ty.to_string(),
+            start_time: 1_000,
+            end_time: 1_000 + duration,
+            child,
+            ..Default::default()
+        }
+    }
+
+    fn encoded(root: TraceNode) -> String {
+        encode_ftv1_trace(&Trace {
+            root: Some(root),
+            ..Default::default()
+        })
+    }
+
+    fn instruments(include: &[&str]) -> FieldUsageInstruments {
+        let mut config: FieldUsageConfig = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "include": include,
+        }))
+        .unwrap();
+        config.validate().unwrap();
+        config.defaults_for_level(
+            DefaultAttributeRequirementLevel::Required,
+            TelemetryDataKind::Metrics,
+        );
+        let meter = meter_provider().meter("test");
+        FieldUsageInstruments {
+            config,
+            field_execution_weight: 10.0,
+            usage: meter.f64_counter(FIELD_USAGE).build(),
+            duration: meter.f64_histogram(FIELD_USAGE_DURATION).build(),
+            errors: meter.f64_counter(FIELD_USAGE_ERRORS).build(),
+            recorded_traces: AtomicUsize::new(0),
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_include_entries() {
+        let config = FieldUsageConfig {
+            include: vec!["Query.topProducts.name".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn it_records_included_fields() {
+        async {
+            let ctx = Context::default();
+            let _ = ctx.insert(OPERATION_NAME, "TopProducts".to_string());
+            let _ = ctx.insert(CLIENT_NAME, "web".to_string());
+            let mut product = field_node("Product", "name", "String", 2_000_000, vec![]);
+            product.error = vec![Error::default()];
+            let root = TraceNode {
+                child: vec![field_node(
+                    "Query",
+                    "topProducts",
+                    "[Product]",
+                    5_000_000,
+                    vec![
+                        TraceNode {
+                            id: Some(Id::Index(0)),
+                            child: vec![product],
+                            ..Default::default()
+                        },
+                        TraceNode {
+                            id: Some(Id::Index(1)),
+                            child: vec![field_node("Product", "upc", "String!", 1_000_000, vec![])],
+                            ..Default::default()
+                        },
+                    ],
+                )],
+                ..Default::default()
+            };
+            let trace = encoded(root);
+            let _ = ctx.insert(SUBGRAPH_FTV1, serde_json::json!([["products", trace]]));
+
+            let instruments = instruments(&["Query.topProducts", "Product.name"]);
+            instruments.on_response_event(&ctx);
+            // The traces were already recorded
+            instruments.on_response_event(&ctx);
+
+            assert_counter!(
+                "graphql.field.usage",
+                10.0,
+                "graphql.type.name" = "Query",
+                "graphql.field.name" = "topProducts",
+                "graphql.field.type" = "Product",
+                "graphql.operation.name" = "TopProducts",
+                "client.name" = "web"
+            );
+            assert_histogram_sum!(
+                "graphql.field.usage.duration",
+                0.005,
+                "graphql.type.name" = "Query",
+                "graphql.field.name" = "topProducts",
+                "graphql.field.type" = "Product",
+                "graphql.operation.name" = "TopProducts",
+                "client.name" = "web"
+            );
+            assert_counter!(
+                "graphql.field.usage.errors",
+                10.0,
+                "graphql.type.name" = "Product",
+                "graphql.field.name" = "name",
+                "graphql.field.type" = "String",
+                "graphql.operation.name" = "TopProducts",
+                "client.name" = "web"
+            );
+            assert_counter_not_exists!(
+                "graphql.field.usage",
+                f64,
+                "graphql.type.name" = "Product",
+                "graphql.field.name" = "upc",
+                "graphql.field.type" = "String",
+                "graphql.operation.name" = "TopProducts",
+                "client.name" = "web"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+}
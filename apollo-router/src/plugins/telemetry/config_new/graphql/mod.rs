@@ -15,6 +15,8 @@ use crate::plugins::telemetry::config_new::DefaultForLevel;
 use crate::plugins::telemetry::config_new::attributes::DefaultAttributeRequirementLevel;
 use crate::plugins::telemetry::config_new::extendable::Extendable;
 use crate::plugins::telemetry::config_new::graphql::attributes::GraphQLAttributes;
+use crate::plugins::telemetry::config_new::graphql::field_usage::FieldUsageConfig;
+use crate::plugins::telemetry::config_new::graphql::field_usage::FieldUsageInstruments;
 use crate::plugins::telemetry::config_new::graphql::selectors::GraphQLSelector;
 use crate::plugins::telemetry::config_new::graphql::selectors::GraphQLValue;
 use crate::plugins::telemetry::config_new::instruments::CustomHistogram;
@@ -24,6 +26,7 @@ use crate::plugins::telemetry::otlp::TelemetryDataKind;
 use crate::services::supergraph;
 
 pub(crate) mod attributes;
+pub(crate) mod field_usage;
 pub(crate) mod selectors;
 
 pub(crate) const FIELD_LENGTH: &str = "graphql.field.list.length";
@@ -41,6 +44,10 @@ pub(crate) struct GraphQLInstrumentsConfig {
     #[serde(rename = "field.execution")]
     pub(crate) field_execution:
         DefaultedStandardInstrument<Extendable<GraphQLAttributes, GraphQLSelector>>,
+
+    /// Counters and a histogram of field executions, durations and errors measured by subgraphs.
+    #[serde(rename = "experimental_field_usage")]
+    pub(crate) field_usage: FieldUsageConfig,
 }
 
 impl DefaultForLevel for GraphQLInstrumentsConfig {
@@ -56,6 +63,9 @@ impl DefaultForLevel for GraphQLInstrumentsConfig {
             self.field_execution
                 .defaults_for_level(requirement_level, kind);
         }
+        if self.field_usage.enabled {
+            self.field_usage.defaults_for_level(requirement_level, kind);
+        }
     }
 }
 
@@ -87,6 +97,7 @@ pub(crate) struct GraphQLInstruments {
             GraphQLSelector,
         >,
     >,
+    pub(crate) field_usage: Option<FieldUsageInstruments>,
     pub(crate) custom: GraphQLCustomInstruments,
 }
 
@@ -133,6 +144,9 @@ impl Instrumented for GraphQLInstruments {
             field_execution.on_response_event(response, ctx);
         }
         self.custom.on_response_event(response, ctx);
+        if let Some(field_usage) = &self.field_usage {
+            field_usage.on_response_event(ctx);
+        }
 
         if (!self.custom.is_empty() || self.list_length.is_some() || self.field_execution.is_some())
             && let Some(executable_document) = ctx.executable_document()
@@ -8,6 +8,7 @@ use tower::BoxError;
 
 use crate::Context;
 use crate::context::OPERATION_NAME;
+use crate::plugins::telemetry::CLIENT_NAME;
 use crate::plugins::telemetry::config::AttributeValue;
 use crate::plugins::telemetry::config_new::Selector;
 use crate::plugins::telemetry::config_new::Stage;
@@ -51,6 +52,13 @@ pub(crate) enum TypeName {
     String,
 }
 
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum ClientName {
+    /// The client name from the request headers
+    String,
+}
+
 #[derive(Deserialize, JsonSchema, Clone, Debug)]
 #[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
 pub(crate) enum GraphQLValue {
@@ -98,6 +106,13 @@ pub(crate) enum GraphQLSelector {
         /// Optional default value.
         default: Option<String>,
     },
+    ClientName {
+        /// The client name from the request headers.
+        #[allow(dead_code)]
+        client_name: ClientName,
+        /// Optional default value.
+        default: Option<String>,
+    },
     StaticField {
         /// A static value
         r#static: AttributeValue,
@@ -172,6 +187,12 @@ impl Selector for GraphQLSelector {
                 }
                 .map(opentelemetry::Value::from)
             }
+            GraphQLSelector::ClientName { default, .. } => ctx
+                .get::<_, String>(CLIENT_NAME)
+                .ok()
+                .flatten()
+                .or_else(|| default.clone())
+                .map(opentelemetry::Value::from),
         }
     }
 
@@ -315,6 +336,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn client_name() {
+        let selector = GraphQLSelector::ClientName {
+            client_name: ClientName::String,
+            default: Some("unknown".to_string()),
+        };
+        let ctx = Context::default();
+        let result = selector.on_response_field(&ty(), field(), &json!(true), &ctx);
+        assert_eq!(result, Some(Value::String("unknown".into())));
+        let _ = ctx.insert(CLIENT_NAME, "some-client".to_string());
+        let result = selector.on_response_field(&ty(), field(), &json!(true), &ctx);
+        assert_eq!(result, Some(Value::String("some-client".into())));
+    }
+
     #[test]
     fn operation_name_defaulted() {
         let selector = GraphQLSelector::OperationName {
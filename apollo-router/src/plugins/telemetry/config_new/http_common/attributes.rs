@@ -155,7 +155,7 @@ impl DefaultForLevel for HttpCommonAttributes {
             DefaultAttributeRequirementLevel::Recommended => {
                 // Recommended
                 match kind {
-                    TelemetryDataKind::Traces => {
+                    TelemetryDataKind::Traces | TelemetryDataKind::Logs => {
                         if self.http_request_body_size.is_none() {
                             self.http_request_body_size = Some(StandardAttribute::Bool(true));
                         }
@@ -162,7 +162,7 @@ impl DefaultForLevel for HttpServerAttributes {
     ) {
         match requirement_level {
             DefaultAttributeRequirementLevel::Required => match kind {
-                TelemetryDataKind::Traces => {
+                TelemetryDataKind::Traces | TelemetryDataKind::Logs => {
                     if self.url_scheme.is_none() {
                         self.url_scheme = Some(StandardAttribute::Bool(true));
                     }
@@ -187,7 +187,7 @@ impl DefaultForLevel for HttpServerAttributes {
                 }
             },
             DefaultAttributeRequirementLevel::Recommended => match kind {
-                TelemetryDataKind::Traces => {
+                TelemetryDataKind::Traces | TelemetryDataKind::Logs => {
                     if self.client_address.is_none() {
                         self.client_address = Some(StandardAttribute::Bool(true));
                     }
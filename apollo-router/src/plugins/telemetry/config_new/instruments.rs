@@ -29,6 +29,10 @@ use super::cache::attributes::CacheAttributes;
 use super::graphql::FIELD_EXECUTION;
 use super::graphql::FIELD_LENGTH;
 use super::graphql::GraphQLInstruments;
+use super::graphql::field_usage::FIELD_USAGE;
+use super::graphql::field_usage::FIELD_USAGE_DURATION;
+use super::graphql::field_usage::FIELD_USAGE_ERRORS;
+use super::graphql::field_usage::FieldUsageInstruments;
 use super::graphql::selectors::ListLength;
 use super::http_server::attributes::HttpServerAttributes;
 use super::router::instruments::RouterInstruments;
@@ -68,6 +72,7 @@ use crate::plugins::telemetry::config_new::subgraph::selectors::SubgraphValue;
 use crate::plugins::telemetry::config_new::supergraph::attributes::SupergraphAttributes;
 use crate::plugins::telemetry::config_new::supergraph::selectors::SupergraphSelector;
 use crate::plugins::telemetry::config_new::supergraph::selectors::SupergraphValue;
+use crate::plugins::telemetry::metrics::exemplars::HistogramExemplars;
 use crate::plugins::telemetry::otlp::TelemetryDataKind;
 use crate::plugins::telemetry::utils::extend_attributes;
 use crate::services::router;
@@ -146,6 +151,7 @@ impl InstrumentsConfig {
                 format!("error for custom graphql instrument {name:?} in condition: {err}")
             })?;
         }
+        self.graphql.attributes.field_usage.validate()?;
         for (name, custom) in &self.cache.custom {
             custom.condition.validate(None).map_err(|err| {
                 format!("error for custom cache instrument {name:?} in condition: {err}")
@@ -160,6 +166,29 @@ impl InstrumentsConfig {
         Ok(())
     }
 
+    /// Whether a histogram has exemplars enabled
+    pub(crate) fn has_exemplars(&self) -> bool {
+        self.router.attributes.has_exemplars()
+            || self.supergraph.attributes.cost.has_exemplars()
+            || self.subgraph.attributes.has_exemplars()
+            || self.connector.attributes.has_exemplars()
+            || self.graphql.attributes.list_length.has_exemplars()
+            || self.router.custom.values().any(Instrument::has_exemplars)
+            || self
+                .supergraph
+                .custom
+                .values()
+                .any(Instrument::has_exemplars)
+            || self.subgraph.custom.values().any(Instrument::has_exemplars)
+            || self
+                .connector
+                .custom
+                .values()
+                .any(Instrument::has_exemplars)
+            || self.graphql.custom.values().any(Instrument::has_exemplars)
+            || self.cache.custom.values().any(Instrument::has_exemplars)
+    }
+
     /// Update the defaults for spans configuration regarding the `default_attribute_requirement_level`
     pub(crate) fn update_defaults(&mut self) {
         self.router
@@ -319,10 +348,11 @@ impl InstrumentsConfig {
                     selectors: match &self.router.attributes.http_server_request_duration {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             Some(attributes.clone())
                         }
                     },
+                    exemplars: self.router.attributes.http_server_request_duration.exemplars(HTTP_SERVER_REQUEST_DURATION_METRIC),
                     updated: false,
                     _phantom: PhantomData,
                 }),
@@ -337,7 +367,7 @@ impl InstrumentsConfig {
                     let selectors = match &self.router.attributes.http_server_request_body_size {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -364,6 +394,7 @@ impl InstrumentsConfig {
                                 default: None,
                             })),
                             selectors,
+                            exemplars: self.router.attributes.http_server_request_body_size.exemplars(HTTP_SERVER_REQUEST_BODY_SIZE_METRIC),
                             updated: false,
                             _phantom: PhantomData,
                         }),
@@ -379,7 +410,7 @@ impl InstrumentsConfig {
                     let selectors = match &self.router.attributes.http_server_response_body_size {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -406,6 +437,7 @@ impl InstrumentsConfig {
                             // so this will always be the uncompressed size.
                             selector: Some(Arc::new(RouterSelector::ResponseSizeHint { response_size_hint: true })),
                             selectors,
+                            exemplars: self.router.attributes.http_server_response_body_size.exemplars(HTTP_SERVER_RESPONSE_BODY_SIZE_METRIC),
                             updated: false,
                             _phantom: PhantomData,
                         }),
@@ -433,7 +465,7 @@ impl InstrumentsConfig {
                     attrs_config: match &self.router.attributes.http_server_active_requests {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => Default::default(),
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             attributes.clone()
                         }
                     },
@@ -611,7 +643,7 @@ impl InstrumentsConfig {
                     let selectors = match &self.subgraph.attributes.http_client_request_duration {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -634,6 +666,7 @@ impl InstrumentsConfig {
                             attributes: Vec::with_capacity(nb_attributes),
                             selector: None,
                             selectors,
+                            exemplars: self.subgraph.attributes.http_client_request_duration.exemplars(HTTP_CLIENT_REQUEST_DURATION_METRIC),
                             updated: false,
                             _phantom: PhantomData,
                         }),
@@ -649,7 +682,7 @@ impl InstrumentsConfig {
                     let selectors = match &self.subgraph.attributes.http_client_request_body_size {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -674,6 +707,7 @@ impl InstrumentsConfig {
                                 subgraph_request_body_size: true,
                             })),
                             selectors,
+                            exemplars: self.subgraph.attributes.http_client_request_body_size.exemplars(HTTP_CLIENT_REQUEST_BODY_SIZE_METRIC),
                             updated: false,
                             _phantom: PhantomData,
                         }),
@@ -689,7 +723,7 @@ impl InstrumentsConfig {
                     let selectors = match &self.subgraph.attributes.http_client_response_body_size {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -714,6 +748,7 @@ impl InstrumentsConfig {
                                 subgraph_response_body_size: true,
                             })),
                             selectors,
+                            exemplars: self.subgraph.attributes.http_client_response_body_size.exemplars(HTTP_CLIENT_RESPONSE_BODY_SIZE_METRIC),
                             updated: false,
                             _phantom: PhantomData,
                         }),
@@ -825,6 +860,41 @@ impl InstrumentsConfig {
             );
         }
 
+        if self.graphql.attributes.field_usage.enabled {
+            static_instruments.insert(
+                FIELD_USAGE.to_string(),
+                StaticInstrument::CounterF64(
+                    meter
+                        .f64_counter(FIELD_USAGE)
+                        .with_description(
+                            "Estimated number of executions of a field, measured by subgraphs.",
+                        )
+                        .build(),
+                ),
+            );
+            static_instruments.insert(
+                FIELD_USAGE_DURATION.to_string(),
+                StaticInstrument::Histogram(
+                    meter
+                        .f64_histogram(FIELD_USAGE_DURATION)
+                        .with_description("Execution duration of a field, measured by subgraphs.")
+                        .with_unit("s")
+                        .build(),
+                ),
+            );
+            static_instruments.insert(
+                FIELD_USAGE_ERRORS.to_string(),
+                StaticInstrument::CounterF64(
+                    meter
+                        .f64_counter(FIELD_USAGE_ERRORS)
+                        .with_description(
+                            "Estimated number of errors of a field, measured by subgraphs.",
+                        )
+                        .build(),
+                ),
+            );
+        }
+
         for (instrument_name, instrument) in &self.graphql.custom {
             match instrument.ty {
                 InstrumentType::Counter => {
@@ -860,6 +930,7 @@ impl InstrumentsConfig {
     pub(crate) fn new_graphql_instruments(
         &self,
         static_instruments: Arc<HashMap<String, StaticInstrument>>,
+        field_level_instrumentation_ratio: f64,
     ) -> GraphQLInstruments {
         GraphQLInstruments {
             list_length: self.graphql.attributes.list_length.is_enabled().then(|| {
@@ -868,7 +939,7 @@ impl InstrumentsConfig {
                     DefaultedStandardInstrument::Bool(_) | DefaultedStandardInstrument::Unset => {
                         None
                     }
-                    DefaultedStandardInstrument::Extendable { attributes } => {
+                    DefaultedStandardInstrument::Extendable { attributes, .. } => {
                         nb_attributes = attributes.custom.len();
                         Some(attributes.clone())
                     }
@@ -893,6 +964,7 @@ impl InstrumentsConfig {
                             list_length: ListLength::Value,
                         })),
                         selectors,
+                        exemplars: self.graphql.attributes.list_length.exemplars(FIELD_LENGTH),
                         updated: false,
                         _phantom: PhantomData,
                     }),
@@ -908,7 +980,7 @@ impl InstrumentsConfig {
                     let selectors = match &self.graphql.attributes.field_execution {
                         DefaultedStandardInstrument::Bool(_)
                         | DefaultedStandardInstrument::Unset => None,
-                        DefaultedStandardInstrument::Extendable { attributes } => {
+                        DefaultedStandardInstrument::Extendable { attributes, .. } => {
                             nb_attributes = attributes.custom.len();
                             Some(attributes.clone())
                         }
@@ -936,6 +1008,31 @@ impl InstrumentsConfig {
                         }),
                     }
                 }),
+            field_usage: (self.graphql.attributes.field_usage.enabled
+                && field_level_instrumentation_ratio > 0.0)
+                .then(|| {
+                    let counter = |name: &str| {
+                        static_instruments
+                            .get(name)
+                            .and_then(|instrument| instrument.as_counter_f64().cloned())
+                            .expect(
+                                "cannot get static instrument for field usage; this should not happen",
+                            )
+                    };
+                    FieldUsageInstruments {
+                        config: self.graphql.attributes.field_usage.clone(),
+                        field_execution_weight: 1.0 / field_level_instrumentation_ratio,
+                        usage: counter(FIELD_USAGE),
+                        duration: static_instruments
+                            .get(FIELD_USAGE_DURATION)
+                            .and_then(|instrument| instrument.as_histogram().cloned())
+                            .expect(
+                                "cannot get static instrument for field usage; this should not happen",
+                            ),
+                        errors: counter(FIELD_USAGE_ERRORS),
+                        recorded_traces: Default::default(),
+                    }
+                }),
             custom: CustomInstruments::new(&self.graphql.custom, static_instruments),
         }
     }
@@ -986,7 +1083,7 @@ impl InstrumentsConfig {
                     DefaultedStandardInstrument::Bool(_) | DefaultedStandardInstrument::Unset => {
                         None
                     }
-                    DefaultedStandardInstrument::Extendable { attributes } => {
+                    DefaultedStandardInstrument::Extendable { attributes, .. } => {
                         nb_attributes = attributes.custom.len();
                         Some(attributes.clone())
                     }
@@ -1023,7 +1120,7 @@ impl InstrumentsConfig {
                     DefaultedStandardInstrument::Bool(_) | DefaultedStandardInstrument::Unset => {
                         None
                     }
-                    DefaultedStandardInstrument::Extendable { attributes } => {
+                    DefaultedStandardInstrument::Extendable { attributes, .. } => {
                         nb_attributes = attributes.custom.len();
                         Some(attributes.clone())
                     }
@@ -1130,13 +1227,17 @@ impl DefaultForLevel for ActiveRequestsAttributes {
 
 #[derive(Clone, Deserialize, JsonSchema, Debug, Default)]
 #[serde(deny_unknown_fields, untagged)]
-#[schemars(rename = "StandardInstrument{T}")]
+#[schemars(rename = "StandardInstrument{T}", bound = "T: JsonSchema + Default")]
 pub(crate) enum DefaultedStandardInstrument<T> {
     #[default]
     Unset,
     Bool(bool),
     Extendable {
+        #[serde(default)]
         attributes: Arc<T>,
+        /// Keep exemplars of the measurements of sampled traces, only applies to histograms.
+        #[serde(default)]
+        exemplars: bool,
     },
 }
 
@@ -1148,6 +1249,21 @@ impl<T> DefaultedStandardInstrument<T> {
             Self::Extendable { .. } => true,
         }
     }
+
+    pub(crate) fn has_exemplars(&self) -> bool {
+        matches!(
+            self,
+            Self::Extendable {
+                exemplars: true,
+                ..
+            }
+        )
+    }
+
+    /// The exemplars of the histogram `name`, if they're enabled
+    pub(crate) fn exemplars(&self, name: &str) -> Option<HistogramExemplars> {
+        self.has_exemplars().then(|| HistogramExemplars::new(name))
+    }
 }
 
 impl<T> DefaultForLevel for DefaultedStandardInstrument<T>
@@ -1168,6 +1284,7 @@ where
                     attrs.defaults_for_levels(requirement_level, kind);
                     *self = Self::Extendable {
                         attributes: Arc::new(attrs),
+                        exemplars: false,
                     }
                 }
             },
@@ -1179,10 +1296,11 @@ where
                     attrs.defaults_for_levels(requirement_level, kind);
                     *self = Self::Extendable {
                         attributes: Arc::new(attrs),
+                        exemplars: false,
                     }
                 }
             },
-            DefaultedStandardInstrument::Extendable { attributes } => {
+            DefaultedStandardInstrument::Extendable { attributes, .. } => {
                 Arc::make_mut(attributes).defaults_for_levels(requirement_level, kind);
             }
             _ => {}
@@ -1198,28 +1316,28 @@ where
     fn on_request(&self, request: &Request) -> Vec<opentelemetry::KeyValue> {
         match self {
             Self::Bool(_) | Self::Unset => Vec::with_capacity(0),
-            Self::Extendable { attributes } => attributes.on_request(request),
+            Self::Extendable { attributes, .. } => attributes.on_request(request),
         }
     }
 
     fn on_response(&self, response: &Response) -> Vec<opentelemetry::KeyValue> {
         match self {
             Self::Bool(_) | Self::Unset => Vec::with_capacity(0),
-            Self::Extendable { attributes } => attributes.on_response(response),
+            Self::Extendable { attributes, .. } => attributes.on_response(response),
         }
     }
 
     fn on_error(&self, error: &BoxError, ctx: &Context) -> Vec<opentelemetry::KeyValue> {
         match self {
             Self::Bool(_) | Self::Unset => Vec::with_capacity(0),
-            Self::Extendable { attributes } => attributes.on_error(error, ctx),
+            Self::Extendable { attributes, .. } => attributes.on_error(error, ctx),
         }
     }
 
     fn on_response_event(&self, response: &EventResponse, ctx: &Context) -> Vec<KeyValue> {
         match self {
             Self::Bool(_) | Self::Unset => Vec::with_capacity(0),
-            Self::Extendable { attributes } => attributes.on_response_event(response, ctx),
+            Self::Extendable { attributes, .. } => attributes.on_response_event(response, ctx),
         }
     }
 }
@@ -1252,6 +1370,21 @@ where
     /// The instrument conditions.
     #[serde(default = "Condition::empty::<E>")]
     condition: Condition<E>,
+
+    /// Keep exemplars of the measurements of sampled traces, only applies to histograms.
+    #[serde(default)]
+    exemplars: bool,
+}
+
+impl<A, E, V> Instrument<A, E, V>
+where
+    A: Default + Debug,
+    E: Debug,
+    for<'a> &'a V: Into<InstrumentValue<E>>,
+{
+    fn has_exemplars(&self) -> bool {
+        self.exemplars && matches!(self.ty, InstrumentType::Histogram)
+    }
 }
 
 impl<A, E, Request, Response, EventResponse, SelectorValue>
@@ -1554,6 +1687,9 @@ where
                                 attributes: Vec::new(),
                                 selector,
                                 selectors: Some(instrument.attributes.clone()),
+                                exemplars: instrument
+                                    .exemplars
+                                    .then(|| HistogramExemplars::new(instrument_name)),
                                 updated: false,
                                 _phantom: PhantomData,
                             };
@@ -2162,12 +2298,26 @@ where
     pub(crate) selector: Option<Arc<T>>,
     pub(crate) selectors: Option<Arc<Extendable<A, T>>>,
     pub(crate) histogram: Option<Histogram<f64>>,
+    pub(crate) exemplars: Option<HistogramExemplars>,
     pub(crate) attributes: Vec<opentelemetry::KeyValue>,
     // Useful when it's an histogram on events to know if we have to count for an event or not
     pub(crate) updated: bool,
     pub(crate) _phantom: PhantomData<EventResponse>,
 }
 
+impl<Request, Response, EventResponse, A, T>
+    CustomHistogramInner<Request, Response, EventResponse, A, T>
+where
+    A: Selectors<Request, Response, EventResponse> + Default,
+    T: Selector<Request = Request, Response = Response>,
+{
+    fn record_exemplar(&self, value: f64, attributes: &[KeyValue]) {
+        if let Some(exemplars) = &self.exemplars {
+            exemplars.record(value, attributes);
+        }
+    }
+}
+
 #[buildstructor::buildstructor]
 impl<Request, Response, EventResponse, A: Default, T>
     CustomHistogram<Request, Response, EventResponse, A, T>
@@ -2182,6 +2332,7 @@ where
         selector: Option<Arc<T>>,
         selectors: Option<Arc<Extendable<A, T>>>,
         histogram: Option<Histogram<f64>>,
+        exemplars: Option<HistogramExemplars>,
         attributes: Vec<KeyValue>,
     ) -> Self {
         Self {
@@ -2192,6 +2343,7 @@ where
                 selector,
                 selectors,
                 histogram,
+                exemplars,
                 updated: false,
                 _phantom: PhantomData,
             }),
@@ -2215,6 +2367,9 @@ where
             let _ = inner.histogram.take();
             return;
         }
+        if let Some(exemplars) = &mut inner.exemplars {
+            exemplars.on_request();
+        }
         if let Some(selectors) = &inner.selectors {
             inner.attributes = selectors.on_request(request).into_iter().collect();
         }
@@ -2291,6 +2446,7 @@ where
         if let (Some(histogram), Some(increment)) = (&inner.histogram, increment.as_ref()) {
             if let Some(value) = value_to_f64(increment) {
                 histogram.record(value, &inner.attributes);
+                inner.record_exemplar(value, &inner.attributes);
             }
             inner.updated = true;
         }
@@ -2347,6 +2503,7 @@ where
         if let (Some(histogram), Some(increment)) = (&inner.histogram, increment.as_ref()) {
             if let Some(value) = value_to_f64(increment) {
                 histogram.record(value, &attrs);
+                inner.record_exemplar(value, &attrs);
             }
             inner.updated = true;
         }
@@ -2375,6 +2532,7 @@ where
             && let Some(value) = value_to_f64(increment)
         {
             histogram.record(value, &attrs);
+            inner.record_exemplar(value, &attrs);
         }
     }
 
@@ -2441,6 +2599,7 @@ where
         if let (Some(histogram), Some(increment)) = (&inner.histogram, increment.as_ref()) {
             if let Some(value) = value_to_f64(increment) {
                 histogram.record(value, &inner.attributes);
+                inner.record_exemplar(value, &inner.attributes);
             }
             // Reset the attributes to the original length, this will discard the new attributes added from selectors.
             inner.attributes.truncate(original_length);
@@ -2482,6 +2641,7 @@ where
                     && let Some(value) = value_to_f64(increment)
                 {
                     histogram.record(value, &inner.attributes);
+                    inner.record_exemplar(value, &inner.attributes);
                 }
             }
         }
@@ -2873,7 +3033,7 @@ mod tests {
                         let graphql_instruments: GraphQLInstruments = config
                             .new_graphql_instruments(Arc::new(
                                 config.new_builtin_graphql_instruments(),
-                            ));
+                            ), 1.0);
                         let context = Context::new();
                         for event in request {
                             match event {
@@ -3915,4 +4075,35 @@ mod tests {
         // When unit is "ms", 1500us should be 1.5 milliseconds
         assert_eq!(duration_to_f64(duration, "ms"), 1.5);
     }
+
+    #[test]
+    fn test_exemplars_are_configured_per_histogram() {
+        let config: InstrumentsConfig = serde_json::from_value(json!({
+            "router": {
+                "http.server.request.duration": {
+                    "exemplars": true
+                },
+                "http.server.request.body.size": {
+                    "attributes": {
+                        "http.request.method": true
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let router = &config.router.attributes;
+        assert!(router.http_server_request_duration.is_enabled());
+        assert!(
+            router
+                .http_server_request_duration
+                .exemplars(HTTP_SERVER_REQUEST_DURATION_METRIC)
+                .is_some()
+        );
+        assert!(
+            router
+                .http_server_request_body_size
+                .exemplars(HTTP_SERVER_REQUEST_BODY_SIZE_METRIC)
+                .is_none()
+        );
+    }
 }
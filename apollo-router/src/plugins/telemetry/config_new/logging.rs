@@ -13,6 +13,7 @@ use serde::de::Visitor;
 
 use crate::plugins::telemetry::config::AttributeValue;
 use crate::plugins::telemetry::config::TraceIdFormat;
+use crate::plugins::telemetry::otlp;
 use crate::plugins::telemetry::resource::ConfigResource;
 
 /// Logging configuration.
@@ -26,6 +27,8 @@ pub(crate) struct Logging {
     #[serde(skip)]
     /// Settings for logging to a file.
     pub(crate) file: File,
+    /// Settings for exporting logs as OpenTelemetry log records over OTLP.
+    pub(crate) otlp: otlp::Config,
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema, Default, PartialEq)]
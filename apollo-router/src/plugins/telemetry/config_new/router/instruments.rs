@@ -58,6 +58,15 @@ pub(crate) struct RouterInstrumentsConfig {
         DefaultedStandardInstrument<Extendable<RouterOverheadAttributes, RouterSelector>>,
 }
 
+impl RouterInstrumentsConfig {
+    pub(crate) fn has_exemplars(&self) -> bool {
+        self.http_server_request_duration.has_exemplars()
+            || self.http_server_request_body_size.has_exemplars()
+            || self.http_server_response_body_size.has_exemplars()
+            || self.router_overhead.has_exemplars()
+    }
+}
+
 impl DefaultForLevel for RouterInstrumentsConfig {
     fn defaults_for_level(
         &mut self,
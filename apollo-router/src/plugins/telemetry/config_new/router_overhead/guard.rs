@@ -26,6 +26,7 @@ impl SubgraphRequestGuard {
             // Increment the active count
             let prev_count = inner_lock.active_count;
             inner_lock.active_count += 1;
+            inner_lock.total_count += 1;
 
             // If this is the first active subgraph request, start timing
             if prev_count == 0 {
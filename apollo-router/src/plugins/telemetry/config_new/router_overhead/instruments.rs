@@ -61,7 +61,7 @@ pub(crate) fn initialize_custom_histogram(
     let mut nb_attributes = 0;
     let selectors = match config {
         DefaultedStandardInstrument::Bool(_) | DefaultedStandardInstrument::Unset => None,
-        DefaultedStandardInstrument::Extendable { attributes } => {
+        DefaultedStandardInstrument::Extendable { attributes, .. } => {
             nb_attributes = attributes.custom.len();
             Some(attributes.clone())
         }
@@ -88,6 +88,7 @@ pub(crate) fn initialize_custom_histogram(
                 },
             )),
             selectors,
+            exemplars: config.exemplars(ROUTER_OVERHEAD_METRIC),
             updated: false,
             _phantom: PhantomData,
         }),
@@ -13,6 +13,8 @@ pub(crate) struct OverheadResult {
     pub(crate) overhead: Duration,
     /// The number of active subgraph requests
     pub(crate) active_subgraph_requests: u64,
+    /// The number of subgraph requests made so far
+    pub(crate) subgraph_requests: u64,
 }
 
 /// Tracks router overhead by measuring time NOT spent waiting for subgraph requests.
@@ -43,6 +45,9 @@ pub(in crate::plugins::telemetry) struct TrackerInner {
 
     /// Count of active subgraph requests
     pub(in crate::plugins::telemetry) active_count: u64,
+
+    /// Count of all the subgraph requests, including completed ones
+    pub(in crate::plugins::telemetry) total_count: u64,
 }
 
 impl Default for RouterOverheadTracker {
@@ -53,6 +58,7 @@ impl Default for RouterOverheadTracker {
                 accumulated_subgraph_time: Duration::ZERO,
                 current_period_start: None,
                 active_count: 0,
+                total_count: 0,
             })),
         }
     }
@@ -80,6 +86,7 @@ impl RouterOverheadTracker {
 
         let inner = self.inner.lock();
         let active_count = inner.active_count;
+        let total_count = inner.total_count;
 
         // If there are still active subgraph requests, accumulate the current period
         let accumulated_time = if active_count > 0 {
@@ -98,6 +105,7 @@ impl RouterOverheadTracker {
         OverheadResult {
             overhead,
             active_subgraph_requests: active_count,
+            subgraph_requests: total_count,
         }
     }
 }
@@ -159,6 +167,7 @@ mod tests {
         // Overhead should be roughly 20ms (the time between subgraph requests)
         // On overloaded CI systems, timing can be very imprecise, so we use generous bounds
         assert_eq!(result.active_subgraph_requests, 0);
+        assert_eq!(result.subgraph_requests, 2);
         assert!(
             result.overhead >= Duration::from_millis(5)
                 && result.overhead <= Duration::from_millis(100),
@@ -211,6 +220,7 @@ mod tests {
         // All time is overhead when there are no subgraph requests
         // On overloaded CI systems, timing can be very imprecise, so we use generous bounds
         assert_eq!(result.active_subgraph_requests, 0);
+        assert_eq!(result.subgraph_requests, 0);
         assert!(
             result.overhead >= Duration::from_millis(80)
                 && result.overhead <= Duration::from_millis(250),
@@ -85,6 +85,8 @@ pub(crate) enum All {
 pub(crate) enum CacheKind {
     Hit,
     Miss,
+    /// Expired entries served from the response cache (not supported by the entity cache)
+    Stale,
 }
 
 #[derive(Deserialize, JsonSchema, Clone, PartialEq, Debug)]
@@ -93,6 +95,7 @@ pub(crate) enum CacheStatus {
     Hit,
     Miss,
     PartialHit,
+    Stale,
     Status,
 }
 
@@ -40,6 +40,10 @@ pub(crate) struct Spans {
     /// Attributes to include on the HTTP client span.
     /// HTTP client spans contain information about HTTP requests made to subgraphs, including any changes made by Rhai scripts.
     pub(crate) http_client: HttpClientSpans,
+
+    /// Compute the critical path of the query plan execution, the chain of dependent fetches that
+    /// determined its duration, and record it on the execution span.
+    pub(crate) experimental_critical_path: bool,
 }
 
 impl Spans {
@@ -38,6 +38,14 @@ pub(crate) struct SubgraphInstrumentsConfig {
         DefaultedStandardInstrument<Extendable<SubgraphAttributes, SubgraphSelector>>,
 }
 
+impl SubgraphInstrumentsConfig {
+    pub(crate) fn has_exemplars(&self) -> bool {
+        self.http_client_request_duration.has_exemplars()
+            || self.http_client_request_body_size.has_exemplars()
+            || self.http_client_response_body_size.has_exemplars()
+    }
+}
+
 impl DefaultForLevel for SubgraphInstrumentsConfig {
     fn defaults_for_level(
         &mut self,
@@ -629,6 +629,7 @@ impl Selector for SubgraphSelector {
                             .fold(0usize, |acc, (_entity_type, cache_hit_miss)| match cache {
                                 CacheKind::Hit => acc + cache_hit_miss.hit,
                                 CacheKind::Miss => acc + cache_hit_miss.miss,
+                                CacheKind::Stale => acc,
                             }) as i64)
                             .into(),
                     ),
@@ -640,6 +641,7 @@ impl Selector for SubgraphSelector {
                                     match cache {
                                         CacheKind::Hit => acc + cache_hit_miss.hit,
                                         CacheKind::Miss => acc + cache_hit_miss.miss,
+                                        CacheKind::Stale => acc,
                                     }
                                 } else {
                                     acc
@@ -671,6 +673,7 @@ impl Selector for SubgraphSelector {
                             .fold(0usize, |acc, (_entity_type, cache_hit_miss)| match cache {
                                 CacheKind::Hit => acc + cache_hit_miss.hit,
                                 CacheKind::Miss => acc + cache_hit_miss.miss,
+                                CacheKind::Stale => acc + cache_hit_miss.stale,
                             }) as i64)
                             .into(),
                     ),
@@ -682,6 +685,7 @@ impl Selector for SubgraphSelector {
                                     match cache {
                                         CacheKind::Hit => acc + cache_hit_miss.hit,
                                         CacheKind::Miss => acc + cache_hit_miss.miss,
+                                        CacheKind::Stale => acc + cache_hit_miss.stale,
                                     }
                                 } else {
                                     acc
@@ -725,37 +729,52 @@ impl Selector for SubgraphSelector {
                     .ok()
                     .flatten()?;
 
-                let (cache_hit, cache_miss, entity_type_exist) = cache_info.0.iter().fold(
-                    (0, 0, false),
-                    |(mut cache_hit, mut cache_miss, mut entity_type_exist),
-                     (current_entity_type, cache_hit_miss)| {
-                        let compute = match entity_type {
-                            Some(EntityType::All(All::All)) | None => true,
-                            Some(EntityType::Named(entity_type_name)) => {
-                                current_entity_type == entity_type_name
+                let (cache_hit, cache_miss, cache_stale, entity_type_exist) =
+                    cache_info.0.iter().fold(
+                        (0, 0, 0, false),
+                        |(
+                            mut cache_hit,
+                            mut cache_miss,
+                            mut cache_stale,
+                            mut entity_type_exist,
+                        ),
+                         (current_entity_type, cache_hit_miss)| {
+                            let compute = match entity_type {
+                                Some(EntityType::All(All::All)) | None => true,
+                                Some(EntityType::Named(entity_type_name)) => {
+                                    current_entity_type == entity_type_name
+                                }
+                            };
+                            if compute {
+                                cache_hit += cache_hit_miss.hit;
+                                cache_miss += cache_hit_miss.miss;
+                                cache_stale += cache_hit_miss.stale;
+                                entity_type_exist = true;
                             }
-                        };
-                        if compute {
-                            cache_hit += cache_hit_miss.hit;
-                            cache_miss += cache_hit_miss.miss;
-                            entity_type_exist = true;
-                        }
 
-                        (cache_hit, cache_miss, entity_type_exist)
-                    },
-                );
+                            (cache_hit, cache_miss, cache_stale, entity_type_exist)
+                        },
+                    );
+                // stale entries are served from the cache, but reported separately from hits
                 entity_type_exist.then(|| match response_cache_status {
-                    CacheStatus::Hit => (cache_hit > 0 && cache_miss == 0).into(),
-                    CacheStatus::Miss => (cache_hit == 0).into(),
-                    CacheStatus::PartialHit => (cache_hit > 0 && cache_miss > 0).into(),
+                    CacheStatus::Hit => {
+                        (cache_hit > 0 && cache_miss == 0 && cache_stale == 0).into()
+                    }
+                    CacheStatus::Miss => (cache_hit == 0 && cache_stale == 0).into(),
+                    CacheStatus::PartialHit => {
+                        (cache_hit + cache_stale > 0 && cache_miss > 0).into()
+                    }
+                    CacheStatus::Stale => (cache_stale > 0 && cache_miss == 0).into(),
                     CacheStatus::Status => {
                         if cache_miss == 0 {
-                            if cache_hit > 0 {
+                            if cache_stale > 0 {
+                                opentelemetry::Value::String("stale".into())
+                            } else if cache_hit > 0 {
                                 opentelemetry::Value::String("hit".into())
                             } else {
                                 opentelemetry::Value::String("miss".into())
                             }
-                        } else if cache_hit > 0 {
+                        } else if cache_hit + cache_stale > 0 {
                             opentelemetry::Value::String("partial_hit".into())
                         } else {
                             opentelemetry::Value::String("miss".into())
@@ -1433,11 +1452,19 @@ mod test {
             [
                 (
                     "Products".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 3, miss: 1 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 3,
+                        miss: 1,
+                        stale: 0,
+                    },
                 ),
                 (
                     "Reviews".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 2, miss: 0 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 2,
+                        miss: 0,
+                        stale: 0,
+                    },
                 ),
             ]
             .into_iter()
@@ -1473,11 +1500,19 @@ mod test {
             [
                 (
                     "Products".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 3, miss: 0 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 3,
+                        miss: 0,
+                        stale: 0,
+                    },
                 ),
                 (
                     "Reviews".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 2, miss: 0 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 2,
+                        miss: 0,
+                        stale: 0,
+                    },
                 ),
             ]
             .into_iter()
@@ -1511,11 +1546,19 @@ mod test {
             [
                 (
                     "Products".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 0, miss: 1 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 0,
+                        miss: 1,
+                        stale: 0,
+                    },
                 ),
                 (
                     "Reviews".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 0, miss: 4 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 0,
+                        miss: 4,
+                        stale: 0,
+                    },
                 ),
             ]
             .into_iter()
@@ -1562,11 +1605,19 @@ mod test {
             [
                 (
                     "Products".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 3, miss: 1 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 3,
+                        miss: 1,
+                        stale: 0,
+                    },
                 ),
                 (
                     "Reviews".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 0, miss: 3 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 0,
+                        miss: 3,
+                        stale: 0,
+                    },
                 ),
             ]
             .into_iter()
@@ -1602,11 +1653,19 @@ mod test {
             [
                 (
                     "Products".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 3, miss: 0 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 3,
+                        miss: 0,
+                        stale: 0,
+                    },
                 ),
                 (
                     "Reviews".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 2, miss: 1 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 2,
+                        miss: 1,
+                        stale: 0,
+                    },
                 ),
             ]
             .into_iter()
@@ -1640,11 +1699,19 @@ mod test {
             [
                 (
                     "Products".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 0, miss: 1 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 0,
+                        miss: 1,
+                        stale: 0,
+                    },
                 ),
                 (
                     "Reviews".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 4, miss: 4 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 4,
+                        miss: 4,
+                        stale: 0,
+                    },
                 ),
             ]
             .into_iter()
@@ -1667,6 +1734,98 @@ mod test {
         );
     }
 
+    #[test]
+    fn response_cache_status_stale() {
+        let selector = SubgraphSelector::ResponseCacheStatus {
+            response_cache_status: CacheStatus::Status,
+            entity_type: None,
+        };
+        let selector_stale = SubgraphSelector::ResponseCacheStatus {
+            response_cache_status: CacheStatus::Stale,
+            entity_type: None,
+        };
+        let selector_count = SubgraphSelector::ResponseCache {
+            response_cache: CacheKind::Stale,
+            entity_type: None,
+        };
+        let context = crate::context::Context::new();
+        let cache_info = response_cache::plugin::CacheSubgraph(
+            [
+                (
+                    "Products".to_string(),
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 3,
+                        miss: 0,
+                        stale: 1,
+                    },
+                ),
+                (
+                    "Reviews".to_string(),
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 2,
+                        miss: 0,
+                        stale: 1,
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let _ = context
+            .insert(
+                response_cache::metrics::CacheMetricContextKey::new("test".to_string()),
+                cache_info,
+            )
+            .unwrap();
+        let response = crate::services::SubgraphResponse::fake_builder()
+            .subgraph_name("test".to_string())
+            .context(context.clone())
+            .build();
+        assert_eq!(
+            selector.on_response(&response),
+            Some(opentelemetry::Value::String("stale".into()))
+        );
+        assert_eq!(
+            selector_stale.on_response(&response),
+            Some(opentelemetry::Value::Bool(true))
+        );
+        assert_eq!(
+            selector_count.on_response(&response),
+            Some(opentelemetry::Value::I64(2))
+        );
+
+        let cache_info = response_cache::plugin::CacheSubgraph(
+            [(
+                "Products".to_string(),
+                response_cache::plugin::CacheHitMiss {
+                    hit: 0,
+                    miss: 2,
+                    stale: 1,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let _ = context
+            .insert(
+                response_cache::metrics::CacheMetricContextKey::new("test".to_string()),
+                cache_info,
+            )
+            .unwrap();
+        let response = crate::services::SubgraphResponse::fake_builder()
+            .subgraph_name("test".to_string())
+            .context(context.clone())
+            .build();
+        assert_eq!(
+            selector.on_response(&response),
+            Some(opentelemetry::Value::String("partial_hit".into()))
+        );
+        assert_eq!(
+            selector_stale.on_response(&response),
+            Some(opentelemetry::Value::Bool(false))
+        );
+    }
+
     #[test]
     fn response_cache_hit_all_entities() {
         let selector = SubgraphSelector::ResponseCache {
@@ -1687,11 +1846,19 @@ mod test {
             [
                 (
                     "Products".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 3, miss: 0 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 3,
+                        miss: 0,
+                        stale: 0,
+                    },
                 ),
                 (
                     "Reviews".to_string(),
-                    response_cache::plugin::CacheHitMiss { hit: 2, miss: 0 },
+                    response_cache::plugin::CacheHitMiss {
+                        hit: 2,
+                        miss: 0,
+                        stale: 0,
+                    },
                 ),
             ]
             .into_iter()
@@ -25,6 +25,7 @@ use crate::plugins::telemetry::config_new::selectors::OperationKind;
 use crate::plugins::telemetry::config_new::selectors::OperationName;
 use crate::plugins::telemetry::config_new::selectors::Query;
 use crate::plugins::telemetry::config_new::selectors::ResponseStatus;
+use crate::query_planner::critical_path::CriticalPath;
 use crate::services::FIRST_EVENT_CONTEXT_KEY;
 use crate::services::supergraph;
 use crate::spec::operation_limits::OperationLimits;
@@ -217,6 +218,25 @@ pub(crate) enum SupergraphSelector {
         /// The context ID
         context_id: bool,
     },
+    /// The time spent in the fetches to a subgraph on the critical path of the query plan, in
+    /// seconds. Requires `telemetry.instrumentation.spans.experimental_critical_path`.
+    CriticalPathDuration {
+        /// The name of the subgraph
+        critical_path_duration: String,
+    },
+}
+
+/// The time spent on the critical path in the fetches to a subgraph, in seconds
+fn subgraph_critical_path_duration(
+    ctx: &Context,
+    subgraph_name: &str,
+) -> Option<opentelemetry::Value> {
+    ctx.extensions()
+        .with_lock(|lock| {
+            lock.get::<CriticalPath>()
+                .and_then(|critical_path| critical_path.subgraph_duration(subgraph_name))
+        })
+        .map(|duration| opentelemetry::Value::F64(duration.as_secs_f64()))
 }
 
 impl Selector for SupergraphSelector {
@@ -417,6 +437,9 @@ impl Selector for SupergraphSelector {
             SupergraphSelector::ContextId { context_id } if *context_id => {
                 Some(opentelemetry::Value::from(response.context.id.clone()))
             }
+            SupergraphSelector::CriticalPathDuration {
+                critical_path_duration,
+            } => subgraph_critical_path_duration(&response.context, critical_path_duration),
             // For request
             _ => None,
         }
@@ -522,6 +545,9 @@ impl Selector for SupergraphSelector {
             SupergraphSelector::ContextId { context_id } if *context_id => {
                 Some(opentelemetry::Value::from(ctx.id.clone()))
             }
+            SupergraphSelector::CriticalPathDuration {
+                critical_path_duration,
+            } => subgraph_critical_path_duration(ctx, critical_path_duration),
             _ => None,
         }
     }
@@ -631,6 +657,7 @@ impl Selector for SupergraphSelector {
                     | SupergraphSelector::Static(_)
                     | SupergraphSelector::StaticField { .. }
                     | SupergraphSelector::ContextId { .. }
+                    | SupergraphSelector::CriticalPathDuration { .. }
             ),
             Stage::ResponseEvent => matches!(
                 self,
@@ -645,6 +672,7 @@ impl Selector for SupergraphSelector {
                     | SupergraphSelector::Static(_)
                     | SupergraphSelector::StaticField { .. }
                     | SupergraphSelector::ContextId { .. }
+                    | SupergraphSelector::CriticalPathDuration { .. }
             ),
             Stage::ResponseField => false,
             Stage::Error => matches!(
@@ -692,6 +720,8 @@ mod test {
     use crate::plugins::telemetry::config_new::selectors::Query;
     use crate::plugins::telemetry::config_new::supergraph::selectors::SupergraphSelector;
     use crate::plugins::telemetry::otel;
+    use crate::query_planner::critical_path::CriticalPath;
+    use crate::query_planner::critical_path::CriticalPathFetch;
     use crate::services::FIRST_EVENT_CONTEXT_KEY;
     use crate::services::SupergraphRequest;
     use crate::services::SupergraphResponse;
@@ -881,6 +911,52 @@ mod test {
         );
     }
 
+    #[test]
+    fn supergraph_critical_path_duration() {
+        let selector = SupergraphSelector::CriticalPathDuration {
+            critical_path_duration: "products".to_string(),
+        };
+        let context = crate::context::Context::new();
+        assert_eq!(
+            selector.on_response_event(&crate::graphql::Response::builder().build(), &context),
+            None
+        );
+
+        let fetch = |subgraph_name: &str, duration_ms| CriticalPathFetch {
+            subgraph_name: subgraph_name.to_string(),
+            path: Default::default(),
+            start: Default::default(),
+            duration: std::time::Duration::from_millis(duration_ms),
+        };
+        context.extensions().with_lock(|lock| {
+            lock.insert(CriticalPath {
+                duration: std::time::Duration::from_millis(100),
+                fetches: vec![
+                    fetch("products", 20),
+                    fetch("reviews", 50),
+                    fetch("products", 30),
+                ],
+            })
+        });
+        assert_eq!(
+            selector
+                .on_response(
+                    &crate::services::SupergraphResponse::fake_builder()
+                        .context(context.clone())
+                        .build()
+                        .unwrap()
+                )
+                .unwrap(),
+            0.05.into()
+        );
+        assert_eq!(
+            selector
+                .on_response_event(&crate::graphql::Response::builder().build(), &context)
+                .unwrap(),
+            0.05.into()
+        );
+    }
+
     #[test]
     fn supergraph_response_context() {
         let selector = SupergraphSelector::ResponseContext {
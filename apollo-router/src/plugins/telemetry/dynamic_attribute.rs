@@ -220,6 +220,10 @@ pub(crate) struct EventAttributes {
 }
 
 impl EventAttributes {
+    pub(crate) fn attributes(&self) -> &Vec<KeyValue> {
+        &self.attributes
+    }
+
     pub(crate) fn extend(&mut self, other: impl IntoIterator<Item = KeyValue>) {
         for kv in other {
             upsert_attribute(&mut self.attributes, kv);
@@ -304,6 +308,43 @@ impl EventDynAttribute for ::tracing::Span {
     }
 }
 
+/// To add events to spans, without emitting them as log events
+pub(crate) trait SpanDynEvent {
+    fn add_span_dyn_event(&self, event: opentelemetry::trace::Event);
+}
+
+impl SpanDynEvent for ::tracing::Span {
+    fn add_span_dyn_event(&self, event: opentelemetry::trace::Event) {
+        self.with_subscriber(move |(id, dispatch)| {
+            if let Some(reg) = dispatch.downcast_ref::<Registry>() {
+                match reg.span(id) {
+                    None => eprintln!("no spanref, this is a bug"),
+                    Some(s) => {
+                        // Span events are only exported with sampled spans
+                        if !s.is_sampled() {
+                            return;
+                        }
+                        let mut extensions = s.extensions_mut();
+                        match extensions.get_mut::<OtelData>() {
+                            Some(otel_data) => otel_data
+                                .builder
+                                .events
+                                .get_or_insert_with(Vec::new)
+                                .push(event),
+                            None => {
+                                // Can't use ::tracing::error! because it could create deadlock on extensions
+                                eprintln!("no OtelData, this is a bug");
+                            }
+                        }
+                    }
+                };
+            } else {
+                ::tracing::error!("no Registry, this is a bug");
+            }
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::Cow;
@@ -0,0 +1,629 @@
+//! Local usage reporting
+//!
+//! Writes the usage data the router builds for Apollo usage reports (operation signatures,
+//! referenced fields and client info) to rolling local files or exports it as OpenTelemetry log
+//! records over OTLP, so that schema usage can be checked without sending reports to GraphOS.
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use opentelemetry::logs::AnyValue;
+use opentelemetry::logs::LogRecord;
+use opentelemetry::logs::Logger;
+use opentelemetry::logs::Severity;
+use opentelemetry_sdk::logs::SdkLogger;
+use opentelemetry_sdk::logs::SdkLoggerProvider;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use time::OffsetDateTime;
+use tower::BoxError;
+
+use crate::Context;
+use crate::apollo_studio_interop::UsageReporting;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::plugins::telemetry::CLIENT_VERSION;
+use crate::plugins::telemetry::SUPERGRAPH_SCHEMA_ID_CONTEXT_KEY;
+use crate::plugins::telemetry::config_new::logging::Rollover;
+use crate::plugins::telemetry::logging::otlp::router_logger;
+use crate::plugins::telemetry::otlp;
+use crate::plugins::telemetry::resource::ConfigResource;
+
+const USAGE_EVENT_NAME: &str = "apollo.router.usage";
+const USAGE_FILE_PREFIX: &str = "usage";
+const USAGE_FILE_EXTENSION: &str = "jsonl";
+/// Records are dropped when the file writer falls behind by this many records
+const USAGE_FILE_QUEUE_SIZE: usize = 10_000;
+
+/// The queue of the usage file writer thread.
+///
+/// A single thread writes the usage files, and is handed over to the reporter of each telemetry
+/// reload, so that the records of the requests still running on the previous configuration don't
+/// interleave with the new ones in the same file.
+static USAGE_FILE_WRITER: LazyLock<Mutex<Option<mpsc::SyncSender<FileMessage>>>> =
+    LazyLock::new(Default::default);
+
+enum FileMessage {
+    /// Writes the next records with this configuration, or drops them if disabled
+    Configure(Option<FileConfig>),
+    Record(Box<UsageRecord>),
+}
+
+/// Local usage reporting configuration
+#[derive(Clone, Default, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Config {
+    /// Write usage records to rolling local files.
+    pub(crate) file: FileConfig,
+    /// Export usage records as OpenTelemetry log records over OTLP.
+    pub(crate) otlp: otlp::Config,
+}
+
+/// Usage file configuration
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct FileConfig {
+    /// Set to true to write usage records to files.
+    pub(crate) enabled: bool,
+    /// The directory of the usage files.
+    pub(crate) directory: PathBuf,
+    /// The period to rollover the usage files.
+    pub(crate) rollover: Rollover,
+    /// The maximum number of usage files to keep, older files are deleted. All the files are kept if unset.
+    pub(crate) max_files: Option<NonZeroUsize>,
+}
+
+impl Default for FileConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: PathBuf::from("usage"),
+            rollover: Rollover::Daily,
+            max_files: None,
+        }
+    }
+}
+
+/// The usage of the schema by an operation, written as one JSON line in usage files and as the
+/// body of a log record over OTLP
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct UsageRecord {
+    #[serde(skip)]
+    time: OffsetDateTime,
+    /// ISO 8601 time at which the operation ended
+    timestamp: String,
+    /// The ID of the supergraph schema the operation ran against
+    schema_id: Option<String>,
+    /// The operation ID, as reported to GraphOS
+    operation_id: String,
+    operation_name: Option<String>,
+    /// The normalized operation signature
+    operation_signature: String,
+    persisted_query_id: Option<String>,
+    client_name: Option<String>,
+    client_version: Option<String>,
+    /// The fields referenced by the operation, by type
+    referenced_fields_by_type: BTreeMap<String, ReferencedFields>,
+    has_errors: bool,
+    duration_seconds: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct ReferencedFields {
+    field_names: Vec<String>,
+    is_interface: bool,
+}
+
+impl UsageRecord {
+    /// Returns the usage record of an operation, if the operation was valid
+    pub(crate) fn new(context: &Context, has_errors: bool, duration: Duration) -> Option<Self> {
+        let usage_reporting = context
+            .extensions()
+            .with_lock(|lock| lock.get::<Arc<UsageReporting>>().cloned())?;
+        let operation_signature = usage_reporting.get_operation_signature()?;
+        let time = OffsetDateTime::now_utc();
+        let mut referenced_fields_by_type: BTreeMap<String, ReferencedFields> = usage_reporting
+            .get_referenced_fields()
+            .into_iter()
+            .map(|(type_name, fields)| {
+                (
+                    type_name,
+                    ReferencedFields {
+                        field_names: fields.field_names,
+                        is_interface: fields.is_interface,
+                    },
+                )
+            })
+            .collect();
+        for fields in referenced_fields_by_type.values_mut() {
+            fields.field_names.sort();
+        }
+        let operation_name = usage_reporting.get_operation_name();
+        Some(Self {
+            time,
+            timestamp: time
+                .format(&time::format_description::well_known::Iso8601::DEFAULT)
+                .unwrap_or_default(),
+            schema_id: context.get(SUPERGRAPH_SCHEMA_ID_CONTEXT_KEY).ok().flatten(),
+            operation_id: usage_reporting.get_operation_id(),
+            operation_name: (!operation_name.is_empty()).then_some(operation_name),
+            operation_signature,
+            persisted_query_id: usage_reporting.get_persisted_query_id(),
+            client_name: context.get(CLIENT_NAME).ok().flatten(),
+            client_version: context.get(CLIENT_VERSION).ok().flatten(),
+            referenced_fields_by_type,
+            has_errors,
+            duration_seconds: duration.as_secs_f64(),
+        })
+    }
+}
+
+/// Sends usage records to the usage files and the OTLP endpoint
+pub(crate) struct LocalUsageReporter {
+    file: Option<mpsc::SyncSender<FileMessage>>,
+    logger: Option<SdkLogger>,
+    logger_provider: Option<SdkLoggerProvider>,
+}
+
+impl LocalUsageReporter {
+    /// Returns the reporter if local usage reporting is enabled
+    pub(crate) fn new(
+        config: &Config,
+        resource: &impl ConfigResource,
+    ) -> Result<Option<Self>, BoxError> {
+        if !config.file.enabled && !config.otlp.enabled {
+            return Ok(None);
+        }
+
+        let file = configure_file_writer(&config.file)?;
+
+        let (logger, logger_provider) = if config.otlp.enabled {
+            let logger_provider = config
+                .otlp
+                .build_logger_provider(resource, "usage-reporting")?;
+            (Some(router_logger(&logger_provider)), Some(logger_provider))
+        } else {
+            (None, None)
+        };
+
+        Ok(Some(Self {
+            file,
+            logger,
+            logger_provider,
+        }))
+    }
+
+    pub(crate) fn report(&self, record: UsageRecord) {
+        if let Some(logger) = &self.logger {
+            let mut log_record = logger.create_log_record();
+            let now = SystemTime::now();
+            log_record.set_timestamp(now);
+            log_record.set_observed_timestamp(now);
+            log_record.set_event_name(USAGE_EVENT_NAME);
+            log_record.set_severity_number(Severity::Info);
+            log_record.set_severity_text("INFO");
+            if let Ok(body) = serde_json::to_value(&record) {
+                log_record.set_body(any_value(body));
+            }
+            logger.emit(log_record);
+        }
+        if let Some(file) = &self.file
+            && let Err(err) = file.try_send(FileMessage::Record(Box::new(record)))
+        {
+            ::tracing::warn!("could not write usage record, record will be dropped: {err}");
+        }
+    }
+}
+
+/// Configures the usage file writer thread, starting it if needed, and returns its queue if the
+/// usage files are enabled
+fn configure_file_writer(
+    config: &FileConfig,
+) -> Result<Option<mpsc::SyncSender<FileMessage>>, BoxError> {
+    let config = config.enabled.then(|| config.clone());
+    let mut file_writer = USAGE_FILE_WRITER.lock();
+    if let Some(sender) = &*file_writer {
+        // Waits for the space in the queue, the records queued before are written with the
+        // previous configuration
+        if sender.send(FileMessage::Configure(config.clone())).is_ok() {
+            return Ok(config.map(|_| sender.clone()));
+        }
+    }
+    let Some(config) = config else {
+        return Ok(None);
+    };
+
+    let (sender, receiver) = mpsc::sync_channel(USAGE_FILE_QUEUE_SIZE);
+    let mut writer = Some(RollingFileWriter::new(config));
+    // The thread keeps running for the next reloads
+    std::thread::Builder::new()
+        .name("usage-reporting".to_string())
+        .spawn(move || {
+            while let Ok(message) = receiver.recv() {
+                // Write all the queued records before flushing
+                let mut next = Some(message);
+                while let Some(message) = next {
+                    match message {
+                        FileMessage::Configure(config) => {
+                            if let Some(writer) = &mut writer
+                                && let Err(err) = writer.flush()
+                            {
+                                ::tracing::error!("could not write usage records: {err}");
+                            }
+                            writer = config.map(RollingFileWriter::new);
+                        }
+                        FileMessage::Record(record) => {
+                            if let Some(writer) = &mut writer
+                                && let Err(err) = writer.write(&record)
+                            {
+                                ::tracing::error!("could not write usage record: {err}");
+                            }
+                        }
+                    }
+                    next = receiver.try_recv().ok();
+                }
+                if let Some(writer) = &mut writer
+                    && let Err(err) = writer.flush()
+                {
+                    ::tracing::error!("could not write usage records: {err}");
+                }
+            }
+        })?;
+    *file_writer = Some(sender.clone());
+    Ok(Some(sender))
+}
+
+/// The usage of an operation, reported once its last response is sent or when dropped, so that
+/// the operations whose response stream is abandoned are reported too
+pub(crate) struct PendingUsage {
+    reporter: Arc<LocalUsageReporter>,
+    context: Context,
+    start: Instant,
+    has_errors: bool,
+    reported: bool,
+}
+
+impl PendingUsage {
+    pub(crate) fn new(reporter: Arc<LocalUsageReporter>, context: Context, start: Instant) -> Self {
+        Self {
+            reporter,
+            context,
+            start,
+            has_errors: false,
+            reported: false,
+        }
+    }
+
+    pub(crate) fn on_errors(&mut self) {
+        self.has_errors = true;
+    }
+
+    /// Reports the usage, unless it was already reported
+    pub(crate) fn report(&mut self) {
+        if std::mem::replace(&mut self.reported, true) {
+            return;
+        }
+        if let Some(record) = UsageRecord::new(&self.context, self.has_errors, self.start.elapsed())
+        {
+            self.reporter.report(record);
+        }
+    }
+}
+
+impl Drop for PendingUsage {
+    fn drop(&mut self) {
+        self.report();
+    }
+}
+
+impl Drop for LocalUsageReporter {
+    fn drop(&mut self) {
+        // Shutting down the logger provider flushes the pending records, which blocks
+        if let Some(logger_provider) = self.logger_provider.take() {
+            tokio::task::spawn_blocking(move || drop(logger_provider));
+        }
+    }
+}
+
+fn any_value(value: serde_json::Value) -> AnyValue {
+    match value {
+        serde_json::Value::Null => AnyValue::String("".into()),
+        serde_json::Value::Bool(value) => value.into(),
+        serde_json::Value::Number(value) => match value.as_i64() {
+            Some(value) => value.into(),
+            None => value.as_f64().unwrap_or_default().into(),
+        },
+        serde_json::Value::String(value) => value.into(),
+        serde_json::Value::Array(values) => {
+            AnyValue::ListAny(Box::new(values.into_iter().map(any_value).collect()))
+        }
+        serde_json::Value::Object(map) => AnyValue::Map(Box::new(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.into(), any_value(value)))
+                .collect(),
+        )),
+    }
+}
+
+/// Appends usage records to JSON lines files, named after the period they cover
+struct RollingFileWriter {
+    config: FileConfig,
+    current: Option<(String, BufWriter<File>)>,
+}
+
+impl RollingFileWriter {
+    fn new(config: FileConfig) -> Self {
+        Self {
+            config,
+            current: None,
+        }
+    }
+
+    fn write(&mut self, record: &UsageRecord) -> std::io::Result<()> {
+        let file_name = file_name(&self.config.rollover, record.time);
+        let writer = match &mut self.current {
+            Some((current_name, writer)) if *current_name == file_name => writer,
+            _ => {
+                self.flush()?;
+                fs::create_dir_all(&self.config.directory)?;
+                let file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.config.directory.join(&file_name))?;
+                self.remove_old_files()?;
+                &mut self.current.insert((file_name, BufWriter::new(file))).1
+            }
+        };
+        serde_json::to_writer(&mut *writer, record)?;
+        writer.write_all(b"\n")
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.current {
+            Some((_, writer)) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn remove_old_files(&self) -> std::io::Result<()> {
+        let Some(max_files) = self.config.max_files else {
+            return Ok(());
+        };
+        let mut files = fs::read_dir(&self.config.directory)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| {
+                name.starts_with(USAGE_FILE_PREFIX) && name.ends_with(USAGE_FILE_EXTENSION)
+            })
+            .collect::<Vec<_>>();
+        // File names sort chronologically
+        files.sort();
+        let excess = files.len().saturating_sub(max_files.get());
+        for name in &files[..excess] {
+            fs::remove_file(self.config.directory.join(name))?;
+        }
+        Ok(())
+    }
+}
+
+fn file_name(rollover: &Rollover, time: OffsetDateTime) -> String {
+    match rollover {
+        Rollover::Hourly => format!(
+            "{USAGE_FILE_PREFIX}.{:04}-{:02}-{:02}-{:02}.{USAGE_FILE_EXTENSION}",
+            time.year(),
+            u8::from(time.month()),
+            time.day(),
+            time.hour()
+        ),
+        Rollover::Daily => format!(
+            "{USAGE_FILE_PREFIX}.{:04}-{:02}-{:02}.{USAGE_FILE_EXTENSION}",
+            time.year(),
+            u8::from(time.month()),
+            time.day()
+        ),
+        Rollover::Never => format!("{USAGE_FILE_PREFIX}.{USAGE_FILE_EXTENSION}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(time: OffsetDateTime) -> UsageRecord {
+        UsageRecord {
+            time,
+            timestamp: "[timestamp]".to_string(),
+            schema_id: Some("schema".to_string()),
+            operation_id: "id".to_string(),
+            operation_name: Some("TopProducts".to_string()),
+            operation_signature: "query TopProducts{topProducts{name}}".to_string(),
+            persisted_query_id: None,
+            client_name: Some("web".to_string()),
+            client_version: None,
+            referenced_fields_by_type: BTreeMap::from([(
+                "Query".to_string(),
+                ReferencedFields {
+                    field_names: vec!["topProducts".to_string()],
+                    is_interface: false,
+                },
+            )]),
+            has_errors: false,
+            duration_seconds: 0.5,
+        }
+    }
+
+    #[test]
+    fn it_builds_records_from_the_usage_reporting() {
+        let context = Context::new();
+        assert_eq!(
+            UsageRecord::new(&context, false, Duration::from_millis(500)),
+            None
+        );
+
+        let usage_reporting = UsageReporting::Operation(
+            serde_json::from_value(serde_json::json!({
+                "operationName": "TopProducts",
+                "operationSignature": "query TopProducts{topProducts{name upc}}",
+                "referencedFieldsByType": {
+                    "Product": {"fieldNames": ["upc", "name"], "isInterface": false},
+                },
+            }))
+            .unwrap(),
+        )
+        .with_pq_id("pq".to_string());
+        context
+            .extensions()
+            .with_lock(|lock| lock.insert(Arc::new(usage_reporting)));
+        let _ = context.insert(CLIENT_NAME, "web".to_string());
+
+        let record = UsageRecord::new(&context, true, Duration::from_millis(500)).unwrap();
+        assert_eq!(record.operation_name.as_deref(), Some("TopProducts"));
+        assert_eq!(
+            record.operation_signature,
+            "query TopProducts{topProducts{name upc}}"
+        );
+        assert_eq!(record.persisted_query_id.as_deref(), Some("pq"));
+        assert_eq!(record.client_name.as_deref(), Some("web"));
+        assert!(record.has_errors);
+        assert_eq!(
+            record.referenced_fields_by_type["Product"].field_names,
+            ["name", "upc"]
+        );
+    }
+
+    #[test]
+    fn it_names_files_after_their_period() {
+        // 2024-03-07 09:15 UTC
+        let time = OffsetDateTime::from_unix_timestamp(1709802900).unwrap();
+        assert_eq!(
+            file_name(&Rollover::Hourly, time),
+            "usage.2024-03-07-09.jsonl"
+        );
+        assert_eq!(file_name(&Rollover::Daily, time), "usage.2024-03-07.jsonl");
+        assert_eq!(file_name(&Rollover::Never, time), "usage.jsonl");
+    }
+
+    #[test]
+    fn it_rolls_files_over() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut writer = RollingFileWriter::new(FileConfig {
+            enabled: true,
+            directory: directory.path().to_path_buf(),
+            rollover: Rollover::Hourly,
+            max_files: NonZeroUsize::new(2),
+        });
+        for hour in [9, 9, 10, 11] {
+            writer
+                // 2024-03-07 00:00 UTC
+                .write(&record(
+                    OffsetDateTime::from_unix_timestamp(1709769600)
+                        .unwrap()
+                        .replace_hour(hour)
+                        .unwrap(),
+                ))
+                .unwrap();
+        }
+        writer.flush().unwrap();
+
+        let mut files = fs::read_dir(directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            ["usage.2024-03-07-10.jsonl", "usage.2024-03-07-11.jsonl"]
+        );
+
+        let lines = fs::read_to_string(directory.path().join("usage.2024-03-07-11.jsonl")).unwrap();
+        insta::assert_snapshot!(lines);
+    }
+
+    #[test]
+    fn it_hands_the_file_writer_over_across_reloads() {
+        let directory = tempfile::tempdir().unwrap();
+        let config = FileConfig {
+            enabled: true,
+            directory: directory.path().to_path_buf(),
+            rollover: Rollover::Never,
+            max_files: None,
+        };
+        let previous = configure_file_writer(&config).unwrap().unwrap();
+        let next = configure_file_writer(&config).unwrap().unwrap();
+        // 2024-03-07 09:15 UTC
+        let time = OffsetDateTime::from_unix_timestamp(1709802900).unwrap();
+        for sender in [&previous, &next, &previous] {
+            sender
+                .send(FileMessage::Record(Box::new(record(time))))
+                .unwrap();
+        }
+        assert!(
+            configure_file_writer(&FileConfig::default())
+                .unwrap()
+                .is_none()
+        );
+
+        // Disabling the files flushes the records queued before, written by the same thread
+        let mut lines = String::new();
+        for _ in 0..100 {
+            lines = fs::read_to_string(directory.path().join("usage.jsonl")).unwrap_or_default();
+            if lines.lines().count() == 3 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(lines.lines().count(), 3);
+        assert!(
+            lines
+                .lines()
+                .all(|line| line == lines.lines().next().unwrap())
+        );
+    }
+
+    #[test]
+    fn it_reports_pending_usage_when_dropped() {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let reporter = Arc::new(LocalUsageReporter {
+            file: Some(sender),
+            logger: None,
+            logger_provider: None,
+        });
+        let context = Context::new();
+        context.extensions().with_lock(|lock| {
+            lock.insert(Arc::new(UsageReporting::Operation(
+                serde_json::from_value(serde_json::json!({
+                    "operationName": "TopProducts",
+                    "operationSignature": "query TopProducts{topProducts{name}}",
+                    "referencedFieldsByType": {},
+                }))
+                .unwrap(),
+            )))
+        });
+
+        let mut usage = PendingUsage::new(reporter.clone(), context.clone(), Instant::now());
+        usage.on_errors();
+        drop(usage);
+        let Ok(FileMessage::Record(record)) = receiver.try_recv() else {
+            panic!("the usage should be reported when dropped");
+        };
+        assert!(record.has_errors);
+
+        let mut usage = PendingUsage::new(reporter, context, Instant::now());
+        usage.report();
+        drop(usage);
+        assert!(matches!(receiver.try_recv(), Ok(FileMessage::Record(_))));
+        assert!(receiver.try_recv().is_err());
+    }
+}
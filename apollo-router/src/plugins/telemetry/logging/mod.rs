@@ -1,4 +1,6 @@
 //TODO move telemetry logging functionality to this file
+pub(crate) mod otlp;
+
 #[cfg(test)]
 mod test {
     use tracing_futures::WithSubscriber;
@@ -0,0 +1,412 @@
+//! Export of logs and events as OpenTelemetry log records over OTLP.
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use http::Uri;
+use opentelemetry::InstrumentationScope;
+use opentelemetry::Key;
+use opentelemetry::Value;
+use opentelemetry::logs::AnyValue;
+use opentelemetry::logs::LogRecord;
+use opentelemetry::logs::Logger;
+use opentelemetry::logs::LoggerProvider;
+use opentelemetry::logs::Severity;
+use opentelemetry::trace::TraceFlags;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_otlp::WithHttpConfig;
+use opentelemetry_otlp::WithTonicConfig;
+use opentelemetry_sdk::logs::SdkLogger;
+use opentelemetry_sdk::logs::SdkLoggerProvider;
+use opentelemetry_sdk::logs::log_processor_with_async_runtime::BatchLogProcessor;
+use tonic::metadata::MetadataMap;
+use tower::BoxError;
+use tracing::field;
+use tracing_core::Event;
+use tracing_core::Field;
+use tracing_core::Level;
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::plugins::telemetry::GLOBAL_TRACER_NAME;
+use crate::plugins::telemetry::config::Conf;
+use crate::plugins::telemetry::consts::EVENT_ATTRIBUTE_OMIT_LOG;
+use crate::plugins::telemetry::dynamic_attribute::EventAttributes;
+use crate::plugins::telemetry::formatters::APOLLO_CONNECTOR_PREFIX;
+use crate::plugins::telemetry::formatters::APOLLO_PRIVATE_PREFIX;
+use crate::plugins::telemetry::formatters::EXCLUDED_ATTRIBUTES;
+use crate::plugins::telemetry::formatters::get_trace_and_span_id;
+use crate::plugins::telemetry::otel::OtelData;
+use crate::plugins::telemetry::otlp::Config;
+use crate::plugins::telemetry::otlp::Protocol;
+use crate::plugins::telemetry::otlp::TelemetryDataKind;
+use crate::plugins::telemetry::otlp::process_endpoint;
+use crate::plugins::telemetry::reload::otel::IsSampled;
+use crate::plugins::telemetry::resource::ConfigResource;
+use crate::plugins::telemetry::tracing::NamedTokioRuntime;
+
+/// Creates the layer exporting log records, and the logger provider backing it, if the OTLP log
+/// exporter is enabled.
+pub(crate) fn create_otlp_log_layer(
+    config: &Conf,
+) -> Result<Option<(OtlpLogLayer, SdkLoggerProvider)>, BoxError> {
+    let logging = &config.exporters.logging;
+    if !logging.otlp.enabled {
+        return Ok(None);
+    }
+
+    // Apply env var overrides to the config
+    let otlp = logging.otlp.clone().with_logs_env_overrides()?;
+    let logger_provider = otlp.build_logger_provider(&logging.common, "otlp-logging")?;
+    let layer = OtlpLogLayer {
+        logger: router_logger(&logger_provider),
+    };
+    Ok(Some((layer, logger_provider)))
+}
+
+/// Returns the logger emitting the log records of the router
+pub(crate) fn router_logger(logger_provider: &SdkLoggerProvider) -> SdkLogger {
+    let scope = InstrumentationScope::builder(GLOBAL_TRACER_NAME)
+        .with_version(env!("CARGO_PKG_VERSION"))
+        .build();
+    logger_provider.logger_with_scope(scope)
+}
+
+impl Config {
+    /// Builds a logger provider exporting log records to this endpoint in batches, from a tokio
+    /// runtime named after `runtime_name`
+    pub(crate) fn build_logger_provider(
+        &self,
+        resource: &impl ConfigResource,
+        runtime_name: &'static str,
+    ) -> Result<SdkLoggerProvider, BoxError> {
+        let exporter = self.build_log_exporter()?;
+        let batch_log_processor =
+            BatchLogProcessor::builder(exporter, NamedTokioRuntime::new(runtime_name))
+                .with_batch_config(self.batch_processor.clone().with_env_overrides()?.into())
+                .build();
+        Ok(SdkLoggerProvider::builder()
+            .with_resource(resource.to_resource())
+            .with_log_processor(batch_log_processor)
+            .build())
+    }
+
+    pub(crate) fn build_log_exporter(&self) -> Result<opentelemetry_otlp::LogExporter, BoxError> {
+        let endpoint_opt =
+            process_endpoint(&self.endpoint, &TelemetryDataKind::Logs, &self.protocol)?;
+        match self.protocol {
+            Protocol::Grpc => {
+                let mut exporter_builder = opentelemetry_otlp::LogExporter::builder()
+                    .with_tonic()
+                    .with_timeout(self.batch_processor.max_export_timeout)
+                    .with_metadata(MetadataMap::from_headers(self.grpc.metadata.clone()));
+                if let Some(endpoint) = endpoint_opt {
+                    if !endpoint.is_empty() {
+                        let tls_url = Uri::try_from(&endpoint)?;
+                        exporter_builder = exporter_builder
+                            .with_tls_config(self.grpc.clone().to_tls_config(&tls_url)?);
+                    }
+                    exporter_builder = exporter_builder.with_endpoint(endpoint);
+                }
+                Ok(exporter_builder.build()?)
+            }
+            Protocol::Http => {
+                let mut exporter_builder = opentelemetry_otlp::LogExporter::builder()
+                    .with_http()
+                    .with_timeout(self.batch_processor.max_export_timeout)
+                    .with_headers(self.http.headers.clone());
+                if let Some(endpoint) = endpoint_opt {
+                    exporter_builder = exporter_builder.with_endpoint(endpoint);
+                }
+                Ok(exporter_builder.build()?)
+            }
+        }
+    }
+}
+
+/// Emits a log record for each event, correlated with the trace of its span
+pub(crate) struct OtlpLogLayer {
+    logger: SdkLogger,
+}
+
+impl<S> Layer<S> for OtlpLogLayer
+where
+    S: tracing_core::Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        if is_exporter_event(meta.target(), meta.level()) {
+            return;
+        }
+
+        let mut visitor = LogRecordVisitor::default();
+        event.record(&mut visitor);
+        if visitor.omit_from_logs {
+            return;
+        }
+
+        let mut record = self.logger.create_log_record();
+        let now = SystemTime::now();
+        record.set_timestamp(now);
+        record.set_observed_timestamp(now);
+        record.set_target(meta.target().to_string());
+        let (severity_number, severity_text) = severity(meta.level());
+        record.set_severity_number(severity_number);
+        record.set_severity_text(severity_text);
+        if let Some(message) = visitor.message.take() {
+            record.set_body(message.into());
+        }
+        record.add_attributes(visitor.attributes);
+
+        let current_span = event
+            .parent()
+            .and_then(|id| ctx.span(id))
+            .or_else(|| ctx.lookup_current());
+        if let Some(span) = current_span {
+            if let Some((trace_id, span_id)) = get_trace_and_span_id(&span) {
+                let trace_flags = if span.is_sampled() {
+                    TraceFlags::SAMPLED
+                } else {
+                    TraceFlags::default()
+                };
+                record.set_trace_context(trace_id, span_id, Some(trace_flags));
+            }
+
+            // Attributes of custom events are left on the span for the stdout formatters
+            let extensions = span.extensions();
+            let event_attributes = extensions
+                .get::<OtelData>()
+                .and_then(|otel_data| otel_data.event_attributes.clone())
+                .map(|attributes| attributes.into_iter().collect::<Vec<_>>())
+                .or_else(|| {
+                    extensions.get::<EventAttributes>().map(|attributes| {
+                        attributes
+                            .attributes()
+                            .iter()
+                            .map(|kv| (kv.key.clone(), kv.value.clone()))
+                            .collect()
+                    })
+                });
+            if let Some(event_attributes) = event_attributes {
+                record.add_attributes(
+                    event_attributes
+                        .into_iter()
+                        .filter(|(key, _)| {
+                            !key.as_str().starts_with(APOLLO_PRIVATE_PREFIX)
+                                && !key.as_str().starts_with(APOLLO_CONNECTOR_PREFIX)
+                        })
+                        .map(|(key, value)| (key, any_value(value))),
+                );
+            }
+        }
+
+        self.logger.emit(record);
+    }
+}
+
+/// The crates of the OTLP exporter transport, whose debug logs are emitted while exporting
+const EXPORTER_TRANSPORT_TARGETS: [&str; 5] = ["hyper", "h2", "tonic", "tower", "reqwest"];
+
+/// Logs of the exporter itself would be exported again, in a feedback loop
+fn is_exporter_event(target: &str, level: &Level) -> bool {
+    if target.starts_with("opentelemetry") {
+        return true;
+    }
+    *level >= Level::DEBUG
+        && EXPORTER_TRANSPORT_TARGETS.iter().any(|crate_name| {
+            target.strip_prefix(crate_name).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with("::") || rest.starts_with('_')
+            })
+        })
+}
+
+fn severity(level: &Level) -> (Severity, &'static str) {
+    match *level {
+        Level::TRACE => (Severity::Trace, "TRACE"),
+        Level::DEBUG => (Severity::Debug, "DEBUG"),
+        Level::INFO => (Severity::Info, "INFO"),
+        Level::WARN => (Severity::Warn, "WARN"),
+        Level::ERROR => (Severity::Error, "ERROR"),
+    }
+}
+
+pub(crate) fn any_value(value: Value) -> AnyValue {
+    match value {
+        Value::Bool(value) => value.into(),
+        Value::I64(value) => value.into(),
+        Value::F64(value) => value.into(),
+        Value::String(value) => value.into(),
+        Value::Array(value) => AnyValue::String(value.to_string().into()),
+        _ => AnyValue::String(value.to_string().into()),
+    }
+}
+
+/// Splits the fields of an event into the body and the attributes of its log record
+#[derive(Default)]
+struct LogRecordVisitor {
+    message: Option<String>,
+    attributes: HashMap<Key, AnyValue>,
+    omit_from_logs: bool,
+}
+
+impl LogRecordVisitor {
+    fn insert(&mut self, field: &Field, value: AnyValue) {
+        let name = field.name();
+        if EXCLUDED_ATTRIBUTES.contains(&name) {
+            return;
+        }
+        let name = name.strip_prefix("r#").unwrap_or(name);
+        self.attributes.insert(Key::new(name.to_string()), value);
+    }
+}
+
+impl field::Visit for LogRecordVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match i64::try_from(value) {
+            Ok(value) => self.insert(field, value.into()),
+            Err(_) => self.insert(field, value.to_string().into()),
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == EVENT_ATTRIBUTE_OMIT_LOG {
+            self.omit_from_logs = value;
+            return;
+        }
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.insert(field, value.to_string().into());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{value:?}"));
+        } else {
+            self.insert(field, format!("{value:?}").into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_sdk::logs::InMemoryLogExporter;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::fmt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::plugins::telemetry::config_new::events::EventLevel;
+    use crate::plugins::telemetry::config_new::events::log_event;
+    use crate::plugins::telemetry::otel;
+
+    fn attribute<'a>(
+        record: &'a opentelemetry_sdk::logs::SdkLogRecord,
+        key: &str,
+    ) -> Option<&'a AnyValue> {
+        record
+            .attributes_iter()
+            .find(|(k, _)| k.as_str() == key)
+            .map(|(_, value)| value)
+    }
+
+    #[test]
+    fn it_filters_exporter_events() {
+        assert!(is_exporter_event("opentelemetry_sdk", &Level::ERROR));
+        assert!(is_exporter_event(
+            "hyper_util::client::legacy::pool",
+            &Level::DEBUG
+        ));
+        assert!(is_exporter_event("h2::codec::framed_write", &Level::TRACE));
+        assert!(is_exporter_event("tonic::transport", &Level::DEBUG));
+        assert!(is_exporter_event("tower::buffer::worker", &Level::TRACE));
+        assert!(is_exporter_event("reqwest::connect", &Level::DEBUG));
+        assert!(!is_exporter_event("hyper::client", &Level::WARN));
+        assert!(!is_exporter_event("towerish", &Level::DEBUG));
+        assert!(!is_exporter_event("apollo_router::services", &Level::DEBUG));
+    }
+
+    #[test]
+    fn it_exports_events_as_log_records() {
+        let exporter = InMemoryLogExporter::default();
+        let logger_provider = SdkLoggerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let layer = OtlpLogLayer {
+            logger: logger_provider.logger("test"),
+        };
+
+        let tracer_provider = SdkTracerProvider::builder().build();
+
+        tracing::subscriber::with_default(
+            fmt::Subscriber::new()
+                .with(
+                    otel::layer()
+                        .force_sampling()
+                        .with_tracer(tracer_provider.tracer("test")),
+                )
+                .with(layer),
+            || {
+                let span = tracing::info_span!("router");
+                let _guard = span.enter();
+                tracing::warn!(subgraph.name = "products", "subgraph is slow");
+                log_event(
+                    EventLevel::Info,
+                    "my.event",
+                    vec![opentelemetry::KeyValue::new(
+                        "http.response.status_code",
+                        200,
+                    )],
+                    "custom event",
+                );
+                tracing::info!({ EVENT_ATTRIBUTE_OMIT_LOG } = true, "omitted");
+            },
+        );
+
+        let logs = exporter.get_emitted_logs().unwrap();
+        assert_eq!(logs.len(), 2);
+
+        let warning = &logs[0].record;
+        assert_eq!(warning.severity_number(), Some(Severity::Warn));
+        assert_eq!(
+            warning.body(),
+            Some(&AnyValue::String("subgraph is slow".into()))
+        );
+        assert_eq!(
+            attribute(warning, "subgraph.name"),
+            Some(&AnyValue::String("products".into()))
+        );
+        let trace_context = warning.trace_context().expect("trace context");
+        assert_ne!(
+            trace_context.trace_id,
+            opentelemetry::trace::TraceId::INVALID
+        );
+        assert_eq!(trace_context.trace_flags, Some(TraceFlags::SAMPLED));
+
+        let event = &logs[1].record;
+        assert_eq!(event.severity_number(), Some(Severity::Info));
+        assert_eq!(event.body(), Some(&AnyValue::String("custom event".into())));
+        assert_eq!(
+            attribute(event, "kind"),
+            Some(&AnyValue::String("my.event".into()))
+        );
+        assert_eq!(
+            attribute(event, "http.response.status_code"),
+            Some(&AnyValue::Int(200))
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Exemplars of the router histograms
+//!
+//! The OpenTelemetry SDK doesn't sample exemplars, so the histograms configured with
+//! `exemplars: true` keep the last measurements of each series, along with the trace of the request
+//! they were recorded for. The Prometheus endpoint attaches them to the histogram buckets when it is
+//! scraped in the OpenMetrics format, and the OTLP exporter to the histogram data points.
+//!
+//! The exemplars are kept in an [`Exemplars`] store which is replaced, along with the exporters
+//! reading it, when the metrics configuration is activated.
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use opentelemetry::KeyValue;
+use opentelemetry::trace::SpanContext;
+use opentelemetry::trace::SpanId;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::trace::TraceId;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::metrics::v1::exemplar;
+use opentelemetry_proto::tonic::metrics::v1::metric;
+use opentelemetry_sdk::metrics::data::AggregatedMetrics;
+use opentelemetry_sdk::metrics::data::MetricData;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use parking_lot::Mutex;
+use tracing::Span;
+
+use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
+
+/// Exemplars kept per series, the most recent one of each bucket is exported
+const EXEMPLARS_PER_SERIES: usize = 16;
+/// Series kept per histogram, matching the default cardinality limit of the metrics
+const MAX_SERIES_PER_HISTOGRAM: usize = 2000;
+
+/// Prometheus labels of a series, sorted by name
+pub(crate) type Labels = Vec<(String, String)>;
+
+/// Exemplars of a histogram by series
+type Series = HashMap<Labels, VecDeque<Exemplar>>;
+
+/// The store of the active metrics configuration, in which the requests record their exemplars
+static ACTIVE: LazyLock<Mutex<Exemplars>> = LazyLock::new(Default::default);
+
+/// Exemplars by histogram name
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Exemplars {
+    histograms: Arc<Mutex<HashMap<String, Series>>>,
+}
+
+impl Exemplars {
+    /// The store in which the requests record their exemplars
+    pub(crate) fn active() -> Self {
+        ACTIVE.lock().clone()
+    }
+
+    /// Records the exemplars of the next requests in this store
+    pub(crate) fn activate(&self) {
+        *ACTIVE.lock() = self.clone();
+    }
+
+    fn record(&self, name: &str, labels: Labels, exemplar: Exemplar) {
+        let mut histograms = self.histograms.lock();
+        let series = histograms.entry(name.to_string()).or_default();
+        if !series.contains_key(&labels) && series.len() >= MAX_SERIES_PER_HISTOGRAM {
+            return;
+        }
+        let exemplars = series.entry(labels).or_default();
+        if exemplars.len() >= EXEMPLARS_PER_SERIES {
+            exemplars.pop_front();
+        }
+        exemplars.push_back(exemplar);
+    }
+
+    /// The exemplars of the Prometheus metric `name`, which is the sanitized name of a histogram
+    /// followed by its unit suffix, if any
+    pub(crate) fn prometheus_histogram(&self, name: &str) -> HashMap<Labels, Vec<Exemplar>> {
+        let histograms = self.histograms.lock();
+        histograms
+            .iter()
+            .filter(|(histogram, _)| {
+                name == histogram.as_str()
+                    || name
+                        .strip_prefix(histogram.as_str())
+                        .and_then(|suffix| suffix.strip_prefix('_'))
+                        .is_some_and(is_unit_suffix)
+            })
+            .max_by_key(|(histogram, _)| histogram.len())
+            .map(|(_, series)| {
+                series
+                    .iter()
+                    .map(|(labels, exemplars)| {
+                        (labels.clone(), exemplars.iter().cloned().collect())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Attaches the most recent exemplar of each bucket to the histogram data points of `request`,
+    /// which was converted from `metrics`
+    pub(crate) fn attach_to_otlp(
+        &self,
+        metrics: &ResourceMetrics,
+        request: &mut ExportMetricsServiceRequest,
+    ) {
+        let histograms = self.histograms.lock();
+        let Some(resource_metrics) = request.resource_metrics.first_mut() else {
+            return;
+        };
+        // The request has the same scopes, metrics and data points as `metrics`, in the same order
+        for (scope_metrics, otlp_scope_metrics) in metrics
+            .scope_metrics()
+            .zip(resource_metrics.scope_metrics.iter_mut())
+        {
+            for (metric, otlp_metric) in scope_metrics
+                .metrics()
+                .zip(otlp_scope_metrics.metrics.iter_mut())
+            {
+                let Some(series) = histograms.get(&sanitize_name(metric.name())) else {
+                    continue;
+                };
+                let Some(metric::Data::Histogram(histogram)) = &mut otlp_metric.data else {
+                    continue;
+                };
+                let data_point_attributes = match metric.data() {
+                    AggregatedMetrics::F64(data) => histogram_attributes(data),
+                    AggregatedMetrics::U64(data) => histogram_attributes(data),
+                    AggregatedMetrics::I64(data) => histogram_attributes(data),
+                };
+                for (attributes, data_point) in data_point_attributes
+                    .iter()
+                    .zip(histogram.data_points.iter_mut())
+                {
+                    let Some(exemplars) = series.get(&labels(attributes)) else {
+                        continue;
+                    };
+                    let mut lower_bound = f64::NEG_INFINITY;
+                    let upper_bounds = data_point
+                        .explicit_bounds
+                        .iter()
+                        .copied()
+                        .chain([f64::INFINITY]);
+                    let mut bucket_exemplars = Vec::new();
+                    for upper_bound in upper_bounds {
+                        // The most recent exemplar of the bucket
+                        if let Some(exemplar) = exemplars.iter().rev().find(|exemplar| {
+                            exemplar.value > lower_bound && exemplar.value <= upper_bound
+                        }) {
+                            bucket_exemplars.push(exemplar.to_otlp());
+                        }
+                        lower_bound = upper_bound;
+                    }
+                    data_point.exemplars.extend(bucket_exemplars);
+                }
+            }
+        }
+    }
+}
+
+/// The attributes of the data points of a histogram
+fn histogram_attributes<T>(data: &MetricData<T>) -> Vec<Vec<KeyValue>> {
+    match data {
+        MetricData::Histogram(histogram) => histogram
+            .data_points()
+            .map(|data_point| data_point.attributes().cloned().collect())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A measurement of a histogram and the span it was recorded for
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Exemplar {
+    pub(crate) value: f64,
+    pub(crate) trace_id: TraceId,
+    pub(crate) span_id: SpanId,
+    pub(crate) time: SystemTime,
+}
+
+impl Exemplar {
+    fn to_otlp(&self) -> opentelemetry_proto::tonic::metrics::v1::Exemplar {
+        opentelemetry_proto::tonic::metrics::v1::Exemplar {
+            filtered_attributes: Vec::new(),
+            time_unix_nano: self
+                .time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos() as u64,
+            span_id: self.span_id.to_bytes().to_vec(),
+            trace_id: self.trace_id.to_bytes().to_vec(),
+            value: Some(exemplar::Value::AsDouble(self.value)),
+        }
+    }
+}
+
+/// Records the exemplars of a histogram instrument for a request
+#[derive(Clone, Debug)]
+pub(crate) struct HistogramExemplars {
+    name: String,
+    /// The span of the request and the store of the active configuration when the request started
+    request: Option<(SpanContext, Exemplars)>,
+}
+
+impl HistogramExemplars {
+    pub(crate) fn new(instrument_name: &str) -> Self {
+        Self {
+            name: sanitize_name(instrument_name),
+            request: None,
+        }
+    }
+
+    /// Keeps the span of the request, exemplars are only recorded for sampled traces
+    pub(crate) fn on_request(&mut self) {
+        self.on_request_with(Exemplars::active());
+    }
+
+    pub(crate) fn on_request_with(&mut self, exemplars: Exemplars) {
+        let span_context = Span::current().context().span().span_context().clone();
+        self.request = span_context
+            .is_sampled()
+            .then_some((span_context, exemplars));
+    }
+
+    pub(crate) fn record(&self, value: f64, attributes: &[KeyValue]) {
+        let Some((span_context, exemplars)) = &self.request else {
+            return;
+        };
+        exemplars.record(
+            &self.name,
+            labels(attributes),
+            Exemplar {
+                value,
+                trace_id: span_context.trace_id(),
+                span_id: span_context.span_id(),
+                time: SystemTime::now(),
+            },
+        );
+    }
+}
+
+fn is_unit_suffix(suffix: &str) -> bool {
+    !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+/// Sanitizes a metric name like the Prometheus exporter
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Converts attributes to Prometheus labels like the Prometheus exporter: names are sanitized, and
+/// the values of the attributes with the same sanitized name are sorted and joined
+fn labels(attributes: &[KeyValue]) -> Labels {
+    let mut labels = BTreeMap::<String, Vec<String>>::new();
+    for attribute in attributes {
+        let name = attribute
+            .key
+            .as_str()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == ':' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        labels
+            .entry(name)
+            .or_default()
+            .push(attribute.value.to_string());
+    }
+    labels
+        .into_iter()
+        .map(|(name, mut values)| {
+            values.sort_unstable();
+            (name, values.join(";"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::trace::TraceFlags;
+    use opentelemetry::trace::TraceState;
+    use opentelemetry_sdk::metrics::InMemoryMetricExporter;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::metrics::periodic_reader_with_async_runtime::PeriodicReader;
+    use opentelemetry_sdk::runtime;
+
+    use super::*;
+
+    fn sampled(name: &str, trace_id: u128, exemplars: &Exemplars) -> HistogramExemplars {
+        let mut histogram_exemplars = HistogramExemplars::new(name);
+        histogram_exemplars.request = Some((
+            SpanContext::new(
+                TraceId::from(trace_id),
+                SpanId::from(1),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ),
+            exemplars.clone(),
+        ));
+        histogram_exemplars
+    }
+
+    #[test]
+    fn it_keeps_the_last_exemplars_of_each_series() {
+        let exemplars = Exemplars::default();
+        let attributes = [
+            KeyValue::new("http.route", "/graphql"),
+            KeyValue::new("http.method", "POST"),
+        ];
+        for trace_id in 1..=(EXEMPLARS_PER_SERIES as u128 + 1) {
+            sampled("test.exemplars.duration", trace_id, &exemplars)
+                .record(trace_id as f64, &attributes);
+        }
+        // not sampled
+        HistogramExemplars::new("test.exemplars.duration").record(0.0, &attributes);
+
+        let series = exemplars.prometheus_histogram("test_exemplars_duration_seconds");
+        let labels = vec![
+            ("http_method".to_string(), "POST".to_string()),
+            ("http_route".to_string(), "/graphql".to_string()),
+        ];
+        let series = &series[&labels];
+        assert_eq!(series.len(), EXEMPLARS_PER_SERIES);
+        assert_eq!(series[0].trace_id, TraceId::from(2));
+        assert_eq!(
+            series.last().unwrap().trace_id,
+            TraceId::from(EXEMPLARS_PER_SERIES as u128 + 1)
+        );
+
+        assert!(exemplars.prometheus_histogram("test_exemplars").is_empty());
+        assert!(
+            exemplars
+                .prometheus_histogram("test_exemplars_duration_2")
+                .is_empty()
+        );
+        // the exemplars are only kept in their store
+        assert!(
+            Exemplars::default()
+                .prometheus_histogram("test_exemplars_duration_seconds")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_attaches_exemplars_to_the_otlp_histogram_data_points() {
+        let exporter = InMemoryMetricExporter::default();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone(), runtime::Tokio).build())
+            .build();
+        let histogram = meter_provider
+            .meter("test")
+            .f64_histogram("test.otlp.duration")
+            .build();
+        let attributes = [KeyValue::new("test.kind", "exemplar")];
+        let other_attributes = [KeyValue::new("test.kind", "other")];
+
+        let exemplars = Exemplars::default();
+        for (trace_id, value) in [(1, 0.3), (2, 0.4), (3, 7.0)] {
+            histogram.record(value, &attributes);
+            sampled("test.otlp.duration", trace_id, &exemplars).record(value, &attributes);
+        }
+        histogram.record(0.3, &other_attributes);
+        meter_provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let metrics = metrics.last().unwrap();
+        let mut request = ExportMetricsServiceRequest::from(metrics);
+        exemplars.attach_to_otlp(metrics, &mut request);
+
+        let metric = &request.resource_metrics[0].scope_metrics[0].metrics[0];
+        let Some(metric::Data::Histogram(histogram)) = &metric.data else {
+            panic!("{metric:?}");
+        };
+        for data_point in &histogram.data_points {
+            let kind = data_point.attributes[0].value.as_ref().unwrap();
+            if format!("{kind:?}").contains("other") {
+                assert!(data_point.exemplars.is_empty());
+                continue;
+            }
+            // the most recent exemplar of the buckets of 0.3 and 0.4, and of 7
+            let trace_ids: Vec<_> = data_point
+                .exemplars
+                .iter()
+                .map(|exemplar| exemplar.trace_id.clone())
+                .collect();
+            assert_eq!(
+                trace_ids,
+                vec![
+                    TraceId::from(2).to_bytes().to_vec(),
+                    TraceId::from(3).to_bytes().to_vec()
+                ]
+            );
+            assert_eq!(
+                data_point.exemplars[0].span_id,
+                SpanId::from(1).to_bytes().to_vec()
+            );
+            assert_eq!(
+                data_point.exemplars[0].value,
+                Some(exemplar::Value::AsDouble(0.4))
+            );
+        }
+        assert_eq!(histogram.data_points.len(), 2);
+    }
+}
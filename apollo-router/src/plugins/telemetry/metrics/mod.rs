@@ -1,5 +1,6 @@
 pub(crate) mod allocation;
 pub(crate) mod apollo;
+pub(crate) mod exemplars;
 pub(crate) mod local_type_stats;
 mod named;
 pub(crate) mod otlp;
@@ -1,7 +1,24 @@
+use std::time::Duration;
+
+use http::HeaderMap;
+use http::HeaderName;
+use http::HeaderValue;
+use http::Uri;
+use http::header::CONTENT_TYPE;
 use opentelemetry_otlp::MetricExporter;
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+use opentelemetry_proto::tonic::collector::metrics::v1::metrics_service_client::MetricsServiceClient;
+use opentelemetry_sdk::error::OTelSdkError;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::metrics::Temporality;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
 use opentelemetry_sdk::metrics::periodic_reader_with_async_runtime::PeriodicReader;
 use opentelemetry_sdk::runtime;
+use prost::Message;
+use tonic::metadata::MetadataMap;
+use tonic::transport::Channel;
 use tower::BoxError;
 
 use crate::metrics::aggregation::MeterProviderType;
@@ -9,6 +26,7 @@ use crate::plugins::telemetry::config::Conf;
 use crate::plugins::telemetry::metrics::NamedMetricExporter;
 use crate::plugins::telemetry::metrics::OverflowMetricExporter;
 use crate::plugins::telemetry::metrics::RetryMetricExporter;
+use crate::plugins::telemetry::metrics::exemplars::Exemplars;
 use crate::plugins::telemetry::otlp::Protocol;
 use crate::plugins::telemetry::otlp::TelemetryDataKind;
 use crate::plugins::telemetry::otlp::process_endpoint;
@@ -28,9 +46,93 @@ impl MetricsConfigurator for super::super::otlp::Config {
         // Apply env var overrides to the config
         let config = self.clone().with_metrics_env_overrides()?;
 
-        let exporter = config.build_metric_exporter()?;
+        // The SDK exporter can't attach exemplars to the histogram data points
+        if let Some(exemplars) = builder.exemplars() {
+            let exporter = config.build_exemplar_metric_exporter(exemplars.clone())?;
+            config.add_reader(builder, exporter);
+        } else {
+            let exporter = config.build_metric_exporter()?;
+            config.add_reader(builder, exporter);
+        }
+
+        Ok(())
+    }
+}
+
+/// Default endpoint of the OTLP metrics exporter over gRPC
+const DEFAULT_GRPC_ENDPOINT: &str = "http://localhost:4317";
+/// Default endpoint of the OTLP metrics exporter over HTTP
+const DEFAULT_HTTP_ENDPOINT: &str = "http://localhost:4318/v1/metrics";
+
+/// Exports the metrics over OTLP with the exemplars of the histograms
+#[derive(Debug)]
+struct ExemplarMetricExporter {
+    client: MetricsClient,
+    exemplars: Exemplars,
+    timeout: Duration,
+    temporality: Temporality,
+}
+
+#[derive(Debug)]
+enum MetricsClient {
+    Grpc {
+        client: Box<MetricsServiceClient<Channel>>,
+        metadata: MetadataMap,
+    },
+    Http {
+        client: reqwest::Client,
+        endpoint: String,
+    },
+}
+
+impl ExemplarMetricExporter {
+    async fn send(&self, request: ExportMetricsServiceRequest) -> Result<(), BoxError> {
+        match &self.client {
+            MetricsClient::Grpc { client, metadata } => {
+                let mut request = tonic::Request::new(request);
+                *request.metadata_mut() = metadata.clone();
+                request.set_timeout(self.timeout);
+                client.as_ref().clone().export(request).await?;
+            }
+            MetricsClient::Http { client, endpoint } => {
+                client
+                    .post(endpoint)
+                    .header(CONTENT_TYPE, "application/x-protobuf")
+                    .body(request.encode_to_vec())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PushMetricExporter for ExemplarMetricExporter {
+    async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
+        let mut request = ExportMetricsServiceRequest::from(metrics);
+        self.exemplars.attach_to_otlp(metrics, &mut request);
+        self.send(request)
+            .await
+            .map_err(|err| OTelSdkError::InternalFailure(format!("export error: {err}")))
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+        Ok(())
+    }
 
-        // // Wrap with retry, then overflow detection, then error prefixing
+    fn temporality(&self) -> Temporality {
+        self.temporality
+    }
+}
+
+impl super::super::otlp::Config {
+    fn add_reader<T: PushMetricExporter>(&self, builder: &mut MetricsBuilder, exporter: T) {
+        // Wrap with retry, then overflow detection, then error prefixing
         let named_exporter = NamedMetricExporter::new(
             OverflowMetricExporter::new_push(RetryMetricExporter::new(exporter)),
             "otlp",
@@ -38,15 +140,65 @@ impl MetricsConfigurator for super::super::otlp::Config {
         builder.with_reader(
             MeterProviderType::Public,
             PeriodicReader::builder(named_exporter, runtime::Tokio)
-                .with_interval(config.batch_processor.scheduled_delay)
+                .with_interval(self.batch_processor.scheduled_delay)
                 .build(),
         );
+    }
 
-        Ok(())
+    fn build_exemplar_metric_exporter(
+        &self,
+        exemplars: Exemplars,
+    ) -> Result<ExemplarMetricExporter, BoxError> {
+        let endpoint =
+            process_endpoint(&self.endpoint, &TelemetryDataKind::Metrics, &self.protocol)?
+                .filter(|endpoint| !endpoint.is_empty());
+        let timeout = self.batch_processor.max_export_timeout;
+        let client = match self.protocol {
+            Protocol::Grpc => {
+                let mut channel = Channel::from_shared(
+                    endpoint
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_GRPC_ENDPOINT.to_string()),
+                )?
+                .timeout(timeout);
+                if let Some(endpoint) = &endpoint {
+                    let tls_url = Uri::try_from(endpoint)?;
+                    channel = channel.tls_config(self.grpc.clone().to_tls_config(&tls_url)?)?;
+                }
+                MetricsClient::Grpc {
+                    client: Box::new(MetricsServiceClient::new(channel.connect_lazy())),
+                    metadata: MetadataMap::from_headers(self.grpc.metadata.clone()),
+                }
+            }
+            Protocol::Http => {
+                let headers = self
+                    .http
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        Ok((
+                            HeaderName::try_from(name.as_str())?,
+                            HeaderValue::try_from(value.as_str())?,
+                        ))
+                    })
+                    .collect::<Result<HeaderMap, BoxError>>()?;
+                MetricsClient::Http {
+                    client: reqwest::Client::builder()
+                        .default_headers(headers)
+                        .timeout(timeout)
+                        .build()?,
+                    endpoint: endpoint.unwrap_or_else(|| DEFAULT_HTTP_ENDPOINT.to_string()),
+                }
+            }
+        };
+        Ok(ExemplarMetricExporter {
+            client,
+            exemplars,
+            timeout,
+            temporality: self.temporality.into(),
+        })
     }
-}
 
-impl super::super::otlp::Config {
     fn build_metric_exporter(&self) -> Result<MetricExporter, BoxError> {
         match self.protocol {
             Protocol::Grpc => self.build_grpc_metric_exporter(),
@@ -55,7 +207,6 @@ impl super::super::otlp::Config {
     }
 
     fn build_grpc_metric_exporter(&self) -> Result<MetricExporter, BoxError> {
-        use http::Uri;
         use opentelemetry_otlp::WithTonicConfig;
         use tonic::metadata::MetadataMap;
 
@@ -1,5 +1,8 @@
+use std::collections::HashSet;
+use std::fmt::Write;
 use std::task::Context;
 use std::task::Poll;
+use std::time::UNIX_EPOCH;
 
 use futures::future::BoxFuture;
 use http::StatusCode;
@@ -7,6 +10,9 @@ use opentelemetry_prometheus::ResourceSelector;
 use prometheus::Encoder;
 use prometheus::Registry;
 use prometheus::TextEncoder;
+use prometheus::proto::LabelPair;
+use prometheus::proto::MetricFamily;
+use prometheus::proto::MetricType;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use tower::BoxError;
@@ -16,6 +22,9 @@ use crate::ListenAddr;
 use crate::metrics::aggregation::MeterProviderType;
 use crate::plugins::telemetry::config::Conf;
 use crate::plugins::telemetry::metrics::OverflowMetricExporter;
+use crate::plugins::telemetry::metrics::exemplars::Exemplar;
+use crate::plugins::telemetry::metrics::exemplars::Exemplars;
+use crate::plugins::telemetry::metrics::exemplars::Labels;
 use crate::plugins::telemetry::reload::metrics::MetricsBuilder;
 use crate::plugins::telemetry::reload::metrics::MetricsConfigurator;
 use crate::services::router;
@@ -93,6 +102,8 @@ impl MetricsConfigurator for Config {
 
 pub(crate) struct PrometheusService {
     pub(crate) registry: Registry,
+    /// The exemplars of the histograms, if an instrument has exemplars enabled
+    pub(crate) exemplars: Option<Exemplars>,
 }
 
 impl Service<router::Request> for PrometheusService {
@@ -106,21 +117,40 @@ impl Service<router::Request> for PrometheusService {
 
     fn call(&mut self, req: router::Request) -> Self::Future {
         let metric_families = self.registry.gather();
+        // Exemplars can only be exported in the OpenMetrics format
+        let exemplars = self.exemplars.clone().filter(|_| {
+            req.router_request
+                .headers()
+                .get_all(http::header::ACCEPT)
+                .iter()
+                .filter_map(|accept| accept.to_str().ok())
+                .any(|accept| accept.contains(OPEN_METRICS_MEDIA_TYPE))
+        });
         Box::pin(async move {
-            let encoder = TextEncoder::new();
-            let mut result = Vec::new();
-            encoder.encode(&metric_families, &mut result)?;
-            // otel 0.19.0 started adding "_total" onto various statistics.
-            // Let's remove any problems they may have created for us.
-            let stats = String::from_utf8_lossy(&result);
-            let modified_stats = stats.replace("_total_total", "_total");
+            let (content_type, stats) = if let Some(exemplars) = exemplars {
+                (
+                    OPEN_METRICS_CONTENT_TYPE,
+                    encode_open_metrics(&metric_families, &exemplars)?,
+                )
+            } else {
+                let encoder = TextEncoder::new();
+                let mut result = Vec::new();
+                encoder.encode(&metric_families, &mut result)?;
+                // otel 0.19.0 started adding "_total" onto various statistics.
+                // Let's remove any problems they may have created for us.
+                let stats = String::from_utf8_lossy(&result);
+                (
+                    "text/plain; version=0.0.4",
+                    stats.replace("_total_total", "_total"),
+                )
+            };
 
             router::Response::http_response_builder()
                 .response(
                     http::Response::builder()
                         .status(StatusCode::OK)
-                        .header(http::header::CONTENT_TYPE, "text/plain; version=0.0.4")
-                        .body(router::body::from_bytes(modified_stats))
+                        .header(http::header::CONTENT_TYPE, content_type)
+                        .body(router::body::from_bytes(stats))
                         .map_err(BoxError::from)?,
                 )
                 .context(req.context)
@@ -128,3 +158,327 @@ impl Service<router::Request> for PrometheusService {
         })
     }
 }
+
+const OPEN_METRICS_MEDIA_TYPE: &str = "application/openmetrics-text";
+const OPEN_METRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Encodes the metrics in the OpenMetrics text format, attaching the exemplars of the histograms to
+/// their buckets
+fn encode_open_metrics(
+    metric_families: &[MetricFamily],
+    exemplars: &Exemplars,
+) -> Result<String, std::fmt::Error> {
+    // The labels added by the exporter rather than by the instruments
+    let resource_labels: HashSet<&str> = metric_families
+        .iter()
+        .filter(|family| family.name() == "target_info")
+        .flat_map(|family| family.get_metric())
+        .flat_map(|metric| metric.get_label())
+        .map(|label| label.name())
+        .collect();
+
+    let mut result = String::new();
+    for family in metric_families {
+        // otel 0.19.0 started adding "_total" onto various statistics.
+        let name = family.name().replace("_total_total", "_total");
+        let (name, metric_type) = match family.get_field_type() {
+            // The counter samples are suffixed with _total, but not their family
+            MetricType::COUNTER => (name.strip_suffix("_total").unwrap_or(&name), "counter"),
+            MetricType::GAUGE => (name.as_str(), "gauge"),
+            MetricType::HISTOGRAM => (name.as_str(), "histogram"),
+            MetricType::SUMMARY => (name.as_str(), "summary"),
+            // The exporter doesn't create untyped metrics
+            MetricType::UNTYPED => continue,
+        };
+        if !family.help().is_empty() {
+            writeln!(result, "# HELP {name} {}", escape(family.help()))?;
+        }
+        writeln!(result, "# TYPE {name} {metric_type}")?;
+
+        let histogram_exemplars = match family.get_field_type() {
+            MetricType::HISTOGRAM => exemplars.prometheus_histogram(name),
+            _ => Default::default(),
+        };
+        for metric in family.get_metric() {
+            let labels = metric.get_label();
+            match family.get_field_type() {
+                MetricType::COUNTER => {
+                    let value = metric.get_counter().value();
+                    write_sample(&mut result, name, "_total", labels, None, value, None)?;
+                }
+                MetricType::GAUGE => {
+                    let value = metric.get_gauge().value();
+                    write_sample(&mut result, name, "", labels, None, value, None)?;
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let exemplars = histogram_exemplars
+                        .get(&instrument_labels(labels, &resource_labels))
+                        .map(Vec::as_slice)
+                        .unwrap_or_default();
+                    let mut lower_bound = f64::NEG_INFINITY;
+                    let mut buckets: Vec<(f64, u64)> = histogram
+                        .get_bucket()
+                        .iter()
+                        .map(|bucket| (bucket.upper_bound(), bucket.cumulative_count()))
+                        .collect();
+                    if buckets.last().map(|(upper_bound, _)| *upper_bound) != Some(f64::INFINITY) {
+                        buckets.push((f64::INFINITY, histogram.get_sample_count()));
+                    }
+                    for (upper_bound, count) in buckets {
+                        // The most recent exemplar of the bucket
+                        let exemplar = exemplars.iter().rev().find(|exemplar| {
+                            exemplar.value > lower_bound && exemplar.value <= upper_bound
+                        });
+                        write_sample(
+                            &mut result,
+                            name,
+                            "_bucket",
+                            labels,
+                            Some(("le", upper_bound)),
+                            count as f64,
+                            exemplar,
+                        )?;
+                        lower_bound = upper_bound;
+                    }
+                    let sum = histogram.get_sample_sum();
+                    write_sample(&mut result, name, "_sum", labels, None, sum, None)?;
+                    let count = histogram.get_sample_count() as f64;
+                    write_sample(&mut result, name, "_count", labels, None, count, None)?;
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        write_sample(
+                            &mut result,
+                            name,
+                            "",
+                            labels,
+                            Some(("quantile", quantile.quantile())),
+                            quantile.value(),
+                            None,
+                        )?;
+                    }
+                    let sum = summary.sample_sum();
+                    write_sample(&mut result, name, "_sum", labels, None, sum, None)?;
+                    let count = summary.sample_count() as f64;
+                    write_sample(&mut result, name, "_count", labels, None, count, None)?;
+                }
+                MetricType::UNTYPED => {}
+            }
+        }
+    }
+    result.push_str("# EOF\n");
+    Ok(result)
+}
+
+fn write_sample(
+    result: &mut String,
+    name: &str,
+    suffix: &str,
+    labels: &[LabelPair],
+    additional_label: Option<(&str, f64)>,
+    value: f64,
+    exemplar: Option<&Exemplar>,
+) -> std::fmt::Result {
+    write!(result, "{name}{suffix}")?;
+    let mut separator = "{";
+    for label in labels {
+        write!(
+            result,
+            "{separator}{}=\"{}\"",
+            label.name(),
+            escape(label.value())
+        )?;
+        separator = ",";
+    }
+    if let Some((label, value)) = additional_label {
+        write!(result, "{separator}{label}=\"{}\"", format_value(value))?;
+        separator = ",";
+    }
+    if separator == "," {
+        result.push('}');
+    }
+    write!(result, " {}", format_value(value))?;
+    if let Some(exemplar) = exemplar {
+        let timestamp = exemplar
+            .time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        write!(
+            result,
+            " # {{trace_id=\"{}\",span_id=\"{}\"}} {} {timestamp:.3}",
+            exemplar.trace_id,
+            exemplar.span_id,
+            format_value(exemplar.value)
+        )?;
+    }
+    result.push('\n');
+    Ok(())
+}
+
+/// The labels of a series set by its instrument
+fn instrument_labels(labels: &[LabelPair], resource_labels: &HashSet<&str>) -> Labels {
+    let mut labels: Labels = labels
+        .iter()
+        .filter(|label| {
+            !label.name().starts_with("otel_scope_") && !resource_labels.contains(label.name())
+        })
+        .map(|label| (label.name().to_string(), label.value().to_string()))
+        .collect();
+    labels.sort();
+    labels
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::Context;
+    use opentelemetry::KeyValue;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry::trace::SpanContext;
+    use opentelemetry::trace::SpanId;
+    use opentelemetry::trace::TraceContextExt;
+    use opentelemetry::trace::TraceFlags;
+    use opentelemetry::trace::TraceId;
+    use opentelemetry::trace::TraceState;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use tower::ServiceExt;
+    use tracing::subscriber;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::plugins::telemetry::metrics::exemplars::HistogramExemplars;
+    use crate::plugins::telemetry::otel;
+
+    #[tokio::test]
+    async fn it_attaches_exemplars_to_the_histogram_buckets() {
+        let registry = Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()
+            .unwrap();
+        let meter_provider = SdkMeterProvider::builder().with_reader(exporter).build();
+        let histogram = meter_provider
+            .meter("test")
+            .f64_histogram("test.open_metrics.duration")
+            .with_unit("s")
+            .build();
+        let attributes = [KeyValue::new("test.kind", "exemplar")];
+
+        let store = Exemplars::default();
+        let mut exemplars = HistogramExemplars::new("test.open_metrics.duration");
+        subscriber::with_default(tracing_subscriber::registry().with(otel::layer()), || {
+            let _context_guard = Context::new()
+                .with_remote_span_context(SpanContext::new(
+                    TraceId::from(42),
+                    SpanId::from(42),
+                    TraceFlags::SAMPLED,
+                    false,
+                    TraceState::default(),
+                ))
+                .attach();
+            let span = tracing::info_span!("test");
+            let _guard = span.enter();
+            exemplars.on_request_with(store.clone());
+        });
+        histogram.record(0.3, &attributes);
+        exemplars.record(0.3, &attributes);
+
+        let mut service = PrometheusService {
+            registry: registry.clone(),
+            exemplars: Some(store),
+        };
+        let scrape = |accept: &'static str| {
+            router::Request::fake_builder()
+                .header(http::header::ACCEPT, accept)
+                .build()
+                .unwrap()
+        };
+        let mut response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(scrape(
+                "application/openmetrics-text;version=1.0.0,text/plain;q=0.5",
+            ))
+            .await
+            .unwrap()
+            .response;
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            OPEN_METRICS_CONTENT_TYPE
+        );
+        let body = router::body::into_bytes(response.body_mut()).await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        let bucket = body
+            .lines()
+            .find(|line| {
+                line.starts_with("test_open_metrics_duration_seconds_bucket{")
+                    && line.contains("le=\"5\"")
+            })
+            .expect(&body);
+        assert!(bucket.contains("test_kind=\"exemplar\""));
+        assert!(
+            bucket.contains(&format!(
+                "}} 1 # {{trace_id=\"{}\",span_id=\"{}\"}} 0.3 ",
+                TraceId::from(42),
+                SpanId::from(42)
+            )),
+            "{bucket}"
+        );
+        // Only the bucket of the measurement has the exemplar
+        assert_eq!(body.matches("trace_id").count(), 1);
+        assert!(body.contains("# TYPE test_open_metrics_duration_seconds histogram\n"));
+        assert!(body.ends_with("# EOF\n"));
+
+        let mut response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(scrape("text/plain"))
+            .await
+            .unwrap()
+            .response;
+        let body = router::body::into_bytes(response.body_mut()).await.unwrap();
+        assert!(!String::from_utf8_lossy(&body).contains("trace_id"));
+
+        // Without exemplars, the metrics are always exported in the Prometheus text format
+        let mut service = PrometheusService {
+            registry,
+            exemplars: None,
+        };
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(scrape("application/openmetrics-text;version=1.0.0"))
+            .await
+            .unwrap()
+            .response;
+        assert_eq!(
+            response.headers()[http::header::CONTENT_TYPE],
+            "text/plain; version=0.0.4"
+        );
+    }
+}
@@ -59,6 +59,9 @@ use tower::ServiceBuilder;
 use tower::ServiceExt;
 use uuid::Uuid;
 
+use self::access_log::AccessLogEntry;
+use self::access_log::AccessLogger;
+use self::access_log::SupergraphErrorCount;
 use self::apollo::ForwardValues;
 use self::apollo::LicensedOperationCountByType;
 use self::apollo::OperationSubType;
@@ -73,6 +76,8 @@ use self::config_new::router::instruments::RouterInstruments;
 use self::config_new::subgraph::events::SubgraphEvents;
 use self::config_new::subgraph::instruments::SubgraphInstruments;
 use self::config_new::supergraph::events::SupergraphEvents;
+use self::local_usage::LocalUsageReporter;
+use self::local_usage::PendingUsage;
 use self::metrics::apollo::studio::SingleTypeStat;
 pub(crate) use self::span_factory::SpanMode;
 use self::tracing::apollo_telemetry::APOLLO_PRIVATE_DURATION_NS;
@@ -96,6 +101,7 @@ use crate::plugins::telemetry::apollo_exporter::proto::reports::StatsContext;
 use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::node::Id::ResponseName;
 use crate::plugins::telemetry::config::AttributeValue;
 use crate::plugins::telemetry::config_new::DatadogId;
+use crate::plugins::telemetry::config_new::Stage;
 use crate::plugins::telemetry::config_new::apollo::instruments::ApolloConnectorInstruments;
 use crate::plugins::telemetry::config_new::apollo::instruments::ApolloSubgraphInstruments;
 use crate::plugins::telemetry::config_new::connector::events::ConnectorEvents;
@@ -129,7 +135,9 @@ use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
 use crate::plugins::telemetry::reload::metrics::MetricsConfigurator;
 use crate::plugins::telemetry::tracing::apollo_telemetry::APOLLO_PRIVATE_OPERATION_SIGNATURE;
 use crate::plugins::telemetry::tracing::apollo_telemetry::decode_ftv1_trace;
+use crate::plugins::telemetry::tracing::sampling_rules::record_sampling_ratio;
 use crate::query_planner::OperationKind;
+use crate::query_planner::critical_path::EnableCriticalPath;
 use crate::register_private_plugin;
 use crate::router_factory::Endpoint;
 use crate::services::ExecutionRequest;
@@ -147,6 +155,7 @@ use crate::services::subgraph;
 use crate::services::supergraph;
 use crate::spec::operation_limits::OperationLimits;
 
+mod access_log;
 pub(crate) mod apollo;
 pub(crate) mod apollo_exporter;
 pub(crate) mod apollo_otlp_exporter;
@@ -158,6 +167,7 @@ mod endpoint;
 mod error_counter;
 mod fmt_layer;
 pub(crate) mod formatters;
+mod local_usage;
 mod logging;
 pub(crate) mod metrics;
 /// Opentelemetry utils
@@ -209,6 +219,8 @@ pub(crate) struct Telemetry {
     supergraph_schema_id: Arc<String>,
     custom_endpoints: MultiMap<ListenAddr, Endpoint>,
     apollo_metrics_sender: apollo_exporter::Sender,
+    local_usage_reporter: Option<Arc<LocalUsageReporter>>,
+    access_logger: Option<Arc<AccessLogger>>,
     field_level_instrumentation_ratio: f64,
     builtin_instruments: RwLock<BuiltinInstruments>,
     activation: Mutex<Option<Activation>>,
@@ -327,12 +339,29 @@ impl PluginPrivate for Telemetry {
                 "Potential configuration error for 'instrumentation': {err}, please check the documentation on https://www.apollographql.com/docs/router/configuration/telemetry/instrumentation/events"
             );
         }
+        for rule in &config.exporters.tracing.common.experimental_sampling_rules {
+            if let Err(err) = rule.condition.validate(Some(Stage::Request)) {
+                ::tracing::warn!(
+                    "Potential configuration error for 'experimental_sampling_rules': {err}, sampling rules are evaluated against the router request"
+                );
+            }
+        }
 
         let field_level_instrumentation_ratio =
             config.calculate_field_level_instrumentation_ratio()?;
 
         let (activation, custom_endpoints, apollo_metrics_sender) =
             reload::prepare(&init.previous_config, &config)?;
+        let local_usage_reporter = LocalUsageReporter::new(
+            &config.apollo.experimental_local_usage_reporting,
+            &config.exporters.logging.common,
+        )?
+        .map(Arc::new);
+        let access_logger = AccessLogger::new(
+            &config.instrumentation.experimental_access_log,
+            &config.exporters.logging.common,
+        )?
+        .map(Arc::new);
 
         if config.instrumentation.spans.mode == SpanMode::Deprecated {
             ::tracing::warn!(
@@ -351,6 +380,8 @@ impl PluginPrivate for Telemetry {
         Ok(Telemetry {
             custom_endpoints,
             apollo_metrics_sender,
+            local_usage_reporter,
+            access_logger,
             supergraph_schema_id: init.supergraph_schema_id,
             field_level_instrumentation_ratio,
             activation: Mutex::new(Some(activation)),
@@ -374,6 +405,7 @@ impl PluginPrivate for Telemetry {
         let enabled_features = self.enabled_features.clone();
         let field_level_instrumentation_ratio = self.field_level_instrumentation_ratio;
         let metrics_sender = self.apollo_metrics_sender.clone();
+        let access_logger = self.access_logger.clone();
         let static_router_instruments = self
             .builtin_instruments
             .read()
@@ -386,14 +418,37 @@ impl PluginPrivate for Telemetry {
         let client_name_key = router_attributes
             .client_name
             .as_ref()
-            .and_then(|a| a.key(CLIENT_NAME_KEY));
+            .and_then(|a| a.key(CLIENT_NAME_KEY))
+            // The tail sampler matches clients on the client name of router spans
+            .or_else(|| {
+                self.config
+                    .exporters
+                    .tracing
+                    .common
+                    .experimental_tail_sampling
+                    .matches_clients()
+                    .then_some(CLIENT_NAME_KEY)
+            });
 
         let client_version_key = router_attributes
             .client_version
             .as_ref()
             .and_then(|a| a.key(CLIENT_VERSION_KEY));
 
+        let sampling_rules = self
+            .config
+            .exporters
+            .tracing
+            .common
+            .experimental_sampling_rules
+            .clone();
+
         ServiceBuilder::new()
+            // Sampling rules must be evaluated before any span is created under the root span
+            .map_request(move |request: router::Request| {
+                record_sampling_ratio(&sampling_rules, &request);
+                request
+            })
             .layer(metrics::allocation::AllocationMetricsLayer::new())
             .map_response(move |response: router::Response| {
                 // The current span *should* be the request span as we are outside the instrument block.
@@ -543,17 +598,29 @@ impl PluginPrivate for Telemetry {
                         config_request.instrumentation.events.new_router_events();
                     custom_events.on_request(request);
 
+                    let access_log_entry = access_logger
+                        .as_ref()
+                        .map(|access_logger| access_logger.on_request(request));
+
                     (
                         custom_attributes,
                         custom_instruments,
                         custom_events,
+                        access_log_entry,
                         request.context.clone(),
                     )
                 },
-                move |(mut custom_attributes, custom_instruments, mut custom_events, ctx): (
+                move |(
+                    mut custom_attributes,
+                    custom_instruments,
+                    mut custom_events,
+                    access_log_entry,
+                    ctx,
+                ): (
                     Vec<KeyValue>,
                     RouterInstruments,
                     RouterEvents,
+                    Option<AccessLogEntry>,
                     Context,
                 ),
                       fut| {
@@ -705,10 +772,23 @@ impl PluginPrivate for Telemetry {
                             custom_events.on_error(err, &ctx);
                         }
 
-                        if let Ok(resp) = response {
+                        let response = if let Ok(resp) = response {
                             Ok(count_router_errors(resp, &config.apollo.errors).await)
                         } else {
                             response
+                        };
+
+                        // The access log line is written once the response body has been sent
+                        match (access_log_entry, response) {
+                            (Some(mut entry), Ok(response)) => {
+                                entry.on_response(&response);
+                                Ok(entry.wrap_response(response))
+                            }
+                            (Some(mut entry), Err(err)) => {
+                                entry.on_error(&err);
+                                Err(err)
+                            }
+                            (None, response) => response,
                         }
                     }
                 },
@@ -719,6 +799,7 @@ impl PluginPrivate for Telemetry {
 
     fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
         let metrics_sender = self.apollo_metrics_sender.clone();
+        let local_usage_reporter = self.local_usage_reporter.clone();
         let span_mode = self.config.instrumentation.spans.mode;
         let config = self.config.clone();
         let config_instrument = self.config.clone();
@@ -800,16 +881,26 @@ impl PluginPrivate for Telemetry {
                         .supergraph
                         .attributes
                         .on_request(req);
-                    Self::populate_context(field_level_instrumentation_ratio, req);
+                    Self::populate_context(
+                        field_level_instrumentation_ratio,
+                        config
+                            .exporters
+                            .tracing
+                            .common
+                            .experimental_resolver_spans
+                            .ratio(),
+                        req,
+                    );
                     let custom_instruments = config
                         .instrumentation
                         .instruments
                         .new_supergraph_instruments(static_supergraph_instruments.clone());
                     custom_instruments.on_request(req);
-                    let custom_graphql_instruments: GraphQLInstruments = config
-                        .instrumentation
-                        .instruments
-                        .new_graphql_instruments(static_graphql_instruments.clone());
+                    let custom_graphql_instruments: GraphQLInstruments =
+                        config.instrumentation.instruments.new_graphql_instruments(
+                            static_graphql_instruments.clone(),
+                            field_level_instrumentation_ratio,
+                        );
                     custom_graphql_instruments.on_request(req);
 
                     let mut supergraph_events =
@@ -840,6 +931,7 @@ impl PluginPrivate for Telemetry {
                       fut| {
                     let config = config_map_res.clone();
                     let sender = metrics_sender.clone();
+                    let local_usage_reporter = local_usage_reporter.clone();
                     let enabled_features = enabled_features.clone();
                     let start = Instant::now();
 
@@ -892,7 +984,7 @@ impl PluginPrivate for Telemetry {
                             custom_graphql_instruments,
                         )
                         .await;
-                        Self::update_metrics_on_response_events(
+                        result = Self::update_metrics_on_response_events(
                             &ctx,
                             config,
                             field_level_instrumentation_ratio,
@@ -900,7 +992,14 @@ impl PluginPrivate for Telemetry {
                             start,
                             result,
                             enabled_features,
-                        )
+                        );
+                        result = match local_usage_reporter {
+                            Some(reporter) => {
+                                Self::report_local_usage(&ctx, reporter, start, result)
+                            }
+                            None => result,
+                        };
+                        Self::count_access_log_errors(&ctx, result)
                     }
                 },
             )
@@ -911,8 +1010,17 @@ impl PluginPrivate for Telemetry {
     fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
         let config = self.config.clone();
         let config_map_res_first = config.clone();
+        let critical_path = config.instrumentation.spans.experimental_critical_path;
 
         ServiceBuilder::new()
+            .map_request(move |req: ExecutionRequest| {
+                if critical_path {
+                    req.context
+                        .extensions()
+                        .with_lock(|lock| lock.insert(EnableCriticalPath));
+                }
+                req
+            })
             .instrument(move |req: &ExecutionRequest| {
                 let operation_kind = req.query_plan.query.operation.kind();
 
@@ -1371,7 +1479,11 @@ impl Telemetry {
         Ok(SupergraphResponse { context, response })
     }
 
-    fn populate_context(field_level_instrumentation_ratio: f64, req: &SupergraphRequest) {
+    fn populate_context(
+        field_level_instrumentation_ratio: f64,
+        resolver_spans_ratio: f64,
+        req: &SupergraphRequest,
+    ) {
         let context = &req.context;
 
         // List of custom attributes for metrics
@@ -1388,6 +1500,11 @@ impl Telemetry {
                 .extensions()
                 .with_lock(|lock| lock.insert(EnableSubgraphFtv1));
         }
+        if rand::rng().random_bool(resolver_spans_ratio) {
+            context
+                .extensions()
+                .with_lock(|lock| lock.insert(EnableResolverSpans));
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -1533,6 +1650,62 @@ impl Telemetry {
         }
     }
 
+    /// Reports the usage of each operation once its last response is sent or its response stream is dropped, or once per request for subscriptions
+    fn report_local_usage(
+        ctx: &Context,
+        reporter: Arc<LocalUsageReporter>,
+        start: Instant,
+        result: Result<supergraph::Response, BoxError>,
+    ) -> Result<supergraph::Response, BoxError> {
+        let mut usage = PendingUsage::new(reporter, ctx.clone(), start);
+        let response = match result {
+            Ok(response) => response,
+            Err(err) => {
+                usage.on_errors();
+                return Err(err);
+            }
+        };
+        let operation_kind: OperationKind =
+            ctx.get(OPERATION_KIND).ok().flatten().unwrap_or_default();
+        // The usage is reported when the stream is dropped before its last response
+        Ok(response.map(move |response_stream| {
+            response_stream
+                .enumerate()
+                .map(move |(idx, response)| {
+                    if !response.errors.is_empty() {
+                        usage.on_errors();
+                    }
+                    let is_last = if operation_kind == OperationKind::Subscription {
+                        idx == 0
+                    } else {
+                        !response.has_next.unwrap_or(false)
+                    };
+                    if is_last {
+                        usage.report();
+                    }
+                    response
+                })
+                .boxed()
+        }))
+    }
+
+    fn count_access_log_errors(
+        ctx: &Context,
+        result: Result<supergraph::Response, BoxError>,
+    ) -> Result<supergraph::Response, BoxError> {
+        let Some(error_count) = ctx
+            .extensions()
+            .with_lock(|lock| lock.get::<SupergraphErrorCount>().cloned())
+        else {
+            return result;
+        };
+        Ok(result?.map(move |response_stream| {
+            response_stream
+                .inspect(move |response| error_count.add(response.errors.len()))
+                .boxed()
+        }))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn update_apollo_metrics(
         context: &Context,
@@ -1698,7 +1871,9 @@ impl Telemetry {
     }
 
     /// Returns `[(subgraph_name, trace), …]`
-    fn subgraph_ftv1_traces(context: &Context) -> Vec<(ByteString, proto::reports::Trace)> {
+    pub(crate) fn subgraph_ftv1_traces(
+        context: &Context,
+    ) -> Vec<(ByteString, proto::reports::Trace)> {
         if let Some(Value::Array(array)) = context.get_json_value(SUBGRAPH_FTV1) {
             array
                 .iter()
@@ -1944,11 +2119,9 @@ fn convert(
 register_private_plugin!("apollo", "telemetry", Telemetry);
 
 fn request_ftv1(mut req: SubgraphRequest) -> SubgraphRequest {
-    if req
-        .context
-        .extensions()
-        .with_lock(|lock| lock.contains_key::<EnableSubgraphFtv1>())
-        && Span::current().context().span().span_context().is_sampled()
+    if req.context.extensions().with_lock(|lock| {
+        lock.contains_key::<EnableSubgraphFtv1>() || lock.contains_key::<EnableResolverSpans>()
+    }) && Span::current().context().span().span_context().is_sampled()
     {
         req.subgraph_request
             .headers_mut()
@@ -1959,26 +2132,33 @@ fn request_ftv1(mut req: SubgraphRequest) -> SubgraphRequest {
 
 fn store_ftv1(subgraph_name: &ByteString, resp: SubgraphResponse) -> SubgraphResponse {
     // Stash the FTV1 data
-    if resp
-        .context
-        .extensions()
-        .with_lock(|lock| lock.contains_key::<EnableSubgraphFtv1>())
+    let (field_level_instrumentation, resolver_spans) =
+        resp.context.extensions().with_lock(|lock| {
+            (
+                lock.contains_key::<EnableSubgraphFtv1>(),
+                lock.contains_key::<EnableResolverSpans>(),
+            )
+        });
+    if (field_level_instrumentation || resolver_spans)
         && let Some(serde_json_bytes::Value::String(ftv1)) =
             resp.response.body().extensions.get("ftv1")
     {
         // Record the ftv1 trace for processing later
         Span::current().record("apollo_private.ftv1", ftv1.as_str());
-        resp.context
-            .upsert_json_value(SUBGRAPH_FTV1, move |value: Value| {
-                let mut vec = match value {
-                    Value::Array(array) => array,
-                    // upsert_json_value populate the entry with null if it was vacant
-                    Value::Null => Vec::new(),
-                    _ => panic!("unexpected JSON value kind"),
-                };
-                vec.push(json!([subgraph_name, ftv1]));
-                Value::Array(vec)
-            })
+        // Only the traces requested for field level instrumentation are used for Apollo field stats
+        if field_level_instrumentation {
+            resp.context
+                .upsert_json_value(SUBGRAPH_FTV1, move |value: Value| {
+                    let mut vec = match value {
+                        Value::Array(array) => array,
+                        // upsert_json_value populate the entry with null if it was vacant
+                        Value::Null => Vec::new(),
+                        _ => panic!("unexpected JSON value kind"),
+                    };
+                    vec.push(json!([subgraph_name, ftv1]));
+                    Value::Array(vec)
+                })
+        }
     }
     resp
 }
@@ -2076,6 +2256,9 @@ pub(crate) fn add_query_attributes(context: &Context, custom_attributes: &mut Ve
 
 struct EnableSubgraphFtv1;
 
+/// Subgraphs are asked for their field execution traces to create resolver spans
+struct EnableResolverSpans;
+
 //
 // Please ensure that any tests added to the tests module use the tokio multi-threaded test executor.
 //
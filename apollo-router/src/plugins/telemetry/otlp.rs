@@ -1,4 +1,4 @@
-//! Shared configuration for Otlp tracing and metrics.
+//! Shared configuration for Otlp tracing, metrics and logs.
 use std::collections::HashMap;
 
 use http::Uri;
@@ -70,6 +70,27 @@ impl Config {
         })
     }
 
+    /// Apply OTEL_EXPORTER_OTLP_* environment variable overrides for logs.
+    /// Env vars take precedence over config values.
+    pub(crate) fn with_logs_env_overrides(self) -> Result<Self, BoxError> {
+        let endpoint = std::env::var("OTEL_EXPORTER_OTLP_LOGS_ENDPOINT")
+            .ok()
+            .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+            .or(self.endpoint);
+
+        let protocol = Self::parse_protocol_env(
+            "OTEL_EXPORTER_OTLP_LOGS_PROTOCOL",
+            "OTEL_EXPORTER_OTLP_PROTOCOL",
+            self.protocol,
+        )?;
+
+        Ok(Config {
+            endpoint,
+            protocol,
+            ..self
+        })
+    }
+
     /// Apply OTEL_EXPORTER_OTLP_* environment variable overrides for metrics.
     /// Env vars take precedence over config values.
     pub(crate) fn with_metrics_env_overrides(self) -> Result<Self, BoxError> {
@@ -120,6 +141,7 @@ impl Config {
 pub(crate) enum TelemetryDataKind {
     Traces,
     Metrics,
+    Logs,
 }
 
 // In older versions of `opentelemetry_otlp` the crate would "helpfully" try to make sure that the
@@ -184,6 +206,7 @@ pub(super) fn process_endpoint(
                     Protocol::Http => match kind {
                         TelemetryDataKind::Metrics => "/v1/metrics",
                         TelemetryDataKind::Traces => "/v1/traces",
+                        TelemetryDataKind::Logs => "/v1/logs",
                     },
                 };
                 if base.ends_with(suffix) {
@@ -411,6 +434,20 @@ mod tests {
             processed_endpoint
         );
 
+        // Logs
+        let endpoint = Some("localhost:4318".to_string());
+        let processed_endpoint =
+            process_endpoint(&endpoint, &TelemetryDataKind::Logs, &Protocol::Http).unwrap();
+        assert_eq!(
+            Some("http://localhost:4318/v1/logs".to_string()),
+            processed_endpoint
+        );
+
+        let endpoint = Some("https://api.apm.com:433/v1/logs".to_string());
+        let processed_endpoint =
+            process_endpoint(&endpoint, &TelemetryDataKind::Logs, &Protocol::Http).unwrap();
+        assert_eq!(endpoint, processed_endpoint);
+
         // Metrics
         let endpoint = None;
         let processed_endpoint =
@@ -10,7 +10,8 @@
 //! - Tracer provider (for distributed tracing)
 //! - Trace propagation configuration
 //! - Prometheus registry (if enabled)
-//! - Logging format layer
+//! - Exemplars store of the histograms
+//! - Logging format layer, and the logger provider exporting logs over OTLP
 //!
 //! ## Safe Resource Management
 //!
@@ -39,9 +40,12 @@ use crate::metrics::aggregation::MeterProviderType;
 use crate::metrics::filter::FilterMeterProvider;
 use crate::metrics::meter_provider_internal;
 use crate::plugins::telemetry::GLOBAL_TRACER_NAME;
+use crate::plugins::telemetry::config::PropagationClients;
+use crate::plugins::telemetry::metrics::exemplars::Exemplars;
 use crate::plugins::telemetry::reload::otel::LayeredTracer;
 use crate::plugins::telemetry::reload::otel::OPENTELEMETRY_TRACER_HANDLE;
 use crate::plugins::telemetry::reload::otel::reload_fmt;
+use crate::plugins::telemetry::tracing::propagation::set_propagation_clients;
 
 /// State container for telemetry components to be activated.
 ///
@@ -54,6 +58,9 @@ pub(crate) struct Activation {
     /// The new tracer propagator. None means leave the existing one
     new_trace_propagator: Option<TextMapCompositePropagator>,
 
+    /// The outbound clients the new tracer propagator applies to
+    new_propagation_clients: Option<PropagationClients>,
+
     /// The new metrics providers. Absent entry for a particular meter provider type
     /// means leave the existing one as is
     new_meter_providers: HashMap<MeterProviderType, FilterMeterProvider>,
@@ -65,9 +72,17 @@ pub(crate) struct Activation {
     /// We can remove this static if eventually we have a facility for plugins to maintain state across reloads.
     prometheus_registry: Option<Registry>,
 
+    /// The store in which the requests record the exemplars of the histograms, read by the
+    /// exporters of the metrics providers. Defaulted to the active store like the registry
+    exemplars: Exemplars,
+
     /// The new format layer
     new_logging_fmt_layer: Option<Box<dyn Layer<LayeredTracer> + Send + Sync>>,
 
+    /// The logger provider backing the new format layer, if logs are exported over OTLP.
+    /// After commit, this holds the previous logger provider until it is dropped
+    new_logger_provider: Option<opentelemetry_sdk::logs::SdkLoggerProvider>,
+
     /// Test instrumentation to track what components were set
     #[cfg(test)]
     test_instrumentation: TestInstrumentation,
@@ -81,21 +96,30 @@ pub(crate) struct TestInstrumentation {
     pub(crate) meter_providers_added: std::collections::HashSet<MeterProviderType>,
     pub(crate) prometheus_registry_set: bool,
     pub(crate) logging_layer_set: bool,
+    pub(crate) logger_provider_set: bool,
 }
 
 /// Allows us to keep track of the last registry that was used. Not ideal. Plugins would be better to have state
 /// that can be maintained across reloads.
 static REGISTRY: LazyLock<Mutex<Option<Registry>>> = LazyLock::new(Default::default);
 
+/// The logger provider of the active format layer, kept here so that it is shut down in a blocking
+/// task once the format layer is replaced.
+static LOGGER_PROVIDER: LazyLock<Mutex<Option<opentelemetry_sdk::logs::SdkLoggerProvider>>> =
+    LazyLock::new(Default::default);
+
 impl Activation {
     pub(crate) fn new() -> Self {
         Self {
             new_trace_provider: None,
             new_trace_propagator: None,
+            new_propagation_clients: None,
             new_meter_providers: HashMap::default(),
             // We can remove this is we allow state to be maintained across plugin reloads
             prometheus_registry: REGISTRY.lock().clone(),
+            exemplars: Exemplars::active(),
             new_logging_fmt_layer: None,
+            new_logger_provider: None,
             #[cfg(test)]
             test_instrumentation: TestInstrumentation::default(),
         }
@@ -112,8 +136,24 @@ impl Activation {
         }
     }
 
-    pub(crate) fn with_tracer_propagator(&mut self, tracer_propagator: TextMapCompositePropagator) {
+    pub(crate) fn with_logger_provider(
+        &mut self,
+        logger_provider: opentelemetry_sdk::logs::SdkLoggerProvider,
+    ) {
+        self.new_logger_provider = Some(logger_provider);
+        #[cfg(test)]
+        {
+            self.test_instrumentation.logger_provider_set = true;
+        }
+    }
+
+    pub(crate) fn with_tracer_propagator(
+        &mut self,
+        tracer_propagator: TextMapCompositePropagator,
+        propagation_clients: PropagationClients,
+    ) {
         self.new_trace_propagator = Some(tracer_propagator);
+        self.new_propagation_clients = Some(propagation_clients);
         #[cfg(test)]
         {
             self.test_instrumentation.tracer_propagator_set = true;
@@ -159,6 +199,14 @@ impl Activation {
         self.prometheus_registry.clone()
     }
 
+    pub(crate) fn with_exemplars(&mut self, exemplars: Exemplars) {
+        self.exemplars = exemplars;
+    }
+
+    pub(crate) fn exemplars(&self) -> Exemplars {
+        self.exemplars.clone()
+    }
+
     #[cfg(test)]
     pub(crate) fn test_instrumentation(&self) -> &TestInstrumentation {
         &self.test_instrumentation
@@ -174,6 +222,7 @@ impl Activation {
     /// 3. Swaps in new meter providers for metrics collection
     /// 4. Updates logging format layer
     /// 5. Stores Prometheus registry for future endpoint creation
+    /// 6. Records the exemplars of the next requests in the store read by the new exporters
     ///
     /// Old providers are safely shut down in blocking tasks to avoid deadlocking the async runtime.
     ///
@@ -185,6 +234,7 @@ impl Activation {
         self.reload_metrics();
         self.reload_logging();
         *REGISTRY.lock() = self.prometheus_registry.clone();
+        self.exemplars.activate();
     }
 
     fn reload_tracing(&mut self) {
@@ -227,6 +277,12 @@ impl Activation {
     fn reload_logging(&mut self) {
         if let Some(fmt_layer) = self.new_logging_fmt_layer.take() {
             reload_fmt(fmt_layer);
+            // The previous format layer is gone, the previous logger provider is now only held
+            // here and will be safely dropped with this activation
+            self.new_logger_provider = std::mem::replace(
+                &mut *LOGGER_PROVIDER.lock(),
+                self.new_logger_provider.take(),
+            );
         }
     }
 
@@ -234,6 +290,9 @@ impl Activation {
         if let Some(propagator) = self.new_trace_propagator.take() {
             opentelemetry::global::set_text_map_propagator(propagator);
         }
+        if let Some(clients) = self.new_propagation_clients.take() {
+            set_propagation_clients(clients);
+        }
     }
 }
 
@@ -250,6 +309,7 @@ impl Drop for Activation {
     fn drop(&mut self) {
         let meter_providers = std::mem::take(&mut self.new_meter_providers);
         let tracer_provider = self.new_trace_provider.take();
+        let logger_provider = self.new_logger_provider.take();
 
         // In tests, drop providers synchronously via block_in_place. This avoids a race
         // condition between spawn_blocking and Runtime::drop: when the tokio test runtime
@@ -264,6 +324,7 @@ impl Drop for Activation {
             block_in_place(|| {
                 drop(meter_providers);
                 drop(tracer_provider);
+                drop(logger_provider);
             });
         }
 
@@ -272,6 +333,7 @@ impl Drop for Activation {
             spawn_blocking(|| {
                 drop(meter_providers);
                 drop(tracer_provider);
+                drop(logger_provider);
             });
         }
     }
@@ -29,6 +29,7 @@ use multimap::MultiMap;
 use tokio::task::block_in_place;
 use tower::BoxError;
 use tower::ServiceExt;
+use tracing_subscriber::Layer;
 
 use crate::Endpoint;
 use crate::ListenAddr;
@@ -39,7 +40,9 @@ use crate::plugins::telemetry::config::Conf;
 use crate::plugins::telemetry::config::MetricView;
 use crate::plugins::telemetry::config_new::cache::CACHE_METRIC;
 use crate::plugins::telemetry::fmt_layer::create_fmt_layer;
+use crate::plugins::telemetry::logging::otlp::create_otlp_log_layer;
 use crate::plugins::telemetry::metrics;
+use crate::plugins::telemetry::metrics::exemplars::Exemplars;
 use crate::plugins::telemetry::metrics::prometheus::PrometheusService;
 use crate::plugins::telemetry::otlp;
 use crate::plugins::telemetry::reload::activation::Activation;
@@ -81,7 +84,7 @@ impl<'a> Builder<'a> {
         // We can't guarantee that exporters from external libraries will not perform blocking io during or after construction.
         // Use block_in_place to avoid any chance of blocking the main rt threads
         block_in_place(|| {
-            self.setup_logging();
+            self.setup_logging()?;
             self.setup_public_tracing()?;
             self.setup_public_metrics()?;
             self.setup_apollo_metrics()?;
@@ -93,10 +96,17 @@ impl<'a> Builder<'a> {
     fn setup_public_metrics(&mut self) -> Result<(), BoxError> {
         if self.is_metrics_config_changed::<metrics::prometheus::Config>()
             || self.is_metrics_config_changed::<otlp::Config>()
+            || self.is_exemplars_config_changed()
             || self.prometheus_force_change()
         {
             ::tracing::debug!("configuring metrics");
             let mut builder = MetricsBuilder::new(self.config);
+            // The exporters read the exemplars of the store they are built with, so a new store
+            // replaces the exemplars of the previous configuration
+            let exemplars = Exemplars::default();
+            if self.has_exemplars() {
+                builder.with_exemplars(exemplars.clone());
+            }
             builder.configure(&self.config.exporters.metrics.prometheus)?;
             builder.configure(&self.config.exporters.metrics.otlp)?;
             // Register memory allocation views with custom buckets
@@ -108,6 +118,7 @@ impl<'a> Builder<'a> {
             let (prometheus_registry, meter_providers, _) = builder.build();
             self.activation
                 .with_prometheus_registry(prometheus_registry);
+            self.activation.with_exemplars(exemplars);
 
             self.activation.add_meter_providers(meter_providers);
         }
@@ -124,6 +135,7 @@ impl<'a> Builder<'a> {
                     path,
                     PrometheusService {
                         registry: prometheus_registry.clone(),
+                        exemplars: self.has_exemplars().then(|| self.activation.exemplars()),
                     }
                     .boxed(),
                 ),
@@ -197,6 +209,19 @@ impl<'a> Builder<'a> {
             || previous_config.exporters.metrics.common != self.config.exporters.metrics.common
     }
 
+    fn has_exemplars(&self) -> bool {
+        self.config.instrumentation.instruments.has_exemplars()
+    }
+
+    /// Detects if exemplars were enabled or disabled on every instrument, which changes the
+    /// exporters of the metrics
+    fn is_exemplars_config_changed(&self) -> bool {
+        let Some(previous_config) = self.previous_config else {
+            return true;
+        };
+        previous_config.instrumentation.instruments.has_exemplars() != self.has_exemplars()
+    }
+
     /// Detects if tracing config has changed for a specific exporter.
     ///
     /// Returns `true` if:
@@ -216,12 +241,26 @@ impl<'a> Builder<'a> {
             &self.config.exporters.tracing.propagation,
             &self.config.exporters.tracing,
         );
-        self.activation.with_tracer_propagator(propagators);
+        self.activation.with_tracer_propagator(
+            propagators,
+            self.config.exporters.tracing.propagation.clients.clone(),
+        );
     }
 
-    fn setup_logging(&mut self) {
+    fn setup_logging(&mut self) -> Result<(), BoxError> {
         ::tracing::debug!("configuring logging");
-        self.activation.with_logging(create_fmt_layer(self.config));
+        let fmt_layer = create_fmt_layer(self.config);
+        match create_otlp_log_layer(self.config)? {
+            Some((otlp_layer, logger_provider)) => {
+                self.activation.with_logger_provider(logger_provider);
+                // The OTLP layer comes first as the stdout formatters consume the attributes of
+                // custom events
+                self.activation
+                    .with_logging(otlp_layer.and_then(fmt_layer).boxed());
+            }
+            None => self.activation.with_logging(fmt_layer),
+        }
+        Ok(())
     }
 
     /// Returns true every 20 calls when Prometheus is enabled.
@@ -365,6 +404,32 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_metrics_reload_on_exemplars_change() {
+        let previous_config = Some(create_config_with_otlp_metrics_enabled());
+        let mut config = create_config_with_otlp_metrics_enabled();
+        config.instrumentation.instruments = serde_json::from_value(serde_json::json!({
+            "router": {
+                "http.server.request.duration": {
+                    "exemplars": true
+                }
+            }
+        }))
+        .unwrap();
+
+        let builder = Builder::new(&previous_config, &config);
+        let (activation, _endpoints, _sender) = builder.build().unwrap();
+
+        let instr = activation.test_instrumentation();
+        // Enabling exemplars changes the OTLP exporter, so metrics should reload
+        assert!(
+            instr
+                .meter_providers_added
+                .contains(&MeterProviderType::Public),
+            "Public meter provider should be added when exemplars are enabled"
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_tracing_reload_on_otlp_change() {
         let previous_config = Some(create_default_config());
@@ -381,6 +446,31 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_logger_provider_set_when_otlp_logging_enabled() {
+        let previous_config = Some(create_default_config());
+        let mut config = create_default_config();
+        config.exporters.logging.otlp.enabled = true;
+
+        let builder = Builder::new(&previous_config, &config);
+        let (activation, _endpoints, _sender) = builder.build().unwrap();
+
+        let instr = activation.test_instrumentation();
+        assert!(instr.logging_layer_set, "Logging should always be set");
+        assert!(
+            instr.logger_provider_set,
+            "Logger provider should be set when OTLP logging is enabled"
+        );
+
+        let config = create_default_config();
+        let builder = Builder::new(&previous_config, &config);
+        let (activation, _endpoints, _sender) = builder.build().unwrap();
+        assert!(
+            !activation.test_instrumentation().logger_provider_set,
+            "Logger provider should not be set when OTLP logging is disabled"
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_apollo_metrics_always_rebuild_when_enabled() {
         let config = create_config_with_apollo_enabled();
@@ -728,6 +818,78 @@ mod tests {
         ));
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_datadog_with_b3_single_header_propagation_fails() {
+        use crate::test_harness::tracing_test;
+        let _guard = tracing_test::dispatcher_guard();
+        let mut config = create_config_with_apollo_enabled();
+        config.exporters.tracing.propagation = Propagation {
+            datadog: Some(true),
+            b3_single_header: true,
+            ..Default::default()
+        };
+
+        let builder = Builder::new(&None, &config);
+        assert!(builder.build().is_ok());
+        assert!(tracing_test::logs_contain(
+            "datadog propagation should not be used with any other propagator except for baggage to avoid trace id conflicts",
+        ));
+    }
+
+    #[test]
+    fn test_b3_propagation_encodings() {
+        use opentelemetry::propagation::TextMapPropagator;
+        use opentelemetry::trace::SpanContext;
+        use opentelemetry::trace::SpanId;
+        use opentelemetry::trace::TraceContextExt;
+        use opentelemetry::trace::TraceFlags;
+        use opentelemetry::trace::TraceId;
+        use opentelemetry::trace::TraceState;
+
+        let context = opentelemetry::Context::new().with_remote_span_context(SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        ));
+        let inject = |zipkin: bool, b3_single_header: bool| {
+            let tracing = Tracing {
+                propagation: Propagation {
+                    zipkin,
+                    b3_single_header,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let propagator = create_propagator(&tracing.propagation, &tracing);
+            let mut headers = http::HeaderMap::new();
+            propagator.inject_context(
+                &context,
+                &mut crate::otel_compat::HeaderInjector(&mut headers),
+            );
+            headers
+        };
+
+        let headers = inject(false, true);
+        assert_eq!(
+            headers.get("b3").unwrap(),
+            "4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-1"
+        );
+        assert!(headers.get("x-b3-traceid").is_none());
+
+        let headers = inject(true, false);
+        assert!(headers.get("b3").is_none());
+        assert_eq!(
+            headers.get("x-b3-traceid").unwrap(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+
+        let headers = inject(true, true);
+        assert!(headers.get("b3").is_some());
+        assert!(headers.get("x-b3-traceid").is_some());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_datadog_with_aws_xray_propagation_fails() {
         use crate::test_harness::tracing_test;
@@ -40,6 +40,7 @@ use crate::plugins::telemetry::apollo_exporter::Sender;
 use crate::plugins::telemetry::config::Conf;
 use crate::plugins::telemetry::config::MetricView;
 use crate::plugins::telemetry::config::MetricsCommon;
+use crate::plugins::telemetry::metrics::exemplars::Exemplars;
 
 /// Trait for metric exporters to contribute to meter provider construction
 pub(crate) trait MetricsConfigurator {
@@ -61,6 +62,8 @@ pub(crate) struct MetricsBuilder<'a> {
     providers_with_readers: HashSet<MeterProviderType>,
     apollo_metrics_sender: Sender,
     prometheus_registry: Option<Registry>,
+    /// The exemplars to export along with the histograms, if any instrument keeps them
+    exemplars: Option<Exemplars>,
     metrics_common: &'a MetricsCommon,
     resource: Resource,
 }
@@ -117,6 +120,7 @@ impl<'a> MetricsBuilder<'a> {
             resource,
             apollo_metrics_sender: Sender::default(),
             prometheus_registry: None,
+            exemplars: None,
             metrics_common: &config.exporters.metrics.common,
         }
     }
@@ -127,6 +131,13 @@ impl<'a> MetricsBuilder<'a> {
         self.prometheus_registry = Some(prometheus_registry);
         self
     }
+    pub(crate) fn with_exemplars(&mut self, exemplars: Exemplars) -> &mut Self {
+        self.exemplars = Some(exemplars);
+        self
+    }
+    pub(crate) fn exemplars(&self) -> Option<&Exemplars> {
+        self.exemplars.as_ref()
+    }
     pub(crate) fn with_apollo_metrics_sender(
         &mut self,
         apollo_metrics_sender: Sender,
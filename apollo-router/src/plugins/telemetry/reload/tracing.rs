@@ -24,6 +24,7 @@ use opentelemetry::propagation::TextMapCompositePropagator;
 use opentelemetry::propagation::TextMapPropagator;
 use opentelemetry_sdk::trace::SdkTracerProvider;
 use opentelemetry_sdk::trace::SpanProcessor;
+use opentelemetry_zipkin::B3Encoding;
 use tower::BoxError;
 
 use crate::plugins::telemetry::CustomTraceIdPropagator;
@@ -32,12 +33,16 @@ use crate::plugins::telemetry::config::Propagation;
 use crate::plugins::telemetry::config::Tracing;
 use crate::plugins::telemetry::config::TracingCommon;
 use crate::plugins::telemetry::config_new::spans::Spans;
+use crate::plugins::telemetry::tracing::propagation::B3SingleHeaderPropagator;
+use crate::plugins::telemetry::tracing::resolver_spans::ResolverSpanProcessor;
+use crate::plugins::telemetry::tracing::tail_sampling::TailSamplingSpanProcessor;
 
 /// Builder for constructing OpenTelemetry tracer providers with multiple exporters
 pub(crate) struct TracingBuilder<'a> {
     common: &'a TracingCommon,
     spans: &'a Spans,
     builder: opentelemetry_sdk::trace::TracerProviderBuilder,
+    exporters: Vec<Box<dyn SpanProcessor>>,
 }
 
 impl<'a> TracingBuilder<'a> {
@@ -49,6 +54,7 @@ impl<'a> TracingBuilder<'a> {
             builder: common.configure_tracer_provider_builder(
                 opentelemetry_sdk::trace::SdkTracerProvider::builder(),
             ),
+            exporters: Vec::new(),
         }
     }
 
@@ -72,7 +78,48 @@ impl<'a> TracingBuilder<'a> {
         self.builder = builder.with_span_processor(span_processor);
     }
 
-    pub(crate) fn build(self) -> SdkTracerProvider {
+    /// Adds the span processor of a third party exporter, behind the resolver spans and the tail
+    /// sampler when they are enabled. Apollo reporting has its own sampling and reads the field
+    /// execution traces itself, so it uses [`Self::with_span_processor`].
+    pub(crate) fn with_exporter_span_processor<T: SpanProcessor + 'static>(
+        &mut self,
+        span_processor: T,
+    ) {
+        if self.common.experimental_tail_sampling.enabled
+            || self.common.experimental_resolver_spans.enabled
+        {
+            self.exporters.push(Box::new(span_processor));
+        } else {
+            self.with_span_processor(span_processor);
+        }
+    }
+
+    pub(crate) fn build(mut self) -> SdkTracerProvider {
+        if !self.exporters.is_empty() {
+            let mut exporters = std::mem::take(&mut self.exporters);
+            let common = self.common;
+            let tail_sampling = &common.experimental_tail_sampling;
+            let resolver_spans = &common.experimental_resolver_spans;
+            // Resolver spans are created first, so that the tail sampler buffers them with their trace
+            tail_sampling.warn_if_head_sampled(&common.sampler);
+            if tail_sampling.enabled && resolver_spans.enabled {
+                exporters = vec![Box::new(TailSamplingSpanProcessor::new(
+                    tail_sampling.clone(),
+                    exporters,
+                ))];
+            }
+            if resolver_spans.enabled {
+                self.with_span_processor(ResolverSpanProcessor::new(
+                    resolver_spans.clone(),
+                    exporters,
+                ));
+            } else {
+                self.with_span_processor(TailSamplingSpanProcessor::new(
+                    tail_sampling.clone(),
+                    exporters,
+                ));
+            }
+        }
         self.builder.build()
     }
 }
@@ -92,13 +139,23 @@ pub(crate) fn create_propagator(
     if tracing.is_trace_context_propagation_enabled() {
         propagators.push(Box::<opentelemetry_sdk::propagation::TraceContextPropagator>::default());
     }
-    if tracing.is_zipkin_propagation_enabled() {
-        propagators.push(Box::<opentelemetry_zipkin::Propagator>::default());
+    // A single propagator handles both B3 encodings, the single header taking precedence on extraction
+    match (
+        tracing.is_zipkin_propagation_enabled(),
+        tracing.is_b3_single_header_propagation_enabled(),
+    ) {
+        (true, true) => propagators.push(Box::new(
+            opentelemetry_zipkin::Propagator::with_encoding(B3Encoding::SingleAndMultiHeader),
+        )),
+        (true, false) => propagators.push(Box::<opentelemetry_zipkin::Propagator>::default()),
+        (false, true) => propagators.push(Box::<B3SingleHeaderPropagator>::default()),
+        (false, false) => {}
     }
     if tracing.is_datadog_propagation_enabled() {
         if tracing.is_jaeger_propagation_enabled()
             || tracing.is_trace_context_propagation_enabled()
             || tracing.is_zipkin_propagation_enabled()
+            || tracing.is_b3_single_header_propagation_enabled()
             || tracing.is_aws_xray_propagation_enabled()
         {
             if tracing.datadog.enabled && propagation.datadog.unwrap_or(false) {
@@ -22,7 +22,7 @@ use crate::plugins::telemetry::tracing::datadog_exporter::propagator::SamplingPr
 #[derive(Debug, Clone)]
 pub(crate) struct DatadogAgentSampling {
     /// The underlying sampler used for initial sampling decisions
-    pub(crate) sampler: opentelemetry_sdk::trace::Sampler,
+    pub(crate) sampler: Box<dyn ShouldSample>,
     /// Flag to enable parent-based sampling for consistent trace sampling
     pub(crate) parent_based_sampler: bool,
 }
@@ -32,12 +32,9 @@ impl DatadogAgentSampling {
     /// # Arguments
     /// * `sampler` - The underlying sampler to use for initial sampling decisions
     /// * `parent_based_sampler` - Whether to use parent-based sampling for consistent trace sampling
-    pub(crate) fn new(
-        sampler: opentelemetry_sdk::trace::Sampler,
-        parent_based_sampler: bool,
-    ) -> Self {
+    pub(crate) fn new(sampler: impl ShouldSample + 'static, parent_based_sampler: bool) -> Self {
         Self {
-            sampler,
+            sampler: Box::new(sampler),
             parent_based_sampler,
         }
     }
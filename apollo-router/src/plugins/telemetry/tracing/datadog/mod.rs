@@ -269,9 +269,9 @@ impl TracingConfigurator for Config {
             .preview_datadog_agent_sampling
             .unwrap_or_default()
         {
-            builder.with_span_processor(batch_processor.always_sampled())
+            builder.with_exporter_span_processor(batch_processor.always_sampled())
         } else {
-            builder.with_span_processor(batch_processor)
+            builder.with_exporter_span_processor(batch_processor)
         }
         Ok(())
     }
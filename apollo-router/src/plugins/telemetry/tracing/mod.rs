@@ -25,7 +25,11 @@ pub(crate) mod datadog;
 pub(crate) mod datadog_exporter;
 mod named;
 pub(crate) mod otlp;
+pub(crate) mod propagation;
 pub(crate) mod reload;
+pub(crate) mod resolver_spans;
+pub(crate) mod sampling_rules;
+pub(crate) mod tail_sampling;
 pub(crate) mod zipkin;
 
 pub(crate) use named::NamedSpanExporter;
@@ -223,6 +227,18 @@ impl From<BatchProcessorConfig> for BatchConfig {
     }
 }
 
+impl From<BatchProcessorConfig> for opentelemetry_sdk::logs::BatchConfig {
+    fn from(config: BatchProcessorConfig) -> Self {
+        // Concurrent exports are not supported by the batch log processor
+        opentelemetry_sdk::logs::BatchConfigBuilder::default()
+            .with_scheduled_delay(config.scheduled_delay)
+            .with_max_queue_size(config.max_queue_size)
+            .with_max_export_batch_size(config.max_export_batch_size)
+            .with_max_export_timeout(config.max_export_timeout)
+            .build()
+    }
+}
+
 impl Display for BatchProcessorConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(&format!("BatchConfig {{ scheduled_delay={}, max_queue_size={}, max_export_batch_size={}, max_export_timeout={}, max_concurrent_exports={} }}",
@@ -46,9 +46,9 @@ impl TracingConfigurator for super::super::otlp::Config {
             .preview_datadog_agent_sampling
             .unwrap_or_default()
         {
-            builder.with_span_processor(batch_span_processor.always_sampled())
+            builder.with_exporter_span_processor(batch_span_processor.always_sampled())
         } else {
-            builder.with_span_processor(batch_span_processor)
+            builder.with_exporter_span_processor(batch_span_processor)
         }
 
         Ok(())
@@ -0,0 +1,148 @@
+//! Trace context propagation to the outbound requests of the router
+//!
+//! Every HTTP client built by the router injects the trace context through [`inject_trace_context`],
+//! so that the configured propagation formats apply uniformly. Each kind of client can opt out in
+//! `telemetry.exporters.tracing.propagation.clients`.
+use std::sync::LazyLock;
+
+use opentelemetry::global::get_text_map_propagator;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::propagation::Injector;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::propagation::text_map_propagator::FieldIter;
+use opentelemetry_zipkin::B3Encoding;
+use parking_lot::RwLock;
+use tracing::Span;
+
+use crate::otel_compat::HeaderInjector;
+use crate::plugins::telemetry::config::PropagationClients;
+use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
+use crate::plugins::telemetry::reload::otel::prepare_context;
+
+/// The outbound clients of the router
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OutboundClient {
+    Subgraph,
+    Connector,
+    Coprocessor,
+    Jwks,
+    Uplink,
+}
+
+/// The clients the trace context is propagated to, updated on each telemetry reload
+static PROPAGATION_CLIENTS: LazyLock<RwLock<PropagationClients>> = LazyLock::new(Default::default);
+
+pub(crate) fn set_propagation_clients(clients: PropagationClients) {
+    *PROPAGATION_CLIENTS.write() = clients;
+}
+
+impl PropagationClients {
+    fn is_enabled(&self, client: OutboundClient) -> bool {
+        match client {
+            OutboundClient::Subgraph => self.subgraph,
+            OutboundClient::Connector => self.connector,
+            OutboundClient::Coprocessor => self.coprocessor,
+            OutboundClient::Jwks => self.jwks,
+            OutboundClient::Uplink => self.uplink,
+        }
+    }
+}
+
+/// Inject the trace context of `span` in the headers of a request sent by `client`, with the
+/// configured propagators, unless propagation is disabled for that client.
+pub(crate) fn inject_trace_context(
+    client: OutboundClient,
+    span: &Span,
+    headers: &mut http::HeaderMap,
+) {
+    let clients = PROPAGATION_CLIENTS.read().clone();
+    inject_trace_context_for(&clients, client, span, headers);
+}
+
+fn inject_trace_context_for(
+    clients: &PropagationClients,
+    client: OutboundClient,
+    span: &Span,
+    headers: &mut http::HeaderMap,
+) {
+    if !clients.is_enabled(client) {
+        return;
+    }
+    get_text_map_propagator(|propagator| {
+        propagator.inject_context(
+            &prepare_context(span.context()),
+            &mut HeaderInjector(headers),
+        );
+    });
+}
+
+const B3_SINGLE_HEADER: &str = "b3";
+
+/// Propagates Zipkin B3 context in the single `b3` header only.
+///
+/// The Zipkin propagator also injects the multiple `X-B3-*` headers when configured with the
+/// single header encoding, so the other headers are filtered out on injection.
+#[derive(Debug)]
+pub(crate) struct B3SingleHeaderPropagator {
+    inner: opentelemetry_zipkin::Propagator,
+    fields: [String; 1],
+}
+
+impl Default for B3SingleHeaderPropagator {
+    fn default() -> Self {
+        Self {
+            inner: opentelemetry_zipkin::Propagator::with_encoding(B3Encoding::SingleHeader),
+            fields: [B3_SINGLE_HEADER.to_string()],
+        }
+    }
+}
+
+struct SingleHeaderInjector<'a>(&'a mut dyn Injector);
+
+impl Injector for SingleHeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if key == B3_SINGLE_HEADER {
+            self.0.set(key, value);
+        }
+    }
+}
+
+impl TextMapPropagator for B3SingleHeaderPropagator {
+    fn inject_context(&self, cx: &opentelemetry::Context, injector: &mut dyn Injector) {
+        self.inner
+            .inject_context(cx, &mut SingleHeaderInjector(injector));
+    }
+
+    fn extract_with_context(
+        &self,
+        cx: &opentelemetry::Context,
+        extractor: &dyn Extractor,
+    ) -> opentelemetry::Context {
+        self.inner.extract_with_context(cx, extractor)
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(self.fields.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_skips_clients_that_opted_out() {
+        assert!(PropagationClients::default().is_enabled(OutboundClient::Jwks));
+
+        let clients = PropagationClients {
+            jwks: false,
+            ..Default::default()
+        };
+        assert!(!clients.is_enabled(OutboundClient::Jwks));
+        assert!(clients.is_enabled(OutboundClient::Uplink));
+
+        let mut headers = http::HeaderMap::new();
+        inject_trace_context_for(&clients, OutboundClient::Jwks, &Span::none(), &mut headers);
+        assert!(headers.is_empty());
+    }
+}
@@ -0,0 +1,496 @@
+//! Resolver spans from subgraph field execution traces
+//!
+//! When enabled, subgraphs are asked for their federated trace (FTV1) on a fraction of the sampled
+//! requests. The per-field timing tree of each trace is turned into spans under the subgraph span
+//! before it reaches the span processors of the exporters.
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use opentelemetry::Context;
+use opentelemetry::Key;
+use opentelemetry::KeyValue;
+use opentelemetry::trace::SpanContext;
+use opentelemetry::trace::SpanId;
+use opentelemetry::trace::SpanKind;
+use opentelemetry::trace::Status;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::IdGenerator;
+use opentelemetry_sdk::trace::RandomIdGenerator;
+use opentelemetry_sdk::trace::Span;
+use opentelemetry_sdk::trace::SpanData;
+use opentelemetry_sdk::trace::SpanEvents;
+use opentelemetry_sdk::trace::SpanLinks;
+use opentelemetry_sdk::trace::SpanProcessor;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::Node;
+use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::node::Id;
+use crate::plugins::telemetry::config::SamplerOption;
+use crate::plugins::telemetry::tracing::apollo_telemetry::APOLLO_PRIVATE_FTV1;
+use crate::plugins::telemetry::tracing::apollo_telemetry::decode_ftv1_trace;
+
+const RESOLVER_SPAN_NAME: &str = "resolver";
+const FIELD_NAME_KEY: Key = Key::from_static_str("graphql.field.name");
+const FIELD_TYPE_KEY: Key = Key::from_static_str("graphql.field.type");
+const PARENT_TYPE_KEY: Key = Key::from_static_str("graphql.type.name");
+const FIELD_PATH_KEY: Key = Key::from_static_str("graphql.field.path");
+
+/// Resolver spans configuration
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Config {
+    /// Ask subgraphs for their field execution traces (FTV1), and export them as resolver spans
+    /// under the subgraph spans
+    pub(crate) enabled: bool,
+    /// The fraction of the sampled requests for which subgraphs are asked for their traces,
+    /// always_on, always_off or a decimal between 0.0 and 1.0 (default: 0.01)
+    pub(crate) sampler: SamplerOption,
+    /// The maximum number of resolver spans created for a subgraph fetch. The resolvers closest
+    /// to the root are kept (default: 500)
+    pub(crate) max_spans_per_fetch: usize,
+    /// Resolvers faster than this are not exported, their children are attached to the closest
+    /// exported resolver (default: all resolvers are exported)
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) min_duration: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sampler: SamplerOption::TraceIdRatioBased(0.01),
+            max_spans_per_fetch: 500,
+            min_duration: None,
+        }
+    }
+}
+
+impl Config {
+    /// The fraction of the sampled requests for which subgraphs are asked for their traces
+    ///
+    /// Fractions >= 1 always sample, like for the trace sampler.
+    pub(crate) fn ratio(&self) -> f64 {
+        if self.enabled {
+            self.sampler.ratio().clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Adds the resolver spans of the subgraph spans carrying a field execution trace, and forwards
+/// all spans to the span processors of the exporters
+pub(crate) struct ResolverSpanProcessor {
+    config: Config,
+    delegates: Vec<Box<dyn SpanProcessor>>,
+    id_generator: RandomIdGenerator,
+}
+
+impl std::fmt::Debug for ResolverSpanProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResolverSpanProcessor")
+            .field("config", &self.config)
+            .field("delegates", &self.delegates)
+            .finish()
+    }
+}
+
+impl ResolverSpanProcessor {
+    pub(crate) fn new(config: Config, delegates: Vec<Box<dyn SpanProcessor>>) -> Self {
+        Self {
+            config,
+            delegates,
+            id_generator: RandomIdGenerator::default(),
+        }
+    }
+
+    fn forward_span(&self, span: SpanData) {
+        if let Some((last, delegates)) = self.delegates.split_last() {
+            for delegate in delegates {
+                delegate.on_end(span.clone());
+            }
+            last.on_end(span);
+        }
+    }
+
+    /// The resolver spans of the field execution trace recorded on a subgraph span
+    ///
+    /// The subgraph clock may differ from the router's, so the trace is centered in the subgraph
+    /// span, splitting the network time between the request and the response.
+    fn resolver_spans(&self, subgraph_span: &SpanData) -> Vec<SpanData> {
+        let Some(trace) = subgraph_span
+            .attributes
+            .iter()
+            .find(|kv| kv.key == APOLLO_PRIVATE_FTV1)
+            .and_then(|kv| decode_ftv1_trace(kv.value.as_str().as_ref()))
+        else {
+            return Vec::new();
+        };
+        let Some(root) = trace.root else {
+            return Vec::new();
+        };
+
+        let span_duration = subgraph_span
+            .end_time
+            .duration_since(subgraph_span.start_time)
+            .unwrap_or_default();
+        let trace_start = subgraph_span.start_time
+            + span_duration.saturating_sub(Duration::from_nanos(trace.duration_ns)) / 2;
+
+        // Breadth first, so that the resolvers closest to the root are kept when there are too many
+        let mut spans = Vec::new();
+        let mut queue: VecDeque<(&Node, SpanId, String)> = root
+            .child
+            .iter()
+            .map(|node| (node, subgraph_span.span_context.span_id(), String::new()))
+            .collect();
+        while let Some((node, parent_span_id, parent_path)) = queue.pop_front() {
+            let path = match &node.id {
+                Some(Id::ResponseName(name)) if parent_path.is_empty() => name.clone(),
+                Some(Id::ResponseName(name)) => format!("{parent_path}.{name}"),
+                Some(Id::Index(index)) => format!("{parent_path}.{index}"),
+                None => parent_path,
+            };
+
+            // List items are not resolvers, and the fastest resolvers may be skipped
+            let duration = Duration::from_nanos(node.end_time.saturating_sub(node.start_time));
+            let is_resolver = matches!(node.id, Some(Id::ResponseName(_)));
+            let exported = is_resolver
+                && self
+                    .config
+                    .min_duration
+                    .is_none_or(|min_duration| duration >= min_duration);
+            let span_id = if exported {
+                if spans.len() >= self.config.max_spans_per_fetch {
+                    break;
+                }
+                let span_id = self.id_generator.new_span_id();
+                spans.push(self.resolver_span(
+                    subgraph_span,
+                    node,
+                    span_id,
+                    parent_span_id,
+                    trace_start,
+                    &path,
+                ));
+                span_id
+            } else {
+                parent_span_id
+            };
+
+            queue.extend(
+                node.child
+                    .iter()
+                    .map(|child| (child, span_id, path.clone())),
+            );
+        }
+        spans
+    }
+
+    fn resolver_span(
+        &self,
+        subgraph_span: &SpanData,
+        node: &Node,
+        span_id: SpanId,
+        parent_span_id: SpanId,
+        trace_start: std::time::SystemTime,
+        path: &str,
+    ) -> SpanData {
+        let field_name = match &node.id {
+            Some(Id::ResponseName(name)) if node.original_field_name.is_empty() => name.as_str(),
+            _ => node.original_field_name.as_str(),
+        };
+        let parent_context = &subgraph_span.span_context;
+        SpanData {
+            span_context: SpanContext::new(
+                parent_context.trace_id(),
+                span_id,
+                parent_context.trace_flags(),
+                false,
+                parent_context.trace_state().clone(),
+            ),
+            parent_span_id,
+            parent_span_is_remote: false,
+            span_kind: SpanKind::Internal,
+            name: Cow::Borrowed(RESOLVER_SPAN_NAME),
+            start_time: trace_start + Duration::from_nanos(node.start_time),
+            end_time: trace_start + Duration::from_nanos(node.end_time.max(node.start_time)),
+            attributes: vec![
+                KeyValue::new(FIELD_NAME_KEY, field_name.to_string()),
+                KeyValue::new(FIELD_TYPE_KEY, node.r#type.clone()),
+                KeyValue::new(PARENT_TYPE_KEY, node.parent_type.clone()),
+                KeyValue::new(FIELD_PATH_KEY, path.to_string()),
+            ],
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: match node.error.first() {
+                Some(error) => Status::error(error.message.clone()),
+                None => Status::Unset,
+            },
+            instrumentation_scope: subgraph_span.instrumentation_scope.clone(),
+        }
+    }
+}
+
+impl SpanProcessor for ResolverSpanProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        for delegate in &self.delegates {
+            delegate.on_start(span, cx);
+        }
+    }
+
+    fn on_end(&self, span: SpanData) {
+        if span.span_context.is_sampled() {
+            for resolver_span in self.resolver_spans(&span) {
+                self.forward_span(resolver_span);
+            }
+        }
+        self.forward_span(span);
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        for delegate in &self.delegates {
+            delegate.force_flush()?;
+        }
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        for delegate in &self.delegates {
+            delegate.shutdown_with_timeout(timeout)?;
+        }
+        Ok(())
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        for delegate in &mut self.delegates {
+            delegate.set_resource(resource);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use opentelemetry::trace::TraceFlags;
+    use opentelemetry::trace::TraceId;
+    use parking_lot::Mutex;
+    use rand::RngExt as _;
+
+    use super::*;
+    use crate::plugins::telemetry::apollo_exporter::proto::reports::Trace;
+    use crate::plugins::telemetry::apollo_exporter::proto::reports::trace::Error;
+    use crate::plugins::telemetry::tracing::apollo_telemetry::encode_ftv1_trace;
+
+    #[test]
+    fn it_clamps_the_ratio() {
+        let config = Config {
+            enabled: true,
+            sampler: SamplerOption::TraceIdRatioBased(1.5),
+            ..Default::default()
+        };
+        assert_eq!(config.ratio(), 1.0);
+        assert!(rand::rng().random_bool(config.ratio()));
+
+        let config = Config {
+            enabled: true,
+            sampler: SamplerOption::TraceIdRatioBased(-0.5),
+            ..Default::default()
+        };
+        assert_eq!(config.ratio(), 0.0);
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct MockSpanProcessor {
+        spans: Arc<Mutex<Vec<SpanData>>>,
+    }
+
+    impl SpanProcessor for MockSpanProcessor {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.spans.lock().push(span);
+        }
+
+        fn force_flush(&self) -> OTelSdkResult {
+            Ok(())
+        }
+
+        fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+            Ok(())
+        }
+    }
+
+    fn field(name: &str, parent_type: &str, start_ms: u64, end_ms: u64) -> Node {
+        Node {
+            id: Some(Id::ResponseName(name.to_string())),
+            original_field_name: name.to_string(),
+            r#type: "String".to_string(),
+            parent_type: parent_type.to_string(),
+            start_time: start_ms * 1_000_000,
+            end_time: end_ms * 1_000_000,
+            ..Default::default()
+        }
+    }
+
+    fn item(index: u32, child: Vec<Node>) -> Node {
+        Node {
+            id: Some(Id::Index(index)),
+            child,
+            ..Default::default()
+        }
+    }
+
+    /// `{ products { name reviews } }` with two products
+    fn subgraph_span(sampled: bool) -> SpanData {
+        let mut reviews = field("reviews", "Product", 2, 8);
+        reviews.error = vec![Error {
+            message: "reviews are unavailable".to_string(),
+            ..Default::default()
+        }];
+        let products = Node {
+            child: vec![
+                item(0, vec![field("name", "Product", 2, 3), reviews]),
+                item(1, vec![field("name", "Product", 2, 3)]),
+            ],
+            ..field("products", "Query", 1, 2)
+        };
+        let trace = Trace {
+            duration_ns: 10_000_000,
+            root: Some(Node {
+                child: vec![products],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let start_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        SpanData {
+            span_context: SpanContext::new(
+                TraceId::from(1),
+                SpanId::from(1),
+                TraceFlags::default().with_sampled(sampled),
+                false,
+                Default::default(),
+            ),
+            parent_span_id: SpanId::INVALID,
+            parent_span_is_remote: false,
+            span_kind: SpanKind::Internal,
+            name: "subgraph".into(),
+            start_time,
+            end_time: start_time + Duration::from_millis(20),
+            attributes: vec![KeyValue::new(
+                APOLLO_PRIVATE_FTV1,
+                encode_ftv1_trace(&trace),
+            )],
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: Status::Unset,
+            instrumentation_scope: Default::default(),
+        }
+    }
+
+    fn process(config: Config, span: SpanData) -> Vec<SpanData> {
+        let delegate = MockSpanProcessor::default();
+        let processor = ResolverSpanProcessor::new(
+            Config {
+                enabled: true,
+                ..config
+            },
+            vec![Box::new(delegate.clone())],
+        );
+        processor.on_end(span);
+        delegate.spans.lock().clone()
+    }
+
+    fn attribute(span: &SpanData, key: Key) -> String {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key == key)
+            .map(|kv| kv.value.to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn it_creates_resolver_spans_under_the_subgraph_span() {
+        let spans = process(Config::default(), subgraph_span(true));
+        let (subgraph, resolvers) = spans.split_last().unwrap();
+        assert_eq!(subgraph.name, "subgraph");
+        assert_eq!(
+            resolvers
+                .iter()
+                .map(|span| attribute(span, FIELD_PATH_KEY))
+                .collect::<Vec<_>>(),
+            [
+                "products",
+                "products.0.name",
+                "products.0.reviews",
+                "products.1.name"
+            ]
+        );
+
+        let products = &resolvers[0];
+        assert_eq!(products.name, RESOLVER_SPAN_NAME);
+        assert_eq!(products.parent_span_id, subgraph.span_context.span_id());
+        assert_eq!(products.span_context.trace_id(), TraceId::from(1));
+        assert_eq!(attribute(products, PARENT_TYPE_KEY), "Query");
+        assert_eq!(attribute(products, FIELD_NAME_KEY), "products");
+        // The 10ms trace is centered in the 20ms subgraph span
+        assert_eq!(
+            products.start_time,
+            subgraph.start_time + Duration::from_millis(6)
+        );
+        assert_eq!(
+            products.end_time,
+            subgraph.start_time + Duration::from_millis(7)
+        );
+
+        for span in &resolvers[1..] {
+            assert_eq!(span.parent_span_id, products.span_context.span_id());
+        }
+        assert_eq!(
+            resolvers[2].status,
+            Status::error("reviews are unavailable")
+        );
+    }
+
+    #[test]
+    fn it_limits_the_resolver_spans() {
+        let spans = process(
+            Config {
+                max_spans_per_fetch: 2,
+                ..Default::default()
+            },
+            subgraph_span(true),
+        );
+        assert_eq!(spans.len(), 3);
+        assert_eq!(attribute(&spans[0], FIELD_PATH_KEY), "products");
+        assert_eq!(attribute(&spans[1], FIELD_PATH_KEY), "products.0.name");
+
+        let spans = process(
+            Config {
+                min_duration: Some(Duration::from_millis(5)),
+                ..Default::default()
+            },
+            subgraph_span(true),
+        );
+        assert_eq!(spans.len(), 2);
+        assert_eq!(attribute(&spans[0], FIELD_PATH_KEY), "products.0.reviews");
+        // The parent resolver was skipped
+        assert_eq!(spans[0].parent_span_id, spans[1].span_context.span_id());
+    }
+
+    #[test]
+    fn it_ignores_unsampled_spans() {
+        let spans = process(Config::default(), subgraph_span(false));
+        assert_eq!(spans.len(), 1);
+    }
+}
@@ -0,0 +1,237 @@
+//! Rule-based head sampling
+//!
+//! The sampling rules are evaluated against the router request before the sampling decision of
+//! the root span is made. The ratio of the first matching rule is recorded on the root span, where
+//! the [`RuleBasedSampler`] reads it.
+use opentelemetry::Context;
+use opentelemetry::Key;
+use opentelemetry::KeyValue;
+use opentelemetry::Value;
+use opentelemetry::trace::Link;
+use opentelemetry::trace::SamplingResult;
+use opentelemetry::trace::SpanKind;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::trace::TraceId;
+use opentelemetry_sdk::trace::Sampler;
+use opentelemetry_sdk::trace::ShouldSample;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::Span;
+
+use crate::plugins::telemetry::config::SamplerOption;
+use crate::plugins::telemetry::config_new::conditions::Condition;
+use crate::plugins::telemetry::config_new::router::selectors::RouterSelector;
+use crate::plugins::telemetry::consts::REQUEST_SPAN_NAME;
+use crate::plugins::telemetry::consts::ROUTER_SPAN_NAME;
+use crate::plugins::telemetry::dynamic_attribute::SpanDynAttribute;
+use crate::services::router;
+
+pub(crate) const APOLLO_PRIVATE_SAMPLING_RATIO: Key =
+    Key::from_static_str("apollo_private.sampling.ratio");
+
+/// A sampler applied to the requests matching a condition
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SamplingRule {
+    /// The condition on the router request
+    pub(crate) condition: Condition<RouterSelector>,
+    /// The sampler of the matching requests, always_on, always_off or a decimal between 0.0 and 1.0
+    pub(crate) sampler: SamplerOption,
+}
+
+/// The sampling ratio of the first rule matching the request
+fn matching_ratio(rules: &[SamplingRule], request: &router::Request) -> Option<f64> {
+    rules
+        .iter()
+        .find(|rule| rule.condition.clone().evaluate_request(request) == Some(true))
+        .map(|rule| rule.sampler.ratio())
+}
+
+/// Records the sampling ratio of the first rule matching the request on the root span
+///
+/// This must happen before any child span is created, as the sampling decision of the root span
+/// is made when it is first needed.
+pub(crate) fn record_sampling_ratio(rules: &[SamplingRule], request: &router::Request) {
+    if rules.is_empty() {
+        return;
+    }
+    let span = Span::current();
+    if span
+        .metadata()
+        .is_none_or(|metadata| ![REQUEST_SPAN_NAME, ROUTER_SPAN_NAME].contains(&metadata.name()))
+    {
+        return;
+    }
+    if let Some(ratio) = matching_ratio(rules, request) {
+        span.set_span_dyn_attribute(APOLLO_PRIVATE_SAMPLING_RATIO, ratio.into());
+    }
+}
+
+/// Samples root spans at the ratio recorded by the matching sampling rule, and falls back to the
+/// configured sampler otherwise
+///
+/// When parent based sampling is enabled, the decision of the parent span is still honored.
+#[derive(Clone, Debug)]
+pub(crate) struct RuleBasedSampler {
+    fallback: Sampler,
+    parent_based: bool,
+}
+
+impl RuleBasedSampler {
+    /// `fallback` is the configured sampler, already parent based if `parent_based` is set
+    pub(crate) fn new(fallback: Sampler, parent_based: bool) -> Self {
+        Self {
+            fallback,
+            parent_based,
+        }
+    }
+}
+
+impl ShouldSample for RuleBasedSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&Context>,
+        trace_id: TraceId,
+        name: &str,
+        span_kind: &SpanKind,
+        attributes: &[KeyValue],
+        links: &[Link],
+    ) -> SamplingResult {
+        let ratio = attributes
+            .iter()
+            .find(|kv| kv.key == APOLLO_PRIVATE_SAMPLING_RATIO)
+            .and_then(|kv| match kv.value {
+                Value::F64(ratio) => Some(ratio),
+                _ => None,
+            });
+        let has_parent = parent_context.is_some_and(|cx| cx.has_active_span());
+        match ratio {
+            Some(ratio) if !(self.parent_based && has_parent) => Sampler::TraceIdRatioBased(ratio)
+                .should_sample(parent_context, trace_id, name, span_kind, attributes, links),
+            _ => self.fallback.should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::SamplingDecision;
+    use opentelemetry::trace::SpanContext;
+    use opentelemetry::trace::SpanId;
+    use opentelemetry::trace::TraceFlags;
+
+    use super::*;
+
+    fn rules() -> Vec<SamplingRule> {
+        serde_yaml::from_str(
+            r#"
+            - condition:
+                eq:
+                  - request_header: x-debug-trace
+                  - "true"
+              sampler: always_on
+            - condition:
+                eq:
+                  - request_header: x-apollo-operation-name
+                  - HealthCheck
+              sampler: 0.001
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn should_sample(
+        sampler: &RuleBasedSampler,
+        parent_context: &Context,
+        ratio: Option<f64>,
+    ) -> SamplingDecision {
+        let attributes: Vec<_> = ratio
+            .map(|ratio| KeyValue::new(APOLLO_PRIVATE_SAMPLING_RATIO, ratio))
+            .into_iter()
+            .collect();
+        sampler
+            .should_sample(
+                Some(parent_context),
+                TraceId::from(1),
+                ROUTER_SPAN_NAME,
+                &SpanKind::Server,
+                &attributes,
+                &[],
+            )
+            .decision
+    }
+
+    #[test]
+    fn it_matches_the_first_rule() {
+        let request = router::Request::fake_builder()
+            .header("x-debug-trace", "true")
+            .header("x-apollo-operation-name", "HealthCheck")
+            .build()
+            .unwrap();
+        assert_eq!(matching_ratio(&rules(), &request), Some(1.0));
+
+        let request = router::Request::fake_builder()
+            .header("x-apollo-operation-name", "HealthCheck")
+            .build()
+            .unwrap();
+        assert_eq!(matching_ratio(&rules(), &request), Some(0.001));
+
+        let request = router::Request::fake_builder()
+            .header("x-apollo-operation-name", "Checkout")
+            .build()
+            .unwrap();
+        assert_eq!(matching_ratio(&rules(), &request), None);
+    }
+
+    #[test]
+    fn it_samples_at_the_recorded_ratio() {
+        let sampler = RuleBasedSampler::new(Sampler::AlwaysOff, false);
+        let root = Context::new();
+        assert_eq!(
+            should_sample(&sampler, &root, Some(1.0)),
+            SamplingDecision::RecordAndSample
+        );
+        assert_eq!(should_sample(&sampler, &root, None), SamplingDecision::Drop);
+
+        let sampler = RuleBasedSampler::new(Sampler::AlwaysOn, false);
+        assert_eq!(
+            should_sample(&sampler, &root, Some(0.0)),
+            SamplingDecision::Drop
+        );
+        assert_eq!(
+            should_sample(&sampler, &root, None),
+            SamplingDecision::RecordAndSample
+        );
+    }
+
+    #[test]
+    fn it_honors_the_parent_decision() {
+        let parent = Context::new().with_remote_span_context(SpanContext::new(
+            TraceId::from(1),
+            SpanId::from(1),
+            TraceFlags::SAMPLED,
+            true,
+            Default::default(),
+        ));
+
+        let sampler =
+            RuleBasedSampler::new(Sampler::ParentBased(Box::new(Sampler::AlwaysOff)), true);
+        assert_eq!(
+            should_sample(&sampler, &parent, Some(0.0)),
+            SamplingDecision::RecordAndSample
+        );
+
+        let sampler = RuleBasedSampler::new(Sampler::AlwaysOn, false);
+        assert_eq!(
+            should_sample(&sampler, &parent, Some(0.0)),
+            SamplingDecision::Drop
+        );
+    }
+}
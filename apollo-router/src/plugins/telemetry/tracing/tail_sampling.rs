@@ -0,0 +1,659 @@
+//! Tail-based sampling of traces
+//!
+//! The spans of a trace are buffered until its local root span ends, then the whole trace is
+//! either forwarded to the exporters or dropped, depending on what happened during the request.
+use std::mem::size_of;
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use bytesize::ByteSize;
+use lru::LruCache;
+use opentelemetry::Context;
+use opentelemetry::Key;
+use opentelemetry::KeyValue;
+use opentelemetry::Value;
+use opentelemetry::trace::SpanId;
+use opentelemetry::trace::Status;
+use opentelemetry::trace::TraceId;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::Span;
+use opentelemetry_sdk::trace::SpanData;
+use opentelemetry_sdk::trace::SpanProcessor;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::plugins::telemetry::config::SamplerOption;
+use crate::plugins::telemetry::config_new::cost::APOLLO_PRIVATE_COST_RESULT;
+use crate::plugins::telemetry::tracing::apollo_telemetry::CLIENT_NAME_KEY;
+
+const OPERATION_NAME_KEY: Key = Key::from_static_str("graphql.operation.name");
+const COST_OK: &str = "COST_OK";
+
+/// Tail-based sampling configuration
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Config {
+    /// Buffer the spans of each trace until its root span ends, and only export the traces
+    /// matching the rules, or sampled at the base rate
+    pub(crate) enabled: bool,
+    /// The fraction of the traces not matching any rule that are exported, between 0.0 and 1.0
+    pub(crate) base_rate: f64,
+    /// The maximum memory used by buffered spans. The least recently active traces are dropped
+    /// when it is reached (default: 64MiB)
+    #[schemars(with = "String")]
+    pub(crate) max_buffer_size: ByteSize,
+    /// The number of recent trace decisions remembered, to forward or drop the spans ending
+    /// after the root span of their trace (default: 10000)
+    pub(crate) max_decisions: NonZeroUsize,
+    /// The traces that are always exported
+    pub(crate) rules: Rules,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            base_rate: 0.01,
+            max_buffer_size: ByteSize::mib(64),
+            max_decisions: NonZeroUsize::new(10_000).expect("not zero"),
+            rules: Rules::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Whether traces are matched on the client name of router spans
+    pub(crate) fn matches_clients(&self) -> bool {
+        self.enabled && !self.rules.client_names.is_empty()
+    }
+
+    /// Only the spans sampled at the head are buffered, so the rules miss the traces dropped by
+    /// a ratio sampler
+    pub(crate) fn warn_if_head_sampled(&self, sampler: &SamplerOption) {
+        if self.enabled && sampler.ratio() < 1.0 {
+            tracing::warn!(
+                "tail sampling only sees the traces sampled by the `sampler`, set it to `always_on` and use `base_rate` instead so that the tail sampling rules apply to every request"
+            );
+        }
+    }
+}
+
+/// Rules keeping a trace regardless of the base rate
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Rules {
+    /// Keep the traces containing a span with an error status
+    pub(crate) errors: bool,
+    /// Keep the traces whose root span lasted at least this long
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) latency_threshold: Option<Duration>,
+    /// Keep the traces of operations with these names
+    pub(crate) operation_names: Vec<String>,
+    /// Keep the traces of requests from clients with these names
+    pub(crate) client_names: Vec<String>,
+    /// Keep the traces of requests rejected by demand control
+    pub(crate) demand_control_rejections: bool,
+}
+
+/// What is known of a trace while its spans are buffered
+#[derive(Default)]
+struct BufferedTrace {
+    spans: Vec<SpanData>,
+    size: usize,
+    error: bool,
+    operation_name: Option<String>,
+    client_name: Option<String>,
+    demand_control_rejected: bool,
+}
+
+impl BufferedTrace {
+    fn push(&mut self, span: SpanData, size: usize) {
+        self.error |= matches!(span.status, Status::Error { .. });
+        for KeyValue { key, value, .. } in &span.attributes {
+            if *key == OPERATION_NAME_KEY {
+                self.operation_name = Some(value.as_str().into_owned());
+            } else if *key == CLIENT_NAME_KEY {
+                self.client_name = Some(value.as_str().into_owned());
+            } else if *key == APOLLO_PRIVATE_COST_RESULT {
+                self.demand_control_rejected = value.as_str() != COST_OK;
+            }
+        }
+        self.size += size;
+        self.spans.push(span);
+    }
+}
+
+struct State {
+    /// Traces waiting for their root span, least recently active first
+    traces: LruCache<TraceId, BufferedTrace>,
+    /// Whether recently decided traces were kept
+    decisions: LruCache<TraceId, bool>,
+    buffered_bytes: usize,
+}
+
+/// Buffers sampled spans by trace and forwards the traces matching the configured rules to the
+/// span processors of the exporters
+///
+/// Spans not sampled at the head are forwarded as they are, as some exporters process them.
+pub(crate) struct TailSamplingSpanProcessor {
+    config: Config,
+    delegates: Vec<Box<dyn SpanProcessor>>,
+    state: Mutex<State>,
+}
+
+impl std::fmt::Debug for TailSamplingSpanProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TailSamplingSpanProcessor")
+            .field("config", &self.config)
+            .field("delegates", &self.delegates)
+            .finish()
+    }
+}
+
+impl TailSamplingSpanProcessor {
+    pub(crate) fn new(config: Config, delegates: Vec<Box<dyn SpanProcessor>>) -> Self {
+        Self {
+            delegates,
+            state: Mutex::new(State {
+                traces: LruCache::unbounded(),
+                decisions: LruCache::new(config.max_decisions),
+                buffered_bytes: 0,
+            }),
+            config,
+        }
+    }
+
+    fn forward(&self, spans: Vec<SpanData>) {
+        for span in spans {
+            self.forward_span(span);
+        }
+    }
+
+    fn forward_span(&self, span: SpanData) {
+        if let Some((last, delegates)) = self.delegates.split_last() {
+            for delegate in delegates {
+                delegate.on_end(span.clone());
+            }
+            last.on_end(span);
+        }
+    }
+
+    /// The rule keeping this trace, if any
+    fn matching_rule(&self, trace: &BufferedTrace, root: &SpanData) -> Option<&'static str> {
+        let rules = &self.config.rules;
+        if rules.errors && trace.error {
+            return Some("error");
+        }
+        if let Some(threshold) = rules.latency_threshold
+            && root
+                .end_time
+                .duration_since(root.start_time)
+                .is_ok_and(|duration| duration >= threshold)
+        {
+            return Some("latency");
+        }
+        if trace
+            .operation_name
+            .as_ref()
+            .is_some_and(|name| rules.operation_names.contains(name))
+        {
+            return Some("operation");
+        }
+        if trace
+            .client_name
+            .as_ref()
+            .is_some_and(|name| rules.client_names.contains(name))
+        {
+            return Some("client");
+        }
+        if rules.demand_control_rejections && trace.demand_control_rejected {
+            return Some("demand_control");
+        }
+        if within_base_rate(root.span_context.trace_id(), self.config.base_rate) {
+            return Some("base_rate");
+        }
+        None
+    }
+
+    /// Drops the least recently active traces until the buffer fits in memory
+    fn evict(&self, state: &mut State) {
+        let max_size = self.config.max_buffer_size.as_u64() as usize;
+        let mut dropped_spans = 0;
+        while state.buffered_bytes > max_size {
+            let Some((trace_id, trace)) = state.traces.pop_lru() else {
+                break;
+            };
+            state.buffered_bytes -= trace.size;
+            state.decisions.put(trace_id, false);
+            dropped_spans += trace.spans.len() as u64;
+        }
+        if dropped_spans > 0 {
+            u64_counter!(
+                "apollo.router.telemetry.tail_sampling.dropped_spans",
+                "Spans dropped by the tail sampler before their trace was complete",
+                dropped_spans,
+                "reason" = "memory_limit"
+            );
+        }
+    }
+}
+
+impl SpanProcessor for TailSamplingSpanProcessor {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        for delegate in &self.delegates {
+            delegate.on_start(span, cx);
+        }
+    }
+
+    fn on_end(&self, span: SpanData) {
+        if !span.span_context.is_sampled() {
+            self.forward_span(span);
+            return;
+        }
+
+        let trace_id = span.span_context.trace_id();
+        let is_root = span.parent_span_id == SpanId::INVALID || span.parent_span_is_remote;
+        let mut state = self.state.lock();
+
+        // Spans ending after their root span follow the decision made for their trace
+        if let Some(keep) = state.decisions.get(&trace_id).copied() {
+            drop(state);
+            if keep {
+                self.forward_span(span);
+            }
+            return;
+        }
+
+        if !is_root {
+            let size = estimated_size(&span);
+            state.buffered_bytes += size;
+            state
+                .traces
+                .get_or_insert_mut(trace_id, Default::default)
+                .push(span, size);
+            self.evict(&mut state);
+            return;
+        }
+
+        let mut trace = state.traces.pop(&trace_id).unwrap_or_default();
+        state.buffered_bytes -= trace.size;
+        trace.push(span, 0);
+        let rule = self.matching_rule(
+            &trace,
+            trace.spans.last().expect("the root span was pushed"),
+        );
+        state.decisions.put(trace_id, rule.is_some());
+        drop(state);
+
+        u64_counter!(
+            "apollo.router.telemetry.tail_sampling.traces",
+            "Traces completed in the tail sampler",
+            1,
+            "sampling.decision" = if rule.is_some() { "keep" } else { "drop" },
+            "sampling.rule" = rule.unwrap_or("none")
+        );
+        if rule.is_some() {
+            self.forward(trace.spans);
+        }
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        for delegate in &self.delegates {
+            delegate.force_flush()?;
+        }
+        Ok(())
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        for delegate in &self.delegates {
+            delegate.shutdown_with_timeout(timeout)?;
+        }
+        Ok(())
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        for delegate in &mut self.delegates {
+            delegate.set_resource(resource);
+        }
+    }
+}
+
+/// Samples the same traces as a trace ID ratio based sampler
+fn within_base_rate(trace_id: TraceId, rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    let bytes = trace_id.to_bytes();
+    let low = u64::from_be_bytes(bytes[8..].try_into().expect("a trace ID has 16 bytes")) >> 1;
+    low < (rate.max(0.0) * (1u64 << 63) as f64) as u64
+}
+
+/// An approximation of the memory held by a span
+fn estimated_size(span: &SpanData) -> usize {
+    fn attributes_size(attributes: &[KeyValue]) -> usize {
+        attributes
+            .iter()
+            .map(|KeyValue { key, value, .. }| {
+                size_of::<KeyValue>()
+                    + key.as_str().len()
+                    + match value {
+                        Value::String(_) | Value::Array(_) => value.as_str().len(),
+                        _ => 0,
+                    }
+            })
+            .sum()
+    }
+
+    size_of::<SpanData>()
+        + span.name.len()
+        + attributes_size(&span.attributes)
+        + span
+            .events
+            .iter()
+            .map(|event| event.name.len() + attributes_size(&event.attributes))
+            .sum::<usize>()
+        + span
+            .links
+            .iter()
+            .map(|link| attributes_size(&link.attributes))
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use opentelemetry::trace::SpanContext;
+    use opentelemetry::trace::SpanKind;
+    use opentelemetry::trace::TraceFlags;
+    use opentelemetry_sdk::trace::SpanEvents;
+    use opentelemetry_sdk::trace::SpanLinks;
+
+    use super::*;
+    use crate::metrics::FutureMetricsExt;
+
+    #[derive(Debug, Clone, Default)]
+    struct MockSpanProcessor {
+        spans: Arc<Mutex<Vec<SpanData>>>,
+    }
+
+    impl SpanProcessor for MockSpanProcessor {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.spans.lock().push(span);
+        }
+
+        fn force_flush(&self) -> OTelSdkResult {
+            Ok(())
+        }
+
+        fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
+            Ok(())
+        }
+    }
+
+    fn span(trace_id: u128, span_id: u64, parent_span_id: u64, sampled: bool) -> SpanData {
+        let start_time = SystemTime::now();
+        SpanData {
+            span_context: SpanContext::new(
+                TraceId::from(trace_id),
+                SpanId::from(span_id),
+                TraceFlags::default().with_sampled(sampled),
+                false,
+                Default::default(),
+            ),
+            parent_span_id: SpanId::from(parent_span_id),
+            parent_span_is_remote: false,
+            span_kind: SpanKind::Internal,
+            name: "span".into(),
+            start_time,
+            end_time: start_time + Duration::from_millis(10),
+            attributes: Vec::new(),
+            dropped_attributes_count: 0,
+            events: SpanEvents::default(),
+            links: SpanLinks::default(),
+            status: Status::Unset,
+            instrumentation_scope: Default::default(),
+        }
+    }
+
+    fn processor(config: Config) -> (TailSamplingSpanProcessor, MockSpanProcessor) {
+        let delegate = MockSpanProcessor::default();
+        let processor = TailSamplingSpanProcessor::new(
+            Config {
+                enabled: true,
+                ..config
+            },
+            vec![Box::new(delegate.clone())],
+        );
+        (processor, delegate)
+    }
+
+    fn exported_traces(delegate: &MockSpanProcessor) -> Vec<TraceId> {
+        let mut traces: Vec<_> = delegate
+            .spans
+            .lock()
+            .iter()
+            .map(|span| span.span_context.trace_id())
+            .collect();
+        traces.dedup();
+        traces
+    }
+
+    #[tokio::test]
+    async fn it_keeps_traces_matching_rules() {
+        async {
+            let (processor, delegate) = processor(Config {
+                base_rate: 0.0,
+                rules: Rules {
+                    errors: true,
+                    latency_threshold: Some(Duration::from_secs(1)),
+                    operation_names: vec!["Checkout".to_string()],
+                    client_names: vec!["ios".to_string()],
+                    demand_control_rejections: true,
+                },
+                ..Default::default()
+            });
+
+            // an error in a child span
+            let mut child = span(1, 2, 1, true);
+            child.status = Status::error("subgraph failed");
+            processor.on_end(child);
+            processor.on_end(span(1, 1, 0, true));
+
+            // a slow root span
+            let mut root = span(2, 1, 0, true);
+            root.end_time = root.start_time + Duration::from_secs(2);
+            processor.on_end(root);
+
+            // an operation name
+            let mut child = span(3, 2, 1, true);
+            child
+                .attributes
+                .push(KeyValue::new(OPERATION_NAME_KEY, "Checkout"));
+            processor.on_end(child);
+            processor.on_end(span(3, 1, 0, true));
+
+            // a client name
+            let mut root = span(4, 1, 0, true);
+            root.attributes.push(KeyValue::new(CLIENT_NAME_KEY, "ios"));
+            processor.on_end(root);
+
+            // a demand control rejection
+            let mut child = span(5, 2, 1, true);
+            child.attributes.push(KeyValue::new(
+                APOLLO_PRIVATE_COST_RESULT,
+                "COST_ESTIMATED_TOO_EXPENSIVE",
+            ));
+            processor.on_end(child);
+            processor.on_end(span(5, 1, 0, true));
+
+            // nothing of interest
+            let mut child = span(6, 2, 1, true);
+            child
+                .attributes
+                .push(KeyValue::new(APOLLO_PRIVATE_COST_RESULT, COST_OK));
+            processor.on_end(child);
+            processor.on_end(span(6, 1, 0, true));
+
+            assert_eq!(
+                exported_traces(&delegate),
+                (1..=5).map(TraceId::from).collect::<Vec<_>>()
+            );
+            assert_eq!(delegate.spans.lock().len(), 8);
+            assert_eq!(processor.state.lock().buffered_bytes, 0);
+
+            assert_counter!(
+                "apollo.router.telemetry.tail_sampling.traces",
+                1,
+                "sampling.decision" = "keep",
+                "sampling.rule" = "error"
+            );
+            assert_counter!(
+                "apollo.router.telemetry.tail_sampling.traces",
+                1,
+                "sampling.decision" = "keep",
+                "sampling.rule" = "demand_control"
+            );
+            assert_counter!(
+                "apollo.router.telemetry.tail_sampling.traces",
+                1,
+                "sampling.decision" = "drop",
+                "sampling.rule" = "none"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[test]
+    fn it_follows_the_decision_for_late_spans() {
+        let (processor, delegate) = processor(Config {
+            base_rate: 0.0,
+            rules: Rules {
+                errors: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let mut root = span(1, 1, 0, true);
+        root.status = Status::error("failed");
+        processor.on_end(root);
+        processor.on_end(span(1, 2, 1, true));
+        processor.on_end(span(2, 1, 0, true));
+        processor.on_end(span(2, 2, 1, true));
+
+        assert_eq!(exported_traces(&delegate), vec![TraceId::from(1)]);
+        assert_eq!(delegate.spans.lock().len(), 2);
+        assert!(processor.state.lock().traces.is_empty());
+    }
+
+    #[test]
+    fn it_remembers_a_limited_number_of_decisions() {
+        let (processor, delegate) = processor(Config {
+            base_rate: 0.0,
+            max_decisions: NonZeroUsize::new(1).unwrap(),
+            rules: Rules {
+                errors: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let mut root = span(1, 1, 0, true);
+        root.status = Status::error("failed");
+        processor.on_end(root);
+        processor.on_end(span(2, 1, 0, true));
+        // the decision for the first trace was evicted
+        processor.on_end(span(1, 2, 1, true));
+
+        assert_eq!(delegate.spans.lock().len(), 1);
+    }
+
+    #[test]
+    fn it_warns_when_the_head_sampler_drops_traces() {
+        use crate::test_harness::tracing_test;
+        let _guard = tracing_test::dispatcher_guard();
+        let config = Config {
+            enabled: true,
+            ..Default::default()
+        };
+
+        config.warn_if_head_sampled(&SamplerOption::TraceIdRatioBased(1.0));
+        assert!(!tracing_test::logs_contain(
+            "tail sampling only sees the traces sampled by the `sampler`"
+        ));
+        config.warn_if_head_sampled(&SamplerOption::TraceIdRatioBased(0.1));
+        assert!(tracing_test::logs_contain(
+            "tail sampling only sees the traces sampled by the `sampler`"
+        ));
+    }
+
+    #[test]
+    fn it_forwards_spans_not_sampled_at_the_head() {
+        let (processor, delegate) = processor(Config {
+            base_rate: 0.0,
+            ..Default::default()
+        });
+
+        processor.on_end(span(1, 2, 1, false));
+        processor.on_end(span(1, 1, 0, false));
+
+        assert_eq!(delegate.spans.lock().len(), 2);
+    }
+
+    #[test]
+    fn it_samples_at_the_base_rate() {
+        let (processor, delegate) = processor(Config {
+            base_rate: 0.5,
+            ..Default::default()
+        });
+
+        for trace_id in 0..1000u128 {
+            let trace_id = (trace_id << 64) | (trace_id * 0x9e37_79b9_7f4a_7c15) as u64 as u128;
+            processor.on_end(span(trace_id, 1, 0, true));
+        }
+
+        let exported = delegate.spans.lock().len();
+        assert!((400..600).contains(&exported), "exported {exported} traces");
+    }
+
+    #[tokio::test]
+    async fn it_drops_traces_over_the_memory_limit() {
+        async {
+            let (processor, delegate) = processor(Config {
+                base_rate: 1.0,
+                max_buffer_size: ByteSize::b(estimated_size(&span(1, 2, 1, true)) as u64 * 3),
+                ..Default::default()
+            });
+
+            // the first trace is the least recently active one once the buffer is full
+            processor.on_end(span(1, 2, 1, true));
+            processor.on_end(span(2, 2, 1, true));
+            processor.on_end(span(2, 3, 1, true));
+            processor.on_end(span(3, 2, 1, true));
+            processor.on_end(span(1, 1, 0, true));
+            processor.on_end(span(2, 1, 0, true));
+            processor.on_end(span(3, 1, 0, true));
+
+            assert_eq!(
+                exported_traces(&delegate),
+                vec![TraceId::from(2), TraceId::from(3)]
+            );
+            assert_eq!(processor.state.lock().buffered_bytes, 0);
+            assert_counter!(
+                "apollo.router.telemetry.tail_sampling.dropped_spans",
+                1,
+                "reason" = "memory_limit"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+}
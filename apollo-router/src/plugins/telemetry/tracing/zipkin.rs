@@ -90,7 +90,7 @@ impl TracingConfigurator for Config {
             .build()?;
 
         let named_exporter = NamedSpanExporter::new(exporter, "zipkin");
-        builder.with_span_processor(
+        builder.with_exporter_span_processor(
             BatchSpanProcessor::builder(named_exporter, NamedTokioRuntime::new("zipkin-tracing"))
                 .with_batch_config(self.batch_processor.clone().with_env_overrides()?.into())
                 .build()
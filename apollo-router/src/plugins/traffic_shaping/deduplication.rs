@@ -1,38 +1,67 @@
-//! De-duplicate subgraph requests in flight. Implemented as a tower Layer.
+//! De-duplicate subgraph requests in flight, and batch `_entities` fetches of concurrent client
+//! requests. Implemented as a tower Layer.
 //!
 //! See [`Layer`] and [`tower::Service`] for more details.
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::task::Poll;
+use std::time::Duration;
 
 use futures::future::BoxFuture;
 use futures::lock::Mutex;
+use serde_json_bytes::Value;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::broadcast::{self};
 use tokio::sync::oneshot;
 use tower::BoxError;
 use tower::Layer;
+use tower::ServiceExt;
+use tracing::Instrument;
+use tracing::Span;
 
+use crate::Context;
 use crate::batching::BatchQuery;
+use crate::graphql;
 use crate::graphql::Request;
 use crate::http_ext;
+use crate::json_ext::Path;
+use crate::json_ext::PathElement;
 use crate::plugins::authorization::CacheKeyMetadata;
 use crate::query_planner::fetch::OperationKind;
 use crate::services::SubgraphRequest;
 use crate::services::SubgraphResponse;
 
-#[derive(Default)]
-pub(crate) struct QueryDeduplicationLayer;
+const REPRESENTATIONS: &str = "representations";
+const ENTITIES: &str = "_entities";
+
+pub(crate) struct QueryDeduplicationLayer {
+    deduplicate: bool,
+    entity_batching_window: Option<Duration>,
+}
+
+impl QueryDeduplicationLayer {
+    /// Returns `None` if neither deduplication nor entity batching are enabled.
+    pub(crate) fn new(deduplicate: bool, entity_batching_window: Option<Duration>) -> Option<Self> {
+        (deduplicate || entity_batching_window.is_some()).then_some(Self {
+            deduplicate,
+            entity_batching_window,
+        })
+    }
+}
 
 impl<S> Layer<S> for QueryDeduplicationLayer
 where
-    S: tower::Service<SubgraphRequest, Response = SubgraphResponse, Error = BoxError> + Clone,
+    S: tower::Service<SubgraphRequest, Response = SubgraphResponse, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    <S as tower::Service<SubgraphRequest>>::Future: Send + 'static,
 {
     type Service = QueryDeduplicationService<S>;
 
     fn layer(&self, service: S) -> Self::Service {
-        QueryDeduplicationService::new(service)
+        QueryDeduplicationService::new(service, self.deduplicate, self.entity_batching_window)
     }
 }
 
@@ -40,6 +69,25 @@ type CacheKey = (http_ext::Request<Request>, Arc<CacheKeyMetadata>);
 
 type WaitMap = Arc<Mutex<HashMap<CacheKey, Sender<Result<CloneSubgraphResponse, String>>>>>;
 
+/// `_entities` fetches waiting for the end of their batching window, by request without
+/// representations
+type BatchMap = Arc<parking_lot::Mutex<HashMap<CacheKey, PendingBatch>>>;
+
+type BatchResult = Result<http::Response<graphql::Response>, String>;
+
+/// The first request of a batch, used to send the merged request, and the requests of the batch
+struct PendingBatch {
+    request: SubgraphRequest,
+    members: Vec<BatchMember>,
+}
+
+struct BatchMember {
+    representations: Vec<Value>,
+    context: Context,
+    span: Span,
+    sender: oneshot::Sender<BatchResult>,
+}
+
 struct CloneSubgraphResponse(SubgraphResponse);
 
 impl Clone for CloneSubgraphResponse {
@@ -56,17 +104,169 @@ impl Clone for CloneSubgraphResponse {
 #[derive(Clone)]
 pub(crate) struct QueryDeduplicationService<S: Clone> {
     service: S,
+    deduplicate: bool,
     wait_map: WaitMap,
+    entity_batching_window: Option<Duration>,
+    batch_map: BatchMap,
 }
 
 impl<S> QueryDeduplicationService<S>
 where
-    S: tower::Service<SubgraphRequest, Response = SubgraphResponse, Error = BoxError> + Clone,
+    S: tower::Service<SubgraphRequest, Response = SubgraphResponse, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    <S as tower::Service<SubgraphRequest>>::Future: Send + 'static,
 {
-    fn new(service: S) -> Self {
+    fn new(service: S, deduplicate: bool, entity_batching_window: Option<Duration>) -> Self {
         QueryDeduplicationService {
             service,
+            deduplicate,
             wait_map: Arc::new(Mutex::new(HashMap::new())),
+            entity_batching_window,
+            batch_map: Default::default(),
+        }
+    }
+
+    /// Adds an `_entities` fetch to the batch of compatible fetches, which is sent when the
+    /// batching window of its first fetch ends.
+    ///
+    /// Fetches are compatible if they only differ by their representations: as the cache key
+    /// contains the headers, only fetches with the same headers are merged. The response of the
+    /// merged fetch is split back by representation.
+    async fn batch(
+        service: S,
+        batch_map: BatchMap,
+        window: Duration,
+        mut request: SubgraphRequest,
+    ) -> Result<SubgraphResponse, BoxError> {
+        let representations = match request
+            .subgraph_request
+            .body_mut()
+            .variables
+            .remove(REPRESENTATIONS)
+        {
+            Some(Value::Array(representations)) => representations,
+            Some(representations) => {
+                request
+                    .subgraph_request
+                    .body_mut()
+                    .variables
+                    .insert(REPRESENTATIONS, representations);
+                return service.oneshot(request).await;
+            }
+            None => return service.oneshot(request).await,
+        };
+        let context = request.context.clone();
+        let subgraph_name = request.subgraph_name.clone();
+        let id = request.id.clone();
+        let cache_key: CacheKey = (
+            (&request.subgraph_request).into(),
+            request.authorization.clone(),
+        );
+
+        let (sender, receiver) = oneshot::channel();
+        let member = BatchMember {
+            representations,
+            context: context.clone(),
+            span: Span::current(),
+            sender,
+        };
+        {
+            let mut locked_batch_map = batch_map.lock();
+            match locked_batch_map.get_mut(&cache_key) {
+                Some(batch) => batch.members.push(member),
+                None => {
+                    locked_batch_map.insert(
+                        cache_key.clone(),
+                        PendingBatch {
+                            request,
+                            members: vec![member],
+                        },
+                    );
+                    let batch_map = batch_map.clone();
+                    tokio::task::spawn(async move {
+                        tokio::time::sleep(window).await;
+                        let batch = batch_map.lock().remove(&cache_key);
+                        if let Some(batch) = batch {
+                            Self::send_batch(service, batch).await;
+                        }
+                    });
+                }
+            }
+        }
+
+        match receiver.await {
+            Ok(Ok(response)) => Ok(SubgraphResponse::new_from_response(
+                response,
+                context,
+                subgraph_name,
+                id,
+            )),
+            Ok(Err(error)) => Err(error.into()),
+            Err(_) => Err("the batched entity fetch was cancelled".into()),
+        }
+    }
+
+    /// Sends the merged request of a batch, with the context of its first request. The context
+    /// entries written while it is sent are copied to the context of every request of the batch,
+    /// and it is traced in its own span, linked to the span of each request.
+    async fn send_batch(service: S, batch: PendingBatch) {
+        let PendingBatch {
+            mut request,
+            members,
+        } = batch;
+        let span = tracing::info_span!("entity_batch", "entity_batch.size" = members.len());
+        let mut senders = Vec::with_capacity(members.len());
+        let mut representations = Vec::new();
+        for member in members {
+            span.follows_from(&member.span);
+            let start = representations.len();
+            representations.extend(member.representations);
+            senders.push((start..representations.len(), member.context, member.sender));
+        }
+        let representation_count = representations.len();
+        request
+            .subgraph_request
+            .body_mut()
+            .variables
+            .insert(REPRESENTATIONS, Value::Array(representations));
+        let batch_context = request.context.clone();
+        let initial_context = Context::new();
+        initial_context.extend(&batch_context);
+
+        let result = service.oneshot(request).instrument(span).await;
+
+        // the first request of the batch shares the context of the merged request, so the entries
+        // are collected before being inserted
+        let written: Vec<(String, Value)> = batch_context
+            .iter()
+            .filter(|entry| {
+                initial_context.get_json_value(entry.key()).as_ref() != Some(entry.value())
+            })
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        for (_, context, _) in &senders {
+            for (key, value) in &written {
+                context.insert_json_value(key.clone(), value.clone());
+            }
+        }
+        match result {
+            Ok(response) => {
+                for (range, _, sender) in senders {
+                    let _ = sender.send(Ok(split_entities_response(
+                        &response.response,
+                        range,
+                        representation_count,
+                    )));
+                }
+            }
+            Err(error) => {
+                let error = error.to_string();
+                for (_, _, sender) in senders {
+                    let _ = sender.send(Err(error.clone()));
+                }
+            }
         }
     }
 
@@ -192,7 +392,13 @@ where
         let service = self.service.clone();
         let mut inner = std::mem::replace(&mut self.service, service);
 
-        if request.operation_kind == OperationKind::Query {
+        if let Some(window) = self.entity_batching_window
+            && is_batchable_entity_fetch(&request)
+        {
+            let batch_map = self.batch_map.clone();
+
+            Box::pin(async move { Self::batch(inner, batch_map, window, request).await })
+        } else if self.deduplicate && request.operation_kind == OperationKind::Query {
             let wait_map = self.wait_map.clone();
 
             Box::pin(async move { Self::dedup(inner, wait_map, request).await })
@@ -202,6 +408,64 @@ where
     }
 }
 
+/// Whether the request is an `_entities` fetch that can be merged with fetches of other client
+/// requests. Requests that are part of a client batch are never merged, as this would break their
+/// batch.
+fn is_batchable_entity_fetch(request: &SubgraphRequest) -> bool {
+    let body = request.subgraph_request.body();
+    request.operation_kind == OperationKind::Query
+        && body.variables.contains_key(REPRESENTATIONS)
+        && body
+            .query
+            .as_ref()
+            .is_some_and(|query| query.contains(ENTITIES))
+        && !request.is_part_of_batch()
+}
+
+/// The response of the merged fetch for the representations in `range`.
+///
+/// Errors located in `_entities` are only kept for their entities, with their path rebased on the
+/// representations of the request. Other errors are kept for every request of the batch.
+fn split_entities_response(
+    response: &http::Response<graphql::Response>,
+    range: std::ops::Range<usize>,
+    representation_count: usize,
+) -> http::Response<graphql::Response> {
+    let mut split = http_ext::Response::from(response).inner;
+    let body = split.body_mut();
+
+    if let Some(Value::Array(entities)) = body
+        .data
+        .as_mut()
+        .and_then(|data| data.as_object_mut())
+        .and_then(|data| data.get_mut(ENTITIES))
+    {
+        // a subgraph returning the wrong number of entities is reported to every request by the
+        // fetch, which checks the number of entities against its representations
+        if entities.len() == representation_count {
+            *entities = entities.drain(range.clone()).collect();
+        }
+    }
+
+    body.errors.retain_mut(|error| {
+        let Some(Path(elements)) = &mut error.path else {
+            return true;
+        };
+        match elements.as_mut_slice() {
+            [PathElement::Key(key, _), PathElement::Index(index), ..] if key == ENTITIES => {
+                if range.contains(index) {
+                    *index -= range.start;
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => true,
+        }
+    });
+    split
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -210,13 +474,12 @@ mod tests {
     use std::sync::atomic::Ordering;
     use std::time::Duration;
 
+    use serde_json_bytes::json;
     use tower::Service;
     use tower::ServiceExt;
 
-    use super::QueryDeduplicationService;
+    use super::*;
     use crate::plugin::test::MockSubgraphService;
-    use crate::services::SubgraphRequest;
-    use crate::services::SubgraphResponse;
 
     // Testing strategy:
     //  - We make our subgraph invocations slow (100ms) to increase our chance of a positive dedup
@@ -280,7 +543,7 @@ mod tests {
                     .build())
             });
 
-        let mut svc = QueryDeduplicationService::new(mock);
+        let mut svc = QueryDeduplicationService::new(mock, true, None);
 
         let request = SubgraphRequest::fake_builder().build();
 
@@ -301,4 +564,161 @@ mod tests {
 
         assert_eq!(1, inner_invocation_count.load(Ordering::Relaxed));
     }
+
+    fn entity_fetch(representations: Value) -> SubgraphRequest {
+        SubgraphRequest::fake_builder()
+            .subgraph_request(
+                http::Request::builder()
+                    .body(
+                        Request::fake_builder()
+                            .query("query($representations: [_Any!]!) { _entities(representations: $representations) { ... on User { name } } }")
+                            .variables(json!({ "representations": representations }).as_object().unwrap().clone())
+                            .build(),
+                    )
+                    .unwrap(),
+            )
+            .build()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_entity_batching_merges_concurrent_fetches() {
+        let inner_invocation_count = Arc::new(AtomicU8::new(0));
+        let inner_invocation_count_1 = inner_invocation_count.clone();
+        let inner = tower::service_fn(move |request: SubgraphRequest| {
+            inner_invocation_count_1.fetch_add(1, Ordering::Relaxed);
+            async move {
+                // answer each representation with its id as name
+                let entities: Vec<Value> = request.subgraph_request.body().variables
+                    [REPRESENTATIONS]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|representation| json!({ "name": representation["id"] }))
+                    .collect();
+                Ok::<_, BoxError>(
+                    SubgraphResponse::fake_builder()
+                        .data(json!({ "_entities": entities }))
+                        .error(
+                            graphql::Error::builder()
+                                .message("cannot resolve user 3")
+                                .path(Path::from("_entities/2/name"))
+                                .build(),
+                        )
+                        .context(request.context)
+                        .build(),
+                )
+            }
+        });
+        let mut svc = QueryDeduplicationService::new(inner, false, Some(Duration::from_millis(50)));
+
+        let fut1 = svc.ready().await.unwrap().call(entity_fetch(
+            json!([{ "__typename": "User", "id": "1" }, { "__typename": "User", "id": "2" }]),
+        ));
+        let fut2 = svc
+            .ready()
+            .await
+            .unwrap()
+            .call(entity_fetch(json!([{ "__typename": "User", "id": "3" }])));
+        let (res1, res2) = tokio::join!(fut1, fut2);
+        let res1 = res1.unwrap().response.into_body();
+        let res2 = res2.unwrap().response.into_body();
+
+        assert_eq!(1, inner_invocation_count.load(Ordering::Relaxed));
+        assert_eq!(
+            res1.data,
+            Some(json!({ "_entities": [{ "name": "1" }, { "name": "2" }] }))
+        );
+        assert!(res1.errors.is_empty());
+        assert_eq!(res2.data, Some(json!({ "_entities": [{ "name": "3" }] })));
+        assert_eq!(res2.errors.len(), 1);
+        assert_eq!(res2.errors[0].path, Some(Path::from("_entities/0/name")));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_entity_batching_only_merges_fetches_with_the_same_headers() {
+        let inner_invocation_count = Arc::new(AtomicU8::new(0));
+        let inner_invocation_count_1 = inner_invocation_count.clone();
+        let inner = tower::service_fn(move |request: SubgraphRequest| {
+            inner_invocation_count_1.fetch_add(1, Ordering::Relaxed);
+            async move {
+                let entities = request.subgraph_request.body().variables[REPRESENTATIONS].clone();
+                request
+                    .context
+                    .insert(
+                        "fetched_by",
+                        request.subgraph_request.headers()["x-user"]
+                            .to_str()
+                            .unwrap()
+                            .to_string(),
+                    )
+                    .unwrap();
+                Ok::<_, BoxError>(
+                    SubgraphResponse::fake_builder()
+                        .data(json!({ "_entities": entities }))
+                        .context(request.context)
+                        .build(),
+                )
+            }
+        });
+        let mut svc = QueryDeduplicationService::new(inner, false, Some(Duration::from_millis(50)));
+
+        let fetch = |user: &str, id: &str| {
+            let mut request = entity_fetch(json!([{ "__typename": "User", "id": id }]));
+            request
+                .subgraph_request
+                .headers_mut()
+                .insert("x-user", user.parse().unwrap());
+            request
+        };
+        let (alice_1, alice_2, bob) = (fetch("alice", "1"), fetch("alice", "2"), fetch("bob", "3"));
+        let (alice_2_context, bob_context) = (alice_2.context.clone(), bob.context.clone());
+        let fut1 = svc.ready().await.unwrap().call(alice_1);
+        let fut2 = svc.ready().await.unwrap().call(alice_2);
+        let fut3 = svc.ready().await.unwrap().call(bob);
+        let (res1, res2, res3) = tokio::join!(fut1, fut2, fut3);
+
+        assert_eq!(2, inner_invocation_count.load(Ordering::Relaxed));
+        assert_eq!(
+            res1.unwrap().response.into_body().data,
+            Some(json!({ "_entities": [{ "__typename": "User", "id": "1" }] }))
+        );
+        assert_eq!(
+            res2.unwrap().response.into_body().data,
+            Some(json!({ "_entities": [{ "__typename": "User", "id": "2" }] }))
+        );
+        assert_eq!(
+            res3.unwrap().response.into_body().data,
+            Some(json!({ "_entities": [{ "__typename": "User", "id": "3" }] }))
+        );
+        // the context entries written by the merged fetch reach every request of the batch
+        assert_eq!(
+            alice_2_context.get::<_, String>("fetched_by").unwrap(),
+            Some("alice".to_string())
+        );
+        assert_eq!(
+            bob_context.get::<_, String>("fetched_by").unwrap(),
+            Some("bob".to_string())
+        );
+    }
+
+    #[test]
+    fn test_split_entities_response_keeps_non_entity_errors() {
+        let response = http::Response::new(
+            graphql::Response::builder()
+                .data(json!({ "_entities": [{ "name": "1" }, { "name": "2" }] }))
+                .error(graphql::Error::builder().message("subgraph error").build())
+                .build(),
+        );
+
+        let split = split_entities_response(&response, 1..2, 2).into_body();
+        assert_eq!(split.data, Some(json!({ "_entities": [{ "name": "2" }] })));
+        assert_eq!(split.errors.len(), 1);
+
+        // the number of entities does not match the representations, so the fetch reports it
+        let split = split_entities_response(&response, 1..2, 3).into_body();
+        assert_eq!(
+            split.data,
+            Some(json!({ "_entities": [{ "name": "1" }, { "name": "2" }] }))
+        );
+    }
 }
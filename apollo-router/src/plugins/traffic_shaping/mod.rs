@@ -59,6 +59,9 @@ trait Merge {
 struct Shaping {
     /// Enable query deduplication
     deduplicate_query: Option<bool>,
+    /// Merge compatible `_entities` fetches of concurrent client requests into a single subgraph
+    /// request
+    experimental_entity_batching: Option<EntityBatchingConfig>,
     /// Enable compression for subgraphs (available compressions are deflate, br, gzip)
     compression: Option<Compression>,
     /// Enable global rate limiting
@@ -90,6 +93,17 @@ struct Shaping {
     experimental_http2_keep_alive_timeout: Option<Duration>,
 }
 
+/// Cross-request entity batching options
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct EntityBatchingConfig {
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How long the first `_entities` fetch of a batch waits for compatible fetches before the
+    /// batch is sent
+    window: Duration,
+}
+
 #[derive(PartialEq, Default, Debug, Clone, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum Http2Config {
@@ -108,6 +122,11 @@ impl Merge for Shaping {
             None => self.clone(),
             Some(fallback) => Shaping {
                 deduplicate_query: self.deduplicate_query.or(fallback.deduplicate_query),
+                experimental_entity_batching: self
+                    .experimental_entity_batching
+                    .as_ref()
+                    .or(fallback.experimental_entity_batching.as_ref())
+                    .cloned(),
                 compression: self.compression.or(fallback.compression),
                 timeout: self.timeout.or(fallback.timeout),
                 global_rate_limit: self
@@ -463,13 +482,14 @@ impl PluginPrivate for TrafficShaping {
                     config.shaping.timeout.unwrap_or(DEFAULT_TIMEOUT),
                 ))
                 .option_layer(rate_limit)
-                .option_layer(
+                .option_layer(QueryDeduplicationLayer::new(
+                    config.shaping.deduplicate_query.unwrap_or_default(),
                     config
                         .shaping
-                        .deduplicate_query
-                        .unwrap_or_default()
-                        .then(QueryDeduplicationLayer::default),
-                )
+                        .experimental_entity_batching
+                        .as_ref()
+                        .map(|entity_batching| entity_batching.window),
+                ))
                 .map_request(move |mut req: SubgraphRequest| {
                     if let Some(compression) = config.shaping.compression {
                         let compression_header_val = HeaderValue::from_str(&compression.to_string()).expect("compression is manually implemented and already have the right values; qed");
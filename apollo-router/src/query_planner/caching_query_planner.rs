@@ -10,6 +10,8 @@ use futures::future::BoxFuture;
 use indexmap::IndexMap;
 use query_planner::QueryPlannerPlugin;
 use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serde::Serialize;
 use sha2::Digest;
 use sha2::Sha256;
 use tokio_util::time::FutureExt;
@@ -41,6 +43,7 @@ use crate::plugins::limits;
 use crate::plugins::progressive_override::LABELS_TO_OVERRIDE_KEY;
 use crate::plugins::telemetry::utils::Timer;
 use crate::query_planner::QueryPlannerService;
+use crate::query_planner::disk_cache::DiskCache;
 use crate::query_planner::fetch::SubgraphSchemas;
 use crate::services::QueryPlannerContent;
 use crate::services::QueryPlannerRequest;
@@ -88,10 +91,10 @@ pub(crate) type InMemoryCachePlanner =
 pub(crate) const APOLLO_OPERATION_ID: &str = "apollo::supergraph::operation_id";
 
 /// Hashed value of query planner configuration for use in cache keys.
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 // XXX(@goto-bus-stop): I think this probably should not be pub(crate), but right now all fields in
 // the cache keys are pub(crate), which I'm not going to change at this time :)
-pub(crate) struct ConfigModeHash(Vec<u8>);
+pub(crate) struct ConfigModeHash(#[serde(with = "hex")] pub(super) Vec<u8>);
 
 impl std::fmt::Display for ConfigModeHash {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -127,6 +130,7 @@ pub(crate) struct CachingQueryPlanner<T: Clone> {
     config_mode_hash: Arc<ConfigModeHash>,
     cooperative_cancellation: CooperativeCancellation,
     config_limits: limits::Config,
+    disk_cache: Option<Arc<DiskCache>>,
 }
 
 fn init_query_plan_from_redis(
@@ -179,6 +183,19 @@ where
             .query_planning
             .experimental_cooperative_cancellation
             .clone();
+        let disk_cache = configuration
+            .supergraph
+            .query_planning
+            .cache
+            .disk
+            .as_ref()
+            .map(|config| {
+                Arc::new(DiskCache::new(
+                    config,
+                    schema.schema_id.clone(),
+                    config_mode_hash.clone(),
+                ))
+            });
 
         Ok(Self {
             cache,
@@ -190,6 +207,7 @@ where
             cooperative_cancellation,
             config_mode_hash,
             config_limits: configuration.limits.clone(),
+            disk_cache,
         })
     }
 
@@ -197,6 +215,7 @@ where
         self.cache.in_memory_cache()
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn warm_up(
         &mut self,
         query_analysis: &QueryAnalysisLayer,
@@ -205,6 +224,7 @@ where
         count: Option<usize>,
         experimental_reuse_query_plans: bool,
         experimental_pql_prewarm: &PersistedQueriesPrewarmQueryPlanCache,
+        bundled_operations: Vec<(String, Option<String>)>,
     ) {
         let _timer = Timer::new(|duration| {
             f64_histogram!(
@@ -214,6 +234,11 @@ where
             );
         });
 
+        // without a previous cache, the router just started and can reuse plans written to disk
+        if previous_cache.is_none() {
+            self.load_from_disk().await;
+        }
+
         let mut service = ServiceBuilder::new().service(
             self.plugins
                 .iter()
@@ -267,12 +292,13 @@ where
 
         let capacity = if should_warm_with_pqs {
             cache_keys.len()
+                + bundled_operations.len()
                 + persisted_queries_operations
                     .as_ref()
                     .map(|ops| ops.len())
                     .unwrap_or(0)
         } else {
-            cache_keys.len()
+            cache_keys.len() + bundled_operations.len()
         };
 
         if capacity > 0 {
@@ -300,6 +326,18 @@ where
 
         all_cache_keys.shuffle(&mut rand::rng());
 
+        // bundled plans are cheap to load since they were planned ahead of time
+        for (query, operation_name) in bundled_operations {
+            all_cache_keys.push(WarmUpCachingQueryKey {
+                query,
+                operation_name,
+                hash: None,
+                metadata: CacheKeyMetadata::default(),
+                plan_options: PlanOptions::default(),
+                config_mode_hash: self.config_mode_hash.clone(),
+            });
+        }
+
         all_cache_keys.extend(cache_keys.into_iter());
 
         let mut count = 0usize;
@@ -413,6 +451,24 @@ where
             "warmed up the query planner cache with {count} queries planned and {reused} queries reused"
         );
     }
+
+    async fn load_from_disk(&self) {
+        let Some(disk_cache) = &self.disk_cache else {
+            return;
+        };
+        let mut loaded = 0usize;
+        for (key, content) in disk_cache.load().await {
+            let mut entry = Ok(content);
+            if init_query_plan_from_redis(&self.subgraph_schemas, &mut entry).is_err() {
+                continue;
+            }
+            self.cache.insert_in_memory(key, entry).await;
+            loaded += 1;
+        }
+        if loaded > 0 {
+            tracing::info!("loaded {loaded} query plans from the disk cache");
+        }
+    }
 }
 
 impl CachingQueryPlanner<QueryPlannerService> {
@@ -420,9 +476,16 @@ impl CachingQueryPlanner<QueryPlannerService> {
         self.delegate.subgraph_schemas()
     }
 
+    pub(crate) fn plan_bundle_operations(&self) -> Vec<(String, Option<String>)> {
+        self.delegate.plan_bundle_operations()
+    }
+
     pub(crate) fn activate(&self) {
         self.cache.activate();
         self.delegate.activate();
+        if let Some(disk_cache) = &self.disk_cache {
+            disk_cache.activate(self.cache.in_memory_cache());
+        }
     }
 }
 
@@ -851,7 +914,7 @@ where
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub(crate) struct CachingQueryKey {
     pub(crate) query: String,
     pub(crate) operation: Option<String>,
@@ -865,7 +928,7 @@ pub(crate) struct CachingQueryKey {
     pub(crate) config_mode_hash: Arc<ConfigModeHash>,
 }
 
-const ROUTER_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub(super) const ROUTER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 impl std::fmt::Display for CachingQueryKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -2430,6 +2493,7 @@ mod tests {
                 Some(1),
                 Default::default(),
                 &Default::default(),
+                Vec::new(),
             )
             .await;
         // wait a beat - items are added to cache asynchronously, so this helps avoid flakiness
@@ -0,0 +1,286 @@
+//! Critical path of query plan executions
+//!
+//! The critical path is the chain of dependent fetches that determined the execution time of the
+//! primary response: all the nodes of a sequence, and the last node to complete in a parallel
+//! node. Deferred fragments are sent after the primary response and are not part of it.
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use opentelemetry::Key;
+use opentelemetry::KeyValue;
+use opentelemetry::trace::Event;
+use parking_lot::Mutex;
+
+use super::PlanNode;
+use super::Primary;
+use crate::json_ext::Path;
+use crate::plugins::telemetry::dynamic_attribute::SpanDynAttribute;
+use crate::plugins::telemetry::dynamic_attribute::SpanDynEvent;
+
+const CRITICAL_PATH_DURATION: Key = Key::from_static_str("critical_path.duration");
+const CRITICAL_PATH_SUBGRAPHS: Key = Key::from_static_str("critical_path.subgraphs");
+const CRITICAL_PATH_FETCH_EVENT: &str = "critical_path.fetch";
+const CRITICAL_PATH_FETCH_DURATION: Key = Key::from_static_str("critical_path.fetch.duration");
+const CRITICAL_PATH_FETCH_CONTRIBUTION: Key =
+    Key::from_static_str("critical_path.fetch.contribution");
+const SUBGRAPH_NAME: Key = Key::from_static_str("subgraph.name");
+const GRAPHQL_PATH: Key = Key::from_static_str("graphql.path");
+
+/// Inserted in the context extensions to compute the critical path of the query plan execution
+pub(crate) struct EnableCriticalPath;
+
+#[derive(Clone)]
+struct NodeTiming {
+    start: Instant,
+    end: Instant,
+    current_dir: Option<Path>,
+}
+
+/// Execution times of the plan nodes
+///
+/// A plan node executes at most once per query plan execution, so nodes are identified by their
+/// address in the plan.
+#[derive(Default)]
+pub(crate) struct NodeTimings {
+    timings: Mutex<HashMap<usize, NodeTiming>>,
+}
+
+impl NodeTimings {
+    /// Records the execution of a node, which started at `start` and ends now
+    pub(crate) fn record(&self, node: &PlanNode, start: Instant, current_dir: &Path) {
+        let current_dir = matches!(node, PlanNode::Fetch(_)).then(|| current_dir.clone());
+        self.timings.lock().insert(
+            node as *const PlanNode as usize,
+            NodeTiming {
+                start,
+                end: Instant::now(),
+                current_dir,
+            },
+        );
+    }
+
+    fn get(&self, node: &PlanNode) -> Option<NodeTiming> {
+        self.timings
+            .lock()
+            .get(&(node as *const PlanNode as usize))
+            .cloned()
+    }
+}
+
+/// A fetch on the critical path
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CriticalPathFetch {
+    pub(crate) subgraph_name: String,
+    pub(crate) path: Path,
+    /// The start of the fetch, relative to the start of the execution
+    pub(crate) start: Duration,
+    pub(crate) duration: Duration,
+}
+
+/// The critical path of a query plan execution, stored in the context extensions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CriticalPath {
+    /// The execution time of the primary response
+    pub(crate) duration: Duration,
+    /// The fetches on the critical path, in execution order
+    pub(crate) fetches: Vec<CriticalPathFetch>,
+}
+
+impl CriticalPath {
+    pub(crate) fn new(root: &PlanNode, timings: &NodeTimings, start: Instant) -> Self {
+        let mut fetches = Vec::new();
+        collect_fetches(root, timings, start, &mut fetches);
+        Self {
+            duration: timings
+                .get(root)
+                .map(|timing| timing.end.duration_since(start))
+                .unwrap_or_default(),
+            fetches,
+        }
+    }
+
+    /// The time spent on the critical path in the fetches to a subgraph, if it is on the path
+    pub(crate) fn subgraph_duration(&self, subgraph_name: &str) -> Option<Duration> {
+        self.fetches
+            .iter()
+            .filter(|fetch| fetch.subgraph_name == subgraph_name)
+            .map(|fetch| fetch.duration)
+            .reduce(|total, duration| total + duration)
+    }
+
+    /// Records the critical path on the span, as attributes and one event per fetch
+    pub(crate) fn record(&self, span: &tracing::Span) {
+        span.set_span_dyn_attributes([
+            KeyValue::new(CRITICAL_PATH_DURATION, self.duration.as_secs_f64()),
+            KeyValue::new(
+                CRITICAL_PATH_SUBGRAPHS,
+                opentelemetry::Value::Array(
+                    self.fetches
+                        .iter()
+                        .map(|fetch| fetch.subgraph_name.clone().into())
+                        .collect::<Vec<opentelemetry::StringValue>>()
+                        .into(),
+                ),
+            ),
+        ]);
+
+        // Events are timestamped at the end of their fetch
+        let execution_start = SystemTime::now() - self.duration;
+        for fetch in &self.fetches {
+            let contribution = if self.duration.is_zero() {
+                0.0
+            } else {
+                fetch.duration.as_secs_f64() / self.duration.as_secs_f64()
+            };
+            span.add_span_dyn_event(Event::new(
+                CRITICAL_PATH_FETCH_EVENT,
+                execution_start + fetch.start + fetch.duration,
+                vec![
+                    KeyValue::new(SUBGRAPH_NAME, fetch.subgraph_name.clone()),
+                    KeyValue::new(GRAPHQL_PATH, fetch.path.to_string()),
+                    KeyValue::new(CRITICAL_PATH_FETCH_DURATION, fetch.duration.as_secs_f64()),
+                    KeyValue::new(CRITICAL_PATH_FETCH_CONTRIBUTION, contribution),
+                ],
+                0,
+            ));
+        }
+    }
+}
+
+fn collect_fetches(
+    node: &PlanNode,
+    timings: &NodeTimings,
+    start: Instant,
+    fetches: &mut Vec<CriticalPathFetch>,
+) {
+    let Some(timing) = timings.get(node) else {
+        // The node was not executed
+        return;
+    };
+    match node {
+        PlanNode::Fetch(fetch_node) => fetches.push(CriticalPathFetch {
+            subgraph_name: fetch_node.service_name.to_string(),
+            path: timing.current_dir.unwrap_or_default(),
+            start: timing.start.duration_since(start),
+            duration: timing.end.duration_since(timing.start),
+        }),
+        PlanNode::Sequence { nodes } => {
+            for node in nodes {
+                collect_fetches(node, timings, start, fetches);
+            }
+        }
+        PlanNode::Parallel { nodes } => {
+            if let Some(last) = nodes
+                .iter()
+                .filter_map(|node| Some((node, timings.get(node)?.end)))
+                .max_by_key(|(_, end)| *end)
+                .map(|(node, _)| node)
+            {
+                collect_fetches(last, timings, start, fetches);
+            }
+        }
+        PlanNode::Flatten(flatten) => collect_fetches(&flatten.node, timings, start, fetches),
+        PlanNode::Defer {
+            primary: Primary { node, .. },
+            ..
+        } => {
+            if let Some(node) = node {
+                collect_fetches(node, timings, start, fetches);
+            }
+        }
+        PlanNode::Condition {
+            if_clause,
+            else_clause,
+            ..
+        } => {
+            // Only one of the clauses was executed
+            for node in [if_clause, else_clause].into_iter().flatten() {
+                collect_fetches(node, timings, start, fetches);
+            }
+        }
+        // Subscriptions stay open, their duration is not part of the execution time
+        PlanNode::Subscription { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch(service_name: &str) -> PlanNode {
+        serde_json::from_value(serde_json::json!({
+            "kind": "Fetch",
+            "serviceName": service_name,
+            "variableUsages": [],
+            "operation": "{ __typename }",
+            "operationKind": "query"
+        }))
+        .unwrap()
+    }
+
+    fn record(timings: &NodeTimings, node: &PlanNode, start: Instant, from_ms: u64, to_ms: u64) {
+        timings.timings.lock().insert(
+            node as *const PlanNode as usize,
+            NodeTiming {
+                start: start + Duration::from_millis(from_ms),
+                end: start + Duration::from_millis(to_ms),
+                current_dir: matches!(node, PlanNode::Fetch(_)).then(Path::empty),
+            },
+        );
+    }
+
+    #[test]
+    fn it_follows_the_slowest_branch_of_parallel_nodes() {
+        // products, then reviews and inventory in parallel, then shipping after inventory
+        let plan = PlanNode::Sequence {
+            nodes: vec![
+                fetch("products"),
+                PlanNode::Parallel {
+                    nodes: vec![
+                        fetch("reviews"),
+                        PlanNode::Sequence {
+                            nodes: vec![fetch("inventory"), fetch("shipping")],
+                        },
+                    ],
+                },
+            ],
+        };
+        let PlanNode::Sequence { nodes } = &plan else {
+            unreachable!()
+        };
+        let PlanNode::Parallel { nodes: parallel } = &nodes[1] else {
+            unreachable!()
+        };
+        let PlanNode::Sequence { nodes: sequence } = &parallel[1] else {
+            unreachable!()
+        };
+
+        let start = Instant::now();
+        let timings = NodeTimings::default();
+        record(&timings, &plan, start, 0, 50);
+        record(&timings, &nodes[0], start, 0, 10);
+        record(&timings, &nodes[1], start, 10, 50);
+        record(&timings, &parallel[0], start, 10, 40);
+        record(&timings, &parallel[1], start, 10, 50);
+        record(&timings, &sequence[0], start, 10, 25);
+        record(&timings, &sequence[1], start, 25, 50);
+
+        let critical_path = CriticalPath::new(&plan, &timings, start);
+        assert_eq!(critical_path.duration, Duration::from_millis(50));
+        assert_eq!(
+            critical_path
+                .fetches
+                .iter()
+                .map(|fetch| (fetch.subgraph_name.as_str(), fetch.duration.as_millis()))
+                .collect::<Vec<_>>(),
+            [("products", 10), ("inventory", 15), ("shipping", 25)]
+        );
+        assert_eq!(
+            critical_path.subgraph_duration("inventory"),
+            Some(Duration::from_millis(15))
+        );
+        assert_eq!(critical_path.subgraph_duration("reviews"), None);
+    }
+}
@@ -0,0 +1,513 @@
+//! Disk tier of the query plan cache.
+//!
+//! Without Redis, the query plan cache would be empty after every restart. The in memory cache is
+//! written to a file periodically, when the query planner is dropped and when the router shuts
+//! down, and loaded when the router starts. Each schema and query planner configuration has its own
+//! file, and entries planned by another router version are discarded.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use lru::LruCache;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use tokio_util::sync::DropGuard;
+use tokio_util::task::TaskTracker;
+use tower::BoxError;
+
+use super::caching_query_planner::CachingQueryKey;
+use super::caching_query_planner::ConfigModeHash;
+use super::caching_query_planner::InMemoryCachePlanner;
+use super::caching_query_planner::ROUTER_VERSION;
+use crate::configuration::QueryPlanDiskCache;
+use crate::error::QueryPlannerError;
+use crate::services::QueryPlannerContent;
+use crate::spec::SchemaHash;
+
+const FILE_PREFIX: &str = "query_plans-";
+const FILE_EXTENSION: &str = "json";
+/// Maximum time the router waits for the disk caches to be written when it shuts down
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Files which were not written for this long are removed. The files of running routers sharing
+/// the directory are written at every interval, so they are kept.
+const OUTDATED_FILE_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Tasks writing the disk caches, stopped and awaited when the router shuts down
+static WRITERS: LazyLock<Mutex<Writers>> = LazyLock::new(Default::default);
+/// Generation and file of the last activated disk cache, by directory. The disk caches of the
+/// query planners replaced by a reload stop writing to disk.
+static ACTIVE_FILES: LazyLock<Mutex<HashMap<PathBuf, (u64, PathBuf)>>> =
+    LazyLock::new(Default::default);
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Default)]
+struct Writers {
+    tracker: TaskTracker,
+    shutdown: CancellationToken,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiskCacheFile {
+    router_version: String,
+    /// Entries from the least to the most recently used
+    entries: Vec<DiskCacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiskCacheEntry {
+    key: CachingQueryKey,
+    content: QueryPlannerContent,
+}
+
+pub(crate) struct DiskCache {
+    directory: PathBuf,
+    path: PathBuf,
+    interval: Duration,
+    schema_id: SchemaHash,
+    config_mode_hash: Arc<ConfigModeHash>,
+    activated: AtomicBool,
+    /// Stops the writer, which writes the cache one last time, when the disk cache is dropped
+    _writer_guard: DropGuard,
+    writer_stopped: CancellationToken,
+}
+
+impl DiskCache {
+    pub(crate) fn new(
+        config: &QueryPlanDiskCache,
+        schema_id: SchemaHash,
+        config_mode_hash: Arc<ConfigModeHash>,
+    ) -> Self {
+        let writer_stopped = CancellationToken::new();
+        Self {
+            directory: config.path.clone(),
+            path: config.path.join(format!(
+                "{FILE_PREFIX}{schema_id}-{config_mode_hash}.{FILE_EXTENSION}"
+            )),
+            interval: config.interval,
+            schema_id,
+            config_mode_hash,
+            activated: AtomicBool::new(false),
+            _writer_guard: writer_stopped.clone().drop_guard(),
+            writer_stopped,
+        }
+    }
+
+    /// Read the entries planned for the current schema and configuration, from the least to the
+    /// most recently used. The files of other schemas and configurations which were not written
+    /// for a week are removed.
+    pub(crate) async fn load(&self) -> Vec<(CachingQueryKey, QueryPlannerContent)> {
+        let directory = self.directory.clone();
+        let path = self.path.clone();
+        let file = tokio::task::spawn_blocking(move || {
+            remove_outdated_files(&directory, &path, SystemTime::now());
+            read(&path)
+        })
+        .await
+        .map_err(BoxError::from)
+        .and_then(|result| result);
+        let file = match file {
+            Ok(Some(file)) => file,
+            Ok(None) => return Vec::new(),
+            Err(err) => {
+                tracing::warn!(
+                    "could not read the query plan cache from {}: {err}",
+                    self.path.display()
+                );
+                return Vec::new();
+            }
+        };
+        if file.router_version != ROUTER_VERSION {
+            return Vec::new();
+        }
+
+        file.entries
+            .into_iter()
+            .filter(|entry| {
+                entry.key.schema_id == self.schema_id
+                    && entry.key.config_mode_hash == self.config_mode_hash
+            })
+            .map(|entry| (entry.key, entry.content))
+            .collect()
+    }
+
+    /// Write `cache` to disk periodically from a background task, and one last time when the disk
+    /// cache is dropped or the router shuts down. The disk cache activated before this one stops
+    /// writing to disk.
+    pub(crate) fn activate(&self, cache: InMemoryCachePlanner) {
+        if self.activated.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let generation = NEXT_GENERATION.fetch_add(1, Ordering::SeqCst);
+        let previous = ACTIVE_FILES
+            .lock()
+            .insert(self.directory.clone(), (generation, self.path.clone()));
+        // the query planner replaced by a reload may have stopped writing already
+        if let Some((_, previous_path)) = previous
+            && previous_path != self.path
+        {
+            tokio::task::spawn_blocking(move || remove(&previous_path));
+        }
+        let writer = Writer {
+            directory: self.directory.clone(),
+            path: self.path.clone(),
+            schema_id: self.schema_id.clone(),
+            config_mode_hash: self.config_mode_hash.clone(),
+            generation,
+            cache,
+        };
+        let stopped = self.writer_stopped.clone();
+        let (tracker, shutdown) = {
+            let writers = WRITERS.lock();
+            (writers.tracker.clone(), writers.shutdown.clone())
+        };
+        let mut interval = tokio::time::interval(self.interval);
+        tracker.spawn(async move {
+            // the first tick completes immediately
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => writer.write().await,
+                    _ = stopped.cancelled() => break,
+                    _ = shutdown.cancelled() => break,
+                }
+            }
+            writer.write().await;
+        });
+    }
+}
+
+/// Stops the writers of the disk caches, and waits for them to write the caches one last time.
+pub(crate) async fn shutdown() {
+    let writers = std::mem::take(&mut *WRITERS.lock());
+    writers.shutdown.cancel();
+    writers.tracker.close();
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, writers.tracker.wait())
+        .await
+        .is_err()
+    {
+        tracing::warn!("timed out writing the query plan cache to disk");
+    }
+}
+
+struct Writer {
+    directory: PathBuf,
+    path: PathBuf,
+    schema_id: SchemaHash,
+    config_mode_hash: Arc<ConfigModeHash>,
+    generation: u64,
+    cache: InMemoryCachePlanner,
+}
+
+impl Writer {
+    async fn write(&self) {
+        let active_path = match ACTIVE_FILES.lock().get(&self.directory) {
+            Some((generation, _)) if *generation == self.generation => None,
+            Some((_, active_path)) => Some(active_path.clone()),
+            None => return,
+        };
+        if let Some(active_path) = active_path {
+            // the query planner was replaced by a reload, its plans are outdated
+            if active_path != self.path {
+                let path = self.path.clone();
+                let _ = tokio::task::spawn_blocking(move || remove(&path)).await;
+            }
+            return;
+        }
+
+        let file = self.snapshot(&*self.cache.lock().await);
+        let path = self.path.clone();
+        let _ = tokio::task::spawn_blocking(move || write(&path, &file)).await;
+    }
+
+    fn snapshot(
+        &self,
+        cache: &LruCache<CachingQueryKey, Result<QueryPlannerContent, Arc<QueryPlannerError>>>,
+    ) -> DiskCacheFile {
+        let mut entries: Vec<_> = cache
+            .iter()
+            .filter(|(key, _)| {
+                key.schema_id == self.schema_id && key.config_mode_hash == self.config_mode_hash
+            })
+            .filter_map(|(key, value)| {
+                Some(DiskCacheEntry {
+                    key: key.clone(),
+                    content: value.as_ref().ok()?.clone(),
+                })
+            })
+            .collect();
+        // the LRU iterates from the most to the least recently used entry
+        entries.reverse();
+        DiskCacheFile {
+            router_version: ROUTER_VERSION.to_string(),
+            entries,
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Option<DiskCacheFile>, BoxError> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write(path: &Path, file: &DiskCacheFile) {
+    let result = (|| -> Result<(), BoxError> {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        // write to a temporary file first so the cache is never read partially written
+        let temporary_path = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        std::fs::write(&temporary_path, serde_json::to_vec(file)?)?;
+        std::fs::rename(&temporary_path, path)?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => tracing::debug!(
+            "wrote {} query plans to {}",
+            file.entries.len(),
+            path.display()
+        ),
+        Err(err) => tracing::warn!(
+            "could not write the query plan cache to {}: {err}",
+            path.display()
+        ),
+    }
+}
+
+fn remove(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => tracing::warn!(
+            "could not remove the outdated query plan cache {}: {err}",
+            path.display()
+        ),
+    }
+}
+
+/// Removes the files of other schemas and query planner configurations which were not written
+/// recently. Other routers sharing the directory may still be using the recent ones.
+fn remove_outdated_files(directory: &Path, path: &Path, now: SystemTime) {
+    let Ok(files) = std::fs::read_dir(directory) else {
+        return;
+    };
+    for file in files.flatten() {
+        let file_path = file.path();
+        let is_query_plan_cache = file_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(FILE_PREFIX))
+            && file_path
+                .extension()
+                .is_some_and(|ext| ext == FILE_EXTENSION);
+        let is_outdated = file
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > OUTDATED_FILE_AGE);
+        if is_query_plan_cache && file_path != path && is_outdated {
+            remove(&file_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::services::query_planner::PlanOptions;
+
+    fn key(query: &str, schema_id: &SchemaHash, config_mode_hash: &[u8]) -> CachingQueryKey {
+        CachingQueryKey {
+            query: query.to_string(),
+            operation: None,
+            hash: Arc::new(schema_id.operation_hash(query, None)),
+            schema_id: schema_id.clone(),
+            metadata: Default::default(),
+            plan_options: PlanOptions::default(),
+            config_mode_hash: Arc::new(ConfigModeHash(config_mode_hash.to_vec())),
+        }
+    }
+
+    fn disk_cache(path: &Path, schema_id: &SchemaHash, config_mode_hash: &[u8]) -> Arc<DiskCache> {
+        Arc::new(DiskCache::new(
+            &QueryPlanDiskCache {
+                path: path.to_path_buf(),
+                interval: Duration::from_secs(60),
+            },
+            schema_id.clone(),
+            Arc::new(ConfigModeHash(config_mode_hash.to_vec())),
+        ))
+    }
+
+    fn entry(
+        query: &str,
+        schema_id: &SchemaHash,
+        config_mode_hash: &[u8],
+    ) -> LruCache<CachingQueryKey, Result<QueryPlannerContent, Arc<QueryPlannerError>>> {
+        let mut lru = LruCache::new(10.try_into().unwrap());
+        lru.put(
+            key(query, schema_id, config_mode_hash),
+            Ok(QueryPlannerContent::IntrospectionDisabled),
+        );
+        lru
+    }
+
+    async fn wait_for_file(path: &Path) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !path.exists() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the disk cache was not written");
+    }
+
+    #[tokio::test]
+    async fn it_keeps_entries_of_the_current_schema_and_configuration() {
+        let directory = tempfile::tempdir().unwrap();
+        let schema_id = SchemaHash::new("type Query { a: String }");
+        let other_schema_id = SchemaHash::new("type Query { b: String }");
+
+        let mut lru = LruCache::new(10.try_into().unwrap());
+        lru.put(
+            key("{ a }", &schema_id, b"config"),
+            Ok(QueryPlannerContent::IntrospectionDisabled),
+        );
+        lru.put(
+            key("{ __typename }", &schema_id, b"config"),
+            Ok(QueryPlannerContent::IntrospectionDisabled),
+        );
+        lru.put(
+            key("{ b }", &other_schema_id, b"config"),
+            Ok(QueryPlannerContent::IntrospectionDisabled),
+        );
+        lru.put(
+            key("{ a b }", &schema_id, b"config"),
+            Err(Arc::new(QueryPlannerError::UnhandledPlannerResult)),
+        );
+        let cache = disk_cache(directory.path(), &schema_id, b"config");
+        let path = cache.path.clone();
+        cache.activate(Arc::new(Mutex::new(lru)));
+        // the cache is written by its writer once it is dropped
+        drop(cache);
+        wait_for_file(&path).await;
+
+        let loaded = disk_cache(directory.path(), &schema_id, b"config")
+            .load()
+            .await;
+        assert_eq!(
+            loaded
+                .iter()
+                .map(|(key, _)| key.query.as_str())
+                .collect::<Vec<_>>(),
+            ["{ a }", "{ __typename }"]
+        );
+
+        let loaded = disk_cache(directory.path(), &schema_id, b"other config")
+            .load()
+            .await;
+        assert!(loaded.is_empty());
+        // another router using the previous configuration may share the directory
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn it_removes_the_files_which_were_not_written_recently() {
+        let directory = tempfile::tempdir().unwrap();
+        let current = directory.path().join("query_plans-current.json");
+        let other = directory.path().join("query_plans-other.json");
+        let unrelated = directory.path().join("unrelated.json");
+        for path in [&current, &other, &unrelated] {
+            std::fs::write(path, "{}").unwrap();
+        }
+
+        remove_outdated_files(directory.path(), &current, SystemTime::now());
+        assert!(other.exists());
+
+        let later = SystemTime::now() + OUTDATED_FILE_AGE + Duration::from_secs(1);
+        remove_outdated_files(directory.path(), &current, later);
+        assert!(current.exists());
+        assert!(!other.exists());
+        assert!(unrelated.exists());
+    }
+
+    #[tokio::test]
+    async fn it_writes_the_caches_when_the_router_shuts_down() {
+        let directory = tempfile::tempdir().unwrap();
+        let schema_id = SchemaHash::new("type Query { a: String }");
+        let cache = disk_cache(directory.path(), &schema_id, b"shutdown");
+        let path = cache.path.clone();
+        cache.activate(Arc::new(Mutex::new(entry(
+            "{ a }",
+            &schema_id,
+            b"shutdown",
+        ))));
+
+        // the disk cache is still referenced, the writer is stopped by the shutdown
+        shutdown().await;
+        assert!(path.exists());
+        drop(cache);
+    }
+
+    #[tokio::test]
+    async fn it_stops_writing_the_cache_of_a_replaced_query_planner() {
+        let directory = tempfile::tempdir().unwrap();
+        let old_schema_id = SchemaHash::new("type Query { a: String }");
+        let new_schema_id = SchemaHash::new("type Query { b: String }");
+
+        let old_cache = disk_cache(directory.path(), &old_schema_id, b"config");
+        let old_path = old_cache.path.clone();
+        old_cache.activate(Arc::new(Mutex::new(entry(
+            "{ a }",
+            &old_schema_id,
+            b"config",
+        ))));
+        let new_cache = disk_cache(directory.path(), &new_schema_id, b"config");
+        let new_path = new_cache.path.clone();
+        assert_ne!(old_path, new_path);
+        new_cache.activate(Arc::new(Mutex::new(entry(
+            "{ b }",
+            &new_schema_id,
+            b"config",
+        ))));
+
+        // the query planner of the previous schema is dropped after a reload
+        drop(old_cache);
+        drop(new_cache);
+        wait_for_file(&new_path).await;
+        assert!(!old_path.exists());
+
+        let loaded = disk_cache(directory.path(), &new_schema_id, b"config")
+            .load()
+            .await;
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].0.query, "{ b }");
+    }
+
+    #[tokio::test]
+    async fn it_ignores_a_missing_cache() {
+        let directory = tempfile::tempdir().unwrap();
+        let schema_id = SchemaHash::new("type Query { a: String }");
+        let loaded = disk_cache(&directory.path().join("missing"), &schema_id, b"config")
+            .load()
+            .await;
+        assert!(loaded.is_empty());
+    }
+}
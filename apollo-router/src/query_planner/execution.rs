@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 use futures::StreamExt;
 use futures::future::join_all;
@@ -9,10 +10,14 @@ use tokio::sync::mpsc;
 use tokio_stream::wrappers::BroadcastStream;
 use tower::ServiceExt;
 use tracing::Instrument;
+use tracing::Span;
 
 use super::DeferredNode;
 use super::PlanNode;
 use super::QueryPlan;
+use super::critical_path::CriticalPath;
+use super::critical_path::EnableCriticalPath;
+use super::critical_path::NodeTimings;
 use super::log;
 use super::subscription::SubscriptionHandle;
 use crate::Context;
@@ -73,6 +78,11 @@ impl QueryPlan {
 
         log::trace_query_plan(&self.root);
         let deferred_fetches = HashMap::new();
+        let timings = context
+            .extensions()
+            .with_lock(|lock| lock.contains_key::<EnableCriticalPath>())
+            .then(NodeTimings::default);
+        let start = Instant::now();
 
         let (value, errors) = self
             .root
@@ -88,12 +98,20 @@ impl QueryPlan {
                     subscription_handle: &subscription_handle,
                     subscription_config,
                     subgraph_schemas,
+                    timings: timings.as_ref(),
                 },
                 &root,
                 &initial_value.unwrap_or_default(),
                 sender,
             )
             .await;
+        if let Some(timings) = timings {
+            let critical_path = CriticalPath::new(&self.root, &timings, start);
+            critical_path.record(&Span::current());
+            context
+                .extensions()
+                .with_lock(|lock| lock.insert(critical_path));
+        }
         if !deferred_fetches.is_empty() {
             u64_counter!(
                 "apollo.router.operations.defer",
@@ -126,6 +144,8 @@ pub(crate) struct ExecutionParameters<'a> {
     pub(crate) root_node: &'a PlanNode,
     pub(crate) subscription_handle: &'a Option<SubscriptionHandle>,
     pub(crate) subscription_config: &'a Option<SubscriptionConfig>,
+    /// Execution times of the plan nodes, when the critical path is computed
+    pub(crate) timings: Option<&'a NodeTimings>,
 }
 
 impl PlanNode {
@@ -138,6 +158,7 @@ impl PlanNode {
     ) -> future::BoxFuture<'a, (Value, Vec<Error>)> {
         Box::pin(async move {
             tracing::trace!("executing plan:\n{:#?}", self);
+            let start = Instant::now();
             let mut value;
             let mut errors;
 
@@ -403,6 +424,7 @@ impl PlanNode {
                                         subscription_handle: parameters.subscription_handle,
                                         subscription_config: parameters.subscription_config,
                                         subgraph_schemas: parameters.subgraph_schemas,
+                                        timings: parameters.timings,
                                     },
                                     current_dir,
                                     &value,
@@ -498,6 +520,9 @@ impl PlanNode {
                 }
             }
 
+            if let Some(timings) = parameters.timings {
+                timings.record(self, start, current_dir);
+            }
             (value, errors)
         })
     }
@@ -590,6 +615,8 @@ impl DeferredNode {
                             subscription_handle: &subscription_handle,
                             subscription_config: &subscription_config,
                             subgraph_schemas: &subgraph_schemas,
+                            // Deferred fragments are not part of the primary response
+                            timings: None,
                         },
                         &Path::default(),
                         &value,
@@ -12,10 +12,13 @@ pub use self::fetch::OperationKind;
 
 mod caching_query_planner;
 mod convert;
+pub(crate) mod critical_path;
+pub(crate) mod disk_cache;
 mod execution;
 pub(crate) mod fetch;
 mod labeler;
 mod plan;
+mod plan_bundle;
 pub(crate) mod query_planner_service;
 pub(crate) mod rewrites;
 pub(crate) mod selection;
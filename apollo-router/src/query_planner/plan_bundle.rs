@@ -0,0 +1,209 @@
+//! Query plans computed ahead of time by the `apollo-federation plan-bundle` command.
+//!
+//! A bundle is only loaded if it was computed for the schema the router is running, with the same
+//! query planner options, and its plans replace query planning for the operations it contains.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use apollo_federation::query_plan::bundle::PLAN_BUNDLE_FORMAT_VERSION;
+use apollo_federation::query_plan::bundle::PlanBundle as PlanBundleFile;
+use apollo_federation::query_plan::bundle::planner_config_hash;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use tower::BoxError;
+
+use super::PlanNode;
+use super::convert::convert_root_query_plan_node;
+use super::query_planner_service::QueryPlanResult;
+use crate::error::ValidationErrors;
+use crate::spec::SchemaHash;
+
+/// Plans of a bundle, by query and operation name
+#[derive(Default)]
+pub(crate) struct PlanBundle {
+    plans: HashMap<(String, Option<String>), BundledPlan>,
+}
+
+struct BundledPlan {
+    root: Option<PlanNode>,
+    formatted: Arc<String>,
+    evaluated_plan_count: u64,
+    evaluated_plan_paths: u64,
+}
+
+impl PlanBundle {
+    /// Read the bundle at `path`, and refuse it if it was computed for another schema or with other
+    /// query planner options.
+    pub(crate) fn load(
+        path: &Path,
+        schema_id: &SchemaHash,
+        config: &QueryPlannerConfig,
+    ) -> Result<Self, BoxError> {
+        let file: PlanBundleFile = serde_json::from_slice(&std::fs::read(path)?)?;
+        Self::from_file(file, schema_id, config)
+    }
+
+    fn from_file(
+        file: PlanBundleFile,
+        schema_id: &SchemaHash,
+        config: &QueryPlannerConfig,
+    ) -> Result<Self, BoxError> {
+        if file.format_version != PLAN_BUNDLE_FORMAT_VERSION {
+            return Err(format!(
+                "unsupported plan bundle format version {}, expected {PLAN_BUNDLE_FORMAT_VERSION}",
+                file.format_version
+            )
+            .into());
+        }
+        if file.schema_hash != schema_id.as_str() {
+            return Err(format!(
+                "the plan bundle was computed for the schema {}, but the router runs the schema {}",
+                file.schema_hash,
+                schema_id.as_str()
+            )
+            .into());
+        }
+        if file.planner_config_hash != planner_config_hash(config) {
+            return Err("the plan bundle was computed with other query planner options than the \
+                router's: `supergraph.generate_query_fragments`, `experimental_type_conditioned_fetching`, \
+                `supergraph.query_planning.experimental_plans_limit` and \
+                `supergraph.query_planning.experimental_paths_limit` must match the \
+                `--generate-fragments`, `--type-conditioned-fetching`, `--max-evaluated-plans` and \
+                `--paths-limit` options of the command"
+                .into());
+        }
+
+        let plans = file
+            .operations
+            .into_iter()
+            .map(|operation| {
+                let plan = BundledPlan {
+                    root: convert_root_query_plan_node(&operation.plan),
+                    formatted: Arc::new(operation.plan.to_string()),
+                    evaluated_plan_count: operation.plan.statistics.evaluated_plan_count.get()
+                        as u64,
+                    evaluated_plan_paths: operation.plan.statistics.evaluated_plan_paths.get()
+                        as u64,
+                };
+                ((operation.query, operation.operation_name), plan)
+            })
+            .collect();
+        Ok(Self { plans })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.plans.len()
+    }
+
+    /// Queries and operation names of the bundled plans
+    pub(crate) fn operations(&self) -> Vec<(String, Option<String>)> {
+        self.plans.keys().cloned().collect()
+    }
+
+    /// Plan of the operation, initialized with `init_query_plan_root_node`.
+    ///
+    /// Bundles only record the operation name of queries containing several operations, so a
+    /// plan recorded without operation name also matches a named request.
+    pub(crate) fn get(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+        init_query_plan_root_node: impl Fn(&mut PlanNode) -> Result<(), ValidationErrors>,
+    ) -> Option<Result<QueryPlanResult, ValidationErrors>> {
+        let plan = self
+            .plans
+            .get(&(query.to_string(), operation_name.map(str::to_string)))
+            .or_else(|| operation_name.and_then(|_| self.plans.get(&(query.to_string(), None))))?;
+
+        let mut root = plan.root.clone();
+        if let Some(node) = &mut root
+            && let Err(err) = init_query_plan_root_node(node)
+        {
+            return Some(Err(err));
+        }
+        Some(Ok(QueryPlanResult {
+            formatted_query_plan: Some(plan.formatted.clone()),
+            query_plan_root_node: root.map(Arc::new),
+            evaluated_plan_count: plan.evaluated_plan_count,
+            evaluated_plan_paths: plan.evaluated_plan_paths,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_federation::query_plan::QueryPlan;
+    use apollo_federation::query_plan::bundle::PlannedOperation;
+
+    use super::*;
+
+    fn file(schema_hash: &str, config: &QueryPlannerConfig) -> PlanBundleFile {
+        let mut file = PlanBundleFile::new(schema_hash, config);
+        file.operations.push(PlannedOperation {
+            id: "1".to_string(),
+            query: "{ me { id } }".to_string(),
+            operation_name: None,
+            plan: QueryPlan::default(),
+        });
+        file
+    }
+
+    #[test]
+    fn it_refuses_a_bundle_computed_for_another_schema() {
+        let schema_id = SchemaHash::new("type Query { me: String }");
+        let config = QueryPlannerConfig::default();
+        let error = PlanBundle::from_file(file("another schema", &config), &schema_id, &config)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("another schema"));
+
+        let bundle =
+            PlanBundle::from_file(file(schema_id.as_str(), &config), &schema_id, &config).unwrap();
+        assert_eq!(bundle.len(), 1);
+    }
+
+    #[test]
+    fn it_refuses_a_bundle_computed_with_other_planner_options() {
+        let schema_id = SchemaHash::new("type Query { me: String }");
+        let configuration = crate::Configuration::default();
+        let router_config = configuration.rust_query_planner_config();
+        // the command does not generate fragments by default, unlike the router
+        let error = PlanBundle::from_file(
+            file(schema_id.as_str(), &QueryPlannerConfig::default()),
+            &schema_id,
+            &router_config,
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("--generate-fragments"));
+
+        // options that don't change bundled plans are not compared
+        let mut config = router_config.clone();
+        config.subgraph_graphql_validation = true;
+        config.incremental_delivery.enable_defer = !config.incremental_delivery.enable_defer;
+        assert!(
+            PlanBundle::from_file(
+                file(schema_id.as_str(), &config),
+                &schema_id,
+                &router_config
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn it_matches_named_requests_of_single_operation_queries() {
+        let schema_id = SchemaHash::new("type Query { me: String }");
+        let config = QueryPlannerConfig::default();
+        let bundle =
+            PlanBundle::from_file(file(schema_id.as_str(), &config), &schema_id, &config).unwrap();
+        assert!(bundle.get("{ me { id } }", None, |_| Ok(())).is_some());
+        assert!(
+            bundle
+                .get("{ me { id } }", Some("Me"), |_| Ok(()))
+                .is_some()
+        );
+        assert!(bundle.get("{ me { name } }", None, |_| Ok(())).is_none());
+    }
+}
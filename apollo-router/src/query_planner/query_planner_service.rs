@@ -47,6 +47,7 @@ use crate::query_planner::convert::convert_root_query_plan_node;
 use crate::query_planner::fetch::SubgraphSchema;
 use crate::query_planner::fetch::SubgraphSchemas;
 use crate::query_planner::labeler::add_defer_labels;
+use crate::query_planner::plan_bundle::PlanBundle;
 use crate::services::QueryPlannerContent;
 use crate::services::QueryPlannerRequest;
 use crate::services::QueryPlannerResponse;
@@ -96,6 +97,7 @@ pub(crate) struct QueryPlannerService {
     compute_jobs_queue_size_gauge: Arc<Mutex<Option<ObservableGauge<u64>>>>,
     signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm,
     introspection: Arc<IntrospectionCache>,
+    plan_bundle: Arc<PlanBundle>,
 }
 
 fn federation_version_instrument(federation_version: Option<i64>) -> ObservableGauge<u64> {
@@ -241,6 +243,27 @@ impl QueryPlannerService {
         let federation_instrument = federation_version_instrument(schema.federation_version());
         let signature_normalization_algorithm =
             TelemetryConfig::signature_normalization_algorithm(&configuration);
+        let plan_bundle = match &configuration.supergraph.query_planning.plan_bundle {
+            Some(path) => match PlanBundle::load(
+                path,
+                &schema.schema_id,
+                &configuration.rust_query_planner_config(),
+            ) {
+                Ok(plan_bundle) => {
+                    tracing::info!(
+                        "loaded {} query plans from the plan bundle {}",
+                        plan_bundle.len(),
+                        path.display()
+                    );
+                    plan_bundle
+                }
+                Err(err) => {
+                    tracing::warn!("the plan bundle {} was not loaded: {err}", path.display());
+                    PlanBundle::default()
+                }
+            },
+            None => PlanBundle::default(),
+        };
 
         Ok(Self {
             planner,
@@ -253,6 +276,7 @@ impl QueryPlannerService {
             compute_jobs_queue_size_gauge: Default::default(),
             signature_normalization_algorithm,
             introspection,
+            plan_bundle: Arc::new(plan_bundle),
         })
     }
 
@@ -264,6 +288,11 @@ impl QueryPlannerService {
         self.subgraph_schemas.clone()
     }
 
+    /// Queries and operation names of the plans loaded from the plan bundle
+    pub(crate) fn plan_bundle_operations(&self) -> Vec<(String, Option<String>)> {
+        self.plan_bundle.operations()
+    }
+
     async fn parse_selections(
         &self,
         query: String,
@@ -318,19 +347,37 @@ impl QueryPlannerService {
         compute_job_type: ComputeJobType,
         query_metrics: OperationLimits<u32>,
     ) -> Result<QueryPlannerContent, MaybeBackPressureError<QueryPlannerError>> {
-        let plan_result = self
-            .plan_inner(
-                doc,
-                operation.clone(),
-                plan_options,
-                compute_job_type,
-                |root_node| {
-                    root_node.init_parsed_operations_and_hash_subqueries(&self.subgraph_schemas)?;
-                    root_node.extract_authorization_metadata(self.schema.supergraph_schema(), &key);
-                    Ok(())
-                },
-            )
-            .await?;
+        let init_query_plan_root_node = |root_node: &mut PlanNode| {
+            root_node.init_parsed_operations_and_hash_subqueries(&self.subgraph_schemas)?;
+            root_node.extract_authorization_metadata(self.schema.supergraph_schema(), &key);
+            Ok(())
+        };
+        // bundled plans were computed for the query as sent by clients, without defer labels,
+        // authorization filtering or progressive override
+        let bundled_plan = (original_query == filtered_query
+            && plan_options.override_conditions.is_empty()
+            && !selections.defer_stats.has_defer)
+            .then(|| {
+                self.plan_bundle.get(
+                    &original_query,
+                    operation.as_deref(),
+                    init_query_plan_root_node,
+                )
+            })
+            .flatten();
+        let plan_result = match bundled_plan {
+            Some(plan_result) => plan_result.map_err(QueryPlannerError::from)?,
+            None => {
+                self.plan_inner(
+                    doc,
+                    operation.clone(),
+                    plan_options,
+                    compute_job_type,
+                    init_query_plan_root_node,
+                )
+                .await?
+            }
+        };
         let QueryPlanResult {
             query_plan_root_node,
             formatted_query_plan,
@@ -118,6 +118,8 @@ pub(crate) struct OciContent {
 pub(crate) enum OciError {
     #[error("oci layer does not have a title")]
     LayerMissingTitle,
+    #[error("oci manifest does not have a layer of media type {0}")]
+    LayerMissing(&'static str),
     #[error("oci distribution error: {0}")]
     Distribution(OciDistributionError),
     #[error("oci parsing error: {0}")]
@@ -129,6 +131,7 @@ pub(crate) enum OciError {
 const APOLLO_REGISTRY_ENDING: &str = "apollographql.com";
 const APOLLO_REGISTRY_USERNAME: &str = "apollo-registry";
 const APOLLO_SCHEMA_MEDIA_TYPE: &str = "application/apollo.schema";
+const APOLLO_PERSISTED_QUERIES_MEDIA_TYPE: &str = "application/apollo.persisted-query-manifest";
 const APOLLO_MANIFEST_LAUNCH_ID_ANNOTATION: &str = "com.apollograph.launch.id";
 
 impl From<oci_client::ParseError> for OciError {
@@ -414,6 +417,28 @@ pub(crate) async fn fetch_oci_manifest_digest(oci_config: &OciConfig) -> Result<
     }
 }
 
+/// Fetch the persisted query manifest stored in the OCI artifact of `oci_config`.
+pub(crate) async fn fetch_oci_persisted_query_manifest(
+    oci_config: &OciConfig,
+) -> Result<String, OciError> {
+    let reference: Reference = oci_config.reference.as_str().parse()?;
+    let auth = build_auth(&reference, &oci_config.apollo_key);
+    let mut client = Client::new(ClientConfig {
+        protocol: oci_config.client_protocol(),
+        ..Default::default()
+    });
+
+    let (manifest, _) =
+        fetch_oci_manifest(&mut client, &auth, &reference, Some(oci_config)).await?;
+    let layer = manifest
+        .layers
+        .iter()
+        .find(|layer| layer.media_type == APOLLO_PERSISTED_QUERIES_MEDIA_TYPE)
+        .ok_or(OciError::LayerMissing(APOLLO_PERSISTED_QUERIES_MEDIA_TYPE))?;
+    let manifest = fetch_oci_blob(&mut client, &reference, layer).await?;
+    Ok(String::from_utf8(manifest)?)
+}
+
 /// Fetch an OCI bundle by parsing the graph artifact reference, building auth,
 /// inferring the correct protocol, and calling the internal fetch function.
 pub(crate) async fn fetch_oci(oci_config: &OciConfig) -> Result<OciContent, OciError> {
@@ -489,6 +514,27 @@ pub(crate) fn create_oci_schema_stream(
 pub(crate) fn stream_from_oci(
     oci_config: OciConfig,
 ) -> impl Stream<Item = Result<SchemaState, OciError>> {
+    poll_oci(oci_config, |oci_config| async move {
+        let oci_result = fetch_oci(&oci_config).await?;
+        tracing::debug!("fetched schema from oci registry");
+        Ok(SchemaState {
+            sdl: oci_result.schema,
+            launch_id: oci_result.launch_id,
+        })
+    })
+}
+
+/// Regularly check the manifest digest of the OCI artifact at the configured polling interval, and
+/// call `fetch` when it changes
+pub(crate) fn poll_oci<T, F, Fut>(
+    oci_config: OciConfig,
+    fetch: F,
+) -> impl Stream<Item = Result<T, OciError>>
+where
+    T: Send + 'static,
+    F: Fn(OciConfig) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<T, OciError>> + Send,
+{
     let (sender, receiver) = channel(2);
 
     let task = async move {
@@ -498,26 +544,21 @@ pub(crate) fn stream_from_oci(
             match fetch_oci_manifest_digest(&oci_config).await {
                 Ok(current_digest) => {
                     if last_digest.as_deref() == Some(current_digest.as_str()) {
-                        // Digest unchanged, skip fetching the full schema
-                        tracing::debug!("oci manifest digest unchanged, skipping schema fetch");
+                        // Digest unchanged, skip fetching the full artifact
+                        tracing::debug!("oci manifest digest unchanged, skipping artifact fetch");
                     } else {
-                        // Digest changed, fetch the full schema
-                        tracing::debug!("oci manifest digest changed, fetching schema");
-
-                        match fetch_oci(&oci_config).await {
-                            Ok(oci_result) => {
-                                tracing::debug!("fetched schema from oci registry");
-                                let schema_state = SchemaState {
-                                    sdl: oci_result.schema,
-                                    launch_id: oci_result.launch_id,
-                                };
-                                if let Err(e) = sender.send(Ok(schema_state)).await {
+                        // Digest changed, fetch the full artifact
+                        tracing::debug!("oci manifest digest changed, fetching artifact");
+
+                        match fetch(oci_config.clone()).await {
+                            Ok(content) => {
+                                if let Err(e) = sender.send(Ok(content)).await {
                                     tracing::debug!(
                                         "failed to push to stream. This is likely to be because the router is shutting down: {e}"
                                     );
                                     break;
                                 } else {
-                                    // Only update the digest if the schema fetch was successful
+                                    // Only update the digest if the artifact fetch was successful
                                     last_digest = Some(current_digest);
                                 }
                             }
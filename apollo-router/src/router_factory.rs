@@ -41,6 +41,7 @@ use crate::services::SupergraphCreator;
 use crate::services::apollo_graph_reference;
 use crate::services::apollo_key;
 use crate::services::http::HttpClientServiceFactory;
+use crate::services::layers::apq::SubgraphApqRegistry;
 use crate::services::layers::persisted_queries::PersistedQueryLayer;
 use crate::services::layers::query_analysis::QueryAnalysisLayer;
 use crate::services::new_service::ServiceFactory;
@@ -404,12 +405,21 @@ pub(crate) async fn create_subgraph_services(
     http_service_factory: &IndexMap<String, HttpClientServiceFactory>,
     configuration: &Configuration,
 ) -> Result<IndexMap<String, SubgraphService>, BoxError> {
+    let apq_registry = match &configuration.apq.subgraph_registry {
+        Some(registry_config) => {
+            let registry = SubgraphApqRegistry::new(&registry_config.cache).await?;
+            registry.activate();
+            Some(Arc::new(registry))
+        }
+        None => None,
+    };
     let mut subgraph_services = IndexMap::default();
     for (name, http_service_factory) in http_service_factory.iter() {
         let subgraph_service = SubgraphService::from_config(
             name.clone(),
             configuration,
             http_service_factory.clone(),
+            apq_registry.clone(),
         )?;
         subgraph_services.insert(name.clone(), subgraph_service);
     }
@@ -14,7 +14,6 @@ use http::header::ACCEPT;
 use http::header::CONTENT_TYPE;
 #[cfg(unix)]
 use hyperlocal;
-use opentelemetry::global::get_text_map_propagator;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
@@ -27,8 +26,6 @@ use tracing::Instrument;
 use super::subgraph::SubgraphRequestId;
 use crate::Context;
 use crate::plugins::telemetry::consts::HTTP_REQUEST_SPAN_NAME;
-use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
-use crate::plugins::telemetry::reload::otel::prepare_context;
 use crate::query_planner::QueryPlan;
 use crate::services::http::HttpRequest;
 use crate::services::http::HttpResponse;
@@ -309,7 +306,7 @@ where
         #[cfg(not(unix))]
         let converted_uri: http::Uri = uri.parse()?;
 
-        let mut http_request = http::Request::builder()
+        let http_request = http::Request::builder()
             .uri(converted_uri)
             .method(Method::POST)
             .header(ACCEPT, "application/json")
@@ -340,19 +337,16 @@ where
             "otel.original_name" = "http_request",
         );
 
-        get_text_map_propagator(|propagator| {
-            propagator.inject_context(
-                &prepare_context(http_req_span.context()),
-                &mut crate::otel_compat::HeaderInjector(http_request.headers_mut()),
-            );
-        });
-
         let request = HttpRequest {
             http_request,
             context,
         };
 
-        let response = client.call(request).instrument(http_req_span).await?;
+        // The client injects the trace context of the current span, so it must be called within the request span
+        let response = http_req_span
+            .in_scope(|| client.call(request))
+            .instrument(http_req_span)
+            .await?;
         router::body::into_bytes(response.http_response.into_body())
             .await
             .map_err(BoxError::from)
@@ -21,7 +21,6 @@ use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::rt::TokioTimer;
 #[cfg(unix)]
 use hyperlocal::UnixConnector;
-use opentelemetry::global::get_text_map_propagator;
 use opentelemetry_semantic_conventions::attribute::HTTP_RESPONSE_STATUS_CODE;
 use pin_project_lite::pin_project;
 use rustls::ClientConfig;
@@ -48,8 +47,8 @@ use crate::error::FetchError;
 use crate::plugins::authentication::subgraph::SigningParamsConfig;
 use crate::plugins::telemetry::config_new::attributes::ERROR_TYPE;
 use crate::plugins::telemetry::dynamic_attribute::SpanDynAttribute;
-use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
-use crate::plugins::telemetry::reload::otel::prepare_context;
+use crate::plugins::telemetry::tracing::propagation::OutboundClient;
+use crate::plugins::telemetry::tracing::propagation::inject_trace_context;
 use crate::plugins::traffic_shaping::Http2Config;
 use crate::services::hickory_dns_connector::AsyncHyperResolver;
 use crate::services::hickory_dns_connector::new_async_http_connector;
@@ -211,6 +210,8 @@ pub(crate) struct HttpClientService {
     #[cfg(unix)]
     unix_client: UnixHTTPClient,
     service: Arc<String>,
+    /// The kind of client, for trace context propagation
+    client: OutboundClient,
 }
 
 impl HttpClientService {
@@ -311,6 +312,7 @@ impl HttpClientService {
             #[cfg(unix)]
             unix_client,
             service: Arc::new(service.into()),
+            client: OutboundClient::Subgraph,
         })
     }
 
@@ -389,7 +391,10 @@ impl HttpClientService {
         let tls_client_config =
             generate_tls_client_config(tls_cert_store, client_cert_config.map(|arc| arc.as_ref()))?;
 
-        Self::new(name, tls_client_config, client_config)
+        Ok(Self {
+            client: OutboundClient::Connector,
+            ..Self::new(name, tls_client_config, client_config)?
+        })
     }
 
     /// Creates a client for talking to coprocessors
@@ -402,7 +407,10 @@ impl HttpClientService {
         // Coprocessors don't use client certificates, so use no client auth
         let tls_client_config = generate_tls_client_config(tls_root_store.clone(), None)?;
 
-        Self::new("coprocessor".to_string(), tls_client_config, client_config)
+        Ok(Self {
+            client: OutboundClient::Coprocessor,
+            ..Self::new("coprocessor".to_string(), tls_client_config, client_config)?
+        })
     }
 
     /// Creates a root certificate store with native certificates. These are used for root-of-trust
@@ -501,12 +509,7 @@ impl tower::Service<HttpRequest> for HttpClientService {
         let service_name = self.service.clone();
         let http_req_span = Span::current();
 
-        get_text_map_propagator(|propagator| {
-            propagator.inject_context(
-                &prepare_context(http_req_span.context()),
-                &mut crate::otel_compat::HeaderInjector(http_request.headers_mut()),
-            );
-        });
+        inject_trace_context(self.client, &http_req_span, http_request.headers_mut());
 
         let (parts, body) = http_request.into_parts();
         let content_encoding = parts.headers.get(&CONTENT_ENCODING);
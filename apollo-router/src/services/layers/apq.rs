@@ -3,6 +3,8 @@
 //! For more information on APQ see:
 //! <https://www.apollographql.com/docs/apollo-server/performance/apq/>
 
+use std::fmt;
+
 use http::HeaderValue;
 use http::StatusCode;
 use http::header::CACHE_CONTROL;
@@ -10,8 +12,11 @@ use serde::Deserialize;
 use serde_json_bytes::Value;
 use sha2::Digest;
 use sha2::Sha256;
+use tower::BoxError;
 
 use crate::cache::DeduplicatingCache;
+use crate::cache::storage::CacheStorage;
+use crate::configuration::Cache;
 use crate::services::SupergraphRequest;
 use crate::services::SupergraphResponse;
 
@@ -200,6 +205,58 @@ pub(crate) fn calculate_hash_for_query(query: &str) -> String {
     hex::encode(hasher.finalize())
 }
 
+/// Hashes of the queries each subgraph is known to have registered, shared by router instances
+/// through Redis.
+pub(crate) struct SubgraphApqRegistry {
+    storage: CacheStorage<SubgraphApqKey, bool>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct SubgraphApqKey {
+    subgraph_name: String,
+    hash: String,
+}
+
+impl fmt::Display for SubgraphApqKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "subgraph_apq:{}:{}", self.subgraph_name, self.hash)
+    }
+}
+
+impl SubgraphApqRegistry {
+    pub(crate) async fn new(config: &Cache) -> Result<Self, BoxError> {
+        Ok(Self {
+            storage: CacheStorage::new(
+                config.in_memory.limit,
+                config.redis.clone(),
+                "APQ subgraph registry",
+            )
+            .await?,
+        })
+    }
+
+    /// Whether the subgraph is known to have registered the query with this hash
+    pub(crate) async fn is_known(&self, subgraph_name: &str, hash: &str) -> bool {
+        let key = SubgraphApqKey {
+            subgraph_name: subgraph_name.to_string(),
+            hash: hash.to_string(),
+        };
+        self.storage.get(&key, |_| Ok(())).await.unwrap_or(false)
+    }
+
+    pub(crate) async fn register(&self, subgraph_name: &str, hash: &str) {
+        let key = SubgraphApqKey {
+            subgraph_name: subgraph_name.to_string(),
+            hash: hash.to_string(),
+        };
+        self.storage.insert(key, true).await;
+    }
+
+    pub(crate) fn activate(&self) {
+        self.storage.activate();
+    }
+}
+
 /// Used when APQ is disabled. Rejects requests that try to use a persisted query hash anyways.
 async fn disabled_apq_request(
     request: SupergraphRequest,
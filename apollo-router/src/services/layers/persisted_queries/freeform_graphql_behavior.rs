@@ -38,9 +38,12 @@ pub(crate) enum FreeformGraphQLBehavior {
 }
 
 impl FreeformGraphQLBehavior {
+    /// Action for a freeform GraphQL request. `scope_safelist` replaces the safelist of the graph
+    /// for clients bound to a safelist scope.
     pub(super) fn action_for_freeform_graphql(
         &self,
         ast: Result<&ast::Document, &str>,
+        scope_safelist: Option<&FreeformGraphQLSafelist>,
     ) -> FreeformGraphQLAction {
         match self {
             FreeformGraphQLBehavior::AllowAll { .. } => FreeformGraphQLAction {
@@ -60,7 +63,7 @@ impl FreeformGraphQLBehavior {
                 log_unknown,
                 ..
             } => {
-                let pq_id = safelist.get_pq_id_for_body(ast);
+                let pq_id = scope_safelist.unwrap_or(safelist).get_pq_id_for_body(ast);
                 if pq_id.is_some() {
                     FreeformGraphQLAction {
                         should_allow: true,
@@ -76,7 +79,7 @@ impl FreeformGraphQLBehavior {
                 }
             }
             FreeformGraphQLBehavior::LogUnlessInSafelist { safelist, .. } => {
-                let pq_id = safelist.get_pq_id_for_body(ast);
+                let pq_id = scope_safelist.unwrap_or(safelist).get_pq_id_for_body(ast);
                 FreeformGraphQLAction {
                     should_allow: true,
                     should_log: pq_id.is_none(),
@@ -3,12 +3,19 @@
 use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use apollo_compiler::ast;
 use futures::prelude::*;
+use futures::stream::BoxStream;
+use http::HeaderMap;
+use http::HeaderName;
+use http::HeaderValue;
+use http::StatusCode;
+use http::header::ETAG;
+use http::header::IF_NONE_MATCH;
 use parking_lot::RwLock;
 use reqwest::Client;
-use tokio::fs::read_to_string;
 use tokio::sync::mpsc;
 use tower::BoxError;
 
@@ -18,7 +25,18 @@ use super::freeform_graphql_behavior::get_freeform_graphql_behavior;
 use super::manifest::FullPersistedQueryOperationId;
 use super::manifest::PersistedQueryManifest;
 use super::manifest::SignedUrlChunk;
+use super::safelist_scope::SafelistScope;
+use super::safelist_scope::SafelistScopes;
+use super::signature::ManifestVerifier;
+use super::signature::read_manifest;
+use super::signature::signature_path;
 use crate::Configuration;
+use crate::configuration::PersistedQueriesRemoteManifest;
+use crate::registry::OciConfig;
+use crate::registry::fetch_oci_persisted_query_manifest;
+use crate::registry::poll_oci;
+use crate::registry::should_use_ssl;
+use crate::registry::validate_oci_reference;
 use crate::uplink::UplinkConfig;
 use crate::uplink::persisted_queries_manifest_stream::MaybePersistedQueriesManifestChunks;
 use crate::uplink::persisted_queries_manifest_stream::PersistedQueriesManifestChunk;
@@ -29,6 +47,8 @@ use crate::uplink::stream_from_uplink_transforming_new_response;
 pub(crate) struct PersistedQueryManifestPollerState {
     persisted_query_manifest: PersistedQueryManifest,
     pub(crate) freeform_graphql_behavior: FreeformGraphQLBehavior,
+    /// Safelists of the clients bound to a scope, which replace the manifest for these clients
+    scopes: Arc<SafelistScopes>,
 }
 
 /// Manages polling uplink for persisted query chunks and unpacking those chunks into a [`PersistedQueryManifest`].
@@ -43,13 +63,31 @@ impl PersistedQueryManifestPoller {
     /// Starts polling immediately and this function only returns after all chunks have been fetched
     /// and the [`PersistedQueryManifest`] has been fully populated.
     pub(crate) async fn new(config: Configuration) -> Result<Self, BoxError> {
-        let manifest_source = ManifestSource::from_config(&config)?;
+        let verifier = match &config.persisted_queries.manifest_signature {
+            Some(_)
+                if config.persisted_queries.local_manifests.is_none()
+                    && config.persisted_queries.safelist.scopes.is_empty() =>
+            {
+                return Err(
+                    "`persisted_queries.manifest_signature` requires `local_manifests`".into(),
+                );
+            }
+            Some(signature_config) => {
+                Some(Arc::new(ManifestVerifier::from_config(signature_config)?))
+            }
+            None => None,
+        };
+        let scopes =
+            SafelistScopes::load(&config.persisted_queries.safelist, verifier.as_deref()).await?;
+        let scope_changes = create_scope_changes_stream(&config, verifier.is_some());
+        let manifest_source = ManifestSource::from_config(&config, verifier.clone())?;
         let manifest_stream = create_manifest_stream(manifest_source).await?;
 
         // Initialize state
         let state = Arc::new(RwLock::new(PersistedQueryManifestPollerState {
             persisted_query_manifest: PersistedQueryManifest::default(),
             freeform_graphql_behavior: FreeformGraphQLBehavior::DenyAll { log_unknown: false },
+            scopes: Arc::new(scopes),
         }));
 
         // Start the background polling task
@@ -62,8 +100,10 @@ impl PersistedQueryManifestPoller {
         tokio::task::spawn(async move {
             poll_manifest_stream(
                 manifest_stream,
+                scope_changes,
                 state_clone,
                 config_clone,
+                verifier,
                 ready_sender,
                 drop_receiver,
             )
@@ -110,17 +150,30 @@ impl PersistedQueryManifestPoller {
 
     pub(crate) fn get_all_operations(&self) -> Vec<String> {
         let state = self.state.read();
-        state.persisted_query_manifest.values().cloned().collect()
+        state
+            .persisted_query_manifest
+            .values()
+            .chain(state.scopes.operation_bodies())
+            .cloned()
+            .collect()
     }
 
+    /// Safelists of the clients bound to a scope, which replace the manifest for these clients
+    pub(crate) fn scopes(&self) -> Arc<SafelistScopes> {
+        self.state.read().scopes.clone()
+    }
+
+    /// Action for a freeform GraphQL request, matched against the safelist of `scope` if the
+    /// client is bound to one.
     pub(crate) fn action_for_freeform_graphql(
         &self,
         ast: Result<&ast::Document, &str>,
+        scope: Option<&SafelistScope>,
     ) -> FreeformGraphQLAction {
         let state = self.state.read();
         state
             .freeform_graphql_behavior
-            .action_for_freeform_graphql(ast)
+            .action_for_freeform_graphql(ast, scope.map(|scope| &scope.safelist))
     }
 
     // Some(bool) means "never allows freeform GraphQL, bool is whether or not to log"
@@ -236,21 +289,47 @@ pub(crate) enum ManifestPollResultOnStartup {
 /// The source of persisted query manifests
 #[derive(Debug)]
 enum ManifestSource {
-    LocalStatic(Vec<String>),
-    LocalHotReload(Vec<String>),
+    LocalStatic(Vec<String>, Option<Arc<ManifestVerifier>>),
+    LocalHotReload(Vec<String>, Option<Arc<ManifestVerifier>>),
+    Url {
+        url: url::Url,
+        headers: HeaderMap,
+        poll_interval: Duration,
+        timeout: Duration,
+    },
+    Oci(OciConfig),
     Uplink(UplinkConfig),
 }
 
 impl ManifestSource {
-    fn from_config(config: &Configuration) -> Result<Self, BoxError> {
+    fn from_config(
+        config: &Configuration,
+        verifier: Option<Arc<ManifestVerifier>>,
+    ) -> Result<Self, BoxError> {
+        if config.persisted_queries.remote_manifest.is_some() {
+            if config.persisted_queries.hot_reload {
+                return Err(
+                    "`persisted_queries.hot_reload` cannot be used with `persisted_queries.remote_manifest`, which is polled every `poll_interval`"
+                        .into(),
+                );
+            }
+            if config.persisted_queries.local_manifests.is_some() {
+                return Err(
+                    "`persisted_queries.local_manifests` and `persisted_queries.remote_manifest` cannot be used together"
+                        .into(),
+                );
+            }
+        }
         let source = if config.persisted_queries.hot_reload {
             if let Some(paths) = &config.persisted_queries.local_manifests {
-                ManifestSource::LocalHotReload(paths.clone())
+                ManifestSource::LocalHotReload(paths.clone(), verifier)
             } else {
                 return Err("`persisted_queries.hot_reload` requires `local_manifests`".into());
             }
         } else if let Some(paths) = &config.persisted_queries.local_manifests {
-            ManifestSource::LocalStatic(paths.clone())
+            ManifestSource::LocalStatic(paths.clone(), verifier)
+        } else if let Some(remote_manifest) = &config.persisted_queries.remote_manifest {
+            Self::from_remote_config(remote_manifest, config.uplink.as_ref())?
         } else if let Some(uplink_config) = config.uplink.as_ref() {
             ManifestSource::Uplink(uplink_config.clone())
         } else {
@@ -262,8 +341,54 @@ impl ManifestSource {
 
         Ok(source)
     }
+
+    fn from_remote_config(
+        config: &PersistedQueriesRemoteManifest,
+        uplink_config: Option<&UplinkConfig>,
+    ) -> Result<Self, BoxError> {
+        match (&config.url, &config.oci_reference) {
+            (Some(url), None) => {
+                let headers = config
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        Ok::<_, BoxError>((
+                            HeaderName::try_from(name.as_str())?,
+                            HeaderValue::try_from(value.as_str())?,
+                        ))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(ManifestSource::Url {
+                    url: url.clone(),
+                    headers,
+                    poll_interval: config.poll_interval,
+                    timeout: config.timeout,
+                })
+            }
+            (None, Some(reference)) => {
+                let (reference, _) = validate_oci_reference(reference)?;
+                Ok(ManifestSource::Oci(OciConfig {
+                    // only used to authenticate to the GraphOS registry
+                    apollo_key: uplink_config
+                        .map(|uplink_config| uplink_config.apollo_key.clone())
+                        .unwrap_or_default(),
+                    use_ssl: should_use_ssl(&reference),
+                    reference,
+                    hot_reload: true,
+                    poll_interval: config.poll_interval,
+                }))
+            }
+            _ => Err(
+                "`persisted_queries.remote_manifest` requires exactly one of `url` and `oci_reference`"
+                    .into(),
+            ),
+        }
+    }
 }
 
+/// Connection timeout when fetching a remote manifest from a URL
+const REMOTE_MANIFEST_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// A stream of manifest updates
 type ManifestStream = dyn Stream<Item = Result<PersistedQueryManifest, BoxError>> + Send + 'static;
 
@@ -271,8 +396,26 @@ async fn create_manifest_stream(
     source: ManifestSource,
 ) -> Result<Pin<Box<ManifestStream>>, BoxError> {
     match source {
-        ManifestSource::LocalStatic(paths) => Ok(stream::once(load_local_manifests(paths)).boxed()),
-        ManifestSource::LocalHotReload(paths) => Ok(create_hot_reload_stream(paths).boxed()),
+        ManifestSource::LocalStatic(paths, verifier) => {
+            Ok(stream::once(load_local_manifests(paths, verifier)).boxed())
+        }
+        ManifestSource::LocalHotReload(paths, verifier) => {
+            Ok(create_hot_reload_stream(paths, verifier).boxed())
+        }
+        ManifestSource::Url {
+            url,
+            headers,
+            poll_interval,
+            timeout,
+        } => {
+            let client = Client::builder()
+                .connect_timeout(timeout.min(REMOTE_MANIFEST_CONNECT_TIMEOUT))
+                .timeout(timeout)
+                .gzip(true)
+                .build()?;
+            Ok(create_url_stream(client, url, headers, poll_interval).boxed())
+        }
+        ManifestSource::Oci(oci_config) => Ok(create_oci_stream(oci_config).boxed()),
         ManifestSource::Uplink(uplink_config) => {
             let client = Client::builder()
                 .timeout(uplink_config.timeout)
@@ -285,8 +428,10 @@ async fn create_manifest_stream(
 
 async fn poll_manifest_stream(
     mut manifest_stream: Pin<Box<ManifestStream>>,
+    mut scope_changes: BoxStream<'static, ()>,
     state: Arc<RwLock<PersistedQueryManifestPollerState>>,
     config: Configuration,
+    verifier: Option<Arc<ManifestVerifier>>,
     ready_sender: mpsc::Sender<ManifestPollResultOnStartup>,
     mut drop_receiver: mpsc::Receiver<()>,
 ) {
@@ -300,10 +445,17 @@ async fn poll_manifest_stream(
                         let operation_count = new_manifest.len();
                         let freeform_graphql_behavior =
                             get_freeform_graphql_behavior(&config, &new_manifest);
+                        // the scopes were loaded with the first manifest, and follow its updates
+                        let scopes = if ready_sender.is_some() {
+                            state.read().scopes.clone()
+                        } else {
+                            reload_scopes(&config, verifier.as_deref(), &state).await
+                        };
 
                         *state.write() = PersistedQueryManifestPollerState {
                             persisted_query_manifest: new_manifest,
                             freeform_graphql_behavior,
+                            scopes,
                         };
                         tracing::info!("persisted query manifest successfully updated ({} operations total)", operation_count);
 
@@ -321,18 +473,38 @@ async fn poll_manifest_stream(
                     None => break,
                 }
             }
+            Some(()) = scope_changes.next() => {
+                let scopes = reload_scopes(&config, verifier.as_deref(), &state).await;
+                state.write().scopes = scopes;
+            }
             _ = drop_receiver.recv() => break,
         }
     }
 }
 
-async fn load_local_manifests(paths: Vec<String>) -> Result<PersistedQueryManifest, BoxError> {
+/// Loads the safelist scopes again, keeping the current ones if a scope manifest is invalid
+async fn reload_scopes(
+    config: &Configuration,
+    verifier: Option<&ManifestVerifier>,
+    state: &RwLock<PersistedQueryManifestPollerState>,
+) -> Arc<SafelistScopes> {
+    match SafelistScopes::load(&config.persisted_queries.safelist, verifier).await {
+        Ok(scopes) => Arc::new(scopes),
+        Err(e) => {
+            tracing::error!("Error reloading persisted query safelist scopes: {}", e);
+            state.read().scopes.clone()
+        }
+    }
+}
+
+async fn load_local_manifests(
+    paths: Vec<String>,
+    verifier: Option<Arc<ManifestVerifier>>,
+) -> Result<PersistedQueryManifest, BoxError> {
     let mut complete_manifest = PersistedQueryManifest::default();
 
     for path in paths.iter() {
-        let raw_file_contents = read_to_string(path).await.map_err(|e| -> BoxError {
-            format!("Failed to read persisted query list file at path: {path}, {e}").into()
-        })?;
+        let raw_file_contents = read_manifest(path, verifier.as_deref()).await?;
 
         let chunk = SignedUrlChunk::parse_and_validate(&raw_file_contents)?;
         complete_manifest.add_chunk(&chunk);
@@ -375,23 +547,135 @@ fn create_uplink_stream(
     })
 }
 
+/// Poll the manifest at `url`, only yielding a new manifest when its content changed according to
+/// its `ETag`.
+fn create_url_stream(
+    client: Client,
+    url: url::Url,
+    headers: HeaderMap,
+    poll_interval: Duration,
+) -> impl Stream<Item = Result<PersistedQueryManifest, BoxError>> {
+    stream::unfold((None, true), move |(etag, first_poll)| {
+        let client = client.clone();
+        let url = url.clone();
+        let headers = headers.clone();
+        async move {
+            if !first_poll {
+                tokio::time::sleep(poll_interval).await;
+            }
+            loop {
+                match fetch_manifest_from_url(&client, &url, &headers, etag.as_ref()).await {
+                    Ok(Some((manifest, etag))) => return Some((Ok(manifest), (etag, false))),
+                    Ok(None) => {
+                        tracing::debug!("persisted query manifest at {url} was not modified");
+                        tokio::time::sleep(poll_interval).await;
+                    }
+                    Err(e) => return Some((Err(e), (etag, false))),
+                }
+            }
+        }
+    })
+}
+
+/// Fetch the manifest at `url`, or `None` if it did not change since the version of `etag`.
+async fn fetch_manifest_from_url(
+    client: &Client,
+    url: &url::Url,
+    headers: &HeaderMap,
+    etag: Option<&HeaderValue>,
+) -> Result<Option<(PersistedQueryManifest, Option<HeaderValue>)>, BoxError> {
+    let mut request = client.get(url.clone()).headers(headers.clone());
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let response = request
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| -> BoxError {
+            format!("error fetching persisted query manifest from {url}: {e}").into()
+        })?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let etag = response.headers().get(ETAG).cloned();
+    let body = response.text().await.map_err(|e| -> BoxError {
+        format!("error reading body of persisted query manifest from {url}: {e}").into()
+    })?;
+    let mut manifest = PersistedQueryManifest::default();
+    manifest.add_chunk(&SignedUrlChunk::parse_and_validate(&body)?);
+    tracing::info!("Loaded {} persisted queries from {url}.", manifest.len());
+    Ok(Some((manifest, etag)))
+}
+
+/// Poll the manifest stored in an OCI artifact, only fetching it when the artifact digest changes.
+fn create_oci_stream(
+    oci_config: OciConfig,
+) -> impl Stream<Item = Result<PersistedQueryManifest, BoxError>> {
+    poll_oci(oci_config, |oci_config| async move {
+        fetch_oci_persisted_query_manifest(&oci_config).await
+    })
+    .map(|result| {
+        let manifest = result?;
+        let mut persisted_query_manifest = PersistedQueryManifest::default();
+        persisted_query_manifest.add_chunk(&SignedUrlChunk::parse_and_validate(&manifest)?);
+        tracing::info!(
+            "Loaded {} persisted queries from the OCI registry.",
+            persisted_query_manifest.len()
+        );
+        Ok(persisted_query_manifest)
+    })
+}
+
+/// Changes of the manifests of the safelist scopes, if `hot_reload` is enabled
+fn create_scope_changes_stream(config: &Configuration, verify: bool) -> BoxStream<'static, ()> {
+    if !config.persisted_queries.hot_reload {
+        return stream::pending().boxed();
+    }
+    let watchers = config
+        .persisted_queries
+        .safelist
+        .scopes
+        .iter()
+        .flat_map(|scope| scope.manifests.iter())
+        // the scopes are already loaded, so the initial event of each watcher is skipped
+        .map(|path| watch_manifest(path, verify).skip(1));
+    stream::select_all(watchers)
+        .chain(stream::pending())
+        .boxed()
+}
+
+/// Emits an event when the watcher starts, then whenever the manifest at `path`, or its signature
+/// if `verify` is set, changes.
+fn watch_manifest(path: &str, verify: bool) -> BoxStream<'static, ()> {
+    let manifest_changes = crate::files::watch(std::path::Path::new(path));
+    if verify {
+        // both watchers emit an event when they start
+        let signature_changes =
+            crate::files::watch(std::path::Path::new(&signature_path(path))).skip(1);
+        stream::select(manifest_changes, signature_changes).boxed()
+    } else {
+        manifest_changes.boxed()
+    }
+}
+
 fn create_hot_reload_stream(
     paths: Vec<String>,
+    verifier: Option<Arc<ManifestVerifier>>,
 ) -> impl Stream<Item = Result<PersistedQueryManifest, BoxError>> {
-    // Create file watchers for each path
+    // Create file watchers for each path. A manifest failing signature verification is rejected
+    // and the previous version of the manifest is kept. As the signature can be written after the
+    // manifest, the manifest is verified again when its signature changes.
     let file_watchers = paths.into_iter().map(|raw_path| {
-        crate::files::watch(std::path::Path::new(&raw_path.clone())).then(move |_| {
+        let verifier = verifier.clone();
+        watch_manifest(&raw_path, verifier.is_some()).then(move |_| {
             let raw_path = raw_path.clone();
+            let verifier = verifier.clone();
             async move {
-                match read_to_string(&raw_path).await {
-                    Ok(raw_file_contents) => {
-                        match SignedUrlChunk::parse_and_validate(&raw_file_contents) {
-                            Ok(chunk) => Ok((raw_path, chunk)),
-                            Err(e) => Err(e),
-                        }
-                    }
-                    Err(e) => Err(e.into()),
-                }
+                let raw_file_contents = read_manifest(&raw_path, verifier.as_deref()).await?;
+                let chunk = SignedUrlChunk::parse_and_validate(&raw_file_contents)?;
+                Ok((raw_path, chunk))
             }
             .boxed()
         })
@@ -655,4 +939,297 @@ mod tests {
             Some(body)
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn hot_reload_keeps_the_previous_manifest_if_its_signature_is_invalid() {
+        use base64::Engine as _;
+        use base64::engine::general_purpose::STANDARD;
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use jsonwebtoken::Algorithm;
+        use jsonwebtoken::EncodingKey;
+
+        use crate::configuration::PersistedQueriesManifestSignature;
+
+        const TESTDATA: &str = "src/services/layers/persisted_queries/testdata";
+        let manifest = |id: &str| {
+            format!(
+                r#"{{"format":"apollo-persisted-query-manifest","version":1,"operations":[{{"id":"{id}","body":"query {{ typename }}"}}]}}"#
+            )
+        };
+        let sign = |manifest: &str| {
+            let key = EncodingKey::from_ed_pem(
+                &std::fs::read(format!("{TESTDATA}/ed25519.key")).unwrap(),
+            )
+            .unwrap();
+            let signature =
+                jsonwebtoken::crypto::sign(manifest.as_bytes(), &key, Algorithm::EdDSA).unwrap();
+            STANDARD.encode(URL_SAFE_NO_PAD.decode(signature).unwrap())
+        };
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("manifest.json");
+        let path = path.to_str().unwrap().to_string();
+        let signature_path = format!("{path}.sig");
+        std::fs::write(&path, manifest("1234")).unwrap();
+        std::fs::write(&signature_path, sign(&manifest("1234"))).unwrap();
+
+        let manifest_manager = PersistedQueryManifestPoller::new(
+            Configuration::fake_builder()
+                .apq(Apq::fake_new(Some(false)))
+                .persisted_query(
+                    PersistedQueries::builder()
+                        .enabled(true)
+                        .local_manifests(vec![path.clone()])
+                        .hot_reload(true)
+                        .manifest_signature(PersistedQueriesManifestSignature {
+                            public_keys: vec![format!("{TESTDATA}/ed25519.pub")],
+                        })
+                        .build(),
+                )
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        assert!(manifest_manager.get_operation_body("1234", None).is_some());
+
+        // the signature of the previous manifest does not match the new one
+        std::fs::write(&path, manifest("5678")).unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(manifest_manager.get_operation_body("1234", None).is_some());
+        assert!(manifest_manager.get_operation_body("5678", None).is_none());
+
+        // the new manifest is loaded once its signature is written
+        std::fs::write(&signature_path, sign(&manifest("5678"))).unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(manifest_manager.get_operation_body("1234", None).is_none());
+        assert!(manifest_manager.get_operation_body("5678", None).is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn hot_reload_reloads_the_manifests_of_safelist_scopes() {
+        use crate::configuration::PersistedQueriesSafelist;
+        use crate::configuration::PersistedQueriesSafelistScope;
+
+        let manifest = |id: &str| {
+            format!(
+                r#"{{"format":"apollo-persisted-query-manifest","version":1,"operations":[{{"id":"{id}","body":"query {{ typename }}"}}]}}"#
+            )
+        };
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("manifest.json");
+        let path = path.to_str().unwrap().to_string();
+        let scope_path = directory.path().join("mobile.json");
+        let scope_path = scope_path.to_str().unwrap().to_string();
+        std::fs::write(&path, manifest("1234")).unwrap();
+        std::fs::write(&scope_path, manifest("5678")).unwrap();
+
+        let manifest_manager = PersistedQueryManifestPoller::new(
+            Configuration::fake_builder()
+                .apq(Apq::fake_new(Some(false)))
+                .persisted_query(
+                    PersistedQueries::builder()
+                        .enabled(true)
+                        .local_manifests(vec![path.clone()])
+                        .hot_reload(true)
+                        .safelist(
+                            PersistedQueriesSafelist::builder()
+                                .enabled(true)
+                                .scopes(vec![PersistedQueriesSafelistScope {
+                                    name: "mobile".to_string(),
+                                    clients: vec!["ios".to_string()],
+                                    manifests: vec![scope_path.clone()],
+                                }])
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        let scope_operation = |id: &str| {
+            manifest_manager
+                .scopes()
+                .scope_for_client("ios")
+                .unwrap()
+                .get_operation_body(id, None)
+        };
+        assert!(scope_operation("5678").is_some());
+
+        std::fs::write(&scope_path, manifest("9012")).unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(scope_operation("5678").is_none());
+        assert!(scope_operation("9012").is_some());
+
+        // an invalid scope manifest keeps the previous scopes
+        std::fs::write(&scope_path, "{").unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(scope_operation("9012").is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn url_stream_only_yields_modified_manifests() {
+        use wiremock::Mock;
+        use wiremock::MockServer;
+        use wiremock::ResponseTemplate;
+        use wiremock::matchers::header;
+        use wiremock::matchers::method;
+
+        let (_, body, _) = fake_manifest();
+        let manifest =
+            std::fs::read_to_string("tests/fixtures/persisted-queries-manifest.json").unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(header("authorization", "Bearer token"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_string(manifest),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let manifest_manager = PersistedQueryManifestPoller::new(
+            Configuration::fake_builder()
+                .apq(Apq::fake_new(Some(false)))
+                .persisted_query(
+                    PersistedQueries::builder()
+                        .enabled(true)
+                        .remote_manifest(PersistedQueriesRemoteManifest {
+                            url: Some(Url::parse(&server.uri()).unwrap()),
+                            headers: [("authorization".to_string(), "Bearer token".to_string())]
+                                .into(),
+                            oci_reference: None,
+                            poll_interval: Duration::from_millis(10),
+                            timeout: Duration::from_secs(30),
+                        })
+                        .build(),
+                )
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            manifest_manager.get_operation_body("5678", None),
+            Some(body)
+        );
+
+        // later polls send the ETag of the manifest and are answered with 304
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.len() > 1);
+        assert!(
+            requests[1..]
+                .iter()
+                .all(|request| request.headers.get("if-none-match").is_some())
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn remote_manifest_times_out() {
+        use wiremock::Mock;
+        use wiremock::MockServer;
+        use wiremock::ResponseTemplate;
+        use wiremock::matchers::method;
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(2),
+            PersistedQueryManifestPoller::new(
+                Configuration::fake_builder()
+                    .apq(Apq::fake_new(Some(false)))
+                    .persisted_query(
+                        PersistedQueries::builder()
+                            .enabled(true)
+                            .remote_manifest(PersistedQueriesRemoteManifest {
+                                url: Some(Url::parse(&server.uri()).unwrap()),
+                                headers: Default::default(),
+                                oci_reference: None,
+                                poll_interval: Duration::from_secs(30),
+                                timeout: Duration::from_millis(100),
+                            })
+                            .build(),
+                    )
+                    .build()
+                    .unwrap(),
+            ),
+        )
+        .await
+        .expect("the manifest request times out");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn remote_manifest_rejects_hot_reload() {
+        let err = PersistedQueryManifestPoller::new(
+            Configuration::fake_builder()
+                .apq(Apq::fake_new(Some(false)))
+                .persisted_query(
+                    PersistedQueries::builder()
+                        .enabled(true)
+                        .hot_reload(true)
+                        .local_manifests(vec!["manifest.json".to_string()])
+                        .remote_manifest(PersistedQueriesRemoteManifest {
+                            url: Some(Url::parse("https://example.com/manifest.json").unwrap()),
+                            headers: Default::default(),
+                            oci_reference: None,
+                            poll_interval: Duration::from_secs(30),
+                            timeout: Duration::from_secs(30),
+                        })
+                        .build(),
+                )
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`persisted_queries.hot_reload` cannot be used with `persisted_queries.remote_manifest`, which is polled every `poll_interval`"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn remote_manifest_requires_a_single_source() {
+        let err = PersistedQueryManifestPoller::new(
+            Configuration::fake_builder()
+                .apq(Apq::fake_new(Some(false)))
+                .persisted_query(
+                    PersistedQueries::builder()
+                        .enabled(true)
+                        .remote_manifest(PersistedQueriesRemoteManifest {
+                            url: Some(Url::parse("https://example.com/manifest.json").unwrap()),
+                            headers: Default::default(),
+                            oci_reference: Some("registry.example.com/manifest:latest".to_string()),
+                            poll_interval: Duration::from_secs(30),
+                            timeout: Duration::from_secs(30),
+                        })
+                        .build(),
+                )
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`persisted_queries.remote_manifest` requires exactly one of `url` and `oci_reference`"
+        );
+    }
 }
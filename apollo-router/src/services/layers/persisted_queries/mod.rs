@@ -4,6 +4,8 @@ mod freeform_graphql_behavior;
 mod id_extractor;
 mod manifest;
 mod manifest_poller;
+mod safelist_scope;
+mod signature;
 
 #[cfg(test)]
 use std::sync::Arc;
@@ -16,13 +18,14 @@ pub use manifest::FullPersistedQueryOperationId;
 pub use manifest::ManifestOperation;
 pub use manifest::PersistedQueryManifest;
 pub(crate) use manifest_poller::PersistedQueryManifestPoller;
+use safelist_scope::SafelistScope;
+use safelist_scope::client_name;
 use tower::BoxError;
 
 use super::query_analysis::ParsedDocument;
 use crate::Configuration;
 use crate::context::PERSISTED_QUERY_ID;
 use crate::graphql::Error as GraphQLError;
-use crate::plugins::telemetry::CLIENT_NAME;
 use crate::services::SupergraphRequest;
 use crate::services::SupergraphResponse;
 
@@ -164,22 +167,20 @@ impl PersistedQueryLayer {
             }
         } else {
             // if there is no query, look up the persisted query in the manifest
-            // and put the body on the `supergraph_request`
-            if let Some(persisted_query_body) = manifest_poller.get_operation_body(
-                persisted_query_id,
-                // Use the first one of these that exists:
-                // - The PQL-specific context name entry
-                //   `apollo_persisted_queries::client_name` (which can be set
-                //   by router_service plugins)
-                // - The same name used by telemetry (ie, the value of the
-                //   header named by `telemetry.apollo.client_name_header`,
-                //   which defaults to `apollographql-client-name` by default)
-                request
-                    .context
-                    .get(PERSISTED_QUERIES_CLIENT_NAME_CONTEXT_KEY)
-                    .unwrap_or_default()
-                    .or_else(|| request.context.get(CLIENT_NAME).unwrap_or_default()),
-            ) {
+            // (or in the manifests of the client's safelist scope) and put the
+            // body on the `supergraph_request`
+            let scopes = manifest_poller.scopes();
+            let client = scopes.client_identity(&request);
+            let scope = client
+                .as_deref()
+                .and_then(|client| scopes.scope_for_client(client));
+            let persisted_query_body = match scope {
+                Some(scope) => scope.get_operation_body(persisted_query_id, client_name(&request)),
+                None => {
+                    manifest_poller.get_operation_body(persisted_query_id, client_name(&request))
+                }
+            };
+            if let Some(persisted_query_body) = persisted_query_body {
                 let body = request.supergraph_request.body_mut();
                 body.query = Some(persisted_query_body);
                 body.extensions.remove("persistedQuery");
@@ -209,11 +210,16 @@ impl PersistedQueryLayer {
                 // safelist later for log_unknown!)
                 Ok(request)
             } else {
+                let mut metric_attributes = vec![opentelemetry::KeyValue::new(
+                    "persisted_queries.not_found".to_string(),
+                    true,
+                )];
+                metric_attributes.extend(violation_attributes(client, scope));
                 u64_counter!(
                     "apollo.router.operations.persisted_queries",
                     "Total requests with persisted queries enabled",
                     1,
-                    persisted_queries.not_found = true
+                    metric_attributes
                 );
                 // if APQ is not enabled, return an error indicating the query was not found
                 Err(supergraph_err_operation_not_found(
@@ -298,7 +304,13 @@ impl PersistedQueryLayer {
         }
 
         let mut metric_attributes = vec![];
-        let freeform_graphql_action = manifest_poller.action_for_freeform_graphql(Ok(&doc.ast));
+        let scopes = manifest_poller.scopes();
+        let client = scopes.client_identity(&request);
+        let scope = client
+            .as_deref()
+            .and_then(|client| scopes.scope_for_client(client));
+        let freeform_graphql_action =
+            manifest_poller.action_for_freeform_graphql(Ok(&doc.ast), scope);
         let skip_enforcement = skip_enforcement(&request);
         let allow = skip_enforcement || freeform_graphql_action.should_allow;
         if !allow {
@@ -306,6 +318,7 @@ impl PersistedQueryLayer {
                 "persisted_queries.safelist.rejected.unknown".to_string(),
                 true,
             ));
+            metric_attributes.extend(violation_attributes(client, scope));
         } else if !freeform_graphql_action.should_allow {
             metric_attributes.push(opentelemetry::KeyValue::new(
                 "persisted_queries.safelist.enforcement_skipped".to_string(),
@@ -352,6 +365,24 @@ impl PersistedQueryLayer {
     }
 }
 
+/// Safelist scope, and identity of the client, of a request violating the safelist. The client is
+/// only recorded when bound to a scope, so that its values are limited to the configured clients.
+fn violation_attributes(
+    client: Option<String>,
+    scope: Option<&SafelistScope>,
+) -> Vec<opentelemetry::KeyValue> {
+    let (Some(client), Some(scope)) = (client, scope) else {
+        return vec![];
+    };
+    vec![
+        opentelemetry::KeyValue::new("persisted_queries.client".to_string(), client),
+        opentelemetry::KeyValue::new(
+            "persisted_queries.safelist.scope".to_string(),
+            scope.name.clone(),
+        ),
+    ]
+}
+
 fn log_unknown_operation(operation_body: &str, enforcement_skipped: bool) {
     tracing::warn!(
         message = "unknown operation",
@@ -504,6 +535,7 @@ mod tests {
     use crate::configuration::Apq;
     use crate::configuration::PersistedQueries;
     use crate::configuration::PersistedQueriesSafelist;
+    use crate::configuration::PersistedQueriesSafelistScope;
     use crate::configuration::Supergraph;
     use crate::graphql;
     use crate::metrics::FutureMetricsExt;
@@ -1085,6 +1117,147 @@ mod tests {
         .await;
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pq_layer_restricts_scoped_clients_to_their_manifests() {
+        async {
+            let manifest = PersistedQueryManifest::from(vec![ManifestOperation {
+                id: "global-id".to_string(),
+                body: "query GlobalOp { me { id } }".to_string(),
+                client_name: None,
+            }]);
+            let (_mock_guard, uplink_config) = mock_pq_uplink(&manifest).await;
+
+            let directory = tempfile::tempdir().unwrap();
+            let mobile_manifest = directory.path().join("mobile.json");
+            std::fs::write(
+                &mobile_manifest,
+                json!({
+                    "format": "apollo-persisted-query-manifest",
+                    "version": 1,
+                    "operations": [{"id": "mobile-id", "body": "query MobileOp { me { name } }"}]
+                })
+                .to_string(),
+            )
+            .unwrap();
+
+            let config = Configuration::fake_builder()
+                .persisted_query(
+                    PersistedQueries::builder()
+                        .enabled(true)
+                        .safelist(
+                            PersistedQueriesSafelist::builder()
+                                .enabled(true)
+                                .scopes(vec![PersistedQueriesSafelistScope {
+                                    name: "mobile".to_string(),
+                                    clients: vec!["ios".to_string()],
+                                    manifests: vec![mobile_manifest.display().to_string()],
+                                }])
+                                .build(),
+                        )
+                        .build(),
+                )
+                .uplink(uplink_config)
+                .apq(Apq::fake_builder().enabled(false).build())
+                .build()
+                .unwrap();
+            let pq_layer = PersistedQueryLayer::new(&config).await.unwrap();
+            let schema = Arc::new(
+                Schema::parse(
+                    include_str!("../../../testdata/supergraph.graphql"),
+                    &Default::default(),
+                )
+                .unwrap(),
+            );
+            let query_analysis_layer = QueryAnalysisLayer::new(schema, Arc::new(config)).await;
+
+            let request = |client: &str, id: Option<&str>, query: Option<&str>| {
+                let context = Context::new();
+                context
+                    .insert(
+                        PERSISTED_QUERIES_CLIENT_NAME_CONTEXT_KEY,
+                        client.to_string(),
+                    )
+                    .unwrap();
+                let mut builder = SupergraphRequest::fake_builder()
+                    .context(context)
+                    .and_query(query.map(str::to_string));
+                if let Some(id) = id {
+                    builder = builder
+                        .extension("persistedQuery", json!({"version": 1, "sha256Hash": id}));
+                }
+                builder.build().unwrap()
+            };
+
+            // Scoped clients can only run the operations of their scope
+            assert!(
+                pq_layer
+                    .supergraph_request(request("ios", Some("mobile-id"), None))
+                    .is_ok()
+            );
+            assert!(
+                pq_layer
+                    .supergraph_request(request("ios", Some("global-id"), None))
+                    .is_err()
+            );
+            assert_counter!(
+                "apollo.router.operations.persisted_queries",
+                1,
+                "persisted_queries.not_found" = true,
+                "persisted_queries.client" = "ios",
+                "persisted_queries.safelist.scope" = "mobile"
+            );
+
+            // Other clients use the persisted query list of the graph
+            assert!(
+                pq_layer
+                    .supergraph_request(request("web", Some("global-id"), None))
+                    .is_ok()
+            );
+            assert!(
+                pq_layer
+                    .supergraph_request(request("web", Some("mobile-id"), None))
+                    .is_err()
+            );
+            // The client is only recorded when bound to a scope
+            assert_counter!(
+                "apollo.router.operations.persisted_queries",
+                1,
+                "persisted_queries.not_found" = true
+            );
+
+            // Freeform GraphQL is matched against the safelist of the scope
+            let analyzed = query_analysis_layer
+                .supergraph_request(request("ios", None, Some("query MobileOp { me { name } }")))
+                .await
+                .unwrap();
+            assert!(
+                pq_layer
+                    .supergraph_request_with_analyzed_query(analyzed)
+                    .await
+                    .is_ok()
+            );
+            let analyzed = query_analysis_layer
+                .supergraph_request(request("ios", None, Some("query GlobalOp { me { id } }")))
+                .await
+                .unwrap();
+            assert!(
+                pq_layer
+                    .supergraph_request_with_analyzed_query(analyzed)
+                    .await
+                    .is_err()
+            );
+            assert_counter!(
+                "apollo.router.operations.persisted_queries",
+                1,
+                "persisted_queries.safelist.rejected.unknown" = true,
+                "persisted_queries.client" = "ios",
+                "persisted_queries.safelist.scope" = "mobile"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn pq_layer_freeform_graphql_with_safelist_log_unknown_false() {
         pq_layer_freeform_graphql_with_safelist(false).await;
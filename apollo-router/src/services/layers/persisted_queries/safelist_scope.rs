@@ -0,0 +1,214 @@
+//! Safelists restricting a set of clients to the operations of their own manifests.
+
+use std::collections::HashMap;
+
+use tower::BoxError;
+
+use super::PERSISTED_QUERIES_CLIENT_NAME_CONTEXT_KEY;
+use super::freeform_graphql_behavior::FreeformGraphQLSafelist;
+use super::manifest::FullPersistedQueryOperationId;
+use super::manifest::PersistedQueryManifest;
+use super::manifest::SignedUrlChunk;
+use super::signature::ManifestVerifier;
+use super::signature::read_manifest;
+use crate::configuration::PersistedQueriesSafelist;
+use crate::configuration::PersistedQueriesSafelistScopeSelector;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::services::SupergraphRequest;
+
+/// The operations allowed for the clients bound to a scope
+#[derive(Debug)]
+pub(crate) struct SafelistScope {
+    pub(crate) name: String,
+    manifest: PersistedQueryManifest,
+    pub(super) safelist: FreeformGraphQLSafelist,
+}
+
+impl SafelistScope {
+    pub(crate) fn get_operation_body(
+        &self,
+        persisted_query_id: &str,
+        client_name: Option<String>,
+    ) -> Option<String> {
+        self.manifest
+            .get(&FullPersistedQueryOperationId {
+                operation_id: persisted_query_id.to_string(),
+                client_name: client_name.clone(),
+            })
+            .or_else(|| {
+                client_name.and_then(|_| {
+                    self.manifest.get(&FullPersistedQueryOperationId {
+                        operation_id: persisted_query_id.to_string(),
+                        client_name: None,
+                    })
+                })
+            })
+            .cloned()
+    }
+}
+
+/// Safelist scopes, and the scope each client is bound to
+#[derive(Debug, Default)]
+pub(crate) struct SafelistScopes {
+    selector: PersistedQueriesSafelistScopeSelector,
+    scopes: Vec<SafelistScope>,
+    scope_by_client: HashMap<String, usize>,
+}
+
+impl SafelistScopes {
+    pub(crate) async fn load(
+        config: &PersistedQueriesSafelist,
+        verifier: Option<&ManifestVerifier>,
+    ) -> Result<Self, BoxError> {
+        if !config.scopes.is_empty() && !config.enabled {
+            return Err("`persisted_queries.safelist.scopes` requires `safelist.enabled`".into());
+        }
+
+        let mut scopes = Self {
+            selector: config.scope_selector.clone(),
+            ..Default::default()
+        };
+        for scope_config in &config.scopes {
+            if scope_config.manifests.is_empty() {
+                return Err(format!(
+                    "the persisted query safelist scope `{}` requires `manifests`",
+                    scope_config.name
+                )
+                .into());
+            }
+            let mut manifest = PersistedQueryManifest::default();
+            for path in &scope_config.manifests {
+                let raw_file_contents = read_manifest(path, verifier).await?;
+                manifest.add_chunk(&SignedUrlChunk::parse_and_validate(&raw_file_contents)?);
+            }
+            tracing::info!(
+                "Loaded {} persisted queries for the safelist scope `{}`.",
+                manifest.len(),
+                scope_config.name
+            );
+
+            let index = scopes.scopes.len();
+            for client in &scope_config.clients {
+                if let Some(other) = scopes.scope_by_client.insert(client.clone(), index) {
+                    return Err(format!(
+                        "the client `{client}` is bound to the persisted query safelist scopes `{}` and `{}`",
+                        scopes.scopes[other].name, scope_config.name
+                    )
+                    .into());
+                }
+            }
+            scopes.scopes.push(SafelistScope {
+                name: scope_config.name.clone(),
+                safelist: FreeformGraphQLSafelist::new(&manifest),
+                manifest,
+            });
+        }
+        Ok(scopes)
+    }
+
+    /// Identity of the client of the request, according to the configured selector
+    pub(crate) fn client_identity(&self, request: &SupergraphRequest) -> Option<String> {
+        match &self.selector {
+            PersistedQueriesSafelistScopeSelector::ClientName => client_name(request),
+            PersistedQueriesSafelistScopeSelector::Header(name) => request
+                .supergraph_request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+
+    /// The scope the client is bound to, if any
+    pub(crate) fn scope_for_client(&self, client: &str) -> Option<&SafelistScope> {
+        self.scope_by_client
+            .get(client)
+            .map(|index| &self.scopes[*index])
+    }
+
+    pub(crate) fn operation_bodies(&self) -> impl Iterator<Item = &String> {
+        self.scopes.iter().flat_map(|scope| scope.manifest.values())
+    }
+}
+
+/// The client name used to look up persisted queries. Use the first one of these that exists:
+/// - The PQL-specific context name entry `apollo_persisted_queries::client_name` (which can be set
+///   by router_service plugins)
+/// - The same name used by telemetry (ie, the value of the header named by
+///   `telemetry.apollo.client_name_header`, which defaults to `apollographql-client-name` by
+///   default)
+pub(crate) fn client_name(request: &SupergraphRequest) -> Option<String> {
+    request
+        .context
+        .get(PERSISTED_QUERIES_CLIENT_NAME_CONTEXT_KEY)
+        .unwrap_or_default()
+        .or_else(|| request.context.get(CLIENT_NAME).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::PersistedQueriesSafelistScope;
+
+    fn scope(name: &str, clients: &[&str]) -> PersistedQueriesSafelistScope {
+        PersistedQueriesSafelistScope {
+            name: name.to_string(),
+            clients: clients.iter().map(|client| client.to_string()).collect(),
+            manifests: vec!["tests/fixtures/persisted-queries-manifest.json".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn it_binds_clients_to_their_scope() {
+        let scopes = SafelistScopes::load(
+            &PersistedQueriesSafelist::builder()
+                .enabled(true)
+                .scopes(vec![scope("mobile", &["ios", "android"])])
+                .build(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let mobile = scopes.scope_for_client("ios").unwrap();
+        assert_eq!(mobile.name, "mobile");
+        assert!(mobile.get_operation_body("5678", None).is_some());
+        assert!(
+            mobile
+                .get_operation_body("5678", Some("ios".to_string()))
+                .is_some()
+        );
+        assert!(mobile.get_operation_body("unknown", None).is_none());
+        assert!(scopes.scope_for_client("web").is_none());
+    }
+
+    #[tokio::test]
+    async fn it_rejects_clients_bound_to_several_scopes() {
+        let err = SafelistScopes::load(
+            &PersistedQueriesSafelist::builder()
+                .enabled(true)
+                .scopes(vec![scope("mobile", &["ios"]), scope("internal", &["ios"])])
+                .build(),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the client `ios` is bound to the persisted query safelist scopes `mobile` and `internal`"
+        );
+    }
+
+    #[test]
+    fn it_identifies_clients_by_header() {
+        let scopes = SafelistScopes {
+            selector: PersistedQueriesSafelistScopeSelector::Header("x-app-id".to_string()),
+            ..Default::default()
+        };
+        let request = SupergraphRequest::fake_builder()
+            .header("x-app-id", "ios")
+            .build()
+            .unwrap();
+        assert_eq!(scopes.client_identity(&request), Some("ios".to_string()));
+    }
+}
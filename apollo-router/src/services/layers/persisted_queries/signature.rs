@@ -0,0 +1,275 @@
+//! Verification of detached signatures of local persisted query manifests.
+//!
+//! The signature of a manifest is read from the file at the manifest path with a `.sig` suffix.
+//! It is either the base64 encoded Ed25519 signature of the manifest file, or a compact JWS with a
+//! detached payload (`<header>..<signature>`) whose payload is the manifest file.
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::crypto;
+use jsonwebtoken::decode_header;
+use tower::BoxError;
+
+use crate::configuration::PersistedQueriesManifestSignature;
+
+const SIGNATURE_SUFFIX: &str = ".sig";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyFamily {
+    Ed,
+    Ec,
+    Rsa,
+}
+
+impl KeyFamily {
+    fn of(algorithm: Algorithm) -> Option<Self> {
+        match algorithm {
+            Algorithm::EdDSA => Some(Self::Ed),
+            Algorithm::ES256 | Algorithm::ES384 => Some(Self::Ec),
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => Some(Self::Rsa),
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => None,
+        }
+    }
+}
+
+/// Public keys trusted to sign persisted query manifests
+pub(crate) struct ManifestVerifier {
+    keys: Vec<(KeyFamily, DecodingKey)>,
+}
+
+impl std::fmt::Debug for ManifestVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManifestVerifier")
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+impl ManifestVerifier {
+    pub(crate) fn from_config(
+        config: &PersistedQueriesManifestSignature,
+    ) -> Result<Self, BoxError> {
+        if config.public_keys.is_empty() {
+            return Err("`persisted_queries.manifest_signature` requires `public_keys`".into());
+        }
+        let keys = config
+            .public_keys
+            .iter()
+            .map(|path| {
+                let pem = std::fs::read(path).map_err(|e| -> BoxError {
+                    format!(
+                        "Failed to read persisted query manifest public key at path: {path}, {e}"
+                    )
+                    .into()
+                })?;
+                parse_public_key(&pem).ok_or_else(|| -> BoxError {
+                    format!("{path} is not a PEM encoded Ed25519, EC or RSA public key").into()
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+
+    /// Check the signature of the manifest at `path`, whose content is `manifest`.
+    pub(crate) async fn verify(&self, path: &str, manifest: &[u8]) -> Result<(), BoxError> {
+        let signature_path = signature_path(path);
+        let result = match tokio::fs::read_to_string(&signature_path).await {
+            Ok(signature) => self.verify_signature(signature.trim(), manifest),
+            Err(e) => {
+                Err(format!("cannot read the signature at path: {signature_path}, {e}").into())
+            }
+        };
+
+        u64_counter_with_unit!(
+            "apollo.router.persisted_queries.manifest.signature",
+            "Signature verifications of local persisted query manifests",
+            "{verification}",
+            1,
+            "result" = if result.is_ok() { "valid" } else { "invalid" }
+        );
+        result.map_err(|e| -> BoxError {
+            tracing::error!(
+                path,
+                "persisted query manifest rejected, its signature could not be verified: {e}"
+            );
+            format!("invalid signature for the persisted query manifest at path: {path}, {e}")
+                .into()
+        })
+    }
+
+    fn verify_signature(&self, signature: &str, manifest: &[u8]) -> Result<(), BoxError> {
+        let (algorithm, message, signature) = match signature.split_once("..") {
+            // compact JWS with a detached payload
+            Some((header, signature)) => {
+                let algorithm = decode_header(format!("{header}..{signature}"))?.alg;
+                let message = format!("{header}.{}", URL_SAFE_NO_PAD.encode(manifest));
+                (algorithm, message.into_bytes(), signature.to_string())
+            }
+            // raw Ed25519 signature
+            None => {
+                let signature = URL_SAFE_NO_PAD.encode(STANDARD.decode(signature)?);
+                (Algorithm::EdDSA, manifest.to_vec(), signature)
+            }
+        };
+        let family = KeyFamily::of(algorithm)
+            .ok_or_else(|| format!("unsupported signature algorithm {algorithm:?}"))?;
+
+        for (key_family, key) in &self.keys {
+            // a key which cannot verify the signature doesn't prevent trying the other keys
+            if *key_family == family
+                && crypto::verify(&signature, &message, key, algorithm).unwrap_or(false)
+            {
+                return Ok(());
+            }
+        }
+        Err("the signature does not match any of the configured public keys".into())
+    }
+}
+
+/// Path of the detached signature of the manifest at `path`
+pub(crate) fn signature_path(path: &str) -> String {
+    format!("{path}{SIGNATURE_SUFFIX}")
+}
+
+fn parse_public_key(pem: &[u8]) -> Option<(KeyFamily, DecodingKey)> {
+    if let Ok(key) = DecodingKey::from_ed_pem(pem) {
+        Some((KeyFamily::Ed, key))
+    } else if let Ok(key) = DecodingKey::from_ec_pem(pem) {
+        Some((KeyFamily::Ec, key))
+    } else if let Ok(key) = DecodingKey::from_rsa_pem(pem) {
+        Some((KeyFamily::Rsa, key))
+    } else {
+        None
+    }
+}
+
+/// Read the manifest at `path`, and check its signature if a verifier is configured.
+pub(crate) async fn read_manifest(
+    path: &str,
+    verifier: Option<&ManifestVerifier>,
+) -> Result<String, BoxError> {
+    let raw_file_contents = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| -> BoxError {
+            format!("Failed to read persisted query list file at path: {path}, {e}").into()
+        })?;
+    if let Some(verifier) = verifier {
+        verifier.verify(path, raw_file_contents.as_bytes()).await?;
+    }
+    Ok(raw_file_contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::EncodingKey;
+    use jsonwebtoken::Header;
+
+    use super::*;
+
+    const TESTDATA: &str = "src/services/layers/persisted_queries/testdata";
+    const MANIFEST: &[u8] =
+        br#"{"format":"apollo-persisted-query-manifest","version":1,"operations":[]}"#;
+
+    fn verifier(keys: &[&str]) -> ManifestVerifier {
+        ManifestVerifier::from_config(&PersistedQueriesManifestSignature {
+            public_keys: keys.iter().map(|key| format!("{TESTDATA}/{key}")).collect(),
+        })
+        .unwrap()
+    }
+
+    fn private_key(name: &str) -> Vec<u8> {
+        std::fs::read(format!("{TESTDATA}/{name}")).unwrap()
+    }
+
+    fn raw_ed25519_signature(manifest: &[u8]) -> String {
+        let key = EncodingKey::from_ed_pem(&private_key("ed25519.key")).unwrap();
+        let signature = crypto::sign(manifest, &key, Algorithm::EdDSA).unwrap();
+        STANDARD.encode(URL_SAFE_NO_PAD.decode(signature).unwrap())
+    }
+
+    fn detached_jws(manifest: &[u8]) -> String {
+        let key = EncodingKey::from_ec_pem(&private_key("es256.key")).unwrap();
+        let header =
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&Header::new(Algorithm::ES256)).unwrap());
+        let message = format!("{header}.{}", URL_SAFE_NO_PAD.encode(manifest));
+        let signature = crypto::sign(message.as_bytes(), &key, Algorithm::ES256).unwrap();
+        format!("{header}..{signature}")
+    }
+
+    #[test]
+    fn it_verifies_raw_ed25519_signatures() {
+        let verifier = verifier(&["es256.pub", "ed25519.pub"]);
+        let signature = raw_ed25519_signature(MANIFEST);
+
+        assert!(verifier.verify_signature(&signature, MANIFEST).is_ok());
+        assert!(
+            verifier
+                .verify_signature(&signature, b"{\"operations\":[]}")
+                .is_err()
+        );
+        assert!(
+            self::verifier(&["es256.pub"])
+                .verify_signature(&signature, MANIFEST)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn it_verifies_detached_jws() {
+        let verifier = verifier(&["ed25519.pub", "es256.pub"]);
+        let signature = detached_jws(MANIFEST);
+
+        assert!(verifier.verify_signature(&signature, MANIFEST).is_ok());
+        assert!(
+            verifier
+                .verify_signature(&signature, b"{\"operations\":[]}")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn it_tries_the_other_keys_when_a_key_cannot_verify_the_signature() {
+        // the P-384 key cannot verify an ES256 signature
+        let verifier = verifier(&["es384.pub", "es256.pub"]);
+        let signature = detached_jws(MANIFEST);
+        assert!(
+            crypto::verify(
+                signature.split_once("..").unwrap().1,
+                b"message",
+                &verifier.keys[0].1,
+                Algorithm::ES256
+            )
+            .is_err()
+        );
+
+        assert!(verifier.verify_signature(&signature, MANIFEST).is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_rejects_a_manifest_without_signature() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("manifest.json");
+        std::fs::write(&path, MANIFEST).unwrap();
+        let path = path.to_str().unwrap();
+        let verifier = verifier(&["ed25519.pub"]);
+
+        assert!(read_manifest(path, Some(&verifier)).await.is_err());
+
+        std::fs::write(format!("{path}.sig"), raw_ed25519_signature(MANIFEST)).unwrap();
+        assert_eq!(
+            read_manifest(path, Some(&verifier))
+                .await
+                .unwrap()
+                .as_bytes(),
+            MANIFEST
+        );
+    }
+}
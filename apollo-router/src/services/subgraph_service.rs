@@ -71,6 +71,7 @@ use crate::services::SubgraphRequest;
 use crate::services::SubgraphResponse;
 use crate::services::http::service::WireByteCount;
 use crate::services::layers::apq;
+use crate::services::layers::apq::SubgraphApqRegistry;
 use crate::services::router;
 use crate::services::subgraph;
 
@@ -112,6 +113,10 @@ pub(crate) struct SubgraphService {
     /// If a subgraph sends the error message PERSISTED_QUERY_NOT_SUPPORTED,
     /// apq is set to false
     apq: Arc<AtomicBool>,
+
+    /// Query hashes known by subgraphs. If set, hash-only requests are only sent for the queries
+    /// the subgraph is known to have registered.
+    apq_registry: Option<Arc<SubgraphApqRegistry>>,
 }
 
 impl SubgraphService {
@@ -119,6 +124,7 @@ impl SubgraphService {
         service: impl Into<String>,
         configuration: &Configuration,
         client_factory: HttpClientServiceFactory,
+        apq_registry: Option<Arc<SubgraphApqRegistry>>,
     ) -> Result<Self, BoxError> {
         let name: String = service.into();
 
@@ -130,7 +136,9 @@ impl SubgraphService {
             .map(|apq| apq.enabled)
             .unwrap_or(configuration.apq.subgraph.all.enabled);
 
-        SubgraphService::new(name, enable_apq, client_factory)
+        let mut subgraph_service = SubgraphService::new(name, enable_apq, client_factory)?;
+        subgraph_service.apq_registry = apq_registry;
+        Ok(subgraph_service)
     }
 
     pub(crate) fn new(
@@ -142,6 +150,7 @@ impl SubgraphService {
             client_factory,
             service: Arc::new(service.into()),
             apq: Arc::new(<AtomicBool>::new(enable_apq)),
+            apq_registry: None,
         })
     }
 }
@@ -186,6 +195,7 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
         let client_factory = self.client_factory.clone();
 
         let arc_apq_enabled = self.apq.clone();
+        let apq_registry = self.apq_registry.clone();
 
         let make_calls = async move {
             // XXX(@goto-bus-stop): We are cloning the subgraph request potentially 3 times below.
@@ -240,6 +250,54 @@ impl tower::Service<SubgraphRequest> for SubgraphService {
                 extensions: extensions_with_apq,
             };
 
+            if let Some(apq_registry) = &apq_registry {
+                let known = apq_registry.is_known(&service_name, &hash_value).await;
+                u64_counter!(
+                    "apollo.router.operations.apq.subgraph",
+                    "Number of subgraph requests with APQ, by whether the subgraph was known to have registered the query",
+                    1,
+                    "subgraph.name" = service_name.clone(),
+                    "apq.registry_hit" = known
+                );
+                if !known {
+                    // Skip the hash-only request, which would fail: send the query along with its
+                    // hash for the subgraph to register it.
+                    apq_body.query = query;
+                    let response = call_http(
+                        request.clone(),
+                        apq_body,
+                        context.clone(),
+                        client_factory.clone(),
+                        &service_name,
+                    )
+                    .await?;
+                    return match get_apq_error(response.response.body()) {
+                        APQError::PersistedQueryNotSupported => {
+                            apq_enabled.store(false, Relaxed);
+                            call_http(
+                                request,
+                                body,
+                                context,
+                                client_factory.clone(),
+                                &service_name,
+                            )
+                            .await
+                        }
+                        APQError::PersistedQueryNotFound => Ok(response),
+                        APQError::Other => {
+                            // The subgraph only stored the query if it could execute it
+                            let gql_response = response.response.body();
+                            if response.response.status().is_success()
+                                && (gql_response.errors.is_empty() || gql_response.data.is_some())
+                            {
+                                apq_registry.register(&service_name, &hash_value).await;
+                            }
+                            Ok(response)
+                        }
+                    };
+                }
+            }
+
             let response = call_http(
                 request.clone(),
                 apq_body.clone(),
@@ -1233,6 +1291,7 @@ mod tests {
     use crate::graphql::Error;
     use crate::graphql::Request;
     use crate::graphql::Response;
+    use crate::metrics::FutureMetricsExt;
     use crate::plugins::subscription::CallbackMode;
     use crate::plugins::subscription::DeduplicationConfig;
     use crate::plugins::subscription::HeartbeatInterval;
@@ -2682,6 +2741,169 @@ mod tests {
         assert_eq!(resp.response.body(), &expected_resp);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_apq_registry_skips_hash_only_requests_for_unknown_queries() {
+        async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let socket_addr = listener.local_addr().unwrap();
+            // whether each request received by the subgraph contained the query
+            let received_queries = Arc::new(parking_lot::Mutex::new(Vec::new()));
+            let received = received_queries.clone();
+            tokio::task::spawn(serve(listener, move |request: http::Request<Body>| {
+                let received = received.clone();
+                async move {
+                    let bytes = router::body::into_bytes(request.into_body()).await.unwrap();
+                    let request: graphql::Request = serde_json::from_slice(&bytes).unwrap();
+                    assert!(request.extensions.contains_key(PERSISTED_QUERY_KEY));
+                    received.lock().push(request.query.is_some());
+                    Ok(http::Response::builder()
+                        .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                        .status(StatusCode::OK)
+                        .body(
+                            serde_json::to_string(&Response {
+                                data: Some(Value::String(ByteString::from("test"))),
+                                ..Response::default()
+                            })
+                            .expect("always valid")
+                            .into(),
+                        )
+                        .unwrap())
+                }
+            }));
+
+            let registry = SubgraphApqRegistry::new(&Default::default()).await.unwrap();
+            let mut configuration = Configuration::default();
+            configuration.apq.subgraph.all.enabled = true;
+            let subgraph_service = SubgraphService::from_config(
+                "test",
+                &configuration,
+                HttpClientServiceFactory::from_config(
+                    "test",
+                    &configuration,
+                    crate::configuration::shared::Client::default(),
+                ),
+                Some(Arc::new(registry)),
+            )
+            .expect("can create a SubgraphService");
+
+            let url = Uri::from_str(&format!("http://{socket_addr}")).unwrap();
+            for _ in 0..2 {
+                subgraph_service
+                    .clone()
+                    .oneshot(
+                        SubgraphRequest::builder()
+                            .supergraph_request(supergraph_request("query"))
+                            .subgraph_request(subgraph_http_request(url.clone(), "query"))
+                            .operation_kind(OperationKind::Query)
+                            .subgraph_name(String::from("test"))
+                            .context(Context::new())
+                            .build(),
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            // the query is sent with its hash until the subgraph registered it
+            assert_eq!(*received_queries.lock(), vec![true, false]);
+            assert_counter!(
+                "apollo.router.operations.apq.subgraph",
+                1,
+                "subgraph.name" = "test",
+                "apq.registry_hit" = false
+            );
+            assert_counter!(
+                "apollo.router.operations.apq.subgraph",
+                1,
+                "subgraph.name" = "test",
+                "apq.registry_hit" = true
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_apq_registry_ignores_failed_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let socket_addr = listener.local_addr().unwrap();
+        let received_queries = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let received = received_queries.clone();
+        tokio::task::spawn(serve(listener, move |request: http::Request<Body>| {
+            let received = received.clone();
+            async move {
+                let bytes = router::body::into_bytes(request.into_body()).await.unwrap();
+                let request: graphql::Request = serde_json::from_slice(&bytes).unwrap();
+                let mut received = received.lock();
+                received.push(request.query.is_some());
+                // The first request fails with a server error, the second with a GraphQL error
+                let (status, response) = match received.len() {
+                    1 => (StatusCode::INTERNAL_SERVER_ERROR, Response::default()),
+                    2 => (
+                        StatusCode::OK,
+                        Response::builder()
+                            .error(
+                                graphql::Error::builder()
+                                    .message("Cannot query field")
+                                    .extension_code("GRAPHQL_VALIDATION_FAILED")
+                                    .build(),
+                            )
+                            .build(),
+                    ),
+                    _ => (
+                        StatusCode::OK,
+                        Response {
+                            data: Some(Value::String(ByteString::from("test"))),
+                            ..Response::default()
+                        },
+                    ),
+                };
+                Ok(http::Response::builder()
+                    .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                    .status(status)
+                    .body(
+                        serde_json::to_string(&response)
+                            .expect("always valid")
+                            .into(),
+                    )
+                    .unwrap())
+            }
+        }));
+
+        let registry = SubgraphApqRegistry::new(&Default::default()).await.unwrap();
+        let mut configuration = Configuration::default();
+        configuration.apq.subgraph.all.enabled = true;
+        let subgraph_service = SubgraphService::from_config(
+            "test",
+            &configuration,
+            HttpClientServiceFactory::from_config(
+                "test",
+                &configuration,
+                crate::configuration::shared::Client::default(),
+            ),
+            Some(Arc::new(registry)),
+        )
+        .expect("can create a SubgraphService");
+
+        let url = Uri::from_str(&format!("http://{socket_addr}")).unwrap();
+        for _ in 0..4 {
+            let _ = subgraph_service
+                .clone()
+                .oneshot(
+                    SubgraphRequest::builder()
+                        .supergraph_request(supergraph_request("query"))
+                        .subgraph_request(subgraph_http_request(url.clone(), "query"))
+                        .operation_kind(OperationKind::Query)
+                        .subgraph_name(String::from("test"))
+                        .context(Context::new())
+                        .build(),
+                )
+                .await;
+        }
+
+        // the hash is only registered once the subgraph executed the query
+        assert_eq!(*received_queries.lock(), vec![true, true, true, false]);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_apq_disabled_subgraph_configuration() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
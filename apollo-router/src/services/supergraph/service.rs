@@ -699,6 +699,7 @@ impl SupergraphCreator {
                 count,
                 experimental_reuse_query_plans,
                 experimental_pql_prewarm,
+                self.query_planner_service.plan_bundle_operations(),
             )
             .await
     }
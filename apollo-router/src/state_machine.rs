@@ -35,6 +35,7 @@ use crate::configuration::Discussed;
 use crate::configuration::ListenAddr;
 use crate::configuration::metrics::Metrics;
 use crate::plugins::telemetry::reload::otel::apollo_opentelemetry_initialized;
+use crate::query_planner::disk_cache;
 use crate::router::Event::UpdateLicense;
 use crate::router_factory::RouterFactory;
 use crate::router_factory::RouterSuperServiceFactory;
@@ -306,6 +307,7 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                 // We ignore the results of recv()
                 let _: Vec<_> = futs.collect().await;
                 tracing::info!("all connections shut down");
+                disk_cache::shutdown().await;
                 state
             }
             _ => Stopped,
@@ -14,6 +14,9 @@ use tower::BoxError;
 use tracing::instrument::WithSubscriber;
 use url::Url;
 
+use crate::plugins::telemetry::tracing::propagation::OutboundClient;
+use crate::plugins::telemetry::tracing::propagation::inject_trace_context;
+
 pub(crate) mod feature_gate_enforcement;
 pub(crate) mod license_enforcement;
 pub(crate) mod license_stream;
@@ -477,9 +480,16 @@ where
     // target: "apollo_router::router::event::schema"
     // timestamp: "2023-08-01T10:40:28.831196Z"
     // That's deeply confusing and very hard to debug. Let's try to help by printing out a helpful error message here
+    let mut trace_headers = http::HeaderMap::new();
+    inject_trace_context(
+        OutboundClient::Uplink,
+        &tracing::Span::current(),
+        &mut trace_headers,
+    );
     let res = client
         .post(url)
         .header("x-router-version", env!("CARGO_PKG_VERSION"))
+        .headers(trace_headers)
         .json(request_body)
         .send()
         .await